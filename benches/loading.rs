@@ -0,0 +1,137 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use dynamic_flows_rs::{
+    bench::{grid_instance, measure_loading, random_instance, DemandPattern, SyntheticInstance},
+    float::{FastF64, F64},
+    network_loader::NetworkLoader,
+};
+
+/// Loads each instance repeatedly, reporting the throughput in breakpoints of the loaded flow.
+/// The heap usage of the flow is printed once per instance, as criterion only measures time.
+fn bench_loading(
+    c: &mut Criterion,
+    group_name: &str,
+    instances: Vec<(String, SyntheticInstance<F64>)>,
+) {
+    let mut group = c.benchmark_group(group_name);
+    group.sample_size(10);
+    for (name, instance) in instances {
+        let measurement = measure_loading(&instance);
+        println!(
+            "{}/{}: {} breakpoints, {} bytes on the heap",
+            group_name, name, measurement.num_breakpoints, measurement.heap_bytes
+        );
+        group.throughput(Throughput::Elements(measurement.num_breakpoints as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(&name),
+            &instance,
+            |b, instance| b.iter(|| instance.instance.load(&instance.path_inflows)),
+        );
+    }
+    group.finish();
+}
+
+fn grid(c: &mut Criterion) {
+    let demand = DemandPattern::Peak { steps: 8 };
+    let instances = [5, 10, 15]
+        .into_iter()
+        .map(|n| {
+            (
+                format!("{n}x{n}"),
+                grid_instance(n, n, n, 3, demand, 50.0, 42),
+            )
+        })
+        .collect();
+    bench_loading(c, "grid", instances);
+}
+
+fn random(c: &mut Criterion) {
+    let demand = DemandPattern::Random { changes: 10 };
+    let instances = [50, 100, 200]
+        .into_iter()
+        .map(|n| {
+            (
+                n.to_string(),
+                random_instance(n, 3, n / 10, 3, demand, 50.0, 42),
+            )
+        })
+        .collect();
+    bench_loading(c, "random", instances);
+}
+
+/// Loads a large grid on several parts concurrently, see `Instance::load_partitioned`.
+fn grid_partitioned(c: &mut Criterion) {
+    let instance: SyntheticInstance<F64> =
+        grid_instance(15, 15, 15, 3, DemandPattern::Peak { steps: 8 }, 50.0, 42);
+    let mut group = c.benchmark_group("grid_partitioned");
+    group.sample_size(10);
+    for num_parts in [1, 2, 4, 8] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(num_parts),
+            &num_parts,
+            |b, &num_parts| {
+                b.iter(|| {
+                    instance
+                        .instance
+                        .load_partitioned(&instance.path_inflows, num_parts)
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+/// Compares the float backends with and without `OrderedFloat` on the same grid.
+fn backends(c: &mut Criterion) {
+    let demand = DemandPattern::Peak { steps: 8 };
+    let double: SyntheticInstance<F64> = grid_instance(10, 10, 10, 3, demand, 50.0, 42);
+    let fast: SyntheticInstance<FastF64> = grid_instance(10, 10, 10, 3, demand, 50.0, 42);
+    let mut group = c.benchmark_group("backends");
+    group.sample_size(10);
+    group.bench_function("F64", |b| {
+        b.iter(|| double.instance.load(&double.path_inflows))
+    });
+    group.bench_function("FastF64", |b| {
+        b.iter(|| fast.instance.load(&fast.path_inflows))
+    });
+    group.finish();
+}
+
+/// Compares loading a large grid with and without recycling the rate maps of each phase, see
+/// `NetworkLoader::with_rate_pooling`. The allocated and reused maps are printed once per variant.
+fn pooling(c: &mut Criterion) {
+    let instance: SyntheticInstance<F64> = grid_instance(
+        15,
+        15,
+        15,
+        3,
+        DemandPattern::Random { changes: 10 },
+        50.0,
+        42,
+    );
+    let network = &instance.instance.network;
+    let path_inflows = instance.instance.flat_path_inflows(&instance.path_inflows);
+    let load = |pooling: bool| {
+        NetworkLoader::new(&path_inflows)
+            .with_rate_pooling(pooling)
+            .build_flow(
+                network.num_edges(),
+                network.capacity(),
+                network.inv_capacity(),
+                network.travel_time(),
+            )
+    };
+    let mut group = c.benchmark_group("pooling");
+    group.sample_size(10);
+    for (name, pooling) in [("pooled", true), ("unpooled", false)] {
+        let (allocated, reused) = load(pooling).rate_pool_stats();
+        println!(
+            "pooling/{}: {} maps allocated, {} reused",
+            name, allocated, reused
+        );
+        group.bench_function(name, |b| b.iter(|| load(pooling)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, grid, random, grid_partitioned, backends, pooling);
+criterion_main!(benches);
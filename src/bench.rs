@@ -0,0 +1,300 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    instance::{Commodity, Instance},
+    network::Network,
+    num::{Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+    point::Point,
+};
+
+/// A reproducible pseudo-random number generator (SplitMix64), so that the generated instances
+/// only depend on the seed and not on the platform or a random crate.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `[0, n)`.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Returns a number in `[low, high)`.
+    pub fn uniform(&mut self, low: f64, high: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        low + unit * (high - low)
+    }
+}
+
+/// How the network inflow rate of each commodity evolves over time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DemandPattern {
+    /// A constant rate during `[0, horizon)`.
+    Constant,
+    /// A rate that rises to a peak in the middle of `[0, horizon)` and falls again in `steps` steps.
+    Peak { steps: usize },
+    /// Random rates that change at `changes` random times in `[0, horizon)`.
+    Random { changes: usize },
+}
+
+/// A generated instance together with the path inflows to load it with, where
+/// path_inflows[i][j] belongs to path j of commodity i.
+#[derive(Debug, Clone)]
+pub struct SyntheticInstance<T: Num> {
+    pub instance: Instance<T>,
+    pub path_inflows: Vec<Vec<PiecewiseConstant<T>>>,
+}
+
+/// Generates the grid with `rows × cols` nodes, where node `r·cols + c` has edges to its right and
+/// lower neighbours, and `num_commodities` commodities from the upper left part to the lower right
+/// part of the grid. Each commodity routes its inflow evenly over `paths_per_commodity` random
+/// monotone paths.
+pub fn grid_instance<T: Num + From<f64>>(
+    rows: usize,
+    cols: usize,
+    num_commodities: usize,
+    paths_per_commodity: usize,
+    demand: DemandPattern,
+    horizon: f64,
+    seed: u64,
+) -> SyntheticInstance<T> {
+    debug_assert!(rows >= 2 && cols >= 2, "The grid needs at least 2×2 nodes.");
+    let mut rng = SplitMix64::new(seed);
+    let mut network = Network::new();
+    // right[node] and down[node] are the edges leaving node to the right and downwards
+    let mut right = vec![usize::MAX; rows * cols];
+    let mut down = vec![usize::MAX; rows * cols];
+    for r in 0..rows {
+        for c in 0..cols {
+            let node = r * cols + c;
+            if c + 1 < cols {
+                right[node] = add_random_edge(&mut network, &mut rng, node, node + 1);
+            }
+            if r + 1 < rows {
+                down[node] = add_random_edge(&mut network, &mut rng, node, node + cols);
+            }
+        }
+    }
+
+    let commodities = (0..num_commodities)
+        .map(|_| {
+            let (r0, c0) = (rng.below(rows / 2), rng.below(cols / 2));
+            let (r1, c1) = (
+                rows / 2 + rng.below(rows - rows / 2),
+                cols / 2 + rng.below(cols - cols / 2),
+            );
+            let paths = (0..paths_per_commodity)
+                .map(|_| {
+                    let (mut r, mut c) = (r0, c0);
+                    let mut path = Vec::with_capacity(r1 - r0 + c1 - c0);
+                    while (r, c) != (r1, c1) {
+                        let go_down = c == c1 || (r < r1 && rng.below(2) == 0);
+                        if go_down {
+                            path.push(down[r * cols + c]);
+                            r += 1;
+                        } else {
+                            path.push(right[r * cols + c]);
+                            c += 1;
+                        }
+                    }
+                    path
+                })
+                .filter(|path| !path.is_empty())
+                .collect::<Vec<_>>();
+            let inflow = demand_inflow(&mut rng, demand, horizon);
+            (paths, inflow)
+        })
+        .filter(|(paths, _)| !paths.is_empty())
+        .collect::<Vec<_>>();
+    synthetic_instance(network, commodities)
+}
+
+/// Generates a random acyclic network with `num_nodes` nodes in which every node has edges to
+/// `out_degree` random later nodes, and `num_commodities` commodities, each of which routes its
+/// inflow evenly over `paths_per_commodity` random paths from a random node to the last node.
+pub fn random_instance<T: Num + From<f64>>(
+    num_nodes: usize,
+    out_degree: usize,
+    num_commodities: usize,
+    paths_per_commodity: usize,
+    demand: DemandPattern,
+    horizon: f64,
+    seed: u64,
+) -> SyntheticInstance<T> {
+    debug_assert!(num_nodes >= 2, "The network needs at least two nodes.");
+    let mut rng = SplitMix64::new(seed);
+    let mut network = Network::new();
+    // outgoing[v] are the edges leaving node v
+    let mut outgoing: Vec<Vec<(usize, usize)>> = vec![Vec::new(); num_nodes];
+    for (tail, outgoing_v) in outgoing.iter_mut().enumerate().take(num_nodes - 1) {
+        // The edge to the next node keeps the last node reachable from every node.
+        let heads: Vec<usize> = std::iter::once(tail + 1)
+            .chain((1..out_degree).map(|_| tail + 1 + rng.below(num_nodes - tail - 1)))
+            .collect();
+        for head in heads {
+            let edge = add_random_edge(&mut network, &mut rng, tail, head);
+            outgoing_v.push((edge, head));
+        }
+    }
+
+    let commodities = (0..num_commodities)
+        .map(|_| {
+            let source = rng.below(num_nodes - 1);
+            let paths = (0..paths_per_commodity)
+                .map(|_| {
+                    let mut path = Vec::new();
+                    let mut node = source;
+                    while node + 1 < num_nodes {
+                        let (edge, head) = outgoing[node][rng.below(outgoing[node].len())];
+                        path.push(edge);
+                        node = head;
+                    }
+                    path
+                })
+                .collect();
+            let inflow = demand_inflow(&mut rng, demand, horizon);
+            (paths, inflow)
+        })
+        .collect();
+    synthetic_instance(network, commodities)
+}
+
+/// Adds an edge with a random capacity in `[1, 4)` and travel time in `[1, 10)`.
+fn add_random_edge<T: Num + From<f64>>(
+    network: &mut Network<T>,
+    rng: &mut SplitMix64,
+    tail: usize,
+    head: usize,
+) -> usize {
+    let capacity = rng.uniform(1.0, 4.0);
+    let travel_time = rng.uniform(1.0, 10.0);
+    network.add_edge(tail, head, capacity.into(), travel_time.into())
+}
+
+fn demand_inflow<T: Num + From<f64>>(
+    rng: &mut SplitMix64,
+    demand: DemandPattern,
+    horizon: f64,
+) -> PiecewiseConstant<T> {
+    let mut steps: Vec<(f64, f64)> = match demand {
+        DemandPattern::Constant => vec![(0.0, rng.uniform(1.0, 3.0))],
+        DemandPattern::Peak { steps } => {
+            let peak = rng.uniform(2.0, 6.0);
+            let steps = steps.max(1);
+            (0..steps)
+                .map(|k| {
+                    let x = (k as f64 + 0.5) / steps as f64;
+                    let time = k as f64 * horizon / steps as f64;
+                    (time, peak * (1.0 - (2.0 * x - 1.0).abs()))
+                })
+                .collect()
+        }
+        DemandPattern::Random { changes } => {
+            let mut times: Vec<f64> = (0..changes).map(|_| rng.uniform(0.0, horizon)).collect();
+            times.sort_by(f64::total_cmp);
+            std::iter::once(0.0)
+                .chain(times)
+                .map(|time| (time, rng.uniform(0.0, 4.0)))
+                .collect()
+        }
+    };
+    steps.push((horizon, 0.0));
+    steps.dedup_by(|b, a| b.0 <= a.0);
+    let points = steps
+        .into_iter()
+        .map(|(time, rate)| Point(time.into(), rate.into()));
+    PiecewiseConstant::new([-T::INFINITY, T::INFINITY], points)
+}
+
+fn synthetic_instance<T: Num + From<f64>>(
+    network: Network<T>,
+    commodities: Vec<(Vec<Vec<usize>>, PiecewiseConstant<T>)>,
+) -> SyntheticInstance<T> {
+    let path_inflows = commodities
+        .iter()
+        .map(|(paths, inflow)| {
+            let share = T::from(1.0 / paths.len() as f64);
+            let path_inflow = inflow * share;
+            vec![path_inflow; paths.len()]
+        })
+        .collect();
+    let commodities = commodities
+        .into_iter()
+        .map(|(paths, inflow)| Commodity { paths, inflow })
+        .collect();
+    SyntheticInstance {
+        instance: Instance::new(network, commodities),
+        path_inflows,
+    }
+}
+
+/// The cost of loading a `SyntheticInstance`, see `measure_loading`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadingMeasurement {
+    pub elapsed: Duration,
+    pub num_breakpoints: usize,
+    pub breakpoints_per_second: f64,
+    // An estimate of the heap usage of the loaded flow, see `DynamicFlow::stats`
+    pub heap_bytes: usize,
+}
+
+/// Loads the instance once and measures the time, the breakpoints of the flow and its memory.
+pub fn measure_loading<T: Num + Tolerance>(instance: &SyntheticInstance<T>) -> LoadingMeasurement {
+    let start = Instant::now();
+    let flow = instance.instance.load(&instance.path_inflows);
+    let elapsed = start.elapsed();
+    let num_breakpoints = flow.num_breakpoints();
+    LoadingMeasurement {
+        elapsed,
+        num_breakpoints,
+        breakpoints_per_second: num_breakpoints as f64 / elapsed.as_secs_f64(),
+        heap_bytes: flow.stats().heap_bytes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::F64;
+
+    use super::{grid_instance, measure_loading, random_instance, DemandPattern};
+
+    #[test]
+    fn it_generates_reproducible_instances() {
+        let demand = DemandPattern::Random { changes: 3 };
+        let grid = |seed| grid_instance::<F64>(4, 5, 6, 2, demand, 10.0, seed);
+        let (a, b) = (grid(7), grid(7));
+        assert_eq!(a.instance.network.num_edges(), 3 * 5 + 4 * 4);
+        assert_eq!(a.instance.network.capacity(), b.instance.network.capacity());
+        assert_eq!(a.path_inflows, b.path_inflows);
+        assert_ne!(
+            a.instance.network.capacity(),
+            grid(8).instance.network.capacity()
+        );
+
+        let random = random_instance::<F64>(20, 3, 5, 2, DemandPattern::Peak { steps: 4 }, 10.0, 1);
+        assert_eq!(random.instance.network.num_edges(), 19 * 3);
+        assert_eq!(random.instance.commodities.len(), 5);
+    }
+
+    #[test]
+    fn it_measures_the_loading() {
+        let instance = grid_instance::<F64>(3, 3, 4, 2, DemandPattern::Constant, 5.0, 0);
+        let measurement = measure_loading(&instance);
+        assert!(measurement.num_breakpoints > 0);
+        assert!(measurement.heap_bytes > 0);
+    }
+}
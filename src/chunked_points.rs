@@ -0,0 +1,258 @@
+use std::collections::VecDeque;
+
+use crate::{
+    num::Num,
+    point::{Point, PointStorage},
+};
+
+/// The number of points per chunk.
+const CHUNK_SIZE: usize = 4096;
+
+/// A sequence of points sorted by x-coordinate, stored in chunks of `CHUNK_SIZE` points.
+///
+/// An alternative to a single `Vec<Point<T>>` for functions with millions of breakpoints:
+/// appending never moves the existing points, so there are no reallocation spikes, and the points
+/// before some time are dropped chunk-wise without moving the remaining ones.
+/// Points are still found by binary search, first over the chunks and then within a chunk.
+/// As a `PointStorage`, e.g. in `PiecewiseLinear<T, ChunkedPoints<T>>`, it records long histories.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkedPoints<T: Num> {
+    chunks: VecDeque<Vec<Point<T>>>,
+    len: usize,
+    /// The first remaining point once points were dropped.
+    valid_from: Option<T>,
+}
+
+impl<T: Num> ChunkedPoints<T> {
+    pub fn new() -> Self {
+        ChunkedPoints {
+            chunks: VecDeque::new(),
+            len: 0,
+            valid_from: None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn push(&mut self, point: Point<T>) {
+        debug_assert!(
+            self.last().is_none_or(|last| last.0 < point.0),
+            "The points are not sorted by x-coordinate."
+        );
+        match self.chunks.back_mut() {
+            Some(chunk) if chunk.len() < CHUNK_SIZE => chunk.push(point),
+            _ => {
+                let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+                chunk.push(point);
+                self.chunks.push_back(chunk);
+            }
+        }
+        self.len += 1;
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Point<T>> {
+        // All chunks but the last are full.
+        self.chunks
+            .get(index / CHUNK_SIZE)
+            .and_then(|chunk| chunk.get(index % CHUNK_SIZE))
+    }
+
+    pub fn first(&self) -> Option<&Point<T>> {
+        self.chunks.front().and_then(|chunk| chunk.first())
+    }
+
+    pub fn last(&self) -> Option<&Point<T>> {
+        self.chunks.back().and_then(|chunk| chunk.last())
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut Point<T>> {
+        self.chunks.back_mut().and_then(|chunk| chunk.last_mut())
+    }
+
+    /// Like `binary_search` on a slice of the points: the index of the point at `at`, or the index
+    /// where a point at `at` would be inserted.
+    pub fn get_rnk(&self, at: T) -> Result<usize, usize> {
+        let chunk = self.chunks.partition_point(|chunk| chunk[0].0 <= at);
+        if chunk == 0 {
+            return Err(0);
+        }
+        let offset = (chunk - 1) * CHUNK_SIZE;
+        match self.chunks[chunk - 1].binary_search_by(|p| p.0.total_cmp(&at)) {
+            Ok(rnk) => Ok(offset + rnk),
+            Err(rnk) => Err(offset + rnk),
+        }
+    }
+
+    /// Drops all chunks that only contain points before the last point at or before `time`.
+    /// Hence, all values from `time` on can still be evaluated, but functions with these points
+    /// panic when evaluated before the first remaining point. Returns the number of dropped
+    /// points, by which the indices of the remaining points decrease.
+    pub fn truncate_before(&mut self, time: T) -> usize {
+        let mut dropped = 0;
+        while self.chunks.get(1).is_some_and(|next| next[0].0 <= time) {
+            dropped += self.chunks.pop_front().unwrap().len();
+        }
+        if dropped > 0 {
+            self.len -= dropped;
+            self.valid_from = Some(self.chunks[0][0].0);
+        }
+        dropped
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Point<T>> + '_ {
+        self.chunks.iter().flatten()
+    }
+}
+
+impl<T: Num> Default for ChunkedPoints<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Num> FromIterator<Point<T>> for ChunkedPoints<T> {
+    fn from_iter<I: IntoIterator<Item = Point<T>>>(iter: I) -> Self {
+        let mut points = ChunkedPoints::new();
+        for point in iter {
+            points.push(point);
+        }
+        points
+    }
+}
+
+impl<T: Num> PointStorage<T> for ChunkedPoints<T> {
+    fn from_points(points: impl IntoIterator<Item = Point<T>>) -> Self {
+        points.into_iter().collect()
+    }
+
+    #[inline]
+    fn num_points(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    fn point(&self, index: usize) -> &Point<T> {
+        self.get(index).unwrap()
+    }
+
+    #[inline]
+    fn last_point_mut(&mut self) -> &mut Point<T> {
+        self.last_mut().unwrap()
+    }
+
+    #[inline]
+    fn push_point(&mut self, point: Point<T>) {
+        self.push(point);
+    }
+
+    #[inline]
+    fn rank(&self, at: &T) -> Result<usize, usize> {
+        self.get_rnk(*at)
+    }
+
+    fn iter_points<'a>(&'a self) -> impl Iterator<Item = &'a Point<T>>
+    where
+        T: 'a,
+    {
+        self.iter()
+    }
+
+    #[inline]
+    fn valid_from(&self) -> Option<T> {
+        self.valid_from
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use smallvec::SmallVec;
+
+    use crate::{
+        float::F64, num::Num, piecewise_constant::PiecewiseConstant,
+        piecewise_linear::PiecewiseLinear, point::Point, tolerance::TolerancePolicy,
+    };
+
+    use super::{ChunkedPoints, CHUNK_SIZE};
+
+    #[test]
+    fn it_searches_and_truncates_chunks() {
+        let n = 3 * CHUNK_SIZE + 5;
+        let mut points: ChunkedPoints<F64> = (0..n)
+            .map(|i| Point((i as f64).into(), (2.0 * i as f64).into()))
+            .collect();
+        assert_eq!(points.len(), n);
+        assert_eq!(
+            points.get(CHUNK_SIZE + 1),
+            Some(&Point(4097.0.into(), 8194.0.into()))
+        );
+        assert_eq!(points.get_rnk(4097.0.into()), Ok(CHUNK_SIZE + 1));
+        assert_eq!(points.get_rnk(4096.5.into()), Err(CHUNK_SIZE + 1));
+        assert_eq!(points.get_rnk((-1.0).into()), Err(0));
+        assert_eq!(points.get_rnk((n as f64).into()), Err(n));
+
+        // The chunk containing the time is kept.
+        assert_eq!(
+            points.truncate_before((2.0 * CHUNK_SIZE as f64 - 1.0).into()),
+            CHUNK_SIZE
+        );
+        assert_eq!(points.len(), 2 * CHUNK_SIZE + 5);
+        assert_eq!(points.first(), Some(&Point(4096.0.into(), 8192.0.into())));
+        assert_eq!(points.get_rnk(4097.0.into()), Ok(1));
+    }
+
+    fn record_long_functions() -> (
+        PiecewiseLinear<F64, ChunkedPoints<F64>>,
+        PiecewiseConstant<F64, ChunkedPoints<F64>>,
+    ) {
+        let tol = TolerancePolicy::default();
+        let mut queue = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            vec![Point(0.0.into(), 0.0.into())],
+        )
+        .to_storage::<ChunkedPoints<F64>>();
+        let mut rate = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            vec![Point(0.0.into(), 0.0.into())],
+        )
+        .to_storage::<ChunkedPoints<F64>>();
+        for i in 0..2 * CHUNK_SIZE {
+            let slope = if i % 2 == 0 { 1.0 } else { -1.0 };
+            queue.extend_with_tolerance(&(i as f64).into(), slope.into(), &tol);
+            rate.extend_with_tolerance(&(i as f64).into(), &(1.0 + slope).into(), &tol);
+        }
+        (queue, rate)
+    }
+
+    #[test]
+    fn it_records_long_functions() {
+        let (mut queue, mut rate) = record_long_functions();
+        assert_eq!(queue.eval(100.5), 0.5);
+        assert_eq!(rate.eval(100.5), 2.0);
+        assert_eq!(rate.eval(101.0), 0.0);
+        assert_eq!(queue.to_storage::<Vec<_>>().eval(100.5), 0.5);
+        assert_eq!(rate.to_storage::<SmallVec<[_; 4]>>().eval(101.5), 0.0);
+
+        queue.truncate_before((1.5 * CHUNK_SIZE as f64).into());
+        rate.truncate_before((1.5 * CHUNK_SIZE as f64).into());
+        assert!(queue.points().len() <= CHUNK_SIZE + 1);
+        assert_eq!(queue.domain()[0], queue.points().first().unwrap().0);
+        assert_eq!(queue.eval(6000.5), 0.5);
+        assert_eq!(rate.eval(6000.5), 2.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "were dropped")]
+    fn it_rejects_evaluations_before_the_truncation() {
+        let (mut queue, _) = record_long_functions();
+        queue.truncate_before((1.5 * CHUNK_SIZE as f64).into());
+        queue.eval(100.5);
+    }
+}
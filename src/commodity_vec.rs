@@ -0,0 +1,520 @@
+use std::fmt::Debug;
+
+use itertools::Either;
+
+/// The largest number of commodities for which maps store their values densely by id.
+/// With more commodities, an edge typically carries only a few of them, so a slot per commodity
+/// would waste memory for every map of the edge.
+pub const MAX_DENSE_COMMODITIES: usize = 256;
+
+/// A map from commodity ids to values.
+///
+/// Commodity ids are small consecutive integers (paths and repeated edge visits), so a vector
+/// avoids the hashing and allocation costs of a `HashMap` in the hot loop of the loading.
+/// For networks with at most `MAX_DENSE_COMMODITIES` commodities, the values are stored densely
+/// by id, where commodities without a value occupy an empty slot. Otherwise, the entries are
+/// stored sparsely, sorted by id, so that the memory is proportional to the number of entries.
+/// The storage is selected by `with_capacity` and `reserve`; maps created by `new` are sparse.
+/// Iteration yields the present entries by ascending id.
+pub struct CommodityVec<T> {
+    storage: Storage<T>,
+    len: usize,
+}
+
+enum Storage<T> {
+    Dense(Vec<Option<T>>),
+    Sparse(Vec<(usize, T)>),
+}
+
+impl<T> CommodityVec<T> {
+    pub fn new() -> Self {
+        CommodityVec {
+            storage: Storage::Sparse(Vec::new()),
+            len: 0,
+        }
+    }
+
+    /// Creates an empty map for a network with `num_commodities` commodities, which holds the ids
+    /// `0..num_commodities` without reallocating if it is dense.
+    pub fn with_capacity(num_commodities: usize) -> Self {
+        let storage = if num_commodities <= MAX_DENSE_COMMODITIES {
+            Storage::Dense(Vec::with_capacity(num_commodities))
+        } else {
+            Storage::Sparse(Vec::new())
+        };
+        CommodityVec { storage, len: 0 }
+    }
+
+    /// Selects the storage for a network with `num_commodities` commodities and makes sure that
+    /// a dense map can hold the ids `0..num_commodities` without reallocating.
+    pub fn reserve(&mut self, num_commodities: usize) {
+        let dense = num_commodities <= MAX_DENSE_COMMODITIES;
+        match &mut self.storage {
+            Storage::Dense(slots) if dense => {
+                slots.reserve(num_commodities.saturating_sub(slots.len()))
+            }
+            Storage::Sparse(_) if !dense => {}
+            _ => {
+                self.convert(dense);
+                self.reserve(num_commodities);
+            }
+        }
+    }
+
+    /// Moves the entries into dense or sparse storage.
+    fn convert(&mut self, dense: bool) {
+        let entries = std::mem::replace(&mut self.storage, Storage::Sparse(Vec::new()));
+        let len = self.len;
+        self.storage = if dense {
+            Storage::Dense(Vec::new())
+        } else {
+            Storage::Sparse(Vec::with_capacity(len))
+        };
+        self.len = 0;
+        for (commodity, value) in (CommodityVec {
+            storage: entries,
+            len,
+        }) {
+            self.insert(commodity, value);
+        }
+    }
+
+    /// Returns whether the values are stored densely by id.
+    pub fn is_dense(&self) -> bool {
+        matches!(self.storage, Storage::Dense(_))
+    }
+
+    /// Returns the number of commodities with a value.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes allocated on the heap for the entries.
+    pub fn heap_size(&self) -> usize {
+        match &self.storage {
+            Storage::Dense(slots) => slots.capacity() * std::mem::size_of::<Option<T>>(),
+            Storage::Sparse(entries) => entries.capacity() * std::mem::size_of::<(usize, T)>(),
+        }
+    }
+
+    /// Returns the position of `commodity` in the sparse `entries`, or where it would be inserted.
+    #[inline]
+    fn search(entries: &[(usize, T)], commodity: usize) -> Result<usize, usize> {
+        // Entries are mostly inserted by ascending id, so check the end first.
+        match entries.last() {
+            Some(&(last, _)) if last < commodity => Err(entries.len()),
+            _ => entries.binary_search_by_key(&commodity, |&(c, _)| c),
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, commodity: usize) -> Option<&T> {
+        match &self.storage {
+            Storage::Dense(slots) => slots.get(commodity).and_then(Option::as_ref),
+            Storage::Sparse(entries) => {
+                Self::search(entries, commodity).ok().map(|i| &entries[i].1)
+            }
+        }
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, commodity: usize) -> Option<&mut T> {
+        match &mut self.storage {
+            Storage::Dense(slots) => slots.get_mut(commodity).and_then(Option::as_mut),
+            Storage::Sparse(entries) => Self::search(entries, commodity)
+                .ok()
+                .map(|i| &mut entries[i].1),
+        }
+    }
+
+    #[inline]
+    pub fn contains_key(&self, commodity: usize) -> bool {
+        self.get(commodity).is_some()
+    }
+
+    /// Sets the value of `commodity` and returns its previous value.
+    /// A dense map switches to sparse storage if the id exceeds `MAX_DENSE_COMMODITIES`.
+    #[inline]
+    pub fn insert(&mut self, commodity: usize, value: T) -> Option<T> {
+        let previous = match &mut self.storage {
+            Storage::Dense(slots) if commodity < MAX_DENSE_COMMODITIES => {
+                if commodity >= slots.len() {
+                    slots.resize_with(commodity + 1, || None);
+                }
+                slots[commodity].replace(value)
+            }
+            Storage::Dense(_) => {
+                self.convert(false);
+                return self.insert(commodity, value);
+            }
+            Storage::Sparse(entries) => match Self::search(entries, commodity) {
+                Ok(i) => Some(std::mem::replace(&mut entries[i].1, value)),
+                Err(i) => {
+                    entries.insert(i, (commodity, value));
+                    None
+                }
+            },
+        };
+        if previous.is_none() {
+            self.len += 1;
+        }
+        previous
+    }
+
+    #[inline]
+    pub fn remove(&mut self, commodity: usize) -> Option<T> {
+        let previous = match &mut self.storage {
+            Storage::Dense(slots) => slots.get_mut(commodity).and_then(Option::take),
+            Storage::Sparse(entries) => Self::search(entries, commodity)
+                .ok()
+                .map(|i| entries.remove(i).1),
+        };
+        if previous.is_some() {
+            self.len -= 1;
+        }
+        previous
+    }
+
+    /// Returns the value of `commodity`, inserting `default` first if there is none.
+    #[inline]
+    pub fn get_or_insert(&mut self, commodity: usize, default: T) -> &mut T {
+        if !self.contains_key(commodity) {
+            self.insert(commodity, default);
+        }
+        self.get_mut(commodity).unwrap()
+    }
+
+    /// Removes all entries, keeping the allocated memory and the storage.
+    pub fn clear(&mut self) {
+        match &mut self.storage {
+            Storage::Dense(slots) => slots.clear(),
+            Storage::Sparse(entries) => entries.clear(),
+        }
+        self.len = 0;
+    }
+
+    /// Removes all entries for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(usize, &mut T) -> bool) {
+        match &mut self.storage {
+            Storage::Dense(slots) => {
+                for (commodity, slot) in slots.iter_mut().enumerate() {
+                    if slot.as_mut().is_some_and(|value| !keep(commodity, value)) {
+                        *slot = None;
+                        self.len -= 1;
+                    }
+                }
+            }
+            Storage::Sparse(entries) => {
+                entries.retain_mut(|(commodity, value)| keep(*commodity, value));
+                self.len = entries.len();
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> + '_ {
+        match &self.storage {
+            Storage::Dense(slots) => Either::Left(
+                slots
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(commodity, slot)| slot.as_ref().map(|value| (commodity, value))),
+            ),
+            Storage::Sparse(entries) => {
+                Either::Right(entries.iter().map(|(commodity, value)| (*commodity, value)))
+            }
+        }
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> + '_ {
+        match &mut self.storage {
+            Storage::Dense(slots) => Either::Left(
+                slots
+                    .iter_mut()
+                    .enumerate()
+                    .filter_map(|(commodity, slot)| slot.as_mut().map(|value| (commodity, value))),
+            ),
+            Storage::Sparse(entries) => Either::Right(
+                entries
+                    .iter_mut()
+                    .map(|(commodity, value)| (*commodity, value)),
+            ),
+        }
+    }
+
+    /// Applies `f` to all values, keeping the storage.
+    pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> CommodityVec<U> {
+        let mut map = CommodityVec {
+            storage: Storage::Sparse(Vec::new()),
+            len: 0,
+        };
+        map.extend_mapped(self, f);
+        map
+    }
+
+    /// Sets the entries of the empty map `self` to `f` applied to the entries of `source`, using
+    /// the storage of `source`.
+    fn extend_mapped<S>(&mut self, source: &CommodityVec<S>, mut f: impl FnMut(&S) -> T) {
+        debug_assert!(self.is_empty());
+        match &source.storage {
+            Storage::Dense(source_slots) => {
+                if !self.is_dense() {
+                    self.storage = Storage::Dense(Vec::new());
+                }
+                let Storage::Dense(slots) = &mut self.storage else {
+                    unreachable!()
+                };
+                slots.extend(source_slots.iter().map(|slot| slot.as_ref().map(&mut f)));
+            }
+            Storage::Sparse(source_entries) => {
+                if self.is_dense() {
+                    self.storage = Storage::Sparse(Vec::new());
+                }
+                let Storage::Sparse(entries) = &mut self.storage else {
+                    unreachable!()
+                };
+                entries.extend(source_entries.iter().map(|(c, value)| (*c, f(value))));
+            }
+        }
+        self.len = source.len;
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().map(|(commodity, _)| commodity)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
+        self.iter_mut().map(|(_, value)| value)
+    }
+}
+
+impl<T: Clone> Clone for CommodityVec<T> {
+    fn clone(&self) -> Self {
+        self.map(T::clone)
+    }
+
+    /// Reuses the allocation of `self` if it has the storage of `source`.
+    fn clone_from(&mut self, source: &Self) {
+        self.clear();
+        self.extend_mapped(source, T::clone);
+    }
+}
+
+impl<T> Default for CommodityVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Two maps are equal if they have the same entries, regardless of their storage.
+impl<T: PartialEq> PartialEq for CommodityVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Debug> Debug for CommodityVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<T> FromIterator<(usize, T)> for CommodityVec<T> {
+    fn from_iter<I: IntoIterator<Item = (usize, T)>>(iter: I) -> Self {
+        let mut map = CommodityVec::new();
+        for (commodity, value) in iter {
+            map.insert(commodity, value);
+        }
+        map
+    }
+}
+
+impl<T, const N: usize> From<[(usize, T); N]> for CommodityVec<T> {
+    fn from(entries: [(usize, T); N]) -> Self {
+        entries.into_iter().collect()
+    }
+}
+
+impl<T> IntoIterator for CommodityVec<T> {
+    type Item = (usize, T);
+    type IntoIter = Either<
+        std::iter::FilterMap<
+            std::iter::Enumerate<std::vec::IntoIter<Option<T>>>,
+            fn((usize, Option<T>)) -> Option<(usize, T)>,
+        >,
+        std::vec::IntoIter<(usize, T)>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        match self.storage {
+            Storage::Dense(slots) => Either::Left(
+                slots
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(commodity, slot)| slot.map(|value| (commodity, value))),
+            ),
+            Storage::Sparse(entries) => Either::Right(entries.into_iter()),
+        }
+    }
+}
+
+/// The maximum number of maps kept for reuse.
+const MAX_POOLED: usize = 1024;
+
+/// Recycles the allocations of `CommodityVec`s that are created and dropped in every phase of
+/// the loading, such as the new inflow and the scaled outflow of an edge.
+#[derive(Debug)]
+pub struct CommodityVecPool<T> {
+    free: Vec<CommodityVec<T>>,
+    // The maximum number of maps kept for reuse
+    limit: usize,
+    allocated: usize,
+    reused: usize,
+}
+
+impl<T> Default for CommodityVecPool<T> {
+    fn default() -> Self {
+        Self::with_limit(MAX_POOLED)
+    }
+}
+
+impl<T> CommodityVecPool<T> {
+    /// Keeps at most `limit` maps for reuse. A limit of zero disables the pooling, so that every
+    /// map is newly allocated.
+    pub fn with_limit(limit: usize) -> Self {
+        CommodityVecPool {
+            free: Vec::new(),
+            limit,
+            allocated: 0,
+            reused: 0,
+        }
+    }
+
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Returns an empty map, reusing a recycled one if possible.
+    pub fn take(&mut self) -> CommodityVec<T> {
+        match self.free.pop() {
+            Some(map) => {
+                self.reused += 1;
+                map
+            }
+            None => {
+                self.allocated += 1;
+                CommodityVec::new()
+            }
+        }
+    }
+
+    /// Returns a copy of `source`, reusing a recycled map if possible.
+    pub fn take_copy(&mut self, source: &CommodityVec<T>) -> CommodityVec<T>
+    where
+        T: Clone,
+    {
+        let mut map = self.take();
+        map.clone_from(source);
+        map
+    }
+
+    /// Returns the map `f(source)`, reusing a recycled map if possible.
+    pub fn take_mapped<S>(
+        &mut self,
+        source: &CommodityVec<S>,
+        f: impl FnMut(&S) -> T,
+    ) -> CommodityVec<T> {
+        let mut map = self.take();
+        map.extend_mapped(source, f);
+        map
+    }
+
+    /// Keeps the allocation of `map` for a later `take`.
+    pub fn recycle(&mut self, mut map: CommodityVec<T>) {
+        if map.heap_size() > 0 && self.free.len() < self.limit {
+            map.clear();
+            self.free.push(map);
+        }
+    }
+
+    /// Returns how many maps were newly created and how many were reused.
+    pub fn stats(&self) -> (usize, usize) {
+        (self.allocated, self.reused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommodityVec, CommodityVecPool, MAX_DENSE_COMMODITIES};
+
+    #[test]
+    fn it_behaves_like_a_map() {
+        let mut map = CommodityVec::from([(3, 1.0), (1, 2.0)]);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(3), Some(&1.0));
+        assert_eq!(map.get(2), None);
+        assert_eq!(map.get(7), None);
+        assert_eq!(map.iter().collect::<Vec<_>>(), vec![(1, &2.0), (3, &1.0)]);
+
+        assert_eq!(map.insert(1, 4.0), Some(2.0));
+        *map.get_or_insert(5, 0.0) += 1.0;
+        assert_eq!(map.remove(3), Some(1.0));
+        assert_eq!(map.remove(3), None);
+        assert_eq!(map, CommodityVec::from([(1, 4.0), (5, 1.0)]));
+
+        map.retain(|_, value| *value > 2.0);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.map(|value| value / 2.0), CommodityVec::from([(1, 2.0)]));
+        assert_eq!(map.into_iter().collect::<Vec<_>>(), vec![(1, 4.0)]);
+        assert_eq!(CommodityVec::<f64>::with_capacity(4), CommodityVec::new());
+    }
+
+    #[test]
+    fn it_selects_the_storage_by_the_commodity_count() {
+        let mut dense = CommodityVec::with_capacity(4);
+        assert!(dense.is_dense());
+        dense.insert(2, 1.0);
+        dense.insert(MAX_DENSE_COMMODITIES, 2.0);
+        assert!(!dense.is_dense());
+        assert_eq!(
+            dense,
+            CommodityVec::from([(2, 1.0), (MAX_DENSE_COMMODITIES, 2.0)])
+        );
+
+        // A single entry of a large id does not allocate a slot for each smaller id.
+        let mut sparse = CommodityVec::with_capacity(100_000);
+        assert!(!sparse.is_dense());
+        sparse.insert(99_999, 1.0);
+        sparse.insert(7, 2.0);
+        assert!(sparse.heap_size() < 100 * std::mem::size_of::<(usize, f64)>());
+        assert_eq!(sparse.keys().collect::<Vec<_>>(), vec![7, 99_999]);
+
+        let mut map = CommodityVec::from([(3, 1.0), (1, 2.0)]);
+        map.reserve(8);
+        assert!(map.is_dense());
+        assert_eq!(map, CommodityVec::from([(1, 2.0), (3, 1.0)]));
+        map.reserve(MAX_DENSE_COMMODITIES + 1);
+        assert!(!map.is_dense());
+        assert_eq!(map.get(3), Some(&1.0));
+    }
+
+    #[test]
+    fn it_recycles_maps() {
+        let mut pool = CommodityVecPool::default();
+        let mut map = pool.take();
+        map.insert(2, 1.0);
+        let copy = pool.take_copy(&map);
+        pool.recycle(map);
+        let doubled = pool.take_mapped(&copy, |value| 2.0 * value);
+        assert_eq!(doubled, CommodityVec::from([(2, 2.0)]));
+        assert_eq!(pool.take(), CommodityVec::new());
+        assert_eq!(pool.stats(), (3, 1));
+    }
+}
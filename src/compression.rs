@@ -0,0 +1,99 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use flate2::{write::GzEncoder, Compression};
+
+/// The compression level of zstd, where 3 is the default of the zstd command line tool.
+const ZSTD_LEVEL: i32 = 3;
+
+/// A file writer that compresses the written data on the fly, depending on the extension of the
+/// file: `.gz` selects gzip, `.zst` selects zstd, and any other file is written uncompressed.
+/// Exported flows are very repetitive, so they typically shrink by an order of magnitude.
+///
+/// The writer must be finished with `finish`, which writes the end of the compressed stream.
+pub enum CompressedWriter {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl CompressedWriter {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(match path.extension().and_then(|e| e.to_str()) {
+            Some("gz") => CompressedWriter::Gzip(GzEncoder::new(file, Compression::default())),
+            Some("zst") => CompressedWriter::Zstd(zstd::Encoder::new(file, ZSTD_LEVEL)?),
+            _ => CompressedWriter::Plain(file),
+        })
+    }
+
+    /// Completes the compressed stream and flushes the file.
+    pub fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            CompressedWriter::Plain(file) => file,
+            CompressedWriter::Gzip(encoder) => encoder.finish()?,
+            CompressedWriter::Zstd(encoder) => encoder.finish()?,
+        };
+        file.flush()
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            CompressedWriter::Plain(file) => file.write(buf),
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            CompressedWriter::Plain(file) => file.flush(),
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        io::{Read, Write},
+    };
+
+    use flate2::read::GzDecoder;
+
+    use super::CompressedWriter;
+
+    #[test]
+    fn it_compresses_by_extension() {
+        let dir = env::temp_dir().join("dynamic-flows-rs-compression");
+        fs::create_dir_all(&dir).unwrap();
+        let content = "edge,time,value\n0,0,0\n".repeat(1000);
+        for name in ["flow.csv", "flow.csv.gz", "flow.csv.zst"] {
+            let mut writer = CompressedWriter::create(&dir.join(name)).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+            writer.finish().unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(dir.join("flow.csv")).unwrap(), content);
+        let gzip = fs::read(dir.join("flow.csv.gz")).unwrap();
+        assert!(gzip.len() * 10 < content.len());
+        let mut decompressed = String::new();
+        GzDecoder::new(gzip.as_slice())
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, content);
+        let zstd = fs::read(dir.join("flow.csv.zst")).unwrap();
+        assert!(zstd.len() * 10 < content.len());
+        assert_eq!(
+            zstd::decode_all(zstd.as_slice()).unwrap(),
+            content.as_bytes()
+        );
+    }
+}
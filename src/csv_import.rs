@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+
+use crate::{
+    format_error::{parse_token, FormatError},
+    instance::{Commodity, Instance},
+    network::Network,
+    num::Num,
+    piecewise_constant::PiecewiseConstant,
+    point::Point,
+};
+
+/// The rows of a CSV file without quoting, whose columns are identified by the header line.
+struct CsvTable<'a> {
+    columns: HashMap<&'a str, usize>,
+    // The fields of each non-empty row with its line number
+    rows: Vec<(usize, Vec<&'a str>)>,
+}
+
+impl<'a> CsvTable<'a> {
+    fn parse(content: &'a str, required: &[&str]) -> Result<Self, FormatError> {
+        let mut lines = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines
+            .next()
+            .ok_or_else(|| FormatError::Invalid(String::from("The header line is missing.")))?;
+        let columns: HashMap<&str, usize> = header
+            .split(',')
+            .enumerate()
+            .map(|(i, name)| (name.trim(), i))
+            .collect();
+        if let Some(missing) = required.iter().find(|c| !columns.contains_key(*c)) {
+            return Err(FormatError::Invalid(format!(
+                "The column {} is missing.",
+                missing
+            )));
+        }
+        let rows = lines
+            .map(|(number, line)| (number, line.split(',').map(str::trim).collect()))
+            .collect();
+        Ok(CsvTable { columns, rows })
+    }
+
+    fn field(&self, number: usize, row: &[&'a str], column: &str) -> Result<&'a str, FormatError> {
+        row.get(self.columns[column])
+            .copied()
+            .ok_or_else(|| FormatError::at_line(number, &format!("The {} is missing.", column)))
+    }
+}
+
+impl<T: Num + From<f64>> Instance<T> {
+    /// Reads an instance from two CSV files:
+    /// `edges` has the columns `tail,head,capacity,travel_time`, where the n-th row is edge n and
+    /// nodes are created up to the largest node id. As the edges use at most two nodes each, node
+    /// ids must be less than twice the number of edges.
+    /// `inflows` has the columns `path,time,rate`, where `path` lists the edges of a path separated
+    /// by spaces, and the network inflow rate of the path is `rate` from `time` until its next row.
+    /// Each path becomes a commodity of its own, in the order in which the paths first appear, so
+    /// the inflow of each commodity is also the inflow of its only path.
+    pub fn from_csv(edges: &str, inflows: &str) -> Result<Self, FormatError> {
+        let table = CsvTable::parse(edges, &["tail", "head", "capacity", "travel_time"])?;
+        let mut network = Network::new();
+        let max_nodes = 2 * table.rows.len();
+        for (number, row) in &table.rows {
+            let number = *number;
+            let tail: usize = parse_token(number, table.field(number, row, "tail")?)?;
+            let head: usize = parse_token(number, table.field(number, row, "head")?)?;
+            if tail >= max_nodes || head >= max_nodes {
+                return Err(FormatError::at_line(
+                    number,
+                    &format!("Node ids must be less than {}.", max_nodes),
+                ));
+            }
+            let capacity: f64 = parse_token(number, table.field(number, row, "capacity")?)?;
+            let travel_time: f64 = parse_token(number, table.field(number, row, "travel_time")?)?;
+            if capacity.is_nan() || capacity <= 0.0 {
+                return Err(FormatError::at_line(
+                    number,
+                    "The capacity must be positive.",
+                ));
+            }
+            if travel_time.is_nan() || travel_time < 0.0 {
+                return Err(FormatError::at_line(number, "The travel time is negative."));
+            }
+            network.add_edge(tail, head, capacity.into(), travel_time.into());
+        }
+
+        let table = CsvTable::parse(inflows, &["path", "time", "rate"])?;
+        let mut paths: Vec<Vec<usize>> = Vec::new();
+        let mut steps: Vec<Vec<(f64, f64)>> = Vec::new();
+        let mut path_by_name: HashMap<&str, usize> = HashMap::new();
+        for (number, row) in &table.rows {
+            let number = *number;
+            let name = table.field(number, row, "path")?;
+            let time: f64 = parse_token(number, table.field(number, row, "time")?)?;
+            let rate: f64 = parse_token(number, table.field(number, row, "rate")?)?;
+            if time.is_nan() || rate.is_nan() || rate < 0.0 {
+                return Err(FormatError::at_line(number, "Invalid time or rate."));
+            }
+            let path = match path_by_name.get(name) {
+                Some(&path) => path,
+                None => {
+                    let edges = name
+                        .split_whitespace()
+                        .map(|edge| parse_token(number, edge))
+                        .collect::<Result<Vec<usize>, FormatError>>()?;
+                    if edges.is_empty() || !network.is_path(&edges) {
+                        return Err(FormatError::at_line(
+                            number,
+                            &format!("`{}` is not a path.", name),
+                        ));
+                    }
+                    path_by_name.insert(name, paths.len());
+                    paths.push(edges);
+                    steps.push(Vec::new());
+                    paths.len() - 1
+                }
+            };
+            steps[path].push((time, rate));
+        }
+
+        let mut commodities = Vec::with_capacity(paths.len());
+        for (path, mut steps) in paths.into_iter().zip(steps) {
+            steps.sort_by(|a, b| a.0.total_cmp(&b.0));
+            if steps.windows(2).any(|w| w[0].0 == w[1].0) {
+                return Err(FormatError::Invalid(format!(
+                    "The path {:?} has several rates at the same time.",
+                    path
+                )));
+            }
+            let points = steps
+                .into_iter()
+                .map(|(time, rate)| Point(time.into(), rate.into()));
+            commodities.push(Commodity {
+                paths: vec![path],
+                inflow: PiecewiseConstant::new([-T::INFINITY, T::INFINITY], points),
+            });
+        }
+        Ok(Instance::new(network, commodities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{float::F64, format_error::FormatError, instance::Instance};
+
+    const EDGES: &str = "tail,head,capacity,travel_time
+0,1,1.0,1.0
+1,2,2.0,0.5
+0,2,1.0,3.0
+";
+
+    const INFLOWS: &str = "path,time,rate
+0 1,2.0,0.0
+2,0.0,1.5
+0 1,0.0,3.0
+";
+
+    #[test]
+    fn it_reads_csv_files() {
+        let instance: Instance<F64> = Instance::from_csv(EDGES, INFLOWS).unwrap();
+        assert_eq!(instance.network.num_nodes(), 3);
+        assert_eq!(instance.network.travel_time()[1], 0.5);
+        assert_eq!(instance.commodities.len(), 2);
+        assert_eq!(instance.commodities[0].paths, vec![vec![0, 1]]);
+        assert_eq!(instance.commodities[0].inflow.eval(1.0), 3.0);
+        assert_eq!(instance.commodities[0].inflow.eval(2.0), 0.0);
+        assert_eq!(instance.commodities[1].inflow.eval(5.0), 1.5);
+
+        let path_inflows: Vec<_> = instance
+            .commodities
+            .iter()
+            .map(|c| vec![c.inflow.clone()])
+            .collect();
+        let flow = instance.load(&path_inflows);
+        assert_eq!(flow.queues()[0].eval(2.0), 4.0);
+
+        let result: Result<Instance<F64>, _> =
+            Instance::from_csv(EDGES, &INFLOWS.replace("0 1", "1 0"));
+        assert!(matches!(result, Err(FormatError::Invalid(_))));
+        let result: Result<Instance<F64>, _> = Instance::from_csv("tail,head\n", INFLOWS);
+        assert!(result.is_err());
+        let result: Result<Instance<F64>, _> =
+            Instance::from_csv(&EDGES.replace("0,2,", "0,1000000000000,"), INFLOWS);
+        assert!(
+            matches!(result, Err(FormatError::Invalid(message)) if message.starts_with("Line 4:"))
+        );
+    }
+}
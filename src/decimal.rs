@@ -0,0 +1,440 @@
+use std::{
+    fmt::{Debug, Display},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+};
+
+use num_traits::{Num as NumTraitsNum, One, Signed, Zero};
+
+use crate::num::{Num, ToF64, Tolerance};
+
+/// A fixed-point number with `DIGITS` decimal places stored as a scaled `i64`.
+///
+/// Additions are exact and products and quotients are rounded to the nearest representable
+/// value, so results are deterministic and independent of the platform.
+/// The raw values `±(i64::MAX - 1)` represent ±∞. Like for `Rational128`, results out of range
+/// become `OVERFLOW` (the raw value `i64::MAX`), which is ordered above all other values and
+/// propagates through all operations, so that the overflow can be detected with `is_nan`.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Decimal(i64);
+
+const DIGITS: u32 = 6;
+const SCALE: i64 = 10i64.pow(DIGITS);
+const RAW_INFINITY: i64 = i64::MAX - 1;
+const RAW_OVERFLOW: i64 = i64::MAX;
+
+impl Decimal {
+    /// The result of an operation whose value is out of range.
+    pub const OVERFLOW: Decimal = Decimal(RAW_OVERFLOW);
+
+    /// Returns the number `raw / 10^DIGITS`.
+    pub const fn from_raw(raw: i64) -> Self {
+        Decimal(raw)
+    }
+
+    pub fn raw(self) -> i64 {
+        self.0
+    }
+
+    #[inline]
+    fn is_infinite(self) -> bool {
+        self.0 == RAW_INFINITY || self.0 == -RAW_INFINITY
+    }
+
+    /// Converts a wide intermediate result, which is `OVERFLOW` if it leaves the finite range.
+    #[inline]
+    fn from_wide(raw: i128) -> Self {
+        if raw.abs() < RAW_INFINITY as i128 {
+            Decimal(raw as i64)
+        } else {
+            Decimal::OVERFLOW
+        }
+    }
+}
+
+/// Divides and rounds half away from zero.
+#[inline]
+fn div_round(numer: i128, denom: i128) -> i128 {
+    let quotient = numer / denom;
+    let remainder = numer % denom;
+    if 2 * remainder.abs() >= denom.abs() {
+        quotient + numer.signum() * denom.signum()
+    } else {
+        quotient
+    }
+}
+
+impl From<i64> for Decimal {
+    #[inline]
+    fn from(val: i64) -> Self {
+        Decimal::from_wide(val as i128 * SCALE as i128)
+    }
+}
+
+impl From<f64> for Decimal {
+    #[inline]
+    fn from(val: f64) -> Self {
+        if val.is_nan() {
+            Decimal::OVERFLOW
+        } else if val == f64::INFINITY {
+            Decimal::INFINITY
+        } else if val == f64::NEG_INFINITY {
+            -Decimal::INFINITY
+        } else {
+            Decimal::from_wide((val * SCALE as f64).round() as i128)
+        }
+    }
+}
+
+impl Rem for Decimal {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        if self.is_nan() || rhs.is_nan() {
+            return Decimal::OVERFLOW;
+        }
+        debug_assert!(!self.is_infinite() && !rhs.is_infinite());
+        Decimal(self.0 % rhs.0)
+    }
+}
+
+impl Div for Decimal {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        if self.is_nan() || rhs.is_nan() {
+            return Decimal::OVERFLOW;
+        }
+        assert!(!rhs.is_zero(), "Division by zero.");
+        match (self.is_infinite(), rhs.is_infinite()) {
+            (false, false) => {
+                Decimal::from_wide(div_round(self.0 as i128 * SCALE as i128, rhs.0 as i128))
+            }
+            (false, true) => Decimal::ZERO,
+            (true, false) if rhs.is_positive() => self,
+            (true, false) => -self,
+            (true, true) => panic!("The quotient {} / {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Sub for Decimal {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for Decimal {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        if self.is_nan() || rhs.is_nan() {
+            Decimal::OVERFLOW
+        } else if !self.is_infinite() && !rhs.is_infinite() {
+            Decimal::from_wide(div_round(self.0 as i128 * rhs.0 as i128, SCALE as i128))
+        } else if self.is_zero() || rhs.is_zero() {
+            // We use the convention 0·∞ = 0.
+            Decimal::ZERO
+        } else if self.is_positive() == rhs.is_positive() {
+            Decimal::INFINITY
+        } else {
+            -Decimal::INFINITY
+        }
+    }
+}
+
+impl Add for Decimal {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self.is_infinite(), rhs.is_infinite()) {
+            _ if self.is_nan() || rhs.is_nan() => Decimal::OVERFLOW,
+            (false, false) => Decimal::from_wide(self.0 as i128 + rhs.0 as i128),
+            (false, true) => rhs,
+            (true, false) => self,
+            (true, true) if self == rhs => self,
+            (true, true) => panic!("The sum {} + {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Zero for Decimal {
+    #[inline]
+    fn zero() -> Self {
+        Decimal::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl One for Decimal {
+    #[inline]
+    fn one() -> Self {
+        Decimal::ONE
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_nan() {
+            return write!(f, "overflow");
+        }
+        if self.is_infinite() {
+            return write!(f, "{}inf", if self.0 < 0 { "-" } else { "" });
+        }
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let integer = (self.0 / SCALE).unsigned_abs();
+        let fraction = (self.0 % SCALE).unsigned_abs();
+        if fraction == 0 {
+            write!(f, "{}{}", sign, integer)
+        } else {
+            let digits = format!("{:0width$}", fraction, width = DIGITS as usize);
+            write!(f, "{}{}.{}", sign, integer, digits.trim_end_matches('0'))
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDecimalError;
+
+impl NumTraitsNum for Decimal {
+    type FromStrRadixErr = ParseDecimalError;
+
+    /// Parses numbers like `-12.5` with at most `DIGITS` decimal places.
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        if radix != 10 {
+            return Err(ParseDecimalError);
+        }
+        let (negative, digits) = match str.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, str),
+        };
+        let (integer, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+        if integer.is_empty() && fraction.is_empty() || fraction.len() > DIGITS as usize {
+            return Err(ParseDecimalError);
+        }
+        let parse = |s: &str| -> Result<i128, ParseDecimalError> {
+            if s.is_empty() {
+                Ok(0)
+            } else if s.bytes().all(|b| b.is_ascii_digit()) {
+                s.parse().map_err(|_| ParseDecimalError)
+            } else {
+                Err(ParseDecimalError)
+            }
+        };
+        let fraction_scale = 10i128.pow(DIGITS - fraction.len() as u32);
+        let fraction = parse(fraction)? * fraction_scale;
+        let raw = parse(integer)?
+            .checked_mul(SCALE as i128)
+            .and_then(|x| x.checked_add(fraction))
+            .filter(|&x| x < RAW_INFINITY as i128)
+            .ok_or(ParseDecimalError)?;
+        Ok(Decimal(if negative { -raw } else { raw } as i64))
+    }
+}
+
+impl Neg for Decimal {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        if self.is_nan() {
+            self
+        } else {
+            Decimal(-self.0)
+        }
+    }
+}
+
+impl std::iter::Sum for Decimal {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Decimal::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Signed for Decimal {
+    #[inline]
+    fn abs(&self) -> Self {
+        if self.is_nan() {
+            *self
+        } else {
+            Decimal(self.0.abs())
+        }
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Decimal::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        if self.is_nan() {
+            *self
+        } else {
+            Decimal(self.0.signum() * SCALE)
+        }
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl AddAssign for Decimal {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Decimal {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Decimal {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl RemAssign for Decimal {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl DivAssign for Decimal {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Num for Decimal {
+    const ZERO: Self = Decimal(0);
+    const ONE: Self = Decimal(SCALE);
+    const INFINITY: Self = Decimal(RAW_INFINITY);
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.cmp(other)
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.0 == RAW_OVERFLOW
+    }
+}
+
+impl Tolerance for Decimal {
+    const EXACT_ARITHMETIC: bool = false;
+    /// Products and quotients round to the last decimal place, so a few units of it are tolerated.
+    const TOL: Self = Decimal(10);
+}
+
+impl ToF64 for Decimal {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        if self.is_nan() {
+            f64::NAN
+        } else if self.is_infinite() {
+            self.0.signum() as f64 * f64::INFINITY
+        } else {
+            self.0 as f64 / SCALE as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::{Num as NumTraitsNum, Signed};
+
+    use crate::{
+        example_instances::{nested_merges, vickrey_bottleneck},
+        num::{Num, ToF64, Tolerance},
+        price_of_anarchy::total_travel_times,
+    };
+
+    use super::Decimal;
+
+    #[test]
+    fn it_rounds_deterministically() {
+        let third = Decimal::ONE / Decimal::from(3);
+        assert_eq!(third, Decimal::from_raw(333_333));
+        assert_eq!(
+            Decimal::from(2) / Decimal::from(3),
+            Decimal::from_raw(666_667)
+        );
+        assert_eq!(third * Decimal::from(3), Decimal::from_raw(999_999));
+        assert_eq!(Decimal::from(0.1) + Decimal::from(0.2), Decimal::from(0.3));
+        assert_eq!(-Decimal::INFINITY * Decimal::from(2), -Decimal::INFINITY);
+        assert_eq!(Decimal::INFINITY * Decimal::ZERO, Decimal::ZERO);
+        assert_eq!(
+            Decimal::from_str_radix("-12.05", 10),
+            Ok(Decimal::from(-12.05))
+        );
+        assert!(Decimal::from_str_radix("1.0000001", 10).is_err());
+        assert_eq!(Decimal::from(-12.05).to_string(), "-12.05");
+        assert_eq!(Decimal::from(-0.5).to_string(), "-0.5");
+    }
+
+    #[test]
+    fn it_reports_overflows() {
+        let big = Decimal::from(1e12);
+        assert_eq!(big * big, Decimal::OVERFLOW);
+        assert!((big * big + Decimal::INFINITY).is_nan());
+        assert!((Decimal::ZERO * (big * big)).is_nan());
+        assert!((-(big * big)).abs().is_nan());
+        assert!((big / Decimal::from(1e-6)).is_nan());
+        assert!(Decimal::from(1e20).is_nan());
+        assert!(Decimal::from(f64::NAN).is_nan());
+        assert!(Decimal::INFINITY < Decimal::OVERFLOW);
+        assert_eq!(Decimal::OVERFLOW.to_string(), "overflow");
+        assert!(Decimal::OVERFLOW.to_f64().is_nan());
+    }
+
+    #[test]
+    fn it_loads_networks() {
+        let example = nested_merges::<Decimal>();
+        let flow = example.instance.load(&example.path_inflows);
+        let total = total_travel_times(&example.instance, &flow).total;
+        assert!((total - example.total_travel_time).abs() <= Decimal::TOL);
+
+        let example = vickrey_bottleneck(
+            Decimal::from(3),
+            Decimal::from(0.5),
+            Decimal::ONE,
+            Decimal::from(0.25),
+        );
+        let flow = example.instance.load(&example.path_inflows);
+        let total = total_travel_times(&example.instance, &flow).total;
+        assert!((total - example.total_travel_time).abs() <= Decimal::TOL);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::{
+    dynamic_flow::DynamicFlow,
+    fixed_point::{to_step_function, IterationStats, StepSizeSchedule, UpdateRule},
+    network::Network,
+    network_loader::PathInflow,
+    num::{max, min, Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+};
+
+/// A commodity that sends a total `volume` along its `paths` and wants to arrive at the end of
+/// its paths within `desired_arrival`. The departure times are chosen by the model.
+#[derive(Debug, Clone)]
+pub struct DepartureTimeCommodity<T: Num> {
+    pub paths: Vec<Vec<usize>>,
+    pub volume: T,
+    pub desired_arrival: [T; 2],
+}
+
+/// The generalized cost `alpha·(travel time) + beta·(earliness) + gamma·(lateness)` of a trip,
+/// where earliness and lateness are measured against the desired arrival window.
+#[derive(Debug, Clone, Copy)]
+pub struct ScheduleDelayCost<T: Num> {
+    pub alpha: T,
+    pub beta: T,
+    pub gamma: T,
+}
+
+impl<T: Num> ScheduleDelayCost<T> {
+    pub fn cost(&self, departure: T, arrival: T, desired_arrival: [T; 2]) -> T {
+        let earliness = max(desired_arrival[0] - arrival, T::ZERO);
+        let lateness = max(arrival - desired_arrival[1], T::ZERO);
+        self.alpha * (arrival - departure) + self.beta * earliness + self.gamma * lateness
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DepartureTimeConfig<T: Num> {
+    /// Departures are distributed over intervals of length `interval_length` within this window.
+    pub departure_window: [T; 2],
+    pub interval_length: T,
+    pub cost: ScheduleDelayCost<T>,
+    pub max_iterations: usize,
+    /// The iteration stops as soon as the relative gap drops below this value.
+    pub target_relative_gap: T,
+    pub update_rule: UpdateRule<T>,
+}
+
+#[derive(Debug)]
+pub struct DepartureTimeResult<T: Num> {
+    /// path_inflows[i][j] is the departure rate on the j-th path of commodity i.
+    pub path_inflows: Vec<Vec<PiecewiseConstant<T>>>,
+    pub flow: DynamicFlow<T>,
+    pub stats: Vec<IterationStats<T>>,
+}
+
+/// Distributes the volume of each commodity over departure intervals and paths such that
+/// (approximately) no particle can decrease its schedule delay cost by switching.
+/// Each iteration loads the network, evaluates the cost of departing at the midpoint of each
+/// interval and moves the volumes towards the cheapest (interval, path) choice of each commodity.
+pub fn run_departure_time_choice<T: Num + Tolerance>(
+    network: &Network<T>,
+    commodities: &[DepartureTimeCommodity<T>],
+    config: &DepartureTimeConfig<T>,
+) -> DepartureTimeResult<T> {
+    let [window_start, window_end] = config.departure_window;
+    debug_assert!(
+        window_start >= T::ZERO,
+        "Departures must not happen before 0."
+    );
+    // Otherwise, the grid would be infinite.
+    assert!(
+        window_start < window_end && window_end < T::INFINITY,
+        "The departure window must be a finite interval."
+    );
+    assert!(config.interval_length > T::ZERO);
+    debug_assert!(config.max_iterations > 0);
+    debug_assert!(commodities
+        .iter()
+        .all(|c| !c.paths.is_empty() && c.paths.iter().all(|p| network.is_path(p))));
+
+    let grid: Vec<T> = {
+        let mut grid = Vec::new();
+        let mut time = window_start;
+        while time < window_end {
+            grid.push(time);
+            time += config.interval_length;
+        }
+        grid
+    };
+    // The last interval is cut off at the end of the departure window.
+    let lengths: Vec<T> = grid
+        .iter()
+        .map(|&start| min(start + config.interval_length, window_end) - start)
+        .collect();
+
+    // volumes[i][j][k] is the volume of commodity i departing on path j during the k-th interval
+    let mut volumes: Vec<Vec<Vec<T>>> = commodities
+        .iter()
+        .map(|c| {
+            let num_choices: T = c
+                .paths
+                .iter()
+                .flat_map(|_| grid.iter())
+                .map(|_| T::ONE)
+                .sum();
+            vec![vec![c.volume / num_choices; grid.len()]; c.paths.len()]
+        })
+        .collect();
+
+    let mut stats: Vec<IterationStats<T>> = Vec::with_capacity(config.max_iterations);
+    let mut step_sizes = StepSizeSchedule::new(config.update_rule);
+    loop {
+        let path_inflows: Vec<Vec<PiecewiseConstant<T>>> = volumes
+            .iter()
+            .map(|v| {
+                v.iter()
+                    .map(|volumes_p| {
+                        let rates: Vec<T> = volumes_p
+                            .iter()
+                            .zip(lengths.iter())
+                            .map(|(&x, &length)| x / length)
+                            .collect();
+                        to_step_function(&grid, &rates, window_end)
+                    })
+                    .collect()
+            })
+            .collect();
+        let flat_path_inflows: Vec<PathInflow<T>> = commodities
+            .iter()
+            .zip(path_inflows.iter())
+            .flat_map(|(c, inflows)| {
+                c.paths
+                    .iter()
+                    .zip(inflows.iter())
+                    .map(|(path, inflow)| PathInflow {
+                        path: path.as_slice(),
+                        inflow,
+                    })
+            })
+            .collect();
+        let flow = network.load(&flat_path_inflows);
+
+        let mut gap = T::ZERO;
+        let mut total = T::ZERO;
+        let mut best_response: Vec<Vec<Vec<T>>> = volumes
+            .iter()
+            .map(|v| vec![vec![T::ZERO; grid.len()]; v.len()])
+            .collect();
+        for (i, commodity) in commodities.iter().enumerate() {
+            let costs: Vec<Vec<T>> = commodity
+                .paths
+                .iter()
+                .map(|path| {
+                    grid.iter()
+                        .zip(lengths.iter())
+                        .map(|(&start, &length)| {
+                            let departure = start + length / (T::ONE + T::ONE);
+                            let arrival = flow.path_arrival_time(
+                                path,
+                                departure,
+                                network.inv_capacity(),
+                                network.travel_time(),
+                            );
+                            config
+                                .cost
+                                .cost(departure, arrival, commodity.desired_arrival)
+                        })
+                        .collect()
+                })
+                .collect();
+            let (best_path, best_interval, best_cost) = costs
+                .iter()
+                .enumerate()
+                .flat_map(|(j, c)| c.iter().enumerate().map(move |(k, &c)| (j, k, c)))
+                .min_by(|a, b| a.2.total_cmp(&b.2))
+                .unwrap();
+            best_response[i][best_path][best_interval] = commodity.volume;
+            for (volumes_p, costs_p) in volumes[i].iter().zip(costs.iter()) {
+                for (&x, &c) in volumes_p.iter().zip(costs_p.iter()) {
+                    gap += x * (c - best_cost);
+                }
+            }
+            total += commodity.volume * best_cost;
+        }
+        let relative_gap = if total > T::ZERO {
+            gap / total
+        } else {
+            T::ZERO
+        };
+
+        let step_size = step_sizes.next(gap);
+        stats.push(IterationStats {
+            iteration: stats.len(),
+            step_size,
+            gap,
+            relative_gap,
+        });
+
+        if relative_gap <= config.target_relative_gap || stats.len() >= config.max_iterations {
+            return DepartureTimeResult {
+                path_inflows,
+                flow,
+                stats,
+            };
+        }
+
+        for (volumes_i, best_response_i) in volumes.iter_mut().zip(best_response.iter()) {
+            for (volumes_p, best_p) in volumes_i.iter_mut().zip(best_response_i.iter()) {
+                for (x, &best) in volumes_p.iter_mut().zip(best_p.iter()) {
+                    *x += step_size * (best - *x);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::abs;
+
+    use crate::{
+        fixed_point::UpdateRule,
+        float::F64,
+        network::Network,
+        num::{Num, Tolerance},
+        piecewise_constant::PiecewiseConstant,
+    };
+
+    use super::{
+        run_departure_time_choice, DepartureTimeCommodity, DepartureTimeConfig, ScheduleDelayCost,
+    };
+
+    #[test]
+    fn it_computes_schedule_delay_costs() {
+        let cost: ScheduleDelayCost<F64> = ScheduleDelayCost {
+            alpha: 1.0.into(),
+            beta: 0.5.into(),
+            gamma: 2.0.into(),
+        };
+        let window = [4.0.into(), 5.0.into()];
+        assert_eq!(cost.cost(0.0.into(), 3.0.into(), window), 3.5);
+        assert_eq!(cost.cost(1.0.into(), 4.5.into(), window), 3.5);
+        assert_eq!(cost.cost(3.0.into(), 6.0.into(), window), 5.0);
+    }
+
+    #[test]
+    fn it_distributes_departures_around_a_bottleneck() {
+        let mut network = Network::new();
+        network.add_edge(0, 1, 1.0.into(), 1.0.into());
+        let commodities = vec![DepartureTimeCommodity {
+            paths: vec![vec![0]],
+            volume: 2.0.into(),
+            desired_arrival: [3.0.into(), 3.0.into()],
+        }];
+        let result = run_departure_time_choice(
+            &network,
+            &commodities,
+            &DepartureTimeConfig {
+                departure_window: [0.0.into(), 4.0.into()],
+                interval_length: 0.5.into(),
+                cost: ScheduleDelayCost {
+                    alpha: 1.0.into(),
+                    beta: 0.5.into(),
+                    gamma: 2.0.into(),
+                },
+                max_iterations: 30,
+                target_relative_gap: F64::ZERO,
+                update_rule: UpdateRule::Msa,
+            },
+        );
+        assert_eq!(result.stats.len(), 30);
+        assert!(result.stats[29].gap < result.stats[0].gap);
+
+        let departures: &PiecewiseConstant<F64> = &result.path_inflows[0][0];
+        let volume: F64 = departures
+            .points()
+            .iter()
+            .map(|p| p.1 * F64::from(0.5))
+            .sum();
+        assert!(abs(volume - F64::from(2.0)) < F64::TOL);
+        assert_eq!(departures.eval(4.0), 0.0);
+    }
+
+    #[test]
+    fn it_ends_the_departures_with_the_window() {
+        let mut network = Network::new();
+        network.add_edge(0, 1, 1.0.into(), 1.0.into());
+        let commodities = vec![DepartureTimeCommodity {
+            paths: vec![vec![0]],
+            volume: 1.0.into(),
+            desired_arrival: [0.0.into(), 10.0.into()],
+        }];
+        let result = run_departure_time_choice(
+            &network,
+            &commodities,
+            &DepartureTimeConfig {
+                departure_window: [0.0.into(), 1.0.into()],
+                interval_length: 0.75.into(),
+                cost: ScheduleDelayCost {
+                    alpha: 1.0.into(),
+                    beta: 0.5.into(),
+                    gamma: 2.0.into(),
+                },
+                max_iterations: 1,
+                target_relative_gap: F64::ZERO,
+                update_rule: UpdateRule::Msa,
+            },
+        );
+        // Half of the volume departs during [0, 0.75), the other half during [0.75, 1).
+        let departures: &PiecewiseConstant<F64> = &result.path_inflows[0][0];
+        assert_eq!(departures.eval(0.5), F64::from(0.5) / F64::from(0.75));
+        assert_eq!(departures.eval(0.9), 2.0);
+        assert_eq!(departures.eval(1.0), 0.0);
+    }
+}
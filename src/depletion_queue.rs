@@ -2,27 +2,29 @@ use std::{cmp::Reverse, collections::HashMap};
 
 use priority_queue::PriorityQueue;
 
-use crate::num::Num;
+use crate::{
+    commodity_vec::CommodityVec,
+    num::{Num, Total},
+};
 
 /// Describes a future change in the outflow of an edge.
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct ChangeEvent<T: Num> {
     pub time: T,
     pub value: ChangeEventValue<T>,
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct ChangeEventValue<T: Num> {
-    pub new_outflow_map: HashMap<usize, T>,
-    pub values_sum: T,
+    pub new_outflow_map: CommodityVec<T>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct DepletionQueue<T: Num> {
     /// Contains a priority queue of all edges whose queues will depete in the future -- sorted by the time of depletion.
-    depletions: PriorityQueue<usize, Reverse<T>>,
+    depletions: PriorityQueue<usize, Reverse<Total<T>>>,
     /// If the depletion of  the queue of an edge, results in a change in the outflow of that edge, then the time of that change is stored here.
-    change_times_after_a_depletion: PriorityQueue<usize, Reverse<T>>,
+    change_times_after_a_depletion: PriorityQueue<usize, Reverse<Total<T>>>,
     new_outflow: HashMap<usize, ChangeEventValue<T>>,
 }
 
@@ -35,6 +37,14 @@ impl<T: Num> DepletionQueue<T> {
         }
     }
 
+    /// Makes sure that `num_edges` edges can have pending depletions without reallocating.
+    pub fn reserve(&mut self, num_edges: usize) {
+        self.depletions
+            .reserve(num_edges.saturating_sub(self.depletions.len()));
+        self.change_times_after_a_depletion
+            .reserve(num_edges.saturating_sub(self.change_times_after_a_depletion.len()));
+    }
+
     pub fn set(
         &mut self,
         edge: usize,
@@ -42,12 +52,12 @@ impl<T: Num> DepletionQueue<T> {
         outflow_change_event: Option<ChangeEvent<T>>,
     ) {
         debug_assert!(depletion_time > -T::INFINITY);
-        self.depletions.push(edge, Reverse(depletion_time));
+        self.depletions.push(edge, Reverse(Total(depletion_time)));
 
         if let Some(change_event) = outflow_change_event {
             self.new_outflow.insert(edge, change_event.value);
             self.change_times_after_a_depletion
-                .push(edge, Reverse(change_event.time));
+                .push(edge, Reverse(Total(change_event.time)));
         } else if self.change_times_after_a_depletion.remove(&edge).is_some() {
             self.new_outflow.remove(&edge);
         }
@@ -60,30 +70,70 @@ impl<T: Num> DepletionQueue<T> {
     }
 
     pub fn pop_by_depletion(&mut self) -> Option<(usize, T, Option<ChangeEvent<T>>)> {
-        let (edge, Reverse(depletion_time)) = self.depletions.pop()?;
-
-        let change_event =
-            self.change_times_after_a_depletion
-                .remove(&edge)
-                .map(|(_, Reverse(change_time))| {
-                    let change_event_val = self.new_outflow.remove(&edge).unwrap();
-                    ChangeEvent {
-                        time: change_time,
-                        value: change_event_val,
-                    }
-                });
+        let (edge, Reverse(Total(depletion_time))) = self.depletions.pop()?;
+
+        let change_event = self.change_times_after_a_depletion.remove(&edge).map(
+            |(_, Reverse(Total(change_time)))| {
+                let change_event_val = self.new_outflow.remove(&edge).unwrap();
+                ChangeEvent {
+                    time: change_time,
+                    value: change_event_val,
+                }
+            },
+        );
         Some((edge, depletion_time, change_event))
     }
 
     pub fn min_depletion_time(&self) -> Option<&T> {
-        self.depletions.peek().map(|(_, Reverse(time))| time)
+        self.depletions.peek().map(|(_, Reverse(Total(time)))| time)
+    }
+
+    /// Returns the number of edges whose queue will deplete.
+    pub fn len(&self) -> usize {
+        self.depletions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.depletions.is_empty()
     }
 
     pub fn min_change_time(&self) -> Option<&T> {
-        return self
-            .change_times_after_a_depletion
+        self.change_times_after_a_depletion
             .peek()
-            .map(|(_, Reverse(time))| time);
+            .map(|(_, Reverse(Total(time)))| time)
+    }
+
+    pub fn map_num<U: Num>(&self, convert: impl Fn(T) -> U) -> DepletionQueue<U> {
+        let map_times = |queue: &PriorityQueue<usize, Reverse<Total<T>>>| {
+            queue
+                .iter()
+                .map(|(&edge, &Reverse(Total(time)))| (edge, Reverse(Total(convert(time)))))
+                .collect()
+        };
+        DepletionQueue {
+            depletions: map_times(&self.depletions),
+            change_times_after_a_depletion: map_times(&self.change_times_after_a_depletion),
+            new_outflow: self
+                .new_outflow
+                .iter()
+                .map(|(&edge, value)| {
+                    let value = ChangeEventValue {
+                        new_outflow_map: value
+                            .new_outflow_map
+                            .iter()
+                            .map(|(i, &rate)| (i, convert(rate)))
+                            .collect(),
+                    };
+                    (edge, value)
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T: Num> Default for DepletionQueue<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
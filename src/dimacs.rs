@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+
+use crate::{
+    format_error::{parse_token, FormatError},
+    network::Network,
+    num::Num,
+};
+
+/// A network read from a DIMACS `.gr` file together with the original ids of its nodes.
+/// The ids are remapped to nodes `0..num_nodes` in the order of their first appearance.
+#[derive(Debug, Clone)]
+pub struct DimacsNetwork<T: Num> {
+    pub network: Network<T>,
+    /// The DIMACS id of each node.
+    pub node_ids: Vec<u64>,
+    nodes_by_id: HashMap<u64, usize>,
+}
+
+impl<T: Num> DimacsNetwork<T> {
+    /// Returns the node with the given DIMACS id, if some arc uses it.
+    pub fn node(&self, id: u64) -> Option<usize> {
+        self.nodes_by_id.get(&id).copied()
+    }
+}
+
+/// Parses a DIMACS `.gr` file with lines
+/// ```text
+/// c <comment>
+/// p sp <nodes> <arcs>
+/// a <tail> <head> <travel time> [<capacity>]
+/// ```
+/// Arcs without a capacity column (like in the shortest path benchmarks) get `default_capacity`.
+/// Nodes are numbered by their first appearance in an arc, so ids need not be consecutive.
+pub fn parse_dimacs<T: Num + From<f64>>(
+    content: &str,
+    default_capacity: f64,
+) -> Result<DimacsNetwork<T>, FormatError> {
+    let mut network = Network::new();
+    let mut node_ids = Vec::new();
+    let mut nodes_by_id: HashMap<u64, usize> = HashMap::new();
+    let mut expected_arcs = None;
+    for (number, line) in content.lines().enumerate().map(|(i, l)| (i + 1, l)) {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first() {
+            None | Some(&"c") => {}
+            Some(&"p") => {
+                if tokens.len() != 4 {
+                    return Err(FormatError::at_line(
+                        number,
+                        "Expected `p <problem> <nodes> <arcs>`.",
+                    ));
+                }
+                expected_arcs = Some(parse_token::<usize>(number, tokens[3])?);
+            }
+            Some(&"a") => {
+                if !(4..=5).contains(&tokens.len()) {
+                    return Err(FormatError::at_line(
+                        number,
+                        "Expected `a <tail> <head> <travel time> [<capacity>]`.",
+                    ));
+                }
+                let mut node = |token: &str| -> Result<usize, FormatError> {
+                    let id: u64 = parse_token(number, token)?;
+                    Ok(*nodes_by_id.entry(id).or_insert_with(|| {
+                        node_ids.push(id);
+                        network.add_node()
+                    }))
+                };
+                let tail = node(tokens[1])?;
+                let head = node(tokens[2])?;
+                let travel_time: f64 = parse_token(number, tokens[3])?;
+                let capacity: f64 = match tokens.get(4) {
+                    Some(token) => parse_token(number, token)?,
+                    None => default_capacity,
+                };
+                if travel_time.is_nan() || travel_time < 0.0 {
+                    return Err(FormatError::at_line(number, "The travel time is negative."));
+                }
+                if capacity.is_nan() || capacity <= 0.0 {
+                    return Err(FormatError::at_line(
+                        number,
+                        "The capacity must be positive.",
+                    ));
+                }
+                network.add_edge(tail, head, capacity.into(), travel_time.into());
+            }
+            Some(_) => return Err(FormatError::at_line(number, "Unknown line type.")),
+        }
+    }
+    match expected_arcs {
+        Some(arcs) if arcs == network.num_edges() => Ok(DimacsNetwork {
+            network,
+            node_ids,
+            nodes_by_id,
+        }),
+        Some(arcs) => Err(FormatError::Invalid(format!(
+            "Expected {} arcs, but found {}.",
+            arcs,
+            network.num_edges()
+        ))),
+        None => Err(FormatError::Invalid(String::from(
+            "The problem line `p` is missing.",
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{float::F64, format_error::FormatError};
+
+    use super::parse_dimacs;
+
+    #[test]
+    fn it_remaps_node_ids() {
+        let content = "c A small graph
+p sp 3 3
+a 10 42 2.5
+a 42 7 1 3.0
+a 10 7 6
+";
+        let dimacs = parse_dimacs::<F64>(content, 2.0).unwrap();
+        let network = &dimacs.network;
+        assert_eq!(network.num_nodes(), 3);
+        assert_eq!(dimacs.node_ids, vec![10, 42, 7]);
+        assert_eq!(dimacs.node(7), Some(2));
+        assert_eq!(dimacs.node(8), None);
+        assert_eq!(
+            network.capacity(),
+            &[F64::from(2.0), 3.0.into(), 2.0.into()]
+        );
+        assert_eq!(network.travel_time()[1], 1.0);
+        assert!(network.is_path(&[0, 1]));
+
+        let missing_arc = content.replace("a 10 7 6\n", "");
+        assert!(matches!(
+            parse_dimacs::<F64>(&missing_arc, 2.0),
+            Err(FormatError::Invalid(_))
+        ));
+        let zero_capacity = content.replace("1 3.0", "1 0");
+        assert!(parse_dimacs::<F64>(&zero_capacity, 2.0).is_err());
+    }
+}
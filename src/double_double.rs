@@ -0,0 +1,373 @@
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+};
+
+use num_traits::{Num as NumTraitsNum, One, Signed, Zero};
+use ordered_float::OrderedFloat;
+
+use crate::num::{Num, ToF64, Tolerance};
+
+/// An unevaluated sum `hi + lo` of two floats with about 106 bits of precision.
+///
+/// This is a middle ground between the speed of `F64` and the exactness of `Rational128`: It is
+/// several times slower than `F64`, but keeps depletion times stable on long horizons with
+/// tiny rates, where `F64` loses most of its digits to cancellation.
+/// Values are normalized, i.e. `|lo|` is at most half an ulp of `hi`, such that they can be
+/// compared lexicographically. Infinite and NaN values have `lo = 0`.
+#[derive(Copy, Clone, Debug)]
+pub struct DoubleDouble {
+    hi: f64,
+    lo: f64,
+}
+
+impl DoubleDouble {
+    pub fn hi(self) -> f64 {
+        self.hi
+    }
+
+    pub fn lo(self) -> f64 {
+        self.lo
+    }
+
+    #[inline]
+    fn normalized(hi: f64, lo: f64) -> Self {
+        if hi.is_finite() {
+            let (hi, lo) = quick_two_sum(hi, lo);
+            DoubleDouble { hi, lo }
+        } else {
+            hi.into()
+        }
+    }
+
+    #[inline]
+    fn key(self) -> (OrderedFloat<f64>, OrderedFloat<f64>) {
+        (OrderedFloat(self.hi), OrderedFloat(self.lo))
+    }
+}
+
+/// Returns `a + b` and its exact rounding error.
+#[inline]
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    (sum, (a - (sum - b_virtual)) + (b - b_virtual))
+}
+
+/// Like `two_sum`, but requires `|a| ≥ |b|`.
+#[inline]
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    (sum, b - (sum - a))
+}
+
+/// Returns `a * b` and its exact rounding error.
+#[inline]
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
+
+impl From<f64> for DoubleDouble {
+    #[inline]
+    fn from(val: f64) -> Self {
+        DoubleDouble { hi: val, lo: 0.0 }
+    }
+}
+
+impl Rem for DoubleDouble {
+    type Output = Self;
+
+    /// The remainder is only computed for the leading components.
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        (self.hi % rhs.hi).into()
+    }
+}
+
+impl Div for DoubleDouble {
+    type Output = Self;
+
+    /// Refines the quotient of the leading components by two correction steps.
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        let q1 = self.hi / rhs.hi;
+        if !q1.is_finite() || !rhs.hi.is_finite() || q1 == 0.0 {
+            return q1.into();
+        }
+        let r = self - rhs * q1.into();
+        let q2 = r.hi / rhs.hi;
+        let r = r - rhs * q2.into();
+        let q3 = r.hi / rhs.hi;
+        DoubleDouble::normalized(q1, q2) + q3.into()
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        let (p1, p2) = two_prod(self.hi, rhs.hi);
+        if !p1.is_finite() {
+            return p1.into();
+        }
+        DoubleDouble::normalized(p1, p2 + (self.hi * rhs.lo + self.lo * rhs.hi))
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        let (s1, s2) = two_sum(self.hi, rhs.hi);
+        if !s1.is_finite() {
+            return s1.into();
+        }
+        let (t1, t2) = two_sum(self.lo, rhs.lo);
+        let (s1, s2) = quick_two_sum(s1, s2 + t1);
+        DoubleDouble::normalized(s1, s2 + t2)
+    }
+}
+
+impl Zero for DoubleDouble {
+    #[inline]
+    fn zero() -> Self {
+        DoubleDouble::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.hi == 0.0
+    }
+}
+
+impl One for DoubleDouble {
+    #[inline]
+    fn one() -> Self {
+        DoubleDouble::ONE
+    }
+}
+
+impl PartialEq for DoubleDouble {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl PartialOrd for DoubleDouble {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.total_cmp(other))
+    }
+}
+
+impl Hash for DoubleDouble {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state)
+    }
+}
+
+impl NumTraitsNum for DoubleDouble {
+    type FromStrRadixErr = <f64 as NumTraitsNum>::FromStrRadixErr;
+
+    /// Parses the value as a float, i.e. with the precision of `F64` only.
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        f64::from_str_radix(str, radix).map(DoubleDouble::from)
+    }
+}
+
+impl Display for DoubleDouble {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.hi, f)
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl std::iter::Sum for DoubleDouble {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(DoubleDouble::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Signed for DoubleDouble {
+    #[inline]
+    fn abs(&self) -> Self {
+        if self.hi < 0.0 {
+            -*self
+        } else {
+            *self
+        }
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            DoubleDouble::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        if self.hi == 0.0 {
+            DoubleDouble::ZERO
+        } else {
+            self.hi.signum().into()
+        }
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.hi > 0.0
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.hi < 0.0
+    }
+}
+
+impl AddAssign for DoubleDouble {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for DoubleDouble {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for DoubleDouble {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl RemAssign for DoubleDouble {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl DivAssign for DoubleDouble {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Num for DoubleDouble {
+    const ZERO: Self = DoubleDouble { hi: 0., lo: 0. };
+    const ONE: Self = DoubleDouble { hi: 1., lo: 0. };
+    const INFINITY: Self = DoubleDouble {
+        hi: f64::INFINITY,
+        lo: 0.,
+    };
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.key().cmp(&other.key())
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.hi.is_nan()
+    }
+}
+
+impl Tolerance for DoubleDouble {
+    const EXACT_ARITHMETIC: bool = false;
+    const TOL: Self = DoubleDouble { hi: 1e-20, lo: 0. };
+}
+
+impl ToF64 for DoubleDouble {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::Signed;
+
+    use crate::{
+        example_instances::vickrey_bottleneck,
+        num::{Num, ToF64},
+        price_of_anarchy::total_travel_times,
+    };
+
+    use super::DoubleDouble;
+
+    #[test]
+    fn it_computes_with_double_precision() {
+        let third = DoubleDouble::ONE / DoubleDouble::from(3.0);
+        assert!(third.lo() != 0.0);
+        assert_eq!(third * DoubleDouble::from(3.0), DoubleDouble::ONE);
+        let tenth = DoubleDouble::ONE / DoubleDouble::from(10.0);
+        let sum: DoubleDouble = (0..10).map(|_| tenth).sum();
+        assert!((sum - DoubleDouble::ONE).abs() < DoubleDouble::from(1e-30));
+        // The float sum of 1e16 and 1 loses the 1.
+        let big = DoubleDouble::from(1e16) + DoubleDouble::ONE;
+        assert_eq!(big - DoubleDouble::from(1e16), DoubleDouble::ONE);
+        assert_eq!(DoubleDouble::INFINITY - tenth, DoubleDouble::INFINITY);
+        assert!(-DoubleDouble::INFINITY < tenth && tenth < third);
+        assert_eq!(
+            DoubleDouble::ONE / DoubleDouble::INFINITY,
+            DoubleDouble::ZERO
+        );
+    }
+
+    #[test]
+    fn it_loads_networks_accurately() {
+        let third = DoubleDouble::ONE / DoubleDouble::from(3.0);
+        let example = vickrey_bottleneck(
+            DoubleDouble::from(3.0),
+            third + third,
+            DoubleDouble::ONE,
+            third,
+        );
+        let flow = example.instance.load(&example.path_inflows);
+        let total = total_travel_times(&example.instance, &flow).total;
+        assert!((total - example.total_travel_time).abs() < DoubleDouble::from(1e-25));
+        assert_eq!(
+            flow.queues()[0].eval(DoubleDouble::from(10.0)).to_f64(),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+};
+
+use num_traits::{Num as NumTraitsNum, One, Signed, Zero};
+
+use crate::num::{Num, ToF64, Tolerance};
+
+/// A value computed simultaneously in a `primary` backend (e.g. `F64`) and a `reference` backend
+/// (e.g. `Rational128`).
+///
+/// Comparisons only consider the primary value, so a computation takes the same branches as it
+/// would in the primary backend alone. After every arithmetic operation, the divergence between
+/// both values is measured, and the operation with the largest divergence so far is recorded for
+/// the current thread (see `max_drift`).
+#[derive(Copy, Clone, Debug)]
+pub struct Dual<P: Num + ToF64, R: Num + ToF64> {
+    pub primary: P,
+    pub reference: R,
+}
+
+/// The operation that produced the largest divergence between the primary and reference values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Drift {
+    pub operation: &'static str,
+    pub primary: f64,
+    pub reference: f64,
+    /// The absolute difference for values of magnitude at most one, the relative difference else.
+    pub divergence: f64,
+}
+
+thread_local! {
+    static MAX_DRIFT: RefCell<Option<Drift>> = const { RefCell::new(None) };
+}
+
+/// Returns the largest drift observed on this thread since the last `reset_drift`.
+pub fn max_drift() -> Option<Drift> {
+    MAX_DRIFT.with(|drift| drift.borrow().clone())
+}
+
+pub fn reset_drift() {
+    MAX_DRIFT.with(|drift| *drift.borrow_mut() = None);
+}
+
+fn divergence(primary: f64, reference: f64) -> f64 {
+    if primary == reference {
+        0.0
+    } else {
+        (primary - reference).abs() / reference.abs().max(1.0)
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> Dual<P, R> {
+    pub fn new(primary: P, reference: R) -> Self {
+        Dual { primary, reference }
+    }
+
+    pub fn divergence(self) -> f64 {
+        divergence(self.primary.to_f64(), self.reference.to_f64())
+    }
+
+    #[inline]
+    fn tracked(self, operation: &'static str) -> Self {
+        let divergence = self.divergence();
+        if divergence > 0.0 {
+            MAX_DRIFT.with(|drift| {
+                let mut drift = drift.borrow_mut();
+                if drift.as_ref().is_none_or(|d| divergence > d.divergence) {
+                    *drift = Some(Drift {
+                        operation,
+                        primary: self.primary.to_f64(),
+                        reference: self.reference.to_f64(),
+                        divergence,
+                    });
+                }
+            });
+        }
+        self
+    }
+}
+
+impl<P: Num + ToF64 + From<f64>, R: Num + ToF64 + From<f64>> From<f64> for Dual<P, R> {
+    #[inline]
+    fn from(val: f64) -> Self {
+        Dual::new(val.into(), val.into())
+    }
+}
+
+macro_rules! dual_binary_op {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl<P: Num + ToF64, R: Num + ToF64> $trait for Dual<P, R> {
+            type Output = Self;
+
+            #[inline]
+            fn $method(self, rhs: Self) -> Self::Output {
+                Dual::new(
+                    self.primary.$method(rhs.primary),
+                    self.reference.$method(rhs.reference),
+                )
+                .tracked(stringify!($method))
+            }
+        }
+
+        impl<P: Num + ToF64, R: Num + ToF64> $assign_trait for Dual<P, R> {
+            #[inline]
+            fn $assign_method(&mut self, rhs: Self) {
+                *self = (*self).$method(rhs);
+            }
+        }
+    };
+}
+
+dual_binary_op!(Add, add, AddAssign, add_assign);
+dual_binary_op!(Sub, sub, SubAssign, sub_assign);
+dual_binary_op!(Mul, mul, MulAssign, mul_assign);
+dual_binary_op!(Div, div, DivAssign, div_assign);
+dual_binary_op!(Rem, rem, RemAssign, rem_assign);
+
+impl<P: Num + ToF64, R: Num + ToF64> Zero for Dual<P, R> {
+    #[inline]
+    fn zero() -> Self {
+        Self::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.primary.is_zero()
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> One for Dual<P, R> {
+    #[inline]
+    fn one() -> Self {
+        Self::ONE
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> PartialEq for Dual<P, R> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.primary == other.primary
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> PartialOrd for Dual<P, R> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.primary.partial_cmp(&other.primary)
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> Hash for Dual<P, R> {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.primary.hash(state)
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseDualError;
+
+impl<P: Num + ToF64, R: Num + ToF64> NumTraitsNum for Dual<P, R> {
+    type FromStrRadixErr = ParseDualError;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        match (P::from_str_radix(str, radix), R::from_str_radix(str, radix)) {
+            (Ok(primary), Ok(reference)) => Ok(Dual::new(primary, reference)),
+            _ => Err(ParseDualError),
+        }
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> Display for Dual<P, R> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.primary, f)
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> Neg for Dual<P, R> {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Dual::new(-self.primary, -self.reference)
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> std::iter::Sum for Dual<P, R> {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> Signed for Dual<P, R> {
+    #[inline]
+    fn abs(&self) -> Self {
+        Dual::new(self.primary.abs(), self.reference.abs())
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Self::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        Dual::new(self.primary.signum(), self.reference.signum())
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.primary.is_positive()
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.primary.is_negative()
+    }
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> Num for Dual<P, R> {
+    const ZERO: Self = Dual {
+        primary: P::ZERO,
+        reference: R::ZERO,
+    };
+    const ONE: Self = Dual {
+        primary: P::ONE,
+        reference: R::ONE,
+    };
+    const INFINITY: Self = Dual {
+        primary: P::INFINITY,
+        reference: R::INFINITY,
+    };
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        self.primary.total_cmp(&other.primary)
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.primary.is_nan()
+    }
+}
+
+impl<P: Tolerance + ToF64, R: Tolerance + ToF64> Tolerance for Dual<P, R> {
+    const EXACT_ARITHMETIC: bool = P::EXACT_ARITHMETIC;
+    const TOL: Self = Dual {
+        primary: P::TOL,
+        reference: R::TOL,
+    };
+}
+
+impl<P: Num + ToF64, R: Num + ToF64> ToF64 for Dual<P, R> {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.primary.to_f64()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::vickrey_bottleneck, float::F64, num::Num,
+        price_of_anarchy::total_travel_times, rational::Rational128,
+    };
+
+    use super::{max_drift, reset_drift, Dual};
+
+    type Checked = Dual<F64, Rational128>;
+
+    #[test]
+    fn it_records_the_largest_drift() {
+        reset_drift();
+        let tenth = Checked::new(0.1.into(), Rational128::new(1, 10));
+        let sum: Checked = (0..10).map(|_| tenth).fold(Checked::ZERO, |a, b| a + b);
+        assert_eq!(sum.reference, Rational128::ONE);
+        let drift = max_drift().unwrap();
+        assert_eq!(drift.operation, "add");
+        assert!(drift.divergence > 0.0 && drift.divergence < 1e-15);
+
+        reset_drift();
+        let exact = Checked::from(0.5) * Checked::from(4.0);
+        assert_eq!(exact.reference, Rational128::from(2));
+        assert_eq!(max_drift(), None);
+    }
+
+    #[test]
+    fn it_detects_the_drift_of_a_loading() {
+        reset_drift();
+        let third = Checked::new((1.0 / 3.0).into(), Rational128::new(1, 3));
+        let example = vickrey_bottleneck(Checked::from(3.0), third + third, Checked::ONE, third);
+        let flow = example.instance.load(&example.path_inflows);
+        let total = total_travel_times(&example.instance, &flow).total;
+        assert!(total.divergence() < 1e-12);
+        // The depletion of the queue leaves a float residual of a few 1e-9 that is exactly zero.
+        let drift = max_drift().unwrap();
+        assert_eq!(drift.reference, 0.0);
+        assert!(drift.divergence < 1e-6);
+    }
+}
@@ -1,95 +1,310 @@
 use std::{
-    cmp::{max, min, Reverse},
     collections::{HashMap, HashSet, VecDeque},
-    hash::Hash,
-    iter,
+    sync::Arc,
 };
 
 use num_traits::abs;
-use priority_queue::PriorityQueue;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    commodity_vec::{CommodityVec, CommodityVecPool},
     depletion_queue::{ChangeEvent, ChangeEventValue, DepletionQueue},
-    num::{Num, Sum},
+    flow_diff::FlowDiff,
+    num::{max, min, Num, Sum, Tolerance},
+    outflow_change_queue::OutflowChangeQueue,
     piecewise_constant::PiecewiseConstant,
-    piecewise_linear::PiecewiseLinear,
+    piecewise_linear::{PiecewiseLinear, PiecewiseLinearCursor},
+    point::Point,
     points,
+    profiling::PointCounts,
+    tolerance::TolerancePolicy,
+    validation::{check_non_negative, check_not_nan},
 };
 
+#[cfg(feature = "profiling")]
+use crate::profiling::ProfilingCounters;
+
 #[derive(Clone, Debug)]
 struct FlowRatesCollectionItem<T: Num> {
     time: T,
-    values: HashMap<usize, T>,
+    values: CommodityVec<T>,
+}
+
+/// The commodity under which a `FlowRatesCollection` with `RateStorage::Aggregate` records the
+/// total rate of all commodities.
+pub const AGGREGATE_COMMODITY: usize = usize::MAX;
+
+/// Which rate functions a `FlowRatesCollection` records.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RateStorage {
+    /// A rate function per commodity.
+    #[default]
+    PerCommodity,
+    /// Only the total rate of all commodities, under the commodity `AGGREGATE_COMMODITY`.
+    /// The rates of the individual commodities are only kept until they are no longer needed for
+    /// building the flow, which saves most of the memory if only the congestion of edges matters.
+    Aggregate,
 }
 
 #[derive(Clone, Debug)]
 pub struct FlowRatesCollection<T: Num> {
     function_by_comm: HashMap<usize, PiecewiseConstant<T>>,
-    accumulative: PiecewiseLinear<T>,
     queue: VecDeque<FlowRatesCollectionItem<T>>,
+    storage: RateStorage,
 }
 impl<T: Num> FlowRatesCollection<T> {
     fn new() -> Self {
         FlowRatesCollection {
             function_by_comm: HashMap::new(),
-            accumulative: PiecewiseLinear::new(
-                [-T::INFINITY, T::INFINITY],
-                T::ZERO,
-                T::ZERO,
-                points!((T::ZERO, T::ZERO)),
-            ),
             queue: VecDeque::new(),
+            storage: RateStorage::PerCommodity,
         }
     }
 
-    pub fn get_values_at_time(&mut self, time: T) -> Option<&HashMap<usize, T>> {
+    pub fn storage(&self) -> RateStorage {
+        self.storage
+    }
+
+    pub fn get_values_at_time(&mut self, time: T) -> Option<&CommodityVec<T>> {
+        self.values_at_time(time, &mut CommodityVecPool::default())
+    }
+
+    /// Like `get_values_at_time`, but keeps the items before `time`, so that the rates can be read
+    /// from a shared snapshot.
+    pub fn peek_values_at_time(&self, time: T) -> Option<&CommodityVec<T>> {
         match self.queue.front() {
             None => None,
             Some(item) => {
                 if item.time > time {
                     panic!("The desired time is not available anymore.")
                 } else {
-                    while self.queue.get(1).is_some_and(|next| next.time <= time) {
-                        self.queue.pop_front();
-                    }
-                    return Some(&self.queue.front().unwrap().values);
+                    let index = self.queue.partition_point(|item| item.time <= time);
+                    Some(&self.queue[index - 1].values)
+                }
+            }
+        }
+    }
+
+    /// Like `get_values_at_time`, but hands the maps of the dropped items to `pool`.
+    fn values_at_time(
+        &mut self,
+        time: T,
+        pool: &mut CommodityVecPool<T>,
+    ) -> Option<&CommodityVec<T>> {
+        match self.queue.front() {
+            None => None,
+            Some(item) => {
+                if item.time > time {
+                    panic!("The desired time is not available anymore.")
+                } else {
+                    self.drop_values_before(time, pool);
+                    Some(&self.queue.front().unwrap().values)
                 }
             }
         }
     }
 
+    /// Drops the items superseded by later items at or before `time` and hands their maps to
+    /// `pool`.
+    fn drop_values_before(&mut self, time: T, pool: &mut CommodityVecPool<T>) {
+        while self.queue.get(1).is_some_and(|next| next.time <= time) {
+            pool.recycle(self.queue.pop_front().unwrap().values);
+        }
+    }
+
     pub fn function_by_comm(&self) -> &HashMap<usize, PiecewiseConstant<T>> {
         &self.function_by_comm
     }
 
-    fn extend(&mut self, from_time: T, values_map: HashMap<usize, T>, values_sum: T) {
+    /// Returns the cumulative flow of all commodities, i.e. the integral of their total rate.
+    /// It is computed on demand from the rates of the commodities, starting at zero at the
+    /// beginning of the (possibly truncated) history.
+    pub fn accumulative(&self) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        let integrals: Vec<PiecewiseLinear<T>> = self
+            .function_by_comm
+            .values()
+            .map(|f| f.integral(T::ZERO))
+            .collect();
+        PiecewiseLinear::sum(&integrals)
+    }
+
+    /// Returns the number of breakpoints of the rate functions of all commodities.
+    fn num_points(&self) -> usize {
+        self.function_by_comm
+            .values()
+            .map(|f| f.points().len())
+            .sum()
+    }
+
+    /// Estimates the bytes allocated on the heap for the rate functions and pending rate maps.
+    fn heap_size(&self) -> usize {
+        let functions: usize = self.function_by_comm.values().map(|f| f.heap_size()).sum();
+        let items: usize = self.queue.iter().map(|item| item.values.heap_size()).sum();
+        functions
+            + items
+            + self.function_by_comm.capacity()
+                * std::mem::size_of::<(usize, PiecewiseConstant<T>)>()
+            + self.queue.capacity() * std::mem::size_of::<FlowRatesCollectionItem<T>>()
+    }
+
+    /// Adds the rates of each commodity `from` to those of `into[from]` and drops `from`.
+    fn merge_commodities(&mut self, into: &HashMap<usize, usize>)
+    where
+        T: Tolerance,
+    {
+        for (from, &to) in into {
+            let Some(f) = self.function_by_comm.remove(from) else {
+                continue;
+            };
+            match self.function_by_comm.get_mut(&to) {
+                Some(g) => *g = &*g + &f,
+                None => {
+                    self.function_by_comm.insert(to, f);
+                }
+            }
+        }
+        for item in self.queue.iter_mut() {
+            for (&from, &to) in into {
+                if let Some(value) = item.values.remove(from) {
+                    *item.values.get_or_insert(to, T::ZERO) += value;
+                }
+            }
+        }
+    }
+
+    /// Renames each commodity `i` to `to[i]`, where `to` maps distinct commodities to distinct
+    /// ones. The aggregate commodity keeps its name.
+    fn rename_commodities(&mut self, to: &[usize]) {
+        let rename = |i: usize| match i {
+            AGGREGATE_COMMODITY => i,
+            _ => to[i],
+        };
+        self.function_by_comm = std::mem::take(&mut self.function_by_comm)
+            .into_iter()
+            .map(|(i, f)| (rename(i), f))
+            .collect();
+        for item in self.queue.iter_mut() {
+            item.values = std::mem::take(&mut item.values)
+                .into_iter()
+                .map(|(i, value)| (rename(i), value))
+                .collect();
+        }
+    }
+
+    /// Drops the rates before `before_time`, see `DynamicFlow::truncate_history`.
+    fn truncate_history(&mut self, before_time: T, pool: &mut CommodityVecPool<T>) {
+        while self
+            .queue
+            .get(1)
+            .is_some_and(|next| next.time <= before_time)
+        {
+            pool.recycle(self.queue.pop_front().unwrap().values);
+        }
+        for f in self.function_by_comm.values_mut() {
+            if f.domain()[0] < before_time {
+                *f = f.restrict(before_time, f.domain()[1]);
+            }
+        }
+    }
+
+    /// Returns the rates of each commodity before `before_time`.
+    fn history(&self, before_time: T) -> HashMap<usize, PiecewiseConstant<T>> {
+        self.function_by_comm
+            .iter()
+            .filter(|(_, f)| f.domain()[0] < before_time)
+            .map(|(&i, f)| (i, f.restrict(f.domain()[0], before_time)))
+            .collect()
+    }
+
+    /// Creates the rates of a completely built flow from its rate functions by commodity, which
+    /// are aggregate if they consist of the commodity `AGGREGATE_COMMODITY` only.
+    pub fn from_functions(function_by_comm: HashMap<usize, PiecewiseConstant<T>>) -> Self {
+        let storage =
+            if function_by_comm.len() == 1 && function_by_comm.contains_key(&AGGREGATE_COMMODITY) {
+                RateStorage::Aggregate
+            } else {
+                RateStorage::PerCommodity
+            };
+        FlowRatesCollection {
+            function_by_comm,
+            queue: VecDeque::new(),
+            storage,
+        }
+    }
+
+    fn map_num<U: Num>(&self, convert: impl Fn(T) -> U) -> FlowRatesCollection<U> {
+        FlowRatesCollection {
+            function_by_comm: self
+                .function_by_comm
+                .iter()
+                .map(|(&i, f)| (i, f.map_num(&convert)))
+                .collect(),
+            queue: self
+                .queue
+                .iter()
+                .map(|item| FlowRatesCollectionItem {
+                    time: convert(item.time),
+                    values: item
+                        .values
+                        .iter()
+                        .map(|(i, &value)| (i, convert(value)))
+                        .collect(),
+                })
+                .collect(),
+            storage: self.storage,
+        }
+    }
+
+    fn extend(&mut self, from_time: T, values_map: CommodityVec<T>, tol: &TolerancePolicy<T>) {
+        if self.storage == RateStorage::Aggregate {
+            let total = values_map
+                .values()
+                .fold(T::ZERO, |total, &value| total + value);
+            self.function_by_comm
+                .entry(AGGREGATE_COMMODITY)
+                .or_insert_with(|| {
+                    PiecewiseConstant::new([T::ZERO, T::INFINITY], points![(T::ZERO, T::ZERO)])
+                })
+                .extend_with_tolerance(&from_time, &total, tol);
+            self.queue.push_back(FlowRatesCollectionItem {
+                time: from_time,
+                values: values_map,
+            });
+            return;
+        }
         match self.queue.back() {
             None => {
                 for (i, value) in values_map.iter() {
                     let mut new_fn =
                         PiecewiseConstant::new([T::ZERO, T::INFINITY], points![(T::ZERO, T::ZERO)]);
-                    new_fn.extend(&from_time, value);
-                    let res = self.function_by_comm.insert(*i, new_fn);
+                    new_fn.extend_with_tolerance(&from_time, value, tol);
+                    let res = self.function_by_comm.insert(i, new_fn);
                     assert!(res.is_none());
                 }
             }
             Some(back) => {
-                debug_assert!(back.time <= from_time + T::TOL);
-                for (&i, value) in values_map.iter() {
+                debug_assert!(back.time <= from_time + tol.tolerance(from_time));
+                for (i, value) in values_map.iter() {
                     self.function_by_comm
                         .entry(i)
-                        .or_insert(PiecewiseConstant::new(
-                            [T::ZERO, T::INFINITY],
-                            points![(T::ZERO, T::ZERO)],
-                        ))
-                        .extend(&from_time, value);
+                        .or_insert_with(|| {
+                            PiecewiseConstant::new(
+                                [T::ZERO, T::INFINITY],
+                                points![(T::ZERO, T::ZERO)],
+                            )
+                        })
+                        .extend_with_tolerance(&from_time, value, tol);
                 }
-                for &i in back.values.keys() {
-                    if !values_map.contains_key(&i) {
+                for i in back.values.keys() {
+                    if !values_map.contains_key(i) {
                         self.function_by_comm
                             .get_mut(&i)
                             .unwrap()
-                            .extend(&from_time, &T::ZERO);
+                            .extend_with_tolerance(&from_time, &T::ZERO, tol);
                     }
                 }
             }
@@ -98,19 +313,100 @@ impl<T: Num> FlowRatesCollection<T> {
             time: from_time,
             values: values_map,
         });
-        self.accumulative.extend(&from_time, values_sum);
     }
 }
 
-/// A PreprocessedOutflowChange described the change of the outflow of an edge.
-/// The time at which this change happens is at most T_e(built_until).
-/// The outflow rate function of edge has already been extended by this change.
-/// Hence, we only keep the time at which the change happens, in order to keep track
-/// of edges that change in the future.
-#[derive(Hash, PartialEq, Eq, Debug)]
-struct PreprocessedOutflowChange<T: Num> {
-    edge: usize,
-    change_time: T,
+/// The expected sizes of the functions of a flow, used to reserve memory upfront instead of
+/// growing the maps and vectors while the flow is built, see `DynamicFlow::reserve`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CapacityHints {
+    // breakpoints[e] is the expected number of breakpoints of the queue of e
+    pub breakpoints: Vec<usize>,
+    // commodities[e] is the expected number of commodities using e
+    pub commodities: Vec<usize>,
+}
+
+impl CapacityHints {
+    /// Expects the same sizes on all edges.
+    pub fn uniform(
+        num_edges: usize,
+        breakpoints_per_edge: usize,
+        commodities_per_edge: usize,
+    ) -> Self {
+        CapacityHints {
+            breakpoints: vec![breakpoints_per_edge; num_edges],
+            commodities: vec![commodities_per_edge; num_edges],
+        }
+    }
+}
+
+/// The part of a flow before `before_time` that was dropped by `DynamicFlow::drain_history`.
+#[derive(Clone, Debug)]
+pub struct FlowHistory<T: Num> {
+    pub before_time: T,
+    // inflow[e][i] is the function fᵢₑ⁺ up to before_time
+    pub inflow: Vec<HashMap<usize, PiecewiseConstant<T>>>,
+    // outflow[e][i] is the function fᵢₑ⁻ up to before_time
+    pub outflow: Vec<HashMap<usize, PiecewiseConstant<T>>>,
+    // queues[e] is the queue length at e up to before_time
+    pub queues: Vec<PiecewiseLinear<T>>,
+}
+
+/// A time interval `[from, to]` to which a flow can be restricted before it is exported, see
+/// `DynamicFlow::restrict`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TimeWindow<T> {
+    pub from: T,
+    pub to: T,
+}
+
+impl<T: Num> TimeWindow<T> {
+    /// Returns the part of the window within `domain`, which is empty if they do not intersect.
+    fn clamp(&self, domain: [T; 2]) -> [T; 2] {
+        let from = min(max(self.from, domain[0]), domain[1]);
+        [from, min(max(self.to, from), domain[1])]
+    }
+}
+
+/// The number of functions listed in `FlowStats::largest_functions`.
+const LARGEST_FUNCTIONS: usize = 10;
+
+/// Where the breakpoints and the memory of a flow go, see `DynamicFlow::stats`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlowStats {
+    // breakpoints[e] is the number of breakpoints of all inflow, outflow and queue functions of e
+    pub breakpoints: Vec<usize>,
+    // An estimate of the bytes allocated on the heap for the functions and rate maps
+    pub heap_bytes: usize,
+    pub pending_outflow_changes: usize,
+    pub pending_depletions: usize,
+    // The functions with the most breakpoints, largest first
+    pub largest_functions: Vec<FunctionStats>,
+}
+
+/// Identifies a function of a flow in `FlowStats`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowFunction {
+    Inflow { edge: usize, commodity: usize },
+    Outflow { edge: usize, commodity: usize },
+    Queue { edge: usize },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FunctionStats {
+    pub function: FlowFunction,
+    pub breakpoints: usize,
+}
+
+/// A queue whose length at its computed depletion time deviates from zero by more than the
+/// tolerance, i.e. a sign that the numeric backend is not precise enough for the instance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToleranceViolation<T: Num> {
+    pub edge: usize,
+    pub time: T,
+    /// The queue length at `time`.
+    pub residual: T,
+    pub tolerance: T,
 }
 
 #[derive(Debug)]
@@ -118,108 +414,622 @@ pub struct DynamicFlow<T: Num> {
     built_until: T,
 
     // inflow[e][i] is the function fᵢₑ⁺
-    inflow: Vec<FlowRatesCollection<T>>,
+    // The functions of an edge are shared with clones of the flow until either is modified.
+    inflow: Vec<Arc<FlowRatesCollection<T>>>,
     // outflow[e][i] is the function fᵢₑ⁻
-    outflow: Vec<FlowRatesCollection<T>>,
+    outflow: Vec<Arc<FlowRatesCollection<T>>>,
     // queues[e] is the queue length at e
-    queues: Vec<PiecewiseLinear<T>>,
-    // A priority queue with times when some edge outflow changes up to (and including) the current arrivel time of the edge
-    outflow_changes: PriorityQueue<PreprocessedOutflowChange<T>, Reverse<T>>,
+    queues: Vec<Arc<PiecewiseLinear<T>>>,
+    // queue_cursors[e] remembers where queues[e] was last evaluated at built_until
+    queue_cursors: Vec<PiecewiseLinearCursor>,
+    // The times when some edge outflow changes up to (and including) the current arrival time of the edge
+    outflow_changes: OutflowChangeQueue<T>,
     // A priority queue with events at which queues deplete
     depletions: DepletionQueue<T>,
+    // Decides when times, rates and queue lengths are considered equal
+    tolerance: TolerancePolicy<T>,
+    // Which rate functions the inflow and outflow of the edges record
+    rate_storage: RateStorage,
+    // The depletions that were not precise by tolerance
+    tolerance_violations: Vec<ToleranceViolation<T>>,
+    // Recycles the rate maps of past phases
+    rate_pool: CommodityVecPool<T>,
+    #[cfg(feature = "profiling")]
+    counters: ProfilingCounters,
+}
+
+/// Cloning is cheap: the clone shares the functions of all edges with `self`, and an edge is only
+/// copied once either flow modifies it. This allows to try alternative continuations of a flow.
+impl<T: Num> Clone for DynamicFlow<T> {
+    fn clone(&self) -> Self {
+        DynamicFlow {
+            built_until: self.built_until,
+            inflow: self.inflow.clone(),
+            outflow: self.outflow.clone(),
+            queues: self.queues.clone(),
+            queue_cursors: self.queue_cursors.clone(),
+            outflow_changes: self.outflow_changes.clone(),
+            depletions: self.depletions.clone(),
+            tolerance: self.tolerance,
+            rate_storage: self.rate_storage,
+            tolerance_violations: self.tolerance_violations.clone(),
+            rate_pool: CommodityVecPool::with_limit(self.rate_pool.limit()),
+            #[cfg(feature = "profiling")]
+            counters: self.counters.clone(),
+        }
+    }
 }
 
 impl<T: Num> DynamicFlow<T> {
-    pub fn new(num_edges: usize) -> Self {
+    pub fn new(num_edges: usize) -> Self
+    where
+        T: Tolerance,
+    {
         DynamicFlow {
             built_until: T::ZERO,
-            inflow: vec![FlowRatesCollection::new(); num_edges],
-            outflow: vec![FlowRatesCollection::new(); num_edges],
-            queues: vec![
-                PiecewiseLinear::new(
-                    [-T::INFINITY, T::INFINITY],
-                    T::ZERO,
-                    T::ZERO,
-                    points!((T::ZERO, T::ZERO)),
-                );
-                num_edges
-            ],
-            outflow_changes: PriorityQueue::new(),
+            inflow: (0..num_edges)
+                .map(|_| Arc::new(FlowRatesCollection::new()))
+                .collect(),
+            outflow: (0..num_edges)
+                .map(|_| Arc::new(FlowRatesCollection::new()))
+                .collect(),
+            queues: (0..num_edges)
+                .map(|_| {
+                    Arc::new(PiecewiseLinear::new(
+                        [-T::INFINITY, T::INFINITY],
+                        T::ZERO,
+                        T::ZERO,
+                        points!((T::ZERO, T::ZERO)),
+                    ))
+                })
+                .collect(),
+            queue_cursors: vec![PiecewiseLinearCursor::new(); num_edges],
+            outflow_changes: OutflowChangeQueue::new(),
             depletions: DepletionQueue::new(),
+            tolerance: TolerancePolicy::default(),
+            rate_storage: RateStorage::PerCommodity,
+            tolerance_violations: Vec::new(),
+            rate_pool: CommodityVecPool::default(),
+            #[cfg(feature = "profiling")]
+            counters: ProfilingCounters::default(),
+        }
+    }
+
+    /// Replaces the default tolerance policy used when extending the flow.
+    pub fn with_tolerance(mut self, tolerance: TolerancePolicy<T>) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Drops the short-lived rate maps of each phase instead of recycling them, e.g. to measure
+    /// the effect of the rate pool.
+    pub fn without_rate_pooling(mut self) -> Self {
+        self.rate_pool = CommodityVecPool::with_limit(0);
+        self
+    }
+
+    /// Replaces which rate functions the edges record, see `RateStorage`.
+    /// Must be set before the flow is extended.
+    pub fn with_rate_storage(mut self, storage: RateStorage) -> Self {
+        debug_assert!(
+            self.built_until == T::ZERO && self.outflow_changes.is_empty(),
+            "The rate storage cannot be changed once the flow is built."
+        );
+        self.rate_storage = storage;
+        for rates in self.inflow.iter_mut().chain(self.outflow.iter_mut()) {
+            Arc::make_mut(rates).storage = storage;
+        }
+        self
+    }
+
+    pub fn rate_storage(&self) -> RateStorage {
+        self.rate_storage
+    }
+
+    pub fn tolerance(&self) -> &TolerancePolicy<T> {
+        &self.tolerance
+    }
+
+    /// Reserves the memory for the expected sizes of the functions and of the event queues.
+    pub fn reserve(&mut self, hints: &CapacityHints) {
+        debug_assert_eq!(hints.breakpoints.len(), self.queues.len());
+        debug_assert_eq!(hints.commodities.len(), self.queues.len());
+        for (edge, &commodities) in hints.commodities.iter().enumerate() {
+            let commodities = match self.rate_storage {
+                RateStorage::PerCommodity => commodities,
+                RateStorage::Aggregate => 1,
+            };
+            for rates in [&mut self.inflow[edge], &mut self.outflow[edge]] {
+                Arc::make_mut(rates).function_by_comm.reserve(commodities);
+            }
+            Arc::make_mut(&mut self.queues[edge]).reserve(hints.breakpoints[edge]);
         }
+        self.outflow_changes.reserve(self.queues.len());
+        self.depletions.reserve(self.queues.len());
+    }
+
+    /// Returns the depletions at which the queue length deviated from zero beyond the tolerance.
+    pub fn tolerance_violations(&self) -> &[ToleranceViolation<T>] {
+        &self.tolerance_violations
     }
 
     pub fn built_until(&self) -> T {
         self.built_until
     }
 
-    pub fn inflow(&self) -> &Vec<FlowRatesCollection<T>> {
+    pub fn inflow(&self) -> &Vec<Arc<FlowRatesCollection<T>>> {
         &self.inflow
     }
 
-    pub fn outflow(&self) -> &Vec<FlowRatesCollection<T>> {
+    pub fn outflow(&self) -> &Vec<Arc<FlowRatesCollection<T>>> {
         &self.outflow
     }
 
-    pub fn outflow_at_built_until(&mut self, edge: usize) -> Option<&HashMap<usize, T>> {
-        self.outflow[edge].get_values_at_time(self.built_until)
+    pub fn inflow_at_built_until(&self, edge: usize) -> Option<&CommodityVec<T>> {
+        self.inflow[edge].peek_values_at_time(self.built_until)
+    }
+
+    pub fn outflow_at_built_until(&self, edge: usize) -> Option<&CommodityVec<T>> {
+        self.outflow[edge].peek_values_at_time(self.built_until)
+    }
+
+    /// Returns a copy of the inflow of `edge` at `built_until` in a map of the rate pool.
+    pub fn copy_inflow_at_built_until(&mut self, edge: usize) -> CommodityVec<T> {
+        match self.inflow[edge].peek_values_at_time(self.built_until) {
+            Some(values) => self.rate_pool.take_copy(values),
+            None => self.rate_pool.take(),
+        }
+    }
+
+    /// Returns a copy of the outflow of `edge` at `built_until` in a map of the rate pool.
+    pub fn copy_outflow_at_built_until(&mut self, edge: usize) -> CommodityVec<T> {
+        match self.outflow[edge].peek_values_at_time(self.built_until) {
+            Some(values) => self.rate_pool.take_copy(values),
+            None => self.rate_pool.take(),
+        }
+    }
+
+    /// Returns a map that is no longer needed to the rate pool for reuse in later phases.
+    pub fn recycle(&mut self, map: CommodityVec<T>) {
+        self.rate_pool.recycle(map);
+    }
+
+    /// Returns how many rate maps the flow allocated and how many it reused.
+    pub fn rate_pool_stats(&self) -> (usize, usize) {
+        self.rate_pool.stats()
     }
 
-    pub fn queues(&self) -> &Vec<PiecewiseLinear<T>> {
+    /// Returns the work done so far, e.g. to see whether the event queues or the piecewise
+    /// functions dominate the loading of an instance.
+    #[cfg(feature = "profiling")]
+    pub fn counters(&self) -> ProfilingCounters {
+        let (maps_allocated, maps_reused) = self.rate_pool.stats();
+        ProfilingCounters {
+            maps_allocated,
+            maps_reused,
+            ..self.counters.clone()
+        }
+    }
+
+    pub fn queues(&self) -> &Vec<Arc<PiecewiseLinear<T>>> {
         &self.queues
     }
 
+    /// Reports the breakpoints per edge, an estimate of the heap usage, the number of pending
+    /// events and the largest functions, e.g. to decide how to simplify or truncate the flow.
+    pub fn stats(&self) -> FlowStats {
+        let mut functions: Vec<FunctionStats> = Vec::new();
+        for edge in 0..self.queues.len() {
+            let rates = |collection: &FlowRatesCollection<T>| {
+                collection
+                    .function_by_comm
+                    .iter()
+                    .map(|(&commodity, f)| (commodity, f.points().len()))
+                    .collect::<Vec<_>>()
+            };
+            for (commodity, breakpoints) in rates(&self.inflow[edge]) {
+                let function = FlowFunction::Inflow { edge, commodity };
+                functions.push(FunctionStats {
+                    function,
+                    breakpoints,
+                });
+            }
+            for (commodity, breakpoints) in rates(&self.outflow[edge]) {
+                let function = FlowFunction::Outflow { edge, commodity };
+                functions.push(FunctionStats {
+                    function,
+                    breakpoints,
+                });
+            }
+            functions.push(FunctionStats {
+                function: FlowFunction::Queue { edge },
+                breakpoints: self.queues[edge].points().len(),
+            });
+        }
+
+        let mut breakpoints = vec![0; self.queues.len()];
+        for f in &functions {
+            let edge = match f.function {
+                FlowFunction::Inflow { edge, .. }
+                | FlowFunction::Outflow { edge, .. }
+                | FlowFunction::Queue { edge } => edge,
+            };
+            breakpoints[edge] += f.breakpoints;
+        }
+
+        let rates: usize = self
+            .inflow
+            .iter()
+            .chain(&self.outflow)
+            .map(|r| r.heap_size())
+            .sum();
+        let queues: usize = self.queues.iter().map(|q| q.heap_size()).sum();
+
+        // Ties are broken by the function to keep the report deterministic.
+        functions.sort_unstable_by_key(|f| {
+            let key = match f.function {
+                FlowFunction::Inflow { edge, commodity } => (edge, 0, commodity),
+                FlowFunction::Outflow { edge, commodity } => (edge, 1, commodity),
+                FlowFunction::Queue { edge } => (edge, 2, 0),
+            };
+            (std::cmp::Reverse(f.breakpoints), key)
+        });
+        functions.truncate(LARGEST_FUNCTIONS);
+
+        FlowStats {
+            breakpoints,
+            heap_bytes: rates + queues,
+            pending_outflow_changes: self.outflow_changes.len(),
+            pending_depletions: self.depletions.len(),
+            largest_functions: functions,
+        }
+    }
+
+    /// Creates a completely built flow from the functions of all edges, e.g. to analyze a flow that
+    /// was built elsewhere. The flow is built until infinity, so it cannot be extended.
+    pub fn from_functions(
+        queues: Vec<PiecewiseLinear<T>>,
+        inflow: Vec<FlowRatesCollection<T>>,
+        outflow: Vec<FlowRatesCollection<T>>,
+    ) -> Self
+    where
+        T: Tolerance,
+    {
+        let num_edges = queues.len();
+        debug_assert_eq!(inflow.len(), num_edges);
+        debug_assert_eq!(outflow.len(), num_edges);
+        let rate_storage = inflow
+            .first()
+            .map_or(RateStorage::PerCommodity, |rates| rates.storage());
+        DynamicFlow {
+            built_until: T::INFINITY,
+            inflow: inflow.into_iter().map(Arc::new).collect(),
+            outflow: outflow.into_iter().map(Arc::new).collect(),
+            queues: queues.into_iter().map(Arc::new).collect(),
+            rate_storage,
+            ..DynamicFlow::new(num_edges)
+        }
+    }
+
+    /// Converts the flow to another numeric backend by applying `convert` to all numbers, e.g. to
+    /// verify a float computation with exact arithmetic or to export an exact flow as floats.
+    /// The flow can be extended further in the new backend, using its default tolerance policy.
+    pub fn map_num<U: Num + Tolerance>(&self, convert: impl Fn(T) -> U) -> DynamicFlow<U> {
+        let map_rates = |rates: &Vec<Arc<FlowRatesCollection<T>>>| {
+            rates
+                .iter()
+                .map(|r| Arc::new(r.map_num(&convert)))
+                .collect()
+        };
+        DynamicFlow {
+            built_until: convert(self.built_until),
+            inflow: map_rates(&self.inflow),
+            outflow: map_rates(&self.outflow),
+            queues: self
+                .queues
+                .iter()
+                .map(|q| Arc::new(q.map_num(&convert)))
+                .collect(),
+            queue_cursors: self.queue_cursors.clone(),
+            outflow_changes: self.outflow_changes.map_num(&convert),
+            depletions: self.depletions.map_num(&convert),
+            tolerance: TolerancePolicy::default(),
+            rate_storage: self.rate_storage,
+            tolerance_violations: self
+                .tolerance_violations
+                .iter()
+                .map(|v| ToleranceViolation {
+                    edge: v.edge,
+                    time: convert(v.time),
+                    residual: convert(v.residual),
+                    tolerance: convert(v.tolerance),
+                })
+                .collect(),
+            rate_pool: CommodityVecPool::default(),
+            #[cfg(feature = "profiling")]
+            counters: ProfilingCounters::default(),
+        }
+    }
+
+    /// Returns whether a queue or flow rate of the flow is not a number, e.g. because the numbers
+    /// of an exact backend like `Rational128` overflowed.
+    pub fn contains_nan(&self) -> bool {
+        let nan_points = |points: &[Point<T>]| points.iter().any(|p| p.0.is_nan() || p.1.is_nan());
+        let nan_rates = |rates: &Vec<Arc<FlowRatesCollection<T>>>| {
+            rates.iter().any(|r| {
+                r.function_by_comm()
+                    .values()
+                    .any(|f| nan_points(f.points()))
+            })
+        };
+        self.built_until.is_nan()
+            || self.queues.iter().any(|q| nan_points(q.points()))
+            || nan_rates(&self.inflow)
+            || nan_rates(&self.outflow)
+    }
+
+    /// Returns the flow with all functions restricted to `window`, e.g. to export only the peak of
+    /// a long horizon. The restricted flow is meant for exporting and analysis, it cannot be
+    /// extended.
+    pub fn restrict(&self, window: TimeWindow<T>) -> DynamicFlow<T>
+    where
+        T: Tolerance,
+    {
+        let restrict_rates = |rates: &Vec<Arc<FlowRatesCollection<T>>>| {
+            rates
+                .iter()
+                .map(|rates| {
+                    FlowRatesCollection::from_functions(
+                        rates
+                            .function_by_comm
+                            .iter()
+                            .map(|(&comm, f)| {
+                                let [from, to] = window.clamp(f.domain());
+                                (comm, f.restrict(from, to))
+                            })
+                            .collect(),
+                    )
+                })
+                .collect()
+        };
+        let queues = self
+            .queues
+            .iter()
+            .map(|q| {
+                let [from, to] = window.clamp(q.domain());
+                q.restrict(from, to)
+            })
+            .collect();
+        DynamicFlow::from_functions(
+            queues,
+            restrict_rates(&self.inflow),
+            restrict_rates(&self.outflow),
+        )
+    }
+
+    /// Merges the flow of each commodity `from` into the commodity `into[from]` on all edges.
+    pub fn merge_commodities(&mut self, into: &HashMap<usize, usize>)
+    where
+        T: Tolerance,
+    {
+        for rates in self.inflow.iter_mut().chain(self.outflow.iter_mut()) {
+            Arc::make_mut(rates).merge_commodities(into);
+        }
+    }
+
+    /// Renames each commodity `i` to `to[i]` on all edges, where `to` maps distinct commodities to
+    /// distinct ones.
+    pub fn rename_commodities(&mut self, to: &[usize]) {
+        for rates in self.inflow.iter_mut().chain(self.outflow.iter_mut()) {
+            Arc::make_mut(rates).rename_commodities(to);
+        }
+    }
+
+    /// Moves the functions of all edges of `part`, a flow on a part of the network, into this flow,
+    /// where edge `i` of `part` is edge `edges[i]` of this flow. Both flows must be fully built.
+    pub fn insert_part(&mut self, part: DynamicFlow<T>, edges: &[usize]) {
+        debug_assert_eq!(part.inflow.len(), edges.len());
+        debug_assert!(
+            self.built_until >= T::INFINITY || self.built_until == T::ZERO,
+            "The flow is partially built."
+        );
+        debug_assert!(
+            part.built_until >= T::INFINITY,
+            "The part is not fully built."
+        );
+        self.built_until = part.built_until;
+        let functions = part
+            .inflow
+            .into_iter()
+            .zip(part.outflow)
+            .zip(part.queues)
+            .zip(part.queue_cursors);
+        for (&edge, (((inflow, outflow), queue), queue_cursor)) in edges.iter().zip(functions) {
+            self.inflow[edge] = inflow;
+            self.outflow[edge] = outflow;
+            self.queues[edge] = queue;
+            self.queue_cursors[edge] = queue_cursor;
+        }
+        self.tolerance_violations
+            .extend(
+                part.tolerance_violations
+                    .into_iter()
+                    .map(|v| ToleranceViolation {
+                        edge: edges[v.edge],
+                        ..v
+                    }),
+            );
+        #[cfg(feature = "profiling")]
+        {
+            self.counters += part.counters;
+        }
+    }
+
+    /// Drops all rates and queue lengths before `before_time`, which must not exceed `built_until`.
+    /// Afterwards, all functions of the flow start at `before_time`, such that memory no longer
+    /// grows with the length of the simulated horizon. The flow can still be extended, but must not
+    /// be evaluated before `before_time` anymore.
+    pub fn truncate_history(&mut self, before_time: T) {
+        debug_assert!(
+            before_time <= self.built_until,
+            "The flow is not built until the truncation time."
+        );
+        for rates in self.inflow.iter_mut().chain(self.outflow.iter_mut()) {
+            Arc::make_mut(rates).truncate_history(before_time, &mut self.rate_pool);
+        }
+        for queue in self.queues.iter_mut() {
+            if queue.domain()[0] < before_time {
+                *queue = Arc::new(queue.restrict(before_time, T::INFINITY));
+            }
+        }
+    }
+
+    /// Like `truncate_history`, but returns the dropped part of the flow first, e.g. to write it
+    /// to disk.
+    pub fn drain_history(&mut self, before_time: T) -> FlowHistory<T> {
+        let history = FlowHistory {
+            before_time,
+            inflow: self.inflow.iter().map(|r| r.history(before_time)).collect(),
+            outflow: self
+                .outflow
+                .iter()
+                .map(|r| r.history(before_time))
+                .collect(),
+            queues: self
+                .queues
+                .iter()
+                .map(|q| q.restrict(q.domain()[0], max(q.domain()[0], before_time)))
+                .collect(),
+        };
+        self.truncate_history(before_time);
+        history
+    }
+
+    /// Returns the total number of breakpoints of all queue and flow rate functions.
+    pub fn num_breakpoints(&self) -> usize {
+        let rates = self.inflow.iter().chain(self.outflow.iter());
+        self.queues.iter().map(|q| q.points().len()).sum::<usize>()
+            + rates
+                .flat_map(|r| r.function_by_comm.values())
+                .map(|f| f.points().len())
+                .sum::<usize>()
+    }
+
+    /// Returns the earliest time after `built_until` at which some queue depletes or some edge
+    /// outflow changes, if any.
+    pub fn next_event_time(&self) -> Option<T> {
+        [
+            self.depletions.min_depletion_time(),
+            self.depletions.min_change_time(),
+            self.outflow_changes.min_change_time(),
+        ]
+        .into_iter()
+        .flatten()
+        .copied()
+        .reduce(min)
+    }
+
+    /// Returns the per-edge differences `self - other` of queues and flow rates.
+    /// Both flows should be built equally far, as functions are compared on their whole domain.
+    pub fn diff(&self, other: &DynamicFlow<T>) -> FlowDiff<T>
+    where
+        T: Tolerance,
+    {
+        FlowDiff::new(self, other)
+    }
+
+    /// Returns the time at which a particle entering `edge` at time `entrance_time` leaves the edge.
+    /// Only meaningful for `entrance_time <= self.built_until`.
+    pub fn exit_time(&self, edge: usize, entrance_time: T, inv_capacity: T, travel_time: T) -> T {
+        let queue = max(self.queues[edge].eval(entrance_time), T::ZERO);
+        entrance_time + queue * inv_capacity + travel_time
+    }
+
+    /// Returns the time at which a particle departing at `departure_time` reaches the end of `path`.
+    pub fn path_arrival_time(
+        &self,
+        path: &[usize],
+        departure_time: T,
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) -> T {
+        path.iter().fold(departure_time, |time, &edge| {
+            self.exit_time(edge, time, inv_capacity[edge], travel_time[edge])
+        })
+    }
+
     /// Extends the flow with constant inflows new_inflow until some edge outflow changes.
     /// Edge inflows not in new_inflow are extended with their previous values.
     /// You can also specify a maximum extension length using max_extension_length.
+    /// If many edges change at once, they are extended in parallel.
+    /// Panics if new_inflow contains an edge more than once.
     /// :returns set of edges where the outflow has changed at the new time `self.built_until`
     pub fn extend(
         &mut self,
-        new_inflow: HashMap<usize, HashMap<usize, T>>,
+        new_inflow: impl IntoIterator<Item = (usize, CommodityVec<T>)>,
         max_extension_time: Option<T>,
         capacity: &[T],
         inv_capacity: &[T],
         travel_time: &[T],
-    ) -> HashSet<usize> {
-        for (edge, new_inflow_e) in new_inflow.into_iter() {
-            if *self.inflow[edge]
-                .get_values_at_time(self.built_until)
-                .unwrap_or(&HashMap::new())
-                == new_inflow_e
-            {
-                continue;
-            }
-            let acc_in: T = new_inflow_e.values().sum_iter();
-            let cur_queue: T = max(self.queues[edge].eval(self.built_until), T::ZERO);
-
-            self.inflow[edge].extend(self.built_until, new_inflow_e.clone(), acc_in);
-
-            let capacity_e = capacity[edge];
-            let inv_capacity_e = inv_capacity[edge];
-            let travel_time_e = travel_time[edge];
-            if acc_in == T::ZERO {
-                self._extend_case_i(edge, cur_queue, capacity_e, inv_capacity_e, travel_time_e);
-            } else if cur_queue == T::ZERO || acc_in >= capacity_e - T::TOL {
-                self._extend_case_ii(
-                    edge,
-                    new_inflow_e,
-                    cur_queue,
-                    acc_in,
-                    capacity_e,
-                    inv_capacity_e,
-                    travel_time_e,
-                );
-            } else {
-                self._extend_case_iii(
-                    edge,
-                    new_inflow_e,
-                    cur_queue,
-                    acc_in,
-                    capacity_e,
-                    inv_capacity_e,
-                    travel_time_e,
-                );
-            }
+    ) -> HashSet<usize>
+    where
+        T: Tolerance,
+    {
+        #[cfg(feature = "profiling")]
+        {
+            self.counters.phases += 1;
+        }
+        let mut new_inflow: Vec<(usize, CommodityVec<T>)> = new_inflow.into_iter().collect();
+        new_inflow.sort_unstable_by_key(|&(edge, _)| edge);
+        if let Some(w) = new_inflow.windows(2).find(|w| w[0].0 == w[1].0) {
+            panic!("The new inflow contains edge {} more than once.", w[0].0);
+        }
+        let edges: Vec<usize> = new_inflow.iter().map(|&(edge, _)| edge).collect();
+        let pool_limit = self.rate_pool.limit();
+
+        let context = ExtensionContext {
+            built_until: self.built_until,
+            capacity,
+            inv_capacity,
+            travel_time,
+            tolerance: &self.tolerance,
+        };
+        let states = pick_mut(&mut self.inflow, &edges)
+            .into_iter()
+            .zip(pick_mut(&mut self.outflow, &edges))
+            .zip(pick_mut(&mut self.queues, &edges))
+            .zip(pick_mut(&mut self.queue_cursors, &edges))
+            .zip(new_inflow)
+            .map(
+                |((((inflow, outflow), queue), queue_cursor), (edge, new_inflow_e))| {
+                    let state = EdgeState {
+                        edge,
+                        inflow,
+                        outflow,
+                        queue,
+                        queue_cursor,
+                    };
+                    (state, new_inflow_e)
+                },
+            );
+        let events: Vec<EdgeEvents<T>> = if edges.len() >= PARALLEL_EDGES {
+            states
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map_init(
+                    || CommodityVecPool::with_limit(pool_limit),
+                    |pool, (state, new_inflow_e)| context.extend_edge(state, new_inflow_e, pool),
+                )
+                .flatten()
+                .collect()
+        } else {
+            let pool = &mut self.rate_pool;
+            states
+                .filter_map(|(state, new_inflow_e)| context.extend_edge(state, new_inflow_e, pool))
+                .collect()
+        };
+        for events_e in events {
+            self.apply_events(events_e);
         }
 
         self.built_until = {
@@ -227,7 +1037,7 @@ impl<T: Num> DynamicFlow<T> {
             if let Some(time) = self.depletions.min_change_time() {
                 new_built_until = min(new_built_until, *time);
             }
-            if let Some((_, Reverse(time))) = self.outflow_changes.peek() {
+            if let Some(time) = self.outflow_changes.min_change_time() {
                 new_built_until = min(new_built_until, *time);
             }
             if let Some(time) = max_extension_time {
@@ -245,187 +1055,389 @@ impl<T: Num> DynamicFlow<T> {
 
         while self
             .outflow_changes
-            .peek()
-            .is_some_and(|(_, Reverse(time))| time <= &self.built_until)
+            .min_change_time()
+            .is_some_and(|time| time <= &self.built_until)
         {
-            changed_edges.insert(self.outflow_changes.pop().unwrap().0.edge);
+            changed_edges.insert(self.outflow_changes.pop().unwrap().0);
+            #[cfg(feature = "profiling")]
+            {
+                self.counters.outflow_changes_processed += 1;
+            }
         }
 
         changed_edges
     }
 
-    fn _extend_case_i(
-        &mut self,
-        edge: usize,
-        cur_queue: T,
-        capacity: T,
-        inv_capacity: T,
-        travel_time: T,
-    ) {
-        let queue_fn = &mut self.queues[edge];
-        let arrival = self.built_until + cur_queue * inv_capacity + travel_time;
-        self.outflow[edge].extend(arrival, HashMap::new(), T::ZERO);
-
-        self.outflow_changes.push(
-            PreprocessedOutflowChange {
-                edge,
-                change_time: arrival,
-            },
-            Reverse(arrival),
+    /// Adds the events of an extended edge to the shared event queues.
+    fn apply_events(&mut self, events: EdgeEvents<T>) {
+        let edge = events.edge;
+        #[cfg(feature = "profiling")]
+        {
+            self.counters.edge_extensions += 1;
+            self.counters.points_created += events.points_created;
+        }
+        self.outflow_changes
+            .push(edge, events.outflow_change, &self.tolerance);
+        match events.depletion {
+            Some((depl_time, change_event)) => self.depletions.set(edge, depl_time, change_event),
+            None => self.depletions.remove(edge),
+        }
+        self.tolerance_violations.extend(events.violation);
+    }
+
+    /// Records a violation if the queue of `edge` deviates from zero at its computed depletion
+    /// `time` by more than rounding errors explain.
+    fn check_depletion(&mut self, edge: usize, time: T, residual: T, scale: T) {
+        let violation = depletion_violation(&self.tolerance, edge, time, residual, scale);
+        self.tolerance_violations.extend(violation);
+    }
+
+    fn _process_depletions(&mut self) {
+        if self.built_until >= T::INFINITY {
+            return;
+        }
+        while self
+            .depletions
+            .min_depletion_time()
+            .is_some_and(|t| t <= &self.built_until)
+        {
+            let (edge, depl_time, change_event) = self.depletions.pop_by_depletion().unwrap();
+            #[cfg(feature = "profiling")]
+            let points_before = (
+                self.queues[edge].points().len(),
+                self.outflow[edge].num_points(),
+            );
+            let queue_e = Arc::make_mut(&mut self.queues[edge]);
+            let scale = abs(queue_e.last_slope()) * abs(depl_time);
+            queue_e.extend_with_tolerance(&depl_time, T::ZERO, &self.tolerance);
+            let queue_e_last = queue_e.points_mut().last_mut().unwrap();
+            let residual = queue_e_last.1;
+            queue_e_last.1 = T::ZERO;
+            self.check_depletion(edge, depl_time, residual, scale);
+
+            if let Some(change_event) = change_event {
+                Arc::make_mut(&mut self.outflow[edge]).extend(
+                    change_event.time,
+                    change_event.value.new_outflow_map,
+                    &self.tolerance,
+                );
+                self.outflow_changes
+                    .push(edge, change_event.time, &self.tolerance);
+            }
+            #[cfg(feature = "profiling")]
+            {
+                self.counters.depletions_processed += 1;
+                self.counters.points_created.queues +=
+                    self.queues[edge].points().len() - points_before.0;
+                self.counters.points_created.outflow +=
+                    self.outflow[edge].num_points() - points_before.1;
+            }
+        }
+    }
+}
+
+/// The number of simultaneously changing edges from which on `extend` processes them in parallel.
+/// Below, the overhead of distributing the work outweighs the gain.
+const PARALLEL_EDGES: usize = 256;
+
+/// The parts of a flow that extending a single edge modifies.
+/// Distinct edges can hence be extended in parallel.
+/// The rates and queue are only copied from a shared snapshot once the inflow has changed.
+struct EdgeState<'a, T: Num> {
+    edge: usize,
+    inflow: &'a mut Arc<FlowRatesCollection<T>>,
+    outflow: &'a mut Arc<FlowRatesCollection<T>>,
+    queue: &'a mut Arc<PiecewiseLinear<T>>,
+    queue_cursor: &'a mut PiecewiseLinearCursor,
+}
+
+/// The changes to the shared event queues caused by extending a single edge.
+struct EdgeEvents<T: Num> {
+    edge: usize,
+    // The time at which the outflow of the edge changes to its new rates
+    outflow_change: T,
+    // The time at which the queue depletes, if it does, and the outflow change this causes
+    depletion: Option<(T, Option<ChangeEvent<T>>)>,
+    violation: Option<ToleranceViolation<T>>,
+    // Only counted with the profiling feature
+    points_created: PointCounts,
+}
+
+/// The read-only data needed to extend the edges of a flow.
+struct ExtensionContext<'a, T: Num> {
+    built_until: T,
+    capacity: &'a [T],
+    inv_capacity: &'a [T],
+    travel_time: &'a [T],
+    tolerance: &'a TolerancePolicy<T>,
+}
+
+impl<T: Num + Tolerance> ExtensionContext<'_, T> {
+    /// Extends the edge with the constant inflow `new_inflow_e` from `built_until` on.
+    /// Returns None if the inflow of the edge does not change.
+    fn extend_edge(
+        &self,
+        mut state: EdgeState<T>,
+        new_inflow_e: CommodityVec<T>,
+        pool: &mut CommodityVecPool<T>,
+    ) -> Option<EdgeEvents<T>> {
+        let edge = state.edge;
+        let current_inflow = state.inflow.peek_values_at_time(self.built_until);
+        if rates_unchanged(current_inflow, &new_inflow_e, self.tolerance) {
+            pool.recycle(new_inflow_e);
+            return None;
+        }
+        // Drops the rates before `built_until`, which are no longer needed for extending.
+        Arc::make_mut(state.inflow).drop_values_before(self.built_until, pool);
+        Arc::make_mut(state.outflow).drop_values_before(self.built_until, pool);
+        for (i, &rate) in new_inflow_e.iter() {
+            check_non_negative(rate, || {
+                format!(
+                    "inflow rate of commodity {} into edge {} at time {}",
+                    i, edge, self.built_until
+                )
+            });
+        }
+        check_not_nan(self.capacity[edge], || format!("capacity of edge {}", edge));
+        check_not_nan(self.travel_time[edge], || {
+            format!("travel time of edge {}", edge)
+        });
+        #[cfg(feature = "profiling")]
+        let points_before = PointCounts {
+            inflow: state.inflow.num_points(),
+            outflow: state.outflow.num_points(),
+            queues: state.queue.points().len(),
+        };
+        let acc_in: T = new_inflow_e.values().sum_iter();
+        let cur_queue: T = max(
+            state.queue_cursor.eval(state.queue, self.built_until),
+            T::ZERO,
         );
 
+        let capacity = self.capacity[edge];
+        #[allow(unused_mut)]
+        let mut events = if acc_in == T::ZERO {
+            self.extend_case_i(&mut state, cur_queue)
+        } else if cur_queue == T::ZERO || acc_in >= capacity - self.tolerance.tolerance(capacity) {
+            self.extend_case_ii(&mut state, &new_inflow_e, cur_queue, acc_in, pool)
+        } else {
+            self.extend_case_iii(&mut state, &new_inflow_e, cur_queue, acc_in, pool)
+        };
+
+        // The cases only borrow the inflow, so it can be moved into the inflow rates last.
+        Arc::make_mut(state.inflow).extend(self.built_until, new_inflow_e, self.tolerance);
+        #[cfg(feature = "profiling")]
+        {
+            events.points_created = PointCounts {
+                inflow: state.inflow.num_points() - points_before.inflow,
+                outflow: state.outflow.num_points() - points_before.outflow,
+                queues: state.queue.points().len() - points_before.queues,
+            };
+        }
+        Some(events)
+    }
+
+    fn extend_case_i(&self, state: &mut EdgeState<T>, cur_queue: T) -> EdgeEvents<T> {
+        let edge = state.edge;
+        let (capacity, inv_capacity) = (self.capacity[edge], self.inv_capacity[edge]);
+        let arrival = self.built_until + cur_queue * inv_capacity + self.travel_time[edge];
+        Arc::make_mut(state.outflow).extend(arrival, CommodityVec::new(), self.tolerance);
+
+        let mut events = EdgeEvents {
+            edge,
+            outflow_change: arrival,
+            depletion: None,
+            violation: None,
+            points_created: PointCounts::default(),
+        };
         if cur_queue == T::ZERO {
             let queue_slope = T::ZERO;
-            queue_fn.extend(&self.built_until, queue_slope);
-            self.depletions.remove(edge);
+            Arc::make_mut(state.queue).extend_with_tolerance(
+                &self.built_until,
+                queue_slope,
+                self.tolerance,
+            );
         } else {
             let queue_slope = -capacity;
-            queue_fn.extend(&self.built_until, queue_slope);
-            let depl_time = self.built_until + cur_queue * inv_capacity;
-            let mille: T = iter::repeat(T::ONE).take(1000).sum();
-            debug_assert!(
-                queue_fn.eval(depl_time) <= mille * T::TOL,
-                "depl_time: {}, queue_fn.eval(depl_time): {}",
-                depl_time,
-                queue_fn.eval(depl_time)
+            Arc::make_mut(state.queue).extend_with_tolerance(
+                &self.built_until,
+                queue_slope,
+                self.tolerance,
             );
-            self.depletions.set(edge, depl_time, None)
+            let depl_time = self.built_until + cur_queue * inv_capacity;
+            let residual = state.queue.eval(depl_time);
+            let scale = cur_queue + capacity * abs(depl_time);
+            events.violation =
+                depletion_violation(self.tolerance, edge, depl_time, residual, scale);
+            events.depletion = Some((depl_time, None));
         }
+        events
     }
 
-    fn _extend_case_ii(
-        &mut self,
-        edge: usize,
-        new_inflow_e: HashMap<usize, T>,
+    fn extend_case_ii(
+        &self,
+        state: &mut EdgeState<T>,
+        new_inflow_e: &CommodityVec<T>,
         cur_queue: T,
         acc_in: T,
-        capacity: T,
-        inv_capacity: T,
-        travel_time: T,
-    ) {
-        let arrival = self.built_until + cur_queue * inv_capacity + travel_time;
+        pool: &mut CommodityVecPool<T>,
+    ) -> EdgeEvents<T> {
+        let edge = state.edge;
+        let capacity = self.capacity[edge];
+        let arrival =
+            self.built_until + cur_queue * self.inv_capacity[edge] + self.travel_time[edge];
 
         let acc_out = min(capacity, acc_in);
-        let factor = acc_out / acc_in;
-        let mut outflow_map: HashMap<usize, T> = new_inflow_e;
-        for (_, v) in outflow_map.iter_mut() {
-            *v *= factor;
-        }
+        let outflow_map = scaled_rates(new_inflow_e, acc_out, acc_in, pool);
+        Arc::make_mut(state.outflow).extend(arrival, outflow_map, self.tolerance);
 
-        self.outflow[edge].extend(arrival, outflow_map, acc_out);
-
-        self.outflow_changes.push(
-            PreprocessedOutflowChange {
-                edge,
-                change_time: arrival,
-            },
-            Reverse(arrival),
-        );
         let queue_slope = max(acc_in - capacity, T::ZERO);
-        self.queues[edge].extend(&self.built_until, queue_slope);
-        self.depletions.remove(edge);
+        Arc::make_mut(state.queue).extend_with_tolerance(
+            &self.built_until,
+            queue_slope,
+            self.tolerance,
+        );
+        EdgeEvents {
+            edge,
+            outflow_change: arrival,
+            depletion: None,
+            violation: None,
+            points_created: PointCounts::default(),
+        }
     }
 
-    fn _extend_case_iii(
-        &mut self,
-        edge: usize,
-        new_inflow_e: HashMap<usize, T>,
+    fn extend_case_iii(
+        &self,
+        state: &mut EdgeState<T>,
+        new_inflow_e: &CommodityVec<T>,
         cur_queue: T,
         acc_in: T,
-        capacity: T,
-        inv_capacity: T,
-        travel_time: T,
-    ) {
-        let arrival = self.built_until + cur_queue * inv_capacity + travel_time;
-        let factor = capacity / acc_in;
-
-        let mut outflow_map: HashMap<usize, T> = new_inflow_e;
-        for (_, v) in outflow_map.iter_mut() {
-            *v *= factor;
-        }
-
-        self.outflow[edge].extend(arrival, outflow_map.clone(), capacity);
+        pool: &mut CommodityVecPool<T>,
+    ) -> EdgeEvents<T> {
+        let edge = state.edge;
+        let (capacity, travel_time) = (self.capacity[edge], self.travel_time[edge]);
+        let arrival = self.built_until + cur_queue * self.inv_capacity[edge] + travel_time;
 
-        self.outflow_changes.push(
-            PreprocessedOutflowChange {
-                edge,
-                change_time: arrival,
-            },
-            Reverse(arrival),
-        );
+        // While the queue depletes, the outflow rate equals the capacity.
+        let outflow_map = scaled_rates(new_inflow_e, capacity, acc_in, pool);
+        Arc::make_mut(state.outflow).extend(arrival, outflow_map, self.tolerance);
 
         let queue_slope = acc_in - capacity;
-        self.queues[edge].extend(&self.built_until, queue_slope);
+        Arc::make_mut(state.queue).extend_with_tolerance(
+            &self.built_until,
+            queue_slope,
+            self.tolerance,
+        );
 
-        let depl_time = self.built_until + cur_queue / queue_slope;
+        let depl_time = self.built_until + cur_queue / (capacity - acc_in);
         let planned_change_time = depl_time + travel_time;
-        let mille: T = iter::repeat(T::ONE).take(1000).sum();
-        debug_assert!(self.queues[edge].eval(depl_time) < mille * T::TOL);
-
-        self.depletions.set(
+        let residual = state.queue.eval(depl_time);
+        let scale = cur_queue + abs(queue_slope) * abs(depl_time);
+        let change_event = ChangeEvent {
+            time: planned_change_time,
+            value: ChangeEventValue {
+                // Once the queue has depleted, the inflow passes through the edge unchanged.
+                new_outflow_map: pool.take_copy(new_inflow_e),
+            },
+        };
+        EdgeEvents {
             edge,
-            depl_time,
-            Some(ChangeEvent {
-                time: planned_change_time,
-                value: ChangeEventValue {
-                    new_outflow_map: outflow_map,
-                    values_sum: acc_in,
-                },
-            }),
-        );
+            outflow_change: arrival,
+            depletion: Some((depl_time, Some(change_event))),
+            violation: depletion_violation(self.tolerance, edge, depl_time, residual, scale),
+            points_created: PointCounts::default(),
+        }
     }
+}
 
-    fn _process_depletions(&mut self) {
-        if self.built_until >= T::INFINITY {
-            return;
-        }
-        while self
-            .depletions
-            .min_depletion_time()
-            .is_some_and(|t| t <= &self.built_until)
-        {
-            let (edge, depl_time, change_event) = self.depletions.pop_by_depletion().unwrap();
-            let queue_e = &mut self.queues[edge];
-            queue_e.extend(&depl_time, T::ZERO);
-            let queue_e_last = queue_e.points_mut().last_mut().unwrap();
-            let mille: T = iter::repeat(T::ONE).take(1000).sum();
-            debug_assert!(abs(queue_e_last.1) < mille * T::TOL);
-            queue_e_last.1 = T::ZERO;
+/// Returns whether the rates `new` equal the `current` ones within tolerance, where missing
+/// commodities have rate zero. Compares in place, so an unchanged inflow costs no allocation.
+fn rates_unchanged<T: Num>(
+    current: Option<&CommodityVec<T>>,
+    new: &CommodityVec<T>,
+    tol: &TolerancePolicy<T>,
+) -> bool {
+    let Some(current) = current else {
+        return new.values().all(|&rate| tol.is_close(rate, T::ZERO));
+    };
+    new.iter()
+        .all(|(i, &rate)| tol.is_close(current.get(i).copied().unwrap_or(T::ZERO), rate))
+        && current
+            .iter()
+            .all(|(i, &rate)| new.contains_key(i) || tol.is_close(rate, T::ZERO))
+}
 
-            if let Some(change_event) = change_event {
-                self.outflow[edge].extend(
-                    change_event.time,
-                    change_event.value.new_outflow_map,
-                    change_event.value.values_sum,
-                );
-                self.outflow_changes.push(
-                    PreprocessedOutflowChange {
-                        edge,
-                        change_time: change_event.time,
-                    },
-                    Reverse(change_event.time),
-                );
-            }
-        }
+/// Returns the rates scaled by `numerator / denominator` in a map of the pool.
+/// Multiplying before dividing keeps the rates integral for integer backends if possible.
+fn scaled_rates<T: Num>(
+    rates: &CommodityVec<T>,
+    numerator: T,
+    denominator: T,
+    pool: &mut CommodityVecPool<T>,
+) -> CommodityVec<T> {
+    if numerator == denominator {
+        return pool.take_copy(rates);
     }
+    pool.take_mapped(rates, |&rate| rate * numerator / denominator)
+}
+
+/// Returns a violation if the queue of `edge` deviates from zero at its computed depletion
+/// `time` by more than rounding errors explain. These grow with the magnitude `scale` of the
+/// values involved in computing the queue length, so the tolerance is scaled accordingly.
+fn depletion_violation<T: Num>(
+    tolerance: &TolerancePolicy<T>,
+    edge: usize,
+    time: T,
+    residual: T,
+    scale: T,
+) -> Option<ToleranceViolation<T>> {
+    let tolerance = (tolerance.absolute + tolerance.relative) * (T::ONE + scale);
+    (abs(residual) > tolerance).then_some(ToleranceViolation {
+        edge,
+        time,
+        residual,
+        tolerance,
+    })
+}
+
+/// Returns mutable references to the elements of `slice` at the sorted and distinct `indices`.
+fn pick_mut<'a, X>(mut slice: &'a mut [X], indices: &[usize]) -> Vec<&'a mut X> {
+    let mut picked = Vec::with_capacity(indices.len());
+    let mut offset = 0;
+    for &index in indices {
+        assert!(index >= offset, "The indices must be sorted and distinct.");
+        let (_, rest) = std::mem::take(&mut slice).split_at_mut(index - offset);
+        let (element, rest) = rest.split_first_mut().unwrap();
+        picked.push(element);
+        slice = rest;
+        offset = index + 1;
+    }
+    picked
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{collections::HashMap, sync::Arc};
 
     use crate::{
-        float::F64, num::Num, piecewise_constant::PiecewiseConstant,
-        piecewise_linear::PiecewiseLinear, points,
+        commodity_vec::CommodityVec,
+        example_instances::vickrey_bottleneck,
+        float::F64,
+        num::{Num, ToF64, Tolerance},
+        piecewise_constant::PiecewiseConstant,
+        piecewise_linear::PiecewiseLinear,
+        points,
+        rational::Rational128,
+        tolerance::TolerancePolicy,
     };
 
-    use super::DynamicFlow;
+    use super::{DynamicFlow, FlowFunction, TimeWindow};
 
     #[test]
     fn test_dynamic_flow_constant_inflow_single_edge() {
         let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(1);
         dynamic_flow.extend(
-            HashMap::from([(0, HashMap::from([(0, 1.0.into())]))]),
+            HashMap::from([(0, CommodityVec::from([(0, 1.0.into())]))]),
             None,
             &[1.0.into()],
             &[1.0.into()],
@@ -433,7 +1445,7 @@ mod tests {
         );
         assert_eq!(dynamic_flow.built_until, 1.0);
         dynamic_flow.extend(
-            HashMap::from([(0, HashMap::from([(0, 1.0.into())]))]),
+            HashMap::from([(0, CommodityVec::from([(0, 1.0.into())]))]),
             None,
             &[1.0.into()],
             &[1.0.into()],
@@ -441,7 +1453,7 @@ mod tests {
         );
         assert_eq!(dynamic_flow.built_until, F64::INFINITY);
         assert_eq!(
-            dynamic_flow.queues[0],
+            *dynamic_flow.queues[0],
             PiecewiseLinear::new(
                 [-F64::INFINITY, F64::INFINITY],
                 0.0,
@@ -455,7 +1467,7 @@ mod tests {
     fn test_dynamic_flow_vanishing_inflow_single_edge() {
         let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(1);
         dynamic_flow.extend(
-            HashMap::from([(0, HashMap::from([(0, 1.0.into())]))]),
+            HashMap::from([(0, CommodityVec::from([(0, 1.0.into())]))]),
             None,
             &[1.0.into()],
             &[1.0.into()],
@@ -463,7 +1475,7 @@ mod tests {
         );
         assert_eq!(dynamic_flow.built_until, 1.0);
         dynamic_flow.extend(
-            HashMap::from([(0, HashMap::from([(0, 1.0.into())]))]),
+            HashMap::from([(0, CommodityVec::from([(0, 1.0.into())]))]),
             Some(2.0.into()),
             &[1.0.into()],
             &[1.0.into()],
@@ -471,7 +1483,7 @@ mod tests {
         );
         assert_eq!(dynamic_flow.built_until, 2.0);
         dynamic_flow.extend(
-            HashMap::from([(0, HashMap::from([(0, 0.0.into())]))]),
+            HashMap::from([(0, CommodityVec::from([(0, 0.0.into())]))]),
             None,
             &[1.0.into()],
             &[1.0.into()],
@@ -479,7 +1491,7 @@ mod tests {
         );
         assert_eq!(dynamic_flow.built_until, 3.0);
         dynamic_flow.extend(
-            HashMap::from([(0, HashMap::from([(0, 0.0.into())]))]),
+            HashMap::from([(0, CommodityVec::from([(0, 0.0.into())]))]),
             None,
             &[1.0.into()],
             &[1.0.into()],
@@ -494,4 +1506,359 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn test_dynamic_flow_depleting_queue_single_edge() {
+        let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(1);
+        dynamic_flow.extend(
+            HashMap::from([(0, CommodityVec::from([(0, 2.0.into())]))]),
+            None,
+            &[1.0.into()],
+            &[1.0.into()],
+            &[1.0.into()],
+        );
+        assert_eq!(dynamic_flow.built_until, 1.0);
+        dynamic_flow.extend(
+            HashMap::from([(0, CommodityVec::from([(0, 0.5.into())]))]),
+            None,
+            &[1.0.into()],
+            &[1.0.into()],
+            &[1.0.into()],
+        );
+        assert_eq!(dynamic_flow.built_until, 3.0);
+        while dynamic_flow.built_until < F64::INFINITY {
+            dynamic_flow.extend(
+                HashMap::new(),
+                None,
+                &[1.0.into()],
+                &[1.0.into()],
+                &[1.0.into()],
+            );
+        }
+        assert_eq!(dynamic_flow.queues[0].eval(2.0), 0.5);
+        assert_eq!(dynamic_flow.queues[0].eval(3.0), 0.0);
+        assert_eq!(dynamic_flow.queues[0].eval(4.0), 0.0);
+        let outflow = &dynamic_flow.outflow[0].function_by_comm()[&0];
+        assert_eq!(outflow.eval(3.5), 1.0);
+        assert_eq!(outflow.eval(4.5), 0.5);
+    }
+
+    #[test]
+    fn it_extends_many_edges_in_parallel() {
+        let num_edges = 2 * super::PARALLEL_EDGES;
+        let capacity: Vec<F64> = (0..num_edges)
+            .map(|e| (1.0 + e as f64 / 8.0).into())
+            .collect();
+        let inv_capacity: Vec<F64> = capacity.iter().map(|&c| F64::ONE / c).collect();
+        let travel_time: Vec<F64> = (0..num_edges)
+            .map(|e| (1.0 + (e % 5) as f64).into())
+            .collect();
+        let inflow = |edges: std::ops::Range<usize>, rate: f64| {
+            edges
+                .map(|e| (e, CommodityVec::from([(0, rate.into())])))
+                .collect::<Vec<_>>()
+        };
+        let load = |edges: std::ops::Range<usize>, dynamic_flow: &mut DynamicFlow<F64>| {
+            let (capacity, inv_capacity, travel_time) = (&capacity, &inv_capacity, &travel_time);
+            dynamic_flow.extend(
+                inflow(edges.clone(), 30.0),
+                Some(1.0.into()),
+                capacity,
+                inv_capacity,
+                travel_time,
+            );
+            dynamic_flow.extend(
+                inflow(edges, 0.5),
+                None,
+                capacity,
+                inv_capacity,
+                travel_time,
+            );
+            while dynamic_flow.built_until < F64::INFINITY {
+                dynamic_flow.extend(Vec::new(), None, capacity, inv_capacity, travel_time);
+            }
+        };
+
+        let mut parallel: DynamicFlow<F64> = DynamicFlow::new(num_edges);
+        load(0..num_edges, &mut parallel);
+        for edge in (0..num_edges).step_by(37) {
+            let mut sequential: DynamicFlow<F64> = DynamicFlow::new(num_edges);
+            load(edge..edge + 1, &mut sequential);
+            assert_eq!(parallel.queues[edge], sequential.queues[edge]);
+            assert_eq!(
+                parallel.outflow[edge].function_by_comm(),
+                sequential.outflow[edge].function_by_comm()
+            );
+        }
+    }
+
+    #[test]
+    fn it_copies_only_modified_edges_of_a_clone() {
+        let (capacity, travel_time) = ([1.0.into(), 1.0.into()], [1.0.into(), 1.0.into()]);
+        let inflow = |rate: f64| {
+            HashMap::from([
+                (0, CommodityVec::from([(0, 2.0.into())])),
+                (1, CommodityVec::from([(0, rate.into())])),
+            ])
+        };
+        let mut base: DynamicFlow<F64> = DynamicFlow::new(2);
+        base.extend(inflow(1.0), None, &capacity, &capacity, &travel_time);
+        let before = base.queues[1].clone();
+
+        let mut alternative = base.clone();
+        let _ = alternative.copy_inflow_at_built_until(0);
+        let _ = alternative.copy_outflow_at_built_until(0);
+        // Edge 0 keeps its inflow.
+        alternative.extend(inflow(3.0), None, &capacity, &capacity, &travel_time);
+        assert!(Arc::ptr_eq(&base.inflow[0], &alternative.inflow[0]));
+        assert!(Arc::ptr_eq(&base.queues[0], &alternative.queues[0]));
+        assert!(Arc::ptr_eq(&base.outflow[0], &alternative.outflow[0]));
+        assert!(!Arc::ptr_eq(&base.queues[1], &alternative.queues[1]));
+        assert_eq!(base.queues[1], before);
+        assert_ne!(alternative.queues[1], before);
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn it_counts_the_work_done() {
+        let example = vickrey_bottleneck(F64::from(2.0), 1.0.into(), 1.0.into(), 2.0.into());
+        let flow = example.instance.load(&example.path_inflows);
+        let counters = flow.counters();
+        assert!(counters.phases > 0);
+        assert_eq!(counters.edge_extensions, 2);
+        assert_eq!(counters.depletions_processed, 1);
+        assert_eq!(counters.outflow_changes_processed, 2);
+        assert_eq!(counters.points_created.inflow, 2);
+        assert!(counters.points_created.queues > 0);
+        assert!(counters.maps_allocated > 0);
+    }
+
+    #[test]
+    fn it_reports_stats() {
+        let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(2);
+        let (capacity, travel_time) = ([1.0.into(), 1.0.into()], [1.0.into(), 1.0.into()]);
+        dynamic_flow.extend(
+            HashMap::from([(0, CommodityVec::from([(0, 2.0.into()), (1, 1.0.into())]))]),
+            None,
+            &capacity,
+            &capacity,
+            &travel_time,
+        );
+        assert_eq!(dynamic_flow.stats().pending_outflow_changes, 0);
+
+        dynamic_flow.extend(
+            HashMap::from([(0, CommodityVec::from([(0, 0.5.into())]))]),
+            None,
+            &capacity,
+            &capacity,
+            &travel_time,
+        );
+        assert_eq!(dynamic_flow.stats().pending_depletions, 1);
+        while dynamic_flow.built_until < F64::INFINITY {
+            dynamic_flow.extend(HashMap::new(), None, &capacity, &capacity, &travel_time);
+        }
+
+        let stats = dynamic_flow.stats();
+        assert_eq!(stats.pending_outflow_changes, 0);
+        assert_eq!(stats.pending_depletions, 0);
+        assert_eq!(stats.breakpoints[1], 1);
+        // Two commodities in and out of edge 0 and the queues of both edges
+        assert_eq!(stats.largest_functions.len(), 6);
+        assert_eq!(
+            stats.largest_functions[0].function,
+            FlowFunction::Outflow {
+                edge: 0,
+                commodity: 0
+            }
+        );
+        assert!(stats
+            .largest_functions
+            .windows(2)
+            .all(|w| w[0].breakpoints >= w[1].breakpoints));
+        let total: usize = stats.largest_functions.iter().map(|f| f.breakpoints).sum();
+        assert_eq!(total, stats.breakpoints.iter().sum::<usize>());
+        assert!(stats.heap_bytes > 0);
+    }
+
+    #[test]
+    fn it_uses_the_tolerance_policy() {
+        let breakpoints = |mut dynamic_flow: DynamicFlow<F64>| {
+            for (time, rate) in [(1.0, 1.0), (2.0, 1.005)] {
+                dynamic_flow.extend(
+                    HashMap::from([(0, CommodityVec::from([(0, rate.into())]))]),
+                    Some(time.into()),
+                    &[2.0.into()],
+                    &[0.5.into()],
+                    &[1.0.into()],
+                );
+            }
+            dynamic_flow.inflow[0].function_by_comm()[&0].points().len()
+        };
+        assert_eq!(breakpoints(DynamicFlow::new(1)), 2);
+        let loose = TolerancePolicy::new(F64::TOL, 0.01.into());
+        assert_eq!(breakpoints(DynamicFlow::new(1).with_tolerance(loose)), 1);
+    }
+
+    #[test]
+    fn it_reports_imprecise_depletions() {
+        let example = vickrey_bottleneck::<F64>(
+            3.0.into(),
+            (2.0 / 3.0).into(),
+            1.0.into(),
+            (1.0 / 3.0).into(),
+        );
+        let flow = example.instance.load(&example.path_inflows);
+        assert!(flow.tolerance_violations().is_empty());
+
+        let mut flow: DynamicFlow<F64> = DynamicFlow::new(1);
+        flow.check_depletion(0, 1.0.into(), 1e-9.into(), 1e3.into());
+        assert!(flow.tolerance_violations().is_empty());
+        flow.check_depletion(0, 1.0.into(), (-1e-3).into(), 1e3.into());
+        assert_eq!(flow.tolerance_violations().len(), 1);
+        assert_eq!(flow.tolerance_violations()[0].residual, -1e-3);
+    }
+
+    #[test]
+    fn it_reuses_rate_maps() {
+        let load = |mut dynamic_flow: DynamicFlow<F64>| {
+            for phase in 0..100 {
+                let rate = if phase % 2 == 0 { 2.0 } else { 0.5 };
+                dynamic_flow.extend(
+                    HashMap::from([(0, CommodityVec::from([(0, rate.into())]))]),
+                    Some(F64::from(phase as f64 + 1.0)),
+                    &[1.0.into()],
+                    &[1.0.into()],
+                    &[1.0.into()],
+                );
+            }
+            dynamic_flow.rate_pool_stats()
+        };
+        // Only the maps of the first phases are allocated, later phases reuse them.
+        let (allocated, reused) = load(DynamicFlow::new(1));
+        assert!(allocated < 10 && reused > 90);
+        let (allocated, reused) = load(DynamicFlow::new(1).without_rate_pooling());
+        assert!(allocated > 90 && reused == 0);
+    }
+
+    #[test]
+    fn it_skips_unchanged_inflows() {
+        let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(1);
+        for (time, rate) in [(1.0, 1.0), (2.0, 1.0 + 1e-12), (3.0, 1.0)] {
+            dynamic_flow.extend(
+                HashMap::from([(0, CommodityVec::from([(0, rate.into()), (1, 0.0.into())]))]),
+                Some(time.into()),
+                &[2.0.into()],
+                &[1.0.into()],
+                &[1.0.into()],
+            );
+        }
+        let inflow = &dynamic_flow.inflow[0].function_by_comm()[&0];
+        assert_eq!(inflow.points().len(), 1);
+        assert!(dynamic_flow.outflow_changes.is_empty());
+        // The maps of the unchanged phases go straight back to the pool.
+        let (allocated, reused) = dynamic_flow.rate_pool_stats();
+        assert_eq!((allocated, reused), (1, 0));
+    }
+
+    #[test]
+    fn it_truncates_the_history() {
+        let example = vickrey_bottleneck::<F64>(2.0.into(), 1.0.into(), 1.0.into(), 2.0.into());
+        let mut flow = example.instance.load(&example.path_inflows);
+        let original_queue = flow.queues[0].clone();
+        let original_outflow = flow.outflow[0].function_by_comm()[&0].clone();
+
+        let inflow = flow.inflow[0].accumulative();
+        assert_eq!(inflow.eval(1.0), 2.0);
+        assert_eq!(inflow.eval(3.0), 4.0);
+        assert_eq!(flow.outflow[0].accumulative().eval(5.0), 4.0);
+
+        let history = flow.drain_history(3.0.into());
+        assert_eq!(history.queues[0].eval(2.0), 2.0);
+        assert_eq!(history.outflow[0][&0].eval(2.0), 1.0);
+        assert_eq!(flow.queues[0].domain()[0], 3.0);
+        for t in [3.0, 3.5, 4.0, 6.0] {
+            assert_eq!(flow.queues[0].eval(t), original_queue.eval(t));
+            let outflow = &flow.outflow[0].function_by_comm()[&0];
+            assert_eq!(outflow.eval(t), original_outflow.eval(t));
+        }
+        assert!(flow.queues[0].points().len() < original_queue.points().len());
+    }
+
+    #[test]
+    fn it_converts_between_backends() {
+        let parameters = [3.0, 0.5, 1.0, 0.25];
+        let [rate, capacity, travel_time, horizon] = parameters.map(F64::from);
+        let float = vickrey_bottleneck(rate, capacity, travel_time, horizon);
+        let float_flow = float.instance.load(&float.path_inflows);
+        let lifted = float_flow.map_num(|x| Rational128::from(x.to_f64()));
+
+        let [rate, capacity, travel_time, horizon] = parameters.map(Rational128::from);
+        let exact = vickrey_bottleneck(rate, capacity, travel_time, horizon);
+        let exact_flow = exact.instance.load(&exact.path_inflows);
+        assert_eq!(lifted.built_until, exact_flow.built_until);
+        assert_eq!(lifted.queues, exact_flow.queues);
+        assert_eq!(
+            lifted.outflow[0].function_by_comm(),
+            exact_flow.outflow[0].function_by_comm()
+        );
+
+        let lowered = exact_flow.map_num(|x| F64::from(x.to_f64()));
+        assert_eq!(lowered.queues, float_flow.queues);
+    }
+
+    #[test]
+    fn it_restricts_to_a_time_window() {
+        let instance = vickrey_bottleneck::<F64>(2.0.into(), 1.0.into(), 1.0.into(), 4.0.into());
+        let flow = instance.instance.load(&instance.path_inflows);
+        let window = TimeWindow {
+            from: 2.0.into(),
+            to: 3.0.into(),
+        };
+        let restricted = flow.restrict(window);
+        assert_eq!(restricted.queues[0].domain(), [window.from, window.to]);
+        assert_eq!(restricted.queues[0].eval(2.5), flow.queues[0].eval(2.5));
+        let outflow = &restricted.outflow[0].function_by_comm()[&0];
+        assert_eq!(outflow.domain(), [window.from, window.to]);
+        assert_eq!(outflow.eval(2.5), 1.0);
+        assert!(restricted.num_breakpoints() < flow.num_breakpoints());
+
+        let early = flow.restrict(TimeWindow {
+            from: (-5.0).into(),
+            to: (-1.0).into(),
+        });
+        assert_eq!(
+            early.outflow[0].function_by_comm()[&0].domain(),
+            [F64::ZERO; 2]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "contains edge 0 more than once")]
+    fn it_rejects_duplicate_edges() {
+        let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(1);
+        dynamic_flow.extend(
+            [
+                (0, CommodityVec::from([(0, 1.0.into())])),
+                (0, CommodityVec::from([(1, 1.0.into())])),
+            ],
+            None,
+            &[1.0.into()],
+            &[1.0.into()],
+            &[1.0.into()],
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "validate")]
+    #[should_panic(expected = "commodity 0 into edge 0")]
+    fn it_rejects_nan_rates_when_validating() {
+        let mut dynamic_flow: DynamicFlow<F64> = DynamicFlow::new(1);
+        dynamic_flow.extend(
+            HashMap::from([(0, CommodityVec::from([(0, f64::NAN.into())]))]),
+            None,
+            &[1.0.into()],
+            &[1.0.into()],
+            &[1.0.into()],
+        );
+    }
 }
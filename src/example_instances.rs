@@ -0,0 +1,218 @@
+use crate::{
+    instance::{Commodity, Instance},
+    network::Network,
+    num::{max, Num},
+    piecewise_constant::PiecewiseConstant,
+    points,
+};
+
+/// A small instance together with its analytically known equilibrium.
+#[derive(Debug, Clone)]
+pub struct ExampleInstance<T: Num> {
+    pub instance: Instance<T>,
+    /// The equilibrium path inflows, where path_inflows[i][j] belongs to path j of commodity i.
+    pub path_inflows: Vec<Vec<PiecewiseConstant<T>>>,
+    /// The total travel time of all particles in the equilibrium.
+    pub total_travel_time: T,
+    /// The maximum queue length of each edge in the equilibrium.
+    pub max_queues: Vec<T>,
+}
+
+/// Vickrey's bottleneck: A single edge receiving the constant inflow `rate` during `[0, duration)`.
+/// If the rate exceeds the capacity, the queue grows linearly up to `(rate - capacity)·duration`.
+pub fn vickrey_bottleneck<T: Num>(
+    rate: T,
+    capacity: T,
+    travel_time: T,
+    duration: T,
+) -> ExampleInstance<T> {
+    let mut network = Network::new();
+    network.add_edge(0, 1, capacity, travel_time);
+    let inflow = PiecewiseConstant::new(
+        [-T::INFINITY, T::INFINITY],
+        points![(T::ZERO, rate), (duration, T::ZERO)],
+    );
+
+    let two = T::ONE + T::ONE;
+    let queue_slope = max(rate - capacity, T::ZERO);
+    // A particle entering at time θ waits (rate - capacity)·θ / capacity.
+    let waiting_time = rate * queue_slope * duration * duration / (two * capacity);
+    ExampleInstance {
+        instance: Instance::new(
+            network,
+            vec![Commodity {
+                paths: vec![vec![0]],
+                inflow: inflow.clone(),
+            }],
+        ),
+        path_inflows: vec![vec![inflow]],
+        total_travel_time: rate * duration * travel_time + waiting_time,
+        max_queues: vec![queue_slope * duration],
+    }
+}
+
+/// Braess' network with nodes s=0, v=1, w=2, t=3 and unit capacities:
+/// The paths s-v-t (edges 0, 4) and s-w-t (edges 3, 2) take 4 time units while the zig-zag path
+/// s-v-w-t (edges 0, 1, 2) takes 2. For `rate <= 1`, no queues build up and the equilibrium
+/// routes all particles along the zig-zag path.
+pub fn braess<T: Num>(rate: T, duration: T) -> ExampleInstance<T> {
+    debug_assert!(
+        rate <= T::ONE,
+        "Only rates up to the capacity have a known solution."
+    );
+    let two = T::ONE + T::ONE;
+    let three = two + T::ONE;
+    let mut network = Network::new();
+    network.add_edge(0, 1, T::ONE, T::ONE);
+    network.add_edge(1, 2, T::ONE, T::ZERO);
+    network.add_edge(2, 3, T::ONE, T::ONE);
+    network.add_edge(0, 2, T::ONE, three);
+    network.add_edge(1, 3, T::ONE, three);
+
+    let inflow = PiecewiseConstant::new(
+        [-T::INFINITY, T::INFINITY],
+        points![(T::ZERO, rate), (duration, T::ZERO)],
+    );
+    let unused = PiecewiseConstant::new([-T::INFINITY, T::INFINITY], points![(T::ZERO, T::ZERO)]);
+    ExampleInstance {
+        instance: Instance::new(
+            network,
+            vec![Commodity {
+                paths: vec![vec![0, 4], vec![3, 2], vec![0, 1, 2]],
+                inflow: inflow.clone(),
+            }],
+        ),
+        path_inflows: vec![vec![unused.clone(), unused, inflow]],
+        total_travel_time: two * rate * duration,
+        max_queues: vec![T::ZERO; 5],
+    }
+}
+
+/// Two parallel edges from node 0 to node 1 with unit capacities and travel times 1 and 2, which a
+/// single commodity enters with rate 3 during `[0, duration)` for `duration >= 1/2`.
+/// In the equilibrium, all particles take the first edge until its waiting time reaches 1 at time
+/// 1/2; afterwards, both edges receive rate 3/2 and their queues grow with rate 1/2.
+pub fn two_parallel_edges<T: Num>(duration: T) -> ExampleInstance<T> {
+    let two = T::ONE + T::ONE;
+    let three = two + T::ONE;
+    let half = T::ONE / two;
+    debug_assert!(duration >= half, "The first edge must fill up first.");
+    let mut network = Network::new();
+    network.add_edge(0, 1, T::ONE, T::ONE);
+    network.add_edge(0, 1, T::ONE, two);
+
+    let inflow = PiecewiseConstant::new(
+        [-T::INFINITY, T::INFINITY],
+        points![(T::ZERO, three), (duration, T::ZERO)],
+    );
+    let first_path = PiecewiseConstant::new(
+        [-T::INFINITY, T::INFINITY],
+        points![(T::ZERO, three), (half, three / two), (duration, T::ZERO)],
+    );
+    let second_path = PiecewiseConstant::new(
+        [-T::INFINITY, T::INFINITY],
+        points![(T::ZERO, T::ZERO), (half, three / two), (duration, T::ZERO)],
+    );
+    // Both paths take 2 + (θ - 1/2)/2 for particles entering at time θ >= 1/2.
+    let split = duration - half;
+    let four = two + two;
+    ExampleInstance {
+        instance: Instance::new(
+            network,
+            vec![Commodity {
+                paths: vec![vec![0], vec![1]],
+                inflow,
+            }],
+        ),
+        path_inflows: vec![vec![first_path, second_path]],
+        total_travel_time: (three * three + three * split * split) / four + three * two * split,
+        max_queues: vec![T::ONE + split / two, split / two],
+    }
+}
+
+/// Two nested merges: Commodities 0 and 1 (rate 1 each) merge into the unit-capacity edge 2,
+/// whose outflow merges with commodity 2 (rate 1) into edge 4 of capacity 3/2.
+/// All inflows last for one time unit. The queue of edge 2 grows to 1 at time 2 and depletes
+/// at time 3, the queue of edge 4 grows to 1/2 at time 3 and depletes at time 4.
+pub fn nested_merges<T: Num>() -> ExampleInstance<T> {
+    let two = T::ONE + T::ONE;
+    let three = two + T::ONE;
+    let ten = (0..10).map(|_| T::ONE).sum();
+    let mut network = Network::new();
+    network.add_edge(0, 2, ten, T::ONE);
+    network.add_edge(1, 2, ten, T::ONE);
+    network.add_edge(2, 4, T::ONE, T::ONE);
+    network.add_edge(3, 4, ten, two);
+    network.add_edge(4, 5, three / two, T::ONE);
+
+    let inflow = PiecewiseConstant::new(
+        [-T::INFINITY, T::INFINITY],
+        points![(T::ZERO, T::ONE), (T::ONE, T::ZERO)],
+    );
+    let paths = [vec![0, 2, 4], vec![1, 2, 4], vec![3, 4]];
+    ExampleInstance {
+        instance: Instance::new(
+            network,
+            paths
+                .iter()
+                .map(|path| Commodity {
+                    paths: vec![path.clone()],
+                    inflow: inflow.clone(),
+                })
+                .collect(),
+        ),
+        path_inflows: vec![vec![inflow.clone()]; 3],
+        // Free-flow travel times 9 plus waiting times ∫q₂ = 1 and ∫q₄ = 1/2.
+        total_travel_time: (0..21).map(|_| T::ONE).sum::<T>() / two,
+        max_queues: vec![T::ZERO, T::ZERO, T::ONE, T::ZERO, T::ONE / two],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{float::F64, price_of_anarchy::total_travel_times};
+
+    use super::{braess, nested_merges, two_parallel_edges, vickrey_bottleneck, ExampleInstance};
+
+    fn assert_solution(example: ExampleInstance<F64>) {
+        let flow = example.instance.load(&example.path_inflows);
+        assert_eq!(
+            total_travel_times(&example.instance, &flow).total,
+            example.total_travel_time
+        );
+        let max_queues: Vec<F64> = flow.queues().iter().map(|q| q.sup_norm()).collect();
+        assert_eq!(max_queues, example.max_queues);
+    }
+
+    #[test]
+    fn it_solves_vickreys_bottleneck() {
+        assert_solution(vickrey_bottleneck(
+            3.0.into(),
+            1.0.into(),
+            1.0.into(),
+            1.0.into(),
+        ));
+        assert_solution(vickrey_bottleneck(
+            1.0.into(),
+            2.0.into(),
+            1.0.into(),
+            4.0.into(),
+        ));
+    }
+
+    #[test]
+    fn it_solves_braess() {
+        assert_solution(braess(1.0.into(), 2.0.into()));
+    }
+
+    #[test]
+    fn it_solves_two_parallel_edges() {
+        assert_solution(two_parallel_edges(1.0.into()));
+        assert_solution(two_parallel_edges(2.5.into()));
+    }
+
+    #[test]
+    fn it_solves_nested_merges() {
+        assert_solution(nested_merges());
+    }
+}
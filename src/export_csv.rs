@@ -0,0 +1,212 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection, AGGREGATE_COMMODITY},
+    node_flow::NodeFlow,
+    num::{Num, ToF64},
+    time_space::{BoundarySide, TimeSpaceDiagram},
+};
+
+/// Writes the breakpoints of all queues in the long format `edge,time,value`. All numbers are
+/// written as decimal floating point numbers, also for exact number types.
+/// Between breakpoints, a queue is linear; after its last breakpoint, it continues with the last
+/// slope, which is zero for all queues of a completely built flow.
+pub fn write_queues_csv<T: Num + ToF64, W: Write>(
+    flow: &DynamicFlow<T>,
+    mut sink: W,
+) -> io::Result<()> {
+    sink.write_all(b"edge,time,value\n")?;
+    for (edge, queue) in flow.queues().iter().enumerate() {
+        for point in queue.points() {
+            writeln!(sink, "{},{},{}", edge, point.0.to_f64(), point.1.to_f64())?;
+        }
+    }
+    sink.flush()
+}
+
+/// Writes the steps of the rate functions of all edges in the long format
+/// `edge,commodity,time,value`, where the rate is `value` from `time` until the next step of the
+/// same edge and commodity. Commodities are sorted by id, and the single rate function of an
+/// aggregate `RateStorage` is written as commodity `total`.
+pub fn write_rates_csv<T: Num + ToF64, W: Write>(
+    rates: &[Arc<FlowRatesCollection<T>>],
+    sink: W,
+) -> io::Result<()> {
+    write_rates("edge", rates, sink)
+}
+
+/// Writes the rates of all nodes like `write_rates_csv`, in the format `node,commodity,time,value`.
+pub fn write_node_rates_csv<T: Num + ToF64, W: Write>(
+    rates: &[Arc<FlowRatesCollection<T>>],
+    sink: W,
+) -> io::Result<()> {
+    write_rates("node", rates, sink)
+}
+
+/// Writes the rate functions of the edges or nodes, whose index is the column `key`, see
+/// `write_rates_csv`.
+fn write_rates<T: Num + ToF64, W: Write>(
+    key: &str,
+    rates: &[Arc<FlowRatesCollection<T>>],
+    mut sink: W,
+) -> io::Result<()> {
+    writeln!(sink, "{},commodity,time,value", key)?;
+    for (index, rates) in rates.iter().enumerate() {
+        let mut commodities: Vec<usize> = rates.function_by_comm().keys().copied().collect();
+        commodities.sort_unstable();
+        for commodity in commodities {
+            let commodity_name = match commodity {
+                AGGREGATE_COMMODITY => String::from("total"),
+                _ => commodity.to_string(),
+            };
+            for point in rates.function_by_comm()[&commodity].points() {
+                let (time, value) = (point.0.to_f64(), point.1.to_f64());
+                writeln!(sink, "{},{},{},{}", index, commodity_name, time, value)?;
+            }
+        }
+    }
+    sink.flush()
+}
+
+/// Writes the breakpoints of the cumulative count curves of a time-space diagram in the long format
+/// `edge,side,position,time,cumulative`, where `side` is `entrance` or `exit`. The curves are
+/// linear between breakpoints and constant after the last one.
+pub fn write_time_space_csv<T: Num + ToF64, W: Write>(
+    diagram: &TimeSpaceDiagram<T>,
+    mut sink: W,
+) -> io::Result<()> {
+    sink.write_all(b"edge,side,position,time,cumulative\n")?;
+    for boundary in &diagram.boundaries {
+        let side = match boundary.side {
+            BoundarySide::Entrance => "entrance",
+            BoundarySide::Exit => "exit",
+        };
+        for point in boundary.cumulative.points() {
+            writeln!(
+                sink,
+                "{},{},{},{},{}",
+                boundary.edge,
+                side,
+                boundary.position.to_f64(),
+                point.0.to_f64(),
+                point.1.to_f64()
+            )?;
+        }
+    }
+    sink.flush()
+}
+
+/// Writes `queues.csv`, `inflow.csv` and `outflow.csv` into the existing directory `dir`.
+pub fn export_csv<T: Num + ToF64>(flow: &DynamicFlow<T>, dir: &Path) -> io::Result<()> {
+    write_queues_csv(flow, BufWriter::new(File::create(dir.join("queues.csv"))?))?;
+    write_rates_csv(
+        flow.inflow(),
+        BufWriter::new(File::create(dir.join("inflow.csv"))?),
+    )?;
+    write_rates_csv(
+        flow.outflow(),
+        BufWriter::new(File::create(dir.join("outflow.csv"))?),
+    )
+}
+
+/// Writes `node_inflow.csv` and `node_outflow.csv` into the existing directory `dir`.
+pub fn export_node_csv<T: Num + ToF64>(nodes: &NodeFlow<T>, dir: &Path) -> io::Result<()> {
+    write_node_rates_csv(
+        &nodes.inflow,
+        BufWriter::new(File::create(dir.join("node_inflow.csv"))?),
+    )?;
+    write_node_rates_csv(
+        &nodes.outflow,
+        BufWriter::new(File::create(dir.join("node_outflow.csv"))?),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        dynamic_flow::RateStorage,
+        float::F64,
+        network::Network,
+        network_loader::{NetworkLoader, PathInflow},
+        num::Num,
+        piecewise_constant::PiecewiseConstant,
+        point::Point,
+        points,
+        rational::Rational128,
+    };
+
+    use super::{write_queues_csv, write_rates_csv};
+
+    #[test]
+    fn it_writes_tidy_tables() {
+        let mut network = Network::new();
+        network.add_edge(0, 1, 1.0.into(), 1.0.into());
+        let inflow: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 2.0), (1.0, 0.0)],
+        );
+        let path_inflows = [PathInflow {
+            path: &[0],
+            inflow: &inflow,
+        }];
+        let flow = network.load(&path_inflows);
+
+        let mut queues = Vec::new();
+        write_queues_csv(&flow, &mut queues).unwrap();
+        assert_eq!(
+            String::from_utf8(queues).unwrap(),
+            "edge,time,value\n0,0,0\n0,1,1\n0,2,0\n"
+        );
+
+        let mut outflow = Vec::new();
+        write_rates_csv(flow.outflow(), &mut outflow).unwrap();
+        assert_eq!(
+            String::from_utf8(outflow).unwrap(),
+            "edge,commodity,time,value\n0,0,0,0\n0,0,1,1\n0,0,3,0\n"
+        );
+
+        let aggregate = NetworkLoader::new(&path_inflows)
+            .with_rate_storage(RateStorage::Aggregate)
+            .build_flow(
+                network.num_edges(),
+                network.capacity(),
+                network.inv_capacity(),
+                network.travel_time(),
+            );
+        let mut inflow = Vec::new();
+        write_rates_csv(aggregate.inflow(), &mut inflow).unwrap();
+        assert_eq!(
+            String::from_utf8(inflow).unwrap(),
+            "edge,commodity,time,value\n0,total,0,2\n0,total,1,0\n"
+        );
+    }
+
+    #[test]
+    fn it_writes_exact_numbers_as_decimals() {
+        let mut network: Network<Rational128> = Network::new();
+        network.add_edge(0, 1, Rational128::new(3, 1), Rational128::new(1, 3));
+        let inflow = PiecewiseConstant::new(
+            [-Rational128::INFINITY, Rational128::INFINITY],
+            vec![
+                Point(Rational128::ZERO, Rational128::ONE),
+                Point(Rational128::new(2, 3), Rational128::ZERO),
+            ],
+        );
+        let flow = network.load(&[PathInflow {
+            path: &[0],
+            inflow: &inflow,
+        }]);
+
+        let mut outflow = Vec::new();
+        write_rates_csv(flow.outflow(), &mut outflow).unwrap();
+        assert_eq!(
+            String::from_utf8(outflow).unwrap(),
+            "edge,commodity,time,value\n0,0,0,0\n0,0,0.3333333333333333,1\n0,0,1,0\n"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use std::{
+    ffi::{c_void, CString},
+    path::Path,
+    ptr,
+};
+
+use hdf5_sys::{
+    h5::{herr_t, hsize_t, H5open},
+    h5a::{H5Aclose, H5Acreate2, H5Awrite},
+    h5d::{H5Dclose, H5Dcreate2, H5Dwrite},
+    h5f::{H5Fclose, H5Fcreate, H5F_ACC_TRUNC},
+    h5g::{H5Gclose, H5Gcreate2},
+    h5i::hid_t,
+    h5p::H5P_DEFAULT,
+    h5s::{H5S_class_t, H5Sclose, H5Screate, H5Screate_simple, H5S_ALL},
+    h5t::H5T_NATIVE_DOUBLE,
+    LOCK,
+};
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection, AGGREGATE_COMMODITY},
+    format_error::FormatError,
+    network::Network,
+    num::{Num, ToF64},
+    point::Point,
+};
+
+/// An open HDF5 object that is closed when dropped.
+struct Object {
+    id: hid_t,
+    close: unsafe extern "C" fn(hid_t) -> herr_t,
+}
+
+impl Object {
+    fn new(
+        id: hid_t,
+        close: unsafe extern "C" fn(hid_t) -> herr_t,
+        what: &str,
+    ) -> Result<Self, FormatError> {
+        if id < 0 {
+            return Err(FormatError::Hdf5(format!("Cannot {}.", what)));
+        }
+        Ok(Object { id, close })
+    }
+}
+
+impl Drop for Object {
+    fn drop(&mut self) {
+        unsafe { (self.close)(self.id) };
+    }
+}
+
+fn check(status: herr_t, what: &str) -> Result<(), FormatError> {
+    if status < 0 {
+        return Err(FormatError::Hdf5(format!("Cannot {}.", what)));
+    }
+    Ok(())
+}
+
+fn c_name(name: &str) -> CString {
+    CString::new(name).expect("Names of HDF5 objects do not contain null bytes.")
+}
+
+fn create_group(loc: &Object, name: &str) -> Result<Object, FormatError> {
+    let c_name = c_name(name);
+    let id = unsafe {
+        H5Gcreate2(
+            loc.id,
+            c_name.as_ptr(),
+            H5P_DEFAULT,
+            H5P_DEFAULT,
+            H5P_DEFAULT,
+        )
+    };
+    Object::new(id, H5Gclose, &format!("create the group `{}`", name))
+}
+
+/// Attaches the scalar attribute `name` with `value` to `loc`.
+fn write_attribute(loc: &Object, name: &str, value: f64) -> Result<(), FormatError> {
+    let c_name = c_name(name);
+    let what = format!("write the attribute `{}`", name);
+    unsafe {
+        let space = Object::new(H5Screate(H5S_class_t::H5S_SCALAR), H5Sclose, &what)?;
+        let attribute = Object::new(
+            H5Acreate2(
+                loc.id,
+                c_name.as_ptr(),
+                *H5T_NATIVE_DOUBLE,
+                space.id,
+                H5P_DEFAULT,
+                H5P_DEFAULT,
+            ),
+            H5Aclose,
+            &what,
+        )?;
+        check(
+            H5Awrite(
+                attribute.id,
+                *H5T_NATIVE_DOUBLE,
+                &value as *const f64 as *const c_void,
+            ),
+            &what,
+        )
+    }
+}
+
+/// Writes the points as the dataset `name` of `loc` with one row `(time, value)` per point.
+fn write_points<T: Num + ToF64>(
+    loc: &Object,
+    name: &str,
+    points: &[Point<T>],
+) -> Result<(), FormatError> {
+    let c_name = c_name(name);
+    let what = format!("write the dataset `{}`", name);
+    let rows: Vec<[f64; 2]> = points
+        .iter()
+        .map(|p| [p.0.to_f64(), p.1.to_f64()])
+        .collect();
+    let dims: [hsize_t; 2] = [rows.len() as hsize_t, 2];
+    unsafe {
+        let space = Object::new(
+            H5Screate_simple(2, dims.as_ptr(), ptr::null()),
+            H5Sclose,
+            &what,
+        )?;
+        let dataset = Object::new(
+            H5Dcreate2(
+                loc.id,
+                c_name.as_ptr(),
+                *H5T_NATIVE_DOUBLE,
+                space.id,
+                H5P_DEFAULT,
+                H5P_DEFAULT,
+                H5P_DEFAULT,
+            ),
+            H5Dclose,
+            &what,
+        )?;
+        check(
+            H5Dwrite(
+                dataset.id,
+                *H5T_NATIVE_DOUBLE,
+                H5S_ALL,
+                H5S_ALL,
+                H5P_DEFAULT,
+                rows.as_ptr() as *const c_void,
+            ),
+            &what,
+        )
+    }
+}
+
+/// Writes the group `name` of `loc` with a dataset of the steps of each commodity, named by the
+/// commodity or `aggregate` for the single rate function of an aggregate `RateStorage`.
+fn write_rates<T: Num + ToF64>(
+    loc: &Object,
+    name: &str,
+    rates: &FlowRatesCollection<T>,
+) -> Result<(), FormatError> {
+    let group = create_group(loc, name)?;
+    let mut commodities: Vec<usize> = rates.function_by_comm().keys().copied().collect();
+    commodities.sort_unstable();
+    for commodity in commodities {
+        let name = match commodity {
+            AGGREGATE_COMMODITY => String::from("aggregate"),
+            _ => commodity.to_string(),
+        };
+        write_points(&group, &name, rates.function_by_comm()[&commodity].points())?;
+    }
+    Ok(())
+}
+
+/// Writes the flow on `network` to the HDF5 file at `path`, replacing an existing file.
+/// Each edge `e` is the group `/edges/<e>` with the attributes `capacity` and `travel_time`, the
+/// dataset `queue` of the breakpoints of its queue, and the groups `inflow` and `outflow` with the
+/// steps of the rates by commodity, see `write_rates`. Each dataset has the shape `(n, 2)`
+/// with the time and the value of a point in each row.
+pub fn export_hdf5<T: Num + ToF64>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    path: &Path,
+) -> Result<(), FormatError> {
+    if flow.queues().len() != network.num_edges() {
+        return Err(FormatError::Invalid(String::from(
+            "The flow does not belong to the network.",
+        )));
+    }
+    let path = CString::new(path.as_os_str().as_encoded_bytes())
+        .map_err(|_| FormatError::Invalid(String::from("The path contains a null byte.")))?;
+    // The library is not thread-safe.
+    let _lock = LOCK.lock();
+    check(unsafe { H5open() }, "initialize the HDF5 library")?;
+    let file = Object::new(
+        unsafe { H5Fcreate(path.as_ptr(), H5F_ACC_TRUNC, H5P_DEFAULT, H5P_DEFAULT) },
+        H5Fclose,
+        "create the file",
+    )?;
+    let edges = create_group(&file, "edges")?;
+    for edge in 0..network.num_edges() {
+        let group = create_group(&edges, &edge.to_string())?;
+        write_attribute(&group, "capacity", network.capacity()[edge].to_f64())?;
+        write_attribute(&group, "travel_time", network.travel_time()[edge].to_f64())?;
+        write_points(&group, "queue", flow.queues()[edge].points())?;
+        write_rates(&group, "inflow", &flow.inflow()[edge])?;
+        write_rates(&group, "outflow", &flow.outflow()[edge])?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, ffi::c_void, process, ptr};
+
+    use hdf5_sys::{
+        h5::hsize_t,
+        h5a::{H5Aclose, H5Aopen, H5Aread},
+        h5d::{H5Dclose, H5Dget_space, H5Dopen2, H5Dread},
+        h5f::{H5Fclose, H5Fopen, H5F_ACC_RDONLY},
+        h5g::{H5Gclose, H5Gopen2},
+        h5p::H5P_DEFAULT,
+        h5s::{H5Sclose, H5Sget_simple_extent_dims, H5S_ALL},
+        h5t::H5T_NATIVE_DOUBLE,
+        LOCK,
+    };
+
+    use crate::{example_instances::vickrey_bottleneck, float::F64, point::Point};
+
+    use super::{c_name, check, export_hdf5, Object};
+
+    fn read_attribute(file: &Object, path: &str, name: &str) -> f64 {
+        let (path, name) = (c_name(path), c_name(name));
+        let mut value = 0.0;
+        unsafe {
+            let group = Object::new(
+                H5Gopen2(file.id, path.as_ptr(), H5P_DEFAULT),
+                H5Gclose,
+                "open the group",
+            )
+            .unwrap();
+            let attribute = Object::new(
+                H5Aopen(group.id, name.as_ptr(), H5P_DEFAULT),
+                H5Aclose,
+                "open the attribute",
+            )
+            .unwrap();
+            check(
+                H5Aread(
+                    attribute.id,
+                    *H5T_NATIVE_DOUBLE,
+                    &mut value as *mut f64 as *mut c_void,
+                ),
+                "read the attribute",
+            )
+            .unwrap();
+        }
+        value
+    }
+
+    fn read_points(file: &Object, path: &str) -> Vec<Point<F64>> {
+        let path = c_name(path);
+        let mut dims: [hsize_t; 2] = [0; 2];
+        unsafe {
+            let dataset = Object::new(
+                H5Dopen2(file.id, path.as_ptr(), H5P_DEFAULT),
+                H5Dclose,
+                "open the dataset",
+            )
+            .unwrap();
+            let space = Object::new(H5Dget_space(dataset.id), H5Sclose, "get the space").unwrap();
+            assert_eq!(
+                H5Sget_simple_extent_dims(space.id, dims.as_mut_ptr(), ptr::null_mut()),
+                2
+            );
+            let mut rows = vec![[0.0; 2]; dims[0] as usize];
+            check(
+                H5Dread(
+                    dataset.id,
+                    *H5T_NATIVE_DOUBLE,
+                    H5S_ALL,
+                    H5S_ALL,
+                    H5P_DEFAULT,
+                    rows.as_mut_ptr() as *mut c_void,
+                ),
+                "read the dataset",
+            )
+            .unwrap();
+            rows.into_iter()
+                .map(|[time, value]| Point(time.into(), value.into()))
+                .collect()
+        }
+    }
+
+    #[test]
+    fn it_writes_and_reads_back_hdf5_files() {
+        let example = vickrey_bottleneck::<F64>(3.0.into(), 1.0.into(), 2.0.into(), 1.0.into());
+        let network = &example.instance.network;
+        let flow = example.instance.load(&example.path_inflows);
+        // A file per process, so that concurrent test runs do not collide.
+        let path = env::temp_dir().join(format!("dynamic-flows-rs-{}.h5", process::id()));
+        export_hdf5(network, &flow, &path).unwrap();
+
+        let _lock = LOCK.lock();
+        let path = c_name(path.to_str().unwrap());
+        let file = Object::new(
+            unsafe { H5Fopen(path.as_ptr(), H5F_ACC_RDONLY, H5P_DEFAULT) },
+            H5Fclose,
+            "open the file",
+        )
+        .unwrap();
+        assert_eq!(read_attribute(&file, "/edges/0", "capacity"), 1.0);
+        assert_eq!(read_attribute(&file, "/edges/0", "travel_time"), 2.0);
+        assert_eq!(
+            read_points(&file, "/edges/0/queue"),
+            flow.queues()[0].points()
+        );
+        for (name, rates) in [("inflow", flow.inflow()), ("outflow", flow.outflow())] {
+            for (commodity, f) in rates[0].function_by_comm() {
+                let path = format!("/edges/0/{}/{}", name, commodity);
+                assert_eq!(read_points(&file, &path), f.points());
+            }
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use std::{fs::File, io::Write, mem, path::Path, sync::Arc};
+
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection, AGGREGATE_COMMODITY},
+    format_error::FormatError,
+    num::{Num, ToF64},
+};
+
+/// The number of rows buffered before they are written as a record batch, which bounds the memory
+/// used by an export independently of the size of the flow.
+const BATCH_ROWS: usize = 1 << 16;
+
+/// The columns of the rows buffered for the next record batch.
+#[derive(Default)]
+struct Rows {
+    edge: Vec<u64>,
+    commodity: Vec<Option<u64>>,
+    time: Vec<f64>,
+    value: Vec<f64>,
+}
+
+impl Rows {
+    /// Moves the buffered rows into a record batch of `schema`, which has the `commodity` column
+    /// if and only if it has four columns.
+    fn take_batch(&mut self, schema: &SchemaRef) -> Result<RecordBatch, FormatError> {
+        let mut columns: Vec<ArrayRef> =
+            vec![Arc::new(UInt64Array::from(mem::take(&mut self.edge)))];
+        let commodity = mem::take(&mut self.commodity);
+        if schema.fields().len() == 4 {
+            columns.push(Arc::new(UInt64Array::from(commodity)));
+        }
+        columns.push(Arc::new(Float64Array::from(mem::take(&mut self.time))));
+        columns.push(Arc::new(Float64Array::from(mem::take(&mut self.value))));
+        Ok(RecordBatch::try_new(schema.clone(), columns)
+            .map_err(parquet::errors::ParquetError::from)?)
+    }
+}
+
+fn schema(with_commodity: bool) -> SchemaRef {
+    let mut fields = vec![Field::new("edge", DataType::UInt64, false)];
+    if with_commodity {
+        fields.push(Field::new("commodity", DataType::UInt64, true));
+    }
+    fields.push(Field::new("time", DataType::Float64, false));
+    fields.push(Field::new("value", DataType::Float64, false));
+    Arc::new(Schema::new(fields))
+}
+
+/// Writes the breakpoints of all queues with the columns `edge`, `time` and `value`, like
+/// `write_queues_csv`.
+pub fn write_queues_parquet<T: Num + ToF64, W: Write + Send>(
+    flow: &DynamicFlow<T>,
+    sink: W,
+) -> Result<(), FormatError> {
+    let schema = schema(false);
+    let mut writer = ArrowWriter::try_new(sink, schema.clone(), None)?;
+    let mut rows = Rows::default();
+    for (edge, queue) in flow.queues().iter().enumerate() {
+        for point in queue.points() {
+            rows.edge.push(edge as u64);
+            rows.time.push(point.0.to_f64());
+            rows.value.push(point.1.to_f64());
+            if rows.edge.len() == BATCH_ROWS {
+                writer.write(&rows.take_batch(&schema)?)?;
+            }
+        }
+    }
+    writer.write(&rows.take_batch(&schema)?)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes the steps of the rate functions of all edges with the columns `edge`, `commodity`,
+/// `time` and `value`, like `write_rates_csv`. The commodity of the single rate function of an
+/// aggregate `RateStorage` is null.
+pub fn write_rates_parquet<T: Num + ToF64, W: Write + Send>(
+    rates: &[Arc<FlowRatesCollection<T>>],
+    sink: W,
+) -> Result<(), FormatError> {
+    let schema = schema(true);
+    let mut writer = ArrowWriter::try_new(sink, schema.clone(), None)?;
+    let mut rows = Rows::default();
+    for (edge, rates) in rates.iter().enumerate() {
+        let mut commodities: Vec<usize> = rates.function_by_comm().keys().copied().collect();
+        commodities.sort_unstable();
+        for commodity in commodities {
+            let commodity_column = match commodity {
+                AGGREGATE_COMMODITY => None,
+                _ => Some(commodity as u64),
+            };
+            for point in rates.function_by_comm()[&commodity].points() {
+                rows.edge.push(edge as u64);
+                rows.commodity.push(commodity_column);
+                rows.time.push(point.0.to_f64());
+                rows.value.push(point.1.to_f64());
+                if rows.edge.len() == BATCH_ROWS {
+                    writer.write(&rows.take_batch(&schema)?)?;
+                }
+            }
+        }
+    }
+    writer.write(&rows.take_batch(&schema)?)?;
+    writer.close()?;
+    Ok(())
+}
+
+/// Writes `queues.parquet`, `inflow.parquet` and `outflow.parquet` into the existing directory
+/// `dir`.
+pub fn export_parquet<T: Num + ToF64>(
+    flow: &DynamicFlow<T>,
+    dir: &Path,
+) -> Result<(), FormatError> {
+    write_queues_parquet(flow, File::create(dir.join("queues.parquet"))?)?;
+    write_rates_parquet(flow.inflow(), File::create(dir.join("inflow.parquet"))?)?;
+    write_rates_parquet(flow.outflow(), File::create(dir.join("outflow.parquet"))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, fs::File};
+
+    use arrow_array::{Float64Array, UInt64Array};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use crate::{
+        bench::{grid_instance, DemandPattern},
+        float::F64,
+    };
+
+    use super::export_parquet;
+
+    #[test]
+    fn it_writes_parquet_files() {
+        let instance = grid_instance::<F64>(5, 5, 4, 2, DemandPattern::Peak { steps: 4 }, 20.0, 3);
+        let flow = instance.instance.load(&instance.path_inflows);
+        let dir = env::temp_dir().join("dynamic-flows-rs-parquet");
+        fs::create_dir_all(&dir).unwrap();
+        export_parquet(&flow, &dir).unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(
+            File::open(dir.join("outflow.parquet")).unwrap(),
+        )
+        .unwrap()
+        .build()
+        .unwrap();
+        let mut num_rows = 0;
+        for batch in reader {
+            let batch = batch.unwrap();
+            let edge = batch
+                .column(0)
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap();
+            let commodity = batch
+                .column(1)
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap();
+            let time = batch
+                .column(2)
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap();
+            let value = batch
+                .column(3)
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .unwrap();
+            for row in 0..batch.num_rows() {
+                let f = &flow.outflow()[edge.value(row) as usize].function_by_comm()
+                    [&(commodity.value(row) as usize)];
+                assert_eq!(f.eval(time.value(row)), value.value(row));
+            }
+            num_rows += batch.num_rows();
+        }
+        let expected: usize = flow
+            .outflow()
+            .iter()
+            .flat_map(|rates| rates.function_by_comm().values())
+            .map(|f| f.points().len())
+            .sum();
+        assert_eq!(num_rows, expected);
+    }
+}
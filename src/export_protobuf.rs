@@ -0,0 +1,201 @@
+use std::{io, io::Write, sync::Arc};
+
+use prost::Message;
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection},
+    network::Network,
+    num::{Num, ToF64},
+    piecewise_constant::PiecewiseConstant,
+    piecewise_linear::PiecewiseLinear,
+};
+
+/// The messages of `proto/dynamic_flows.proto`. They are written by hand instead of being generated
+/// by `prost-build`, so that building the crate does not require `protoc`; any change to the
+/// schema has to be mirrored here.
+pub mod proto {
+    use std::collections::HashMap;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Edge {
+        #[prost(uint64, tag = "1")]
+        pub tail: u64,
+        #[prost(uint64, tag = "2")]
+        pub head: u64,
+        #[prost(double, tag = "3")]
+        pub capacity: f64,
+        #[prost(double, tag = "4")]
+        pub travel_time: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct Network {
+        #[prost(uint64, tag = "1")]
+        pub num_nodes: u64,
+        #[prost(message, repeated, tag = "2")]
+        pub edges: Vec<Edge>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PiecewiseLinear {
+        #[prost(double, repeated, tag = "1")]
+        pub times: Vec<f64>,
+        #[prost(double, repeated, tag = "2")]
+        pub values: Vec<f64>,
+        #[prost(double, tag = "3")]
+        pub first_slope: f64,
+        #[prost(double, tag = "4")]
+        pub last_slope: f64,
+        #[prost(double, tag = "5")]
+        pub domain_start: f64,
+        #[prost(double, tag = "6")]
+        pub domain_end: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct PiecewiseConstant {
+        #[prost(double, repeated, tag = "1")]
+        pub times: Vec<f64>,
+        #[prost(double, repeated, tag = "2")]
+        pub values: Vec<f64>,
+        #[prost(double, tag = "3")]
+        pub domain_start: f64,
+        #[prost(double, tag = "4")]
+        pub domain_end: f64,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct FlowRates {
+        #[prost(map = "uint64, message", tag = "1")]
+        pub by_commodity: HashMap<u64, PiecewiseConstant>,
+    }
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    pub struct DynamicFlow {
+        #[prost(message, repeated, tag = "1")]
+        pub queues: Vec<PiecewiseLinear>,
+        #[prost(message, repeated, tag = "2")]
+        pub inflow: Vec<FlowRates>,
+        #[prost(message, repeated, tag = "3")]
+        pub outflow: Vec<FlowRates>,
+    }
+}
+
+fn linear_message<T: Num + ToF64>(f: &PiecewiseLinear<T>) -> proto::PiecewiseLinear {
+    let [domain_start, domain_end] = f.domain();
+    proto::PiecewiseLinear {
+        times: f.points().iter().map(|p| p.0.to_f64()).collect(),
+        values: f.points().iter().map(|p| p.1.to_f64()).collect(),
+        first_slope: f.first_slope().to_f64(),
+        last_slope: f.last_slope().to_f64(),
+        domain_start: domain_start.to_f64(),
+        domain_end: domain_end.to_f64(),
+    }
+}
+
+fn constant_message<T: Num + ToF64>(f: &PiecewiseConstant<T>) -> proto::PiecewiseConstant {
+    let [domain_start, domain_end] = f.domain();
+    proto::PiecewiseConstant {
+        times: f.points().iter().map(|p| p.0.to_f64()).collect(),
+        values: f.points().iter().map(|p| p.1.to_f64()).collect(),
+        domain_start: domain_start.to_f64(),
+        domain_end: domain_end.to_f64(),
+    }
+}
+
+fn rates_message<T: Num + ToF64>(rates: &[Arc<FlowRatesCollection<T>>]) -> Vec<proto::FlowRates> {
+    rates
+        .iter()
+        .map(|rates| proto::FlowRates {
+            by_commodity: rates
+                .function_by_comm()
+                .iter()
+                .map(|(&commodity, f)| (commodity as u64, constant_message(f)))
+                .collect(),
+        })
+        .collect()
+}
+
+impl<T: Num + ToF64> Network<T> {
+    pub fn to_protobuf(&self) -> proto::Network {
+        proto::Network {
+            num_nodes: self.num_nodes() as u64,
+            edges: (0..self.num_edges())
+                .map(|edge| proto::Edge {
+                    tail: self.tail(edge) as u64,
+                    head: self.head(edge) as u64,
+                    capacity: self.capacity()[edge].to_f64(),
+                    travel_time: self.travel_time()[edge].to_f64(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<T: Num + ToF64> DynamicFlow<T> {
+    pub fn to_protobuf(&self) -> proto::DynamicFlow {
+        proto::DynamicFlow {
+            queues: self.queues().iter().map(|q| linear_message(q)).collect(),
+            inflow: rates_message(self.inflow()),
+            outflow: rates_message(self.outflow()),
+        }
+    }
+}
+
+/// Writes the flow as a `DynamicFlow` message of `proto/dynamic_flows.proto`.
+pub fn write_flow_protobuf<T: Num + ToF64, W: Write>(
+    flow: &DynamicFlow<T>,
+    mut sink: W,
+) -> io::Result<()> {
+    sink.write_all(&flow.to_protobuf().encode_to_vec())?;
+    sink.flush()
+}
+
+/// Writes the network as a `Network` message of `proto/dynamic_flows.proto`.
+pub fn write_network_protobuf<T: Num + ToF64, W: Write>(
+    network: &Network<T>,
+    mut sink: W,
+) -> io::Result<()> {
+    sink.write_all(&network.to_protobuf().encode_to_vec())?;
+    sink.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+
+    use crate::{example_instances::nested_merges, float::F64, num::ToF64};
+
+    use super::{proto, write_flow_protobuf, write_network_protobuf};
+
+    #[test]
+    fn it_writes_decodable_messages() {
+        let instance = nested_merges::<F64>();
+        let network = &instance.instance.network;
+        let mut bytes = Vec::new();
+        write_network_protobuf(network, &mut bytes).unwrap();
+        let decoded = proto::Network::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded.num_nodes as usize, network.num_nodes());
+        assert_eq!(decoded.edges.len(), network.num_edges());
+        assert_eq!(decoded.edges[2].head as usize, network.head(2));
+
+        let flow = instance.instance.load(&instance.path_inflows);
+        let mut bytes = Vec::new();
+        write_flow_protobuf(&flow, &mut bytes).unwrap();
+        let decoded = proto::DynamicFlow::decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, flow.to_protobuf());
+        for (queue, expected) in decoded.queues.iter().zip(flow.queues()) {
+            assert_eq!(queue.times.len(), expected.points().len());
+            assert_eq!(queue.domain_end, f64::INFINITY);
+            assert_eq!(queue.last_slope, expected.last_slope().to_f64());
+        }
+        for (rates, expected) in decoded.outflow.iter().zip(flow.outflow()) {
+            for (&commodity, f) in expected.function_by_comm() {
+                assert_eq!(
+                    rates.by_commodity[&(commodity as u64)].values.len(),
+                    f.points().len()
+                );
+            }
+        }
+    }
+}
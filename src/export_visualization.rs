@@ -1,12 +1,26 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Write,
+    sync::Arc,
+};
+
 use crate::{
     dynamic_flow::{DynamicFlow, FlowRatesCollection},
-    num::Num,
+    format_error::FormatError,
+    instance::Instance,
+    node_flow::NodeFlow,
+    num::{Num, ToF64, Tolerance},
     piecewise_constant::PiecewiseConstant,
     piecewise_linear::PiecewiseLinear,
+    point::Point,
+    points,
+    tolerance::TolerancePolicy,
 };
 use serde::{
+    de::{self, Visitor},
     ser::{SerializeMap, SerializeStruct},
-    Serialize, Serializer,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 
 struct JsonNumber(f64);
@@ -30,6 +44,46 @@ impl Serialize for JsonNumber {
     }
 }
 
+impl<'de> Deserialize<'de> for JsonNumber {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct JsonNumberVisitor;
+
+        impl Visitor<'_> for JsonNumberVisitor {
+            type Value = JsonNumber;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a number, \"Infinity\", \"-Infinity\" or \"NaN\"")
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<JsonNumber, E> {
+                Ok(JsonNumber(v))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<JsonNumber, E> {
+                Ok(JsonNumber(v as f64))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<JsonNumber, E> {
+                Ok(JsonNumber(v as f64))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<JsonNumber, E> {
+                match v {
+                    "Infinity" => Ok(JsonNumber(f64::INFINITY)),
+                    "-Infinity" => Ok(JsonNumber(f64::NEG_INFINITY)),
+                    "NaN" => Ok(JsonNumber(f64::NAN)),
+                    _ => Err(E::invalid_value(de::Unexpected::Str(v), &self)),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(JsonNumberVisitor)
+    }
+}
+
 struct SerializableIterator<I: Serialize, T: Iterator<Item = I>>(T);
 
 impl<I: Serialize, T: Iterator<Item = I> + Clone> Serialize for SerializableIterator<I, T> {
@@ -43,7 +97,7 @@ impl<I: Serialize, T: Iterator<Item = I> + Clone> Serialize for SerializableIter
 
 pub struct VisualizationPiecewiseLinear<'a, T: Num>(&'a PiecewiseLinear<T>);
 
-impl<'a, T: Num> Serialize for VisualizationPiecewiseLinear<'a, T> {
+impl<'a, T: Num + ToF64> Serialize for VisualizationPiecewiseLinear<'a, T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -66,7 +120,7 @@ impl<'a, T: Num> Serialize for VisualizationPiecewiseLinear<'a, T> {
 
 pub struct VisualizationPiecewiseConstant<'a, T: Num>(&'a PiecewiseConstant<T>);
 
-impl<'a, T: Num> Serialize for VisualizationPiecewiseConstant<'a, T> {
+impl<'a, T: Num + ToF64> Serialize for VisualizationPiecewiseConstant<'a, T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -85,38 +139,315 @@ impl<'a, T: Num> Serialize for VisualizationPiecewiseConstant<'a, T> {
     }
 }
 
-pub struct VisualizationDynamicFlow<'a, T: Num>(&'a DynamicFlow<T>);
+pub struct VisualizationDynamicFlow<'a, T: Num>(pub &'a DynamicFlow<T>);
 
-impl<'a, T: Num> Serialize for VisualizationDynamicFlow<'a, T> {
+impl<'a, T: Num + ToF64> Serialize for VisualizationDynamicFlow<'a, T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
         let mut res = serializer.serialize_struct("DynamicFlow", 3)?;
+        serialize_flow_fields(&mut res, self.0)?;
+        res.end()
+    }
+}
+
+/// Serializes the fields `queues`, `inflow` and `outflow` of `VisualizationDynamicFlow`.
+fn serialize_flow_fields<S: SerializeStruct, T: Num + ToF64>(
+    res: &mut S,
+    flow: &DynamicFlow<T>,
+) -> Result<(), S::Error> {
+    res.serialize_field(
+        "queues",
+        &SerializableIterator(
+            flow.queues()
+                .iter()
+                .map(|q| VisualizationPiecewiseLinear(q)),
+        ),
+    )?;
+    res.serialize_field(
+        "inflow",
+        &SerializableIterator(flow.inflow().iter().map(|f| VisualizationFlowRates(f))),
+    )?;
+    res.serialize_field(
+        "outflow",
+        &SerializableIterator(flow.outflow().iter().map(|f| VisualizationFlowRates(f))),
+    )
+}
+
+impl<'a, T: Num + ToF64> VisualizationDynamicFlow<'a, T> {
+    /// Writes the same structure as the JSON export in the binary MessagePack format, which stores
+    /// numbers as exact doubles instead of decimal strings. Structs are encoded as maps with their
+    /// field names, and the commodities of the flow rates as integer keys.
+    pub fn write_msgpack<W: Write>(&self, sink: &mut W) -> Result<(), FormatError> {
+        self.serialize(&mut rmp_serde::Serializer::new(sink).with_struct_map())?;
+        Ok(())
+    }
+}
+
+/// A flow together with the instance it was built for, so that a frontend can draw the network.
+/// The fields of `VisualizationDynamicFlow` are preceded by `network`, with the number of nodes and
+/// the tail, head, capacity and travel time of each edge, and by `commodities`, with the paths of
+/// each commodity. Readers of `VisualizationDynamicFlow` can ignore the additional fields.
+pub struct VisualizationInstanceFlow<'a, T: Num> {
+    pub instance: &'a Instance<T>,
+    pub flow: &'a DynamicFlow<T>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VisualizationEdge {
+    tail: usize,
+    head: usize,
+    capacity: JsonNumber,
+    travel_time: JsonNumber,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VisualizationNetwork {
+    num_nodes: usize,
+    edges: Vec<VisualizationEdge>,
+}
+
+#[derive(Serialize)]
+struct VisualizationCommodity<'a> {
+    paths: &'a [Vec<usize>],
+}
+
+impl<'a, T: Num + ToF64> Serialize for VisualizationInstanceFlow<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let network = &self.instance.network;
+        let mut res = serializer.serialize_struct("InstanceFlow", 5)?;
         res.serialize_field(
-            "queues",
+            "network",
+            &VisualizationNetwork {
+                num_nodes: network.num_nodes(),
+                edges: (0..network.num_edges())
+                    .map(|edge| VisualizationEdge {
+                        tail: network.tail(edge),
+                        head: network.head(edge),
+                        capacity: JsonNumber(network.capacity()[edge].to_f64()),
+                        travel_time: JsonNumber(network.travel_time()[edge].to_f64()),
+                    })
+                    .collect(),
+            },
+        )?;
+        res.serialize_field(
+            "commodities",
             &SerializableIterator(
-                self.0
-                    .queues()
+                self.instance
+                    .commodities
                     .iter()
-                    .map(|q| VisualizationPiecewiseLinear(q)),
+                    .map(|c| VisualizationCommodity { paths: &c.paths }),
             ),
         )?;
+        serialize_flow_fields(&mut res, self.flow)?;
+        res.end()
+    }
+}
+
+/// Options of `WebVisualizationFlow`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebVisualizationOptions {
+    /// The coordinates of each node. By default, the nodes are placed on the unit circle.
+    pub node_positions: Option<Vec<[f64; 2]>>,
+    /// The CSS color of each commodity. By default, `DEFAULT_COLORS` are repeated.
+    pub commodity_colors: Option<Vec<String>>,
+}
+
+pub const DEFAULT_COLORS: [&str; 6] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b",
+];
+
+/// A flow in the JSON structure of the web visualization of the Python predictor project, i.e.
+/// `{"network": {"nodes", "edges", "commodities"}, "flow": {"inflow", "outflow", "queues"}}`,
+/// where the inflow and outflow of each edge are lists indexed by commodity of the instance.
+/// The flow rates of the paths of a commodity are added up, which requires per-commodity rates.
+pub struct WebVisualizationFlow<'a, T: Num> {
+    pub instance: &'a Instance<T>,
+    pub flow: &'a DynamicFlow<T>,
+    pub options: &'a WebVisualizationOptions,
+}
+
+#[derive(Serialize)]
+struct WebNode {
+    id: usize,
+    x: f64,
+    y: f64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebEdge {
+    id: usize,
+    from: usize,
+    to: usize,
+    capacity: JsonNumber,
+    transit_time: JsonNumber,
+}
+
+#[derive(Serialize)]
+struct WebCommodity<'a> {
+    id: usize,
+    color: &'a str,
+}
+
+#[derive(Serialize)]
+struct WebNetwork<'a> {
+    nodes: Vec<WebNode>,
+    edges: Vec<WebEdge>,
+    commodities: Vec<WebCommodity<'a>>,
+}
+
+impl<'a, T: Num + ToF64> WebVisualizationFlow<'a, T> {
+    /// Returns the network, or an error if the options do not cover all nodes and commodities.
+    fn network(&self) -> Result<WebNetwork<'a>, String> {
+        let network = &self.instance.network;
+        let num_nodes = network.num_nodes();
+        let num_commodities = self.instance.commodities.len();
+        if let Some(positions) = &self.options.node_positions {
+            if positions.len() < num_nodes {
+                return Err(format!(
+                    "There are {} node positions for {} nodes.",
+                    positions.len(),
+                    num_nodes
+                ));
+            }
+        }
+        if let Some(colors) = &self.options.commodity_colors {
+            if colors.len() < num_commodities {
+                return Err(format!(
+                    "There are {} commodity colors for {} commodities.",
+                    colors.len(),
+                    num_commodities
+                ));
+            }
+        }
+        let nodes = (0..num_nodes)
+            .map(|id| {
+                let [x, y] = match &self.options.node_positions {
+                    Some(positions) => positions[id],
+                    None => {
+                        let angle = 2.0 * std::f64::consts::PI * id as f64 / num_nodes as f64;
+                        [angle.cos(), angle.sin()]
+                    }
+                };
+                WebNode { id, x, y }
+            })
+            .collect();
+        let edges = (0..network.num_edges())
+            .map(|id| WebEdge {
+                id,
+                from: network.tail(id),
+                to: network.head(id),
+                capacity: JsonNumber(network.capacity()[id].to_f64()),
+                transit_time: JsonNumber(network.travel_time()[id].to_f64()),
+            })
+            .collect();
+        let options = self.options;
+        let commodities = (0..num_commodities)
+            .map(|id| WebCommodity {
+                id,
+                color: match &options.commodity_colors {
+                    Some(colors) => &colors[id],
+                    None => DEFAULT_COLORS[id % DEFAULT_COLORS.len()],
+                },
+            })
+            .collect();
+        Ok(WebNetwork {
+            nodes,
+            edges,
+            commodities,
+        })
+    }
+
+    /// Returns the rate functions of each edge by commodity of the instance, where the paths are
+    /// numbered consecutively by commodity as in `Instance::flat_path_inflows`.
+    fn rates_by_commodity(
+        &self,
+        rates: &[Arc<FlowRatesCollection<T>>],
+    ) -> Result<Vec<Vec<PiecewiseConstant<T>>>, String>
+    where
+        T: Tolerance,
+    {
+        let commodity_of_path: Vec<usize> = self
+            .instance
+            .commodities
+            .iter()
+            .enumerate()
+            .flat_map(|(commodity, c)| std::iter::repeat_n(commodity, c.paths.len()))
+            .collect();
+        let zero = PiecewiseConstant::new([T::ZERO, T::INFINITY], points![(T::ZERO, T::ZERO)]);
+        rates
+            .iter()
+            .map(|rates| {
+                let mut by_comm = vec![zero.clone(); self.instance.commodities.len()];
+                for (&path, f) in rates.function_by_comm() {
+                    let commodity = *commodity_of_path.get(path).ok_or_else(|| {
+                        String::from("The web visualization needs the rates of each path.")
+                    })?;
+                    by_comm[commodity] = &by_comm[commodity] + f;
+                }
+                Ok(by_comm)
+            })
+            .collect()
+    }
+}
+
+impl<'a, T: Num + Tolerance + ToF64> Serialize for WebVisualizationFlow<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let rates = |rates| {
+            self.rates_by_commodity(rates)
+                .map_err(<S::Error as serde::ser::Error>::custom)
+        };
+        let network = self
+            .network()
+            .map_err(<S::Error as serde::ser::Error>::custom)?;
+        let (inflow, outflow) = (rates(self.flow.inflow())?, rates(self.flow.outflow())?);
+        let mut res = serializer.serialize_struct("WebVisualization", 2)?;
+        res.serialize_field("network", &network)?;
         res.serialize_field(
-            "inflow",
-            &SerializableIterator(self.0.inflow().iter().map(|f| VisualizationFlowRates(f))),
-        )?;
-        res.serialize_field(
-            "outflow",
-            &SerializableIterator(self.0.outflow().iter().map(|f| VisualizationFlowRates(f))),
+            "flow",
+            &WebFlow {
+                inflow: web_rates(&inflow),
+                outflow: web_rates(&outflow),
+                queues: SerializableIterator(
+                    self.flow
+                        .queues()
+                        .iter()
+                        .map(|q| VisualizationPiecewiseLinear(q)),
+                ),
+            },
         )?;
         res.end()
     }
 }
 
+fn web_rates<T: Num + ToF64>(rates: &[Vec<PiecewiseConstant<T>>]) -> impl Serialize + '_ {
+    SerializableIterator(
+        rates.iter().map(|by_comm| {
+            SerializableIterator(by_comm.iter().map(VisualizationPiecewiseConstant))
+        }),
+    )
+}
+
+#[derive(Serialize)]
+struct WebFlow<R: Serialize, Q: Serialize> {
+    inflow: R,
+    outflow: R,
+    queues: Q,
+}
+
 pub struct VisualizationFlowRates<'a, T: Num>(&'a FlowRatesCollection<T>);
 
-impl<'a, T: Num> Serialize for VisualizationFlowRates<'a, T> {
+impl<'a, T: Num + ToF64> Serialize for VisualizationFlowRates<'a, T> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
@@ -130,17 +461,412 @@ impl<'a, T: Num> Serialize for VisualizationFlowRates<'a, T> {
     }
 }
 
+/// The rates of a `NodeFlow` as `{"inflow": [...], "outflow": [...]}`, where the rates of each
+/// node are written like the rates of an edge in `VisualizationDynamicFlow`.
+pub struct VisualizationNodeFlow<'a, T: Num>(pub &'a NodeFlow<T>);
+
+impl<'a, T: Num + ToF64> Serialize for VisualizationNodeFlow<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut res = serializer.serialize_struct("NodeFlow", 2)?;
+        res.serialize_field(
+            "inflow",
+            &SerializableIterator(self.0.inflow.iter().map(|f| VisualizationFlowRates(f))),
+        )?;
+        res.serialize_field(
+            "outflow",
+            &SerializableIterator(self.0.outflow.iter().map(|f| VisualizationFlowRates(f))),
+        )?;
+        res.end()
+    }
+}
+
+/// A function as written by `VisualizationPiecewiseLinear` or `VisualizationPiecewiseConstant`,
+/// where the slopes are only present for piecewise linear functions.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportedFunction {
+    times: Vec<JsonNumber>,
+    values: Vec<JsonNumber>,
+    first_slope: Option<JsonNumber>,
+    last_slope: Option<JsonNumber>,
+    domain: [JsonNumber; 2],
+}
+
+impl ImportedFunction {
+    fn points<T: Num + From<f64>>(&self) -> Result<Vec<Point<T>>, FormatError> {
+        let [start, end] = self.domain.each_ref().map(|x| x.0);
+        let valid = !self.times.is_empty()
+            && self.times.len() == self.values.len()
+            && start <= end
+            && self.times.first().unwrap().0 >= start
+            && self.times.last().unwrap().0 <= end
+            && self.times.windows(2).all(|w| w[0].0 < w[1].0)
+            && self.values.iter().all(|v| !v.0.is_nan());
+        if !valid {
+            return Err(FormatError::Invalid(String::from(
+                "A function has no points, unsorted times or points outside of its domain.",
+            )));
+        }
+        Ok(self
+            .times
+            .iter()
+            .zip(&self.values)
+            .map(|(time, value)| Point(time.0.into(), value.0.into()))
+            .collect())
+    }
+
+    fn to_piecewise_linear<T: Num + From<f64>>(&self) -> Result<PiecewiseLinear<T>, FormatError> {
+        let slope = |slope: &Option<JsonNumber>| match slope {
+            Some(JsonNumber(slope)) if !slope.is_nan() => Ok(*slope),
+            _ => Err(FormatError::Invalid(String::from(
+                "A queue has no valid slopes.",
+            ))),
+        };
+        Ok(PiecewiseLinear::new(
+            self.domain.each_ref().map(|x| x.0),
+            slope(&self.first_slope)?,
+            slope(&self.last_slope)?,
+            self.points()?,
+        ))
+    }
+
+    fn to_piecewise_constant<T: Num + From<f64>>(
+        &self,
+    ) -> Result<PiecewiseConstant<T>, FormatError> {
+        Ok(PiecewiseConstant::new(
+            self.domain.each_ref().map(|x| x.0),
+            self.points()?,
+        ))
+    }
+}
+
+/// A flow as written by `VisualizationDynamicFlow`.
+#[derive(Deserialize)]
+struct ImportedDynamicFlow {
+    queues: Vec<ImportedFunction>,
+    inflow: Vec<HashMap<usize, ImportedFunction>>,
+    outflow: Vec<HashMap<usize, ImportedFunction>>,
+}
+
+impl ImportedDynamicFlow {
+    fn to_flow<T: Num + Tolerance + From<f64>>(&self) -> Result<DynamicFlow<T>, FormatError> {
+        let num_edges = self.queues.len();
+        if self.inflow.len() != num_edges || self.outflow.len() != num_edges {
+            return Err(FormatError::Invalid(String::from(
+                "The queues, inflow and outflow have different numbers of edges.",
+            )));
+        }
+        let rates = |rates: &Vec<HashMap<usize, ImportedFunction>>| {
+            rates
+                .iter()
+                .map(|by_comm| {
+                    let functions = by_comm
+                        .iter()
+                        .map(|(&comm, f)| Ok((comm, f.to_piecewise_constant()?)))
+                        .collect::<Result<_, FormatError>>()?;
+                    Ok(FlowRatesCollection::from_functions(functions))
+                })
+                .collect::<Result<Vec<_>, FormatError>>()
+        };
+        let queues = self
+            .queues
+            .iter()
+            .map(ImportedFunction::to_piecewise_linear)
+            .collect::<Result<_, _>>()?;
+        Ok(DynamicFlow::from_functions(
+            queues,
+            rates(&self.inflow)?,
+            rates(&self.outflow)?,
+        ))
+    }
+}
+
+impl<T: Num + From<f64>> DynamicFlow<T> {
+    /// Reads a flow exported by `VisualizationDynamicFlow` as JSON, so that its metrics can be
+    /// computed again. The flow is completely built, see `DynamicFlow::from_functions`.
+    pub fn from_visualization_json(json: &str) -> Result<Self, FormatError>
+    where
+        T: Tolerance,
+    {
+        serde_json::from_str::<ImportedDynamicFlow>(json)?.to_flow()
+    }
+
+    /// Reads a flow exported by `VisualizationDynamicFlow::write_msgpack`.
+    pub fn from_visualization_msgpack(msgpack: &[u8]) -> Result<Self, FormatError>
+    where
+        T: Tolerance,
+    {
+        rmp_serde::from_slice::<ImportedDynamicFlow>(msgpack)?.to_flow()
+    }
+}
+
+/// Exports only the data of a flow that was built since the previous export, for streaming a flow
+/// to a frontend while it is being built.
+///
+/// Each export contains, for every function with breakpoints at or after the `built_until` of the
+/// previous export, its breakpoints from that time on. Existing breakpoints are only ever
+/// overwritten at the end of a function, so a frontend applies an export by dropping its
+/// breakpoints from the first exported time on and appending the exported ones.
+/// Rate functions of commodities that are new on an edge are exported in full with their domain.
+#[derive(Debug, Clone)]
+pub struct IncrementalExport<T: Num> {
+    exported_until: T,
+    // The exported rate functions by edge and commodity
+    exported_inflow: HashSet<(usize, usize)>,
+    exported_outflow: HashSet<(usize, usize)>,
+}
+
+impl<T: Num> IncrementalExport<T> {
+    pub fn new() -> Self {
+        IncrementalExport {
+            exported_until: -T::INFINITY,
+            exported_inflow: HashSet::new(),
+            exported_outflow: HashSet::new(),
+        }
+    }
+
+    /// Returns the data built since the previous call, which is serialized when it is exported.
+    pub fn next<'a>(&mut self, flow: &'a DynamicFlow<T>) -> VisualizationFlowDelta<'a, T> {
+        let since = self.exported_until;
+        self.exported_until = flow.built_until();
+        VisualizationFlowDelta {
+            flow,
+            since,
+            new_inflow: new_functions(flow.inflow(), &mut self.exported_inflow),
+            new_outflow: new_functions(flow.outflow(), &mut self.exported_outflow),
+        }
+    }
+}
+
+/// Returns the rate functions by edge and commodity that are not in `exported` and adds them.
+fn new_functions<T: Num>(
+    rates: &[Arc<FlowRatesCollection<T>>],
+    exported: &mut HashSet<(usize, usize)>,
+) -> HashSet<(usize, usize)> {
+    let mut new = HashSet::new();
+    for (edge, rates) in rates.iter().enumerate() {
+        for &comm in rates.function_by_comm().keys() {
+            if exported.insert((edge, comm)) {
+                new.insert((edge, comm));
+            }
+        }
+    }
+    new
+}
+
+impl<T: Num> Default for IncrementalExport<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the data built by each extension phase of a loading to `sink` as newline-delimited JSON,
+/// i.e. one `VisualizationFlowDelta` per line, so that a dashboard can follow a long loading live.
+/// Each line is flushed as soon as it is written.
+pub struct FlowExportSink<W: Write, T: Num> {
+    sink: W,
+    export: IncrementalExport<T>,
+}
+
+impl<W: Write, T: Num + ToF64> FlowExportSink<W, T> {
+    pub fn new(sink: W) -> Self {
+        FlowExportSink {
+            sink,
+            export: IncrementalExport::new(),
+        }
+    }
+
+    /// Writes the data of `flow` built since the previous call as a line.
+    pub fn write(&mut self, flow: &DynamicFlow<T>) -> Result<(), FormatError> {
+        serde_json::to_writer(&mut self.sink, &self.export.next(flow))?;
+        self.sink.write_all(b"\n")?;
+        self.sink.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+/// Returns the points at or after `since`, where points within the tolerance before `since` are
+/// included as they may have been overwritten.
+fn points_since<'a, T: Num>(
+    points: &'a [Point<T>],
+    since: T,
+    tol: &TolerancePolicy<T>,
+) -> &'a [Point<T>] {
+    let from = if since == -T::INFINITY {
+        since
+    } else {
+        since - tol.tolerance(since)
+    };
+    &points[points.partition_point(|p| p.0 < from)..]
+}
+
+struct VisualizationPointsDelta<'a, T: Num> {
+    points: &'a [Point<T>],
+    last_slope: Option<T>,
+    // Only for functions exported in full
+    domain: Option<[T; 2]>,
+}
+
+impl<'a, T: Num + ToF64> Serialize for VisualizationPointsDelta<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut res = serializer.serialize_struct("PointsDelta", 4)?;
+        res.serialize_field(
+            "times",
+            &SerializableIterator(self.points.iter().map(|p| JsonNumber(p.0.to_f64()))),
+        )?;
+        res.serialize_field(
+            "values",
+            &SerializableIterator(self.points.iter().map(|p| JsonNumber(p.1.to_f64()))),
+        )?;
+        if let Some(last_slope) = self.last_slope {
+            res.serialize_field("lastSlope", &JsonNumber(last_slope.to_f64()))?;
+        }
+        if let Some(domain) = self.domain {
+            res.serialize_field("domain", &domain.map(|x| JsonNumber(x.to_f64())))?;
+        }
+        res.end()
+    }
+}
+
+/// The data of a flow built after `since`, see `IncrementalExport`.
+pub struct VisualizationFlowDelta<'a, T: Num> {
+    flow: &'a DynamicFlow<T>,
+    since: T,
+    // The rate functions by edge and commodity exported for the first time
+    new_inflow: HashSet<(usize, usize)>,
+    new_outflow: HashSet<(usize, usize)>,
+}
+
+impl<'a, T: Num + ToF64> VisualizationFlowDelta<'a, T> {
+    fn queue_deltas(&self) -> SerializableEntries<usize, VisualizationPointsDelta<'a, T>> {
+        let (since, tol) = (self.since, self.flow.tolerance());
+        let deltas = self
+            .flow
+            .queues()
+            .iter()
+            .enumerate()
+            .filter_map(|(edge, queue)| {
+                let points = points_since(queue.points(), since, tol);
+                (!points.is_empty()).then_some((
+                    edge,
+                    VisualizationPointsDelta {
+                        points,
+                        last_slope: Some(queue.last_slope()),
+                        domain: None,
+                    },
+                ))
+            })
+            .collect();
+        SerializableEntries(deltas)
+    }
+
+    fn rates_deltas(
+        &self,
+        rates: &'a [Arc<FlowRatesCollection<T>>],
+        new: &HashSet<(usize, usize)>,
+    ) -> SerializableEntries<usize, RatesDelta<'a, T>> {
+        let (since, tol) = (self.since, self.flow.tolerance());
+        let deltas = rates
+            .iter()
+            .enumerate()
+            .filter_map(|(edge, rates)| {
+                let mut by_comm: Vec<_> = rates
+                    .function_by_comm()
+                    .iter()
+                    .filter_map(|(&comm, f)| {
+                        let delta = if new.contains(&(edge, comm)) {
+                            VisualizationPointsDelta {
+                                points: f.points(),
+                                last_slope: None,
+                                domain: Some(f.domain()),
+                            }
+                        } else {
+                            VisualizationPointsDelta {
+                                points: points_since(f.points(), since, tol),
+                                last_slope: None,
+                                domain: None,
+                            }
+                        };
+                        (!delta.points.is_empty()).then_some((comm, delta))
+                    })
+                    .collect();
+                by_comm.sort_by_key(|(comm, _)| *comm);
+                (!by_comm.is_empty()).then_some((edge, SerializableEntries(by_comm)))
+            })
+            .collect();
+        SerializableEntries(deltas)
+    }
+}
+
+/// The changed rate functions of an edge by commodity.
+type RatesDelta<'a, T> = SerializableEntries<usize, VisualizationPointsDelta<'a, T>>;
+
+struct SerializableEntries<K: Serialize, V: Serialize>(Vec<(K, V)>);
+
+impl<K: Serialize, V: Serialize> Serialize for SerializableEntries<K, V> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, v)))
+    }
+}
+
+impl<'a, T: Num + ToF64> Serialize for VisualizationFlowDelta<'a, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let mut res = serializer.serialize_struct("DynamicFlowDelta", 5)?;
+        res.serialize_field("since", &JsonNumber(self.since.to_f64()))?;
+        res.serialize_field("builtUntil", &JsonNumber(self.flow.built_until().to_f64()))?;
+        res.serialize_field("queues", &self.queue_deltas())?;
+        res.serialize_field(
+            "inflow",
+            &self.rates_deltas(self.flow.inflow(), &self.new_inflow),
+        )?;
+        res.serialize_field(
+            "outflow",
+            &self.rates_deltas(self.flow.outflow(), &self.new_outflow),
+        )?;
+        res.end()
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
+    use serde::{de::IgnoredAny, Deserialize};
+
     use crate::{
+        dynamic_flow::DynamicFlow,
+        example_instances::nested_merges,
         float::F64,
-        network_loader::{NetworkLoader, PathInflow},
-        num::Num,
+        format_error::FormatError,
+        instance::{Commodity, Instance},
+        network::Network,
+        network_loader::{LoaderEvent, NetworkLoader, PathInflow},
+        num::{Num, ToF64},
         piecewise_constant::PiecewiseConstant,
         points,
+        scenario::metrics,
     };
 
-    use super::VisualizationDynamicFlow;
+    use super::{
+        FlowExportSink, IncrementalExport, VisualizationDynamicFlow, VisualizationInstanceFlow,
+        WebVisualizationFlow, WebVisualizationOptions,
+    };
 
     #[test]
     pub fn test_serialization_to_json() {
@@ -169,4 +895,282 @@ mod tests {
         let result = serde_json::to_string_pretty(&VisualizationDynamicFlow(&flow)).unwrap();
         println!("{}", result)
     }
+
+    #[test]
+    fn it_writes_messagepack() {
+        #[derive(Deserialize)]
+        struct Queue {
+            times: Vec<f64>,
+            values: Vec<f64>,
+        }
+        #[derive(Deserialize)]
+        struct Flow {
+            queues: Vec<Queue>,
+            outflow: Vec<HashMap<usize, IgnoredAny>>,
+        }
+
+        let instance = nested_merges::<F64>();
+        let flow = instance.instance.load(&instance.path_inflows);
+        let mut msgpack = Vec::new();
+        VisualizationDynamicFlow(&flow)
+            .write_msgpack(&mut msgpack)
+            .unwrap();
+
+        let decoded: Flow = rmp_serde::from_slice(&msgpack).unwrap();
+        for (queue, expected) in decoded.queues.iter().zip(flow.queues()) {
+            let times: Vec<f64> = expected.points().iter().map(|p| p.0.to_f64()).collect();
+            let values: Vec<f64> = expected.points().iter().map(|p| p.1.to_f64()).collect();
+            assert_eq!(queue.times, times);
+            assert_eq!(queue.values, values);
+        }
+        for (rates, expected) in decoded.outflow.iter().zip(flow.outflow()) {
+            assert_eq!(rates.len(), expected.function_by_comm().len());
+        }
+    }
+
+    #[test]
+    fn it_imports_exported_flows() {
+        let instance = nested_merges::<F64>();
+        let flow = instance.instance.load(&instance.path_inflows);
+        let expected = metrics(String::from("merges"), &instance.instance, &flow);
+
+        let json = serde_json::to_string(&VisualizationDynamicFlow(&flow)).unwrap();
+        let imported: DynamicFlow<F64> = DynamicFlow::from_visualization_json(&json).unwrap();
+        assert_eq!(imported.queues(), flow.queues());
+        assert_eq!(imported.built_until(), F64::INFINITY);
+        for (rates, expected) in imported.outflow().iter().zip(flow.outflow()) {
+            assert_eq!(rates.function_by_comm(), expected.function_by_comm());
+        }
+        assert_eq!(
+            metrics(String::from("merges"), &instance.instance, &imported),
+            expected
+        );
+
+        let mut msgpack = Vec::new();
+        VisualizationDynamicFlow(&flow)
+            .write_msgpack(&mut msgpack)
+            .unwrap();
+        let imported: DynamicFlow<F64> = DynamicFlow::from_visualization_msgpack(&msgpack).unwrap();
+        assert_eq!(
+            metrics(String::from("merges"), &instance.instance, &imported),
+            expected
+        );
+
+        let unsorted = json.replacen("\"times\":[", "\"times\":[5,", 1);
+        let result: Result<DynamicFlow<F64>, _> = DynamicFlow::from_visualization_json(&unsorted);
+        assert!(matches!(result, Err(FormatError::Invalid(_))));
+    }
+
+    #[test]
+    fn it_embeds_the_network() {
+        let instance = nested_merges::<F64>();
+        let flow = instance.instance.load(&instance.path_inflows);
+        let json = serde_json::to_value(VisualizationInstanceFlow {
+            instance: &instance.instance,
+            flow: &flow,
+        })
+        .unwrap();
+        let network = &instance.instance.network;
+        assert_eq!(json["network"]["numNodes"], network.num_nodes());
+        let edges = json["network"]["edges"].as_array().unwrap();
+        assert_eq!(edges.len(), network.num_edges());
+        assert_eq!(edges[1]["tail"], network.tail(1));
+        assert_eq!(edges[1]["head"], network.head(1));
+        assert_eq!(edges[1]["travelTime"], network.travel_time()[1].to_f64());
+        assert_eq!(
+            json["commodities"][0]["paths"],
+            serde_json::to_value(&instance.instance.commodities[0].paths).unwrap()
+        );
+
+        let imported: DynamicFlow<F64> =
+            DynamicFlow::from_visualization_json(&json.to_string()).unwrap();
+        assert_eq!(imported.queues(), flow.queues());
+    }
+
+    #[test]
+    fn it_writes_the_web_visualization_format() {
+        let mut network = Network::new();
+        network.add_edge(0, 1, 10.0.into(), 1.0.into());
+        network.add_edge(0, 1, 10.0.into(), 2.0.into());
+        network.add_edge(1, 2, 10.0.into(), 1.0.into());
+        let inflow: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (4.0, 0.0)],
+        );
+        let commodities = vec![
+            Commodity {
+                paths: vec![vec![0, 2], vec![1, 2]],
+                inflow: inflow.clone(),
+            },
+            Commodity {
+                paths: vec![vec![0, 2]],
+                inflow: inflow.clone(),
+            },
+        ];
+        let instance = Instance::new(network, commodities);
+        let path_inflows = vec![vec![inflow.clone(), inflow.clone()], vec![inflow.clone()]];
+        let flow = instance.load(&path_inflows);
+        let options = WebVisualizationOptions {
+            commodity_colors: Some(vec![String::from("red"), String::from("blue")]),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(WebVisualizationFlow {
+            instance: &instance,
+            flow: &flow,
+            options: &options,
+        })
+        .unwrap();
+
+        assert_eq!(json["network"]["nodes"].as_array().unwrap().len(), 3);
+        assert_eq!(json["network"]["edges"][2]["from"], 1);
+        assert_eq!(json["network"]["edges"][1]["transitTime"], 2.0);
+        assert_eq!(json["network"]["commodities"][1]["color"], "blue");
+        let inflow = json["flow"]["inflow"][2].as_array().unwrap();
+        assert_eq!(inflow.len(), 2);
+        // Both paths of commodity 0 enter edge 2 during [2, 5)
+        assert_eq!(
+            inflow[0]["times"],
+            serde_json::json!([0.0, 1.0, 2.0, 5.0, 6.0])
+        );
+        assert_eq!(
+            inflow[0]["values"],
+            serde_json::json!([0.0, 1.0, 2.0, 1.0, 0.0])
+        );
+        assert_eq!(inflow[1]["values"], serde_json::json!([0.0, 1.0, 0.0]));
+        assert_eq!(json["flow"]["queues"][0]["domain"][1], "Infinity");
+
+        let options = WebVisualizationOptions {
+            node_positions: Some(vec![[0.0, 0.0], [1.0, 0.0]]),
+            commodity_colors: Some(vec![String::from("red")]),
+        };
+        let error = serde_json::to_value(WebVisualizationFlow {
+            instance: &instance,
+            flow: &flow,
+            options: &options,
+        })
+        .unwrap_err();
+        assert_eq!(error.to_string(), "There are 2 node positions for 3 nodes.");
+    }
+
+    #[test]
+    fn it_streams_the_loading() {
+        let instance = nested_merges::<F64>();
+        let network = &instance.instance.network;
+        let path_inflows = instance.instance.flat_path_inflows(&instance.path_inflows);
+        let mut sink = FlowExportSink::new(Vec::new());
+        let flow = NetworkLoader::new(&path_inflows)
+            .build_flow_streaming(
+                network.num_edges(),
+                network.capacity(),
+                network.inv_capacity(),
+                network.travel_time(),
+                &mut sink,
+            )
+            .unwrap();
+        assert_eq!(flow.queues(), network.load(&path_inflows).queues());
+
+        let output = String::from_utf8(sink.into_inner()).unwrap();
+        let lines: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert!(lines.len() > 1);
+        assert_eq!(lines[0]["since"], "-Infinity");
+        assert_eq!(lines.last().unwrap()["builtUntil"], "Infinity");
+        for pair in lines.windows(2) {
+            assert_eq!(pair[0]["builtUntil"], pair[1]["since"]);
+        }
+    }
+
+    /// The points of the functions by edge and commodity as replayed by a frontend
+    type ReplayedRates = HashMap<(String, String), Vec<(f64, f64)>>;
+
+    fn replay_rates(replayed: &mut ReplayedRates, delta: &serde_json::Value) {
+        for (edge, by_comm) in delta.as_object().unwrap() {
+            for (comm, f) in by_comm.as_object().unwrap() {
+                let [times, values] = ["times", "values"].map(|key| {
+                    f[key]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|x| x.as_f64().unwrap())
+                        .collect::<Vec<f64>>()
+                });
+                let points = replayed.entry((edge.clone(), comm.clone())).or_default();
+                points.retain(|&(t, _)| t < times[0]);
+                points.extend(times.into_iter().zip(values));
+            }
+        }
+    }
+
+    fn rates_points(flow: &DynamicFlow<F64>, outflow: bool) -> ReplayedRates {
+        let rates = if outflow {
+            flow.outflow()
+        } else {
+            flow.inflow()
+        };
+        let mut points = HashMap::new();
+        for (edge, rates) in rates.iter().enumerate() {
+            for (comm, f) in rates.function_by_comm() {
+                let f_points = f.points().iter().map(|p| (p.0.to_f64(), p.1.to_f64()));
+                points.insert((edge.to_string(), comm.to_string()), f_points.collect());
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn it_exports_only_newly_built_data() {
+        let inflow = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 3.0), (2.0, 1.0), (4.0, 2.0), (6.0, 0.0)],
+        );
+        let path_inflows = [PathInflow {
+            path: &[0, 1],
+            inflow: &inflow,
+        }];
+        let mut loader: NetworkLoader<F64> = NetworkLoader::new(&path_inflows);
+        let (capacity, inv_capacity, travel_time) = (
+            [1.0.into(), 2.0.into()],
+            [1.0.into(), 0.5.into()],
+            [1.0.into(), 1.0.into()],
+        );
+        let mut flow = DynamicFlow::new(2);
+        let mut export = IncrementalExport::new();
+        // The queue of edge 0 as replayed by a frontend
+        let mut times: Vec<f64> = Vec::new();
+        let mut exported_points = 0;
+        let (mut inflow, mut outflow) = (HashMap::new(), HashMap::new());
+        loop {
+            let event = loader.step(&mut flow, &capacity, &inv_capacity, &travel_time);
+            let delta = serde_json::to_value(export.next(&flow)).unwrap();
+            replay_rates(&mut inflow, &delta["inflow"]);
+            replay_rates(&mut outflow, &delta["outflow"]);
+            if let Some(queue) = delta["queues"].get("0") {
+                let delta_times: Vec<f64> = queue["times"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|t| t.as_f64().unwrap())
+                    .collect();
+                times.retain(|&t| t < delta_times[0]);
+                times.extend(&delta_times);
+                exported_points += delta_times.len();
+            }
+            if event == LoaderEvent::Finished {
+                break;
+            }
+        }
+        let queue_times: Vec<f64> = flow.queues()[0]
+            .points()
+            .iter()
+            .map(|p| p.0.to_f64())
+            .collect();
+        assert_eq!(times, queue_times);
+        assert!(exported_points < 2 * queue_times.len());
+        assert_eq!(inflow, rates_points(&flow, false));
+        assert_eq!(outflow, rates_points(&flow, true));
+        let delta = serde_json::to_value(export.next(&flow)).unwrap();
+        assert!(delta["queues"].as_object().unwrap().is_empty());
+    }
 }
@@ -0,0 +1,285 @@
+use crate::{
+    dynamic_flow::DynamicFlow,
+    instance::Instance,
+    num::{Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+    point::Point,
+};
+
+/// Determines the step size with which the path inflows move towards the best response.
+#[derive(Debug, Clone, Copy)]
+pub enum UpdateRule<T: Num> {
+    /// Method of successive averages: The step size in iteration `k` is `1 / (k + 1)`.
+    Msa,
+    /// A constant step size in `(0, 1]`.
+    Constant(T),
+    /// Self-regulating averaging: The step size is `1 / beta` where `beta` starts at one and grows
+    /// by `big_gamma > 1` whenever the gap increased compared to the previous iteration,
+    /// and by `gamma < 1` otherwise.
+    SelfRegulatingAveraging { gamma: T, big_gamma: T },
+}
+
+/// Computes the step sizes of consecutive iterations according to an update rule.
+#[derive(Debug, Clone)]
+pub struct StepSizeSchedule<T: Num> {
+    rule: UpdateRule<T>,
+    iteration: T,
+    beta: T,
+    previous_gap: Option<T>,
+}
+
+impl<T: Num> StepSizeSchedule<T> {
+    pub fn new(rule: UpdateRule<T>) -> Self {
+        Self {
+            rule,
+            iteration: T::ZERO,
+            beta: T::ONE,
+            previous_gap: None,
+        }
+    }
+
+    /// Returns the step size of the next iteration given the gap observed in that iteration.
+    pub fn next(&mut self, gap: T) -> T {
+        self.iteration += T::ONE;
+        let step_size = match self.rule {
+            UpdateRule::Msa => T::ONE / self.iteration,
+            UpdateRule::Constant(step_size) => step_size,
+            UpdateRule::SelfRegulatingAveraging { gamma, big_gamma } => {
+                if let Some(previous_gap) = self.previous_gap {
+                    self.beta += if gap >= previous_gap {
+                        big_gamma
+                    } else {
+                        gamma
+                    };
+                }
+                T::ONE / self.beta
+            }
+        };
+        self.previous_gap = Some(gap);
+        step_size
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FixedPointConfig<T: Num> {
+    /// Path inflows are only updated within `[0, horizon)`; afterwards, the network inflow is zero.
+    pub horizon: T,
+    /// The length of the intervals on which path inflows are kept constant.
+    pub interval_length: T,
+    pub max_iterations: usize,
+    /// The iteration stops as soon as the relative gap drops below this value.
+    pub target_relative_gap: T,
+    pub update_rule: UpdateRule<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IterationStats<T: Num> {
+    pub iteration: usize,
+    /// The step size that was used to compute the path inflows of the next iteration.
+    pub step_size: T,
+    /// The total excess travel time of all particles compared to the currently fastest paths.
+    pub gap: T,
+    /// The gap divided by the total travel time along the currently fastest paths.
+    pub relative_gap: T,
+}
+
+#[derive(Debug)]
+pub struct FixedPointResult<T: Num> {
+    /// path_inflows[i][j] is the inflow rate of the j-th path of commodity i.
+    pub path_inflows: Vec<Vec<PiecewiseConstant<T>>>,
+    pub flow: DynamicFlow<T>,
+    pub stats: Vec<IterationStats<T>>,
+}
+
+/// Runs a fixed-point iteration on the path inflows:
+/// In each iteration, the network is loaded with the current path inflows, path travel times are
+/// evaluated at the start of each interval and the inflows are moved towards the best response
+/// (routing all inflow of an interval onto a currently fastest path) according to the update rule.
+pub fn run_fixed_point_iteration<T: Num + Tolerance>(
+    instance: &Instance<T>,
+    config: &FixedPointConfig<T>,
+) -> FixedPointResult<T> {
+    // Otherwise, the grid would be infinite.
+    assert!(
+        !config.horizon.is_nan() && config.horizon < T::INFINITY,
+        "The horizon must be finite."
+    );
+    assert!(config.interval_length > T::ZERO);
+    debug_assert!(config.max_iterations > 0);
+    let network = &instance.network;
+    let commodities = &instance.commodities;
+
+    let grid: Vec<T> = {
+        let mut grid = Vec::new();
+        let mut time = T::ZERO;
+        while time < config.horizon {
+            grid.push(time);
+            time += config.interval_length;
+        }
+        grid
+    };
+
+    // splits[i][j][k] is the inflow rate of path j of commodity i during the k-th interval
+    let mut splits: Vec<Vec<Vec<T>>> = commodities
+        .iter()
+        .map(|c| {
+            let num_paths: T = c.paths.iter().map(|_| T::ONE).sum();
+            let share: Vec<T> = grid.iter().map(|&t| c.inflow.eval(t) / num_paths).collect();
+            vec![share; c.paths.len()]
+        })
+        .collect();
+
+    let mut stats: Vec<IterationStats<T>> = Vec::with_capacity(config.max_iterations);
+    let mut step_sizes = StepSizeSchedule::new(config.update_rule);
+    loop {
+        let path_inflows: Vec<Vec<PiecewiseConstant<T>>> = splits
+            .iter()
+            .map(|s| {
+                s.iter()
+                    .map(|rates| to_step_function(&grid, rates, config.horizon))
+                    .collect()
+            })
+            .collect();
+        let flow = instance.load(&path_inflows);
+
+        let mut gap = T::ZERO;
+        let mut total = T::ZERO;
+        let mut best_response: Vec<Vec<Vec<T>>> = splits
+            .iter()
+            .map(|s| vec![vec![T::ZERO; grid.len()]; s.len()])
+            .collect();
+        for (i, commodity) in commodities.iter().enumerate() {
+            for (k, &time) in grid.iter().enumerate() {
+                let travel_times: Vec<T> = commodity
+                    .paths
+                    .iter()
+                    .map(|path| {
+                        flow.path_arrival_time(
+                            path,
+                            time,
+                            network.inv_capacity(),
+                            network.travel_time(),
+                        ) - time
+                    })
+                    .collect();
+                let (best_path, &best_time) = travel_times
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .unwrap();
+                let demand: T = splits[i].iter().map(|rates| rates[k]).sum();
+                best_response[i][best_path][k] = demand;
+                for (j, &travel_time) in travel_times.iter().enumerate() {
+                    gap += splits[i][j][k] * (travel_time - best_time) * config.interval_length;
+                }
+                total += demand * best_time * config.interval_length;
+            }
+        }
+        let relative_gap = if total > T::ZERO {
+            gap / total
+        } else {
+            T::ZERO
+        };
+
+        let iteration = stats.len();
+        let step_size = step_sizes.next(gap);
+        stats.push(IterationStats {
+            iteration,
+            step_size,
+            gap,
+            relative_gap,
+        });
+
+        if relative_gap <= config.target_relative_gap || stats.len() >= config.max_iterations {
+            return FixedPointResult {
+                path_inflows,
+                flow,
+                stats,
+            };
+        }
+
+        for (splits_i, best_response_i) in splits.iter_mut().zip(best_response.iter()) {
+            for (rates, best_rates) in splits_i.iter_mut().zip(best_response_i.iter()) {
+                for (rate, &best_rate) in rates.iter_mut().zip(best_rates.iter()) {
+                    *rate += step_size * (best_rate - *rate);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the step function with value `rates[k]` on `[grid[k], grid[k + 1])`
+/// that vanishes from `horizon` on. The horizon must be finite and after the last grid point.
+pub fn to_step_function<T: Num>(grid: &[T], rates: &[T], horizon: T) -> PiecewiseConstant<T> {
+    debug_assert!(horizon < T::INFINITY && grid.last().is_none_or(|&t| t < horizon));
+    let mut points: Vec<Point<T>> = grid
+        .iter()
+        .zip(rates.iter())
+        .map(|(&t, &v)| Point(t, v))
+        .collect();
+    points.push(Point(horizon, T::ZERO));
+    PiecewiseConstant::new([-T::INFINITY, T::INFINITY], points)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{example_instances::two_parallel_edges, float::F64, num::Num};
+
+    use super::{run_fixed_point_iteration, FixedPointConfig, UpdateRule};
+
+    #[test]
+    fn it_reduces_the_gap_with_msa() {
+        let instance = two_parallel_edges(4.0.into()).instance;
+        let result = run_fixed_point_iteration(
+            &instance,
+            &FixedPointConfig {
+                horizon: 4.0.into(),
+                interval_length: 0.25.into(),
+                max_iterations: 40,
+                target_relative_gap: F64::ZERO,
+                update_rule: UpdateRule::Msa,
+            },
+        );
+        assert_eq!(result.stats.len(), 40);
+        assert_eq!(result.stats[1].step_size, 0.5);
+        assert!(result.stats[39].relative_gap < result.stats[0].relative_gap);
+        assert_eq!(result.flow.built_until(), F64::INFINITY);
+    }
+
+    #[test]
+    fn it_stops_once_the_target_gap_is_reached() {
+        let instance = two_parallel_edges(4.0.into()).instance;
+        let result = run_fixed_point_iteration(
+            &instance,
+            &FixedPointConfig {
+                horizon: 1.0.into(),
+                interval_length: 1.0.into(),
+                max_iterations: 10,
+                target_relative_gap: F64::INFINITY,
+                update_rule: UpdateRule::SelfRegulatingAveraging {
+                    gamma: 0.5.into(),
+                    big_gamma: 1.5.into(),
+                },
+            },
+        );
+        assert_eq!(result.stats.len(), 1);
+        assert_eq!(result.path_inflows[0][0].eval(0.5), 1.5);
+        assert_eq!(result.path_inflows[0][1].eval(0.5), 1.5);
+        assert_eq!(result.path_inflows[0][1].eval(1.5), 0.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "The horizon must be finite.")]
+    fn it_rejects_an_infinite_horizon() {
+        run_fixed_point_iteration(
+            &two_parallel_edges(4.0.into()).instance,
+            &FixedPointConfig {
+                horizon: F64::INFINITY,
+                interval_length: 1.0.into(),
+                max_iterations: 1,
+                target_relative_gap: F64::ZERO,
+                update_rule: UpdateRule::Msa,
+            },
+        );
+    }
+}
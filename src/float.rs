@@ -7,7 +7,7 @@ use std::{
 use num_traits::{Num as NumTraitsNum, One, Signed, Zero};
 use ordered_float::OrderedFloat;
 
-use crate::num::Num;
+use crate::num::{compensated_sum, Num, ToF64, Tolerance};
 
 #[repr(transparent)]
 #[derive(Copy, Clone, Debug)]
@@ -108,7 +108,7 @@ impl PartialEq<f64> for F64 {
 impl PartialOrd for F64 {
     #[inline]
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.0.partial_cmp(&other.0)
+        Some(self.total_cmp(other))
     }
 
     #[inline]
@@ -132,15 +132,6 @@ impl PartialOrd for F64 {
     }
 }
 
-impl Eq for F64 {}
-
-impl Ord for F64 {
-    #[inline]
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.0.cmp(&other.0)
-    }
-}
-
 impl NumTraitsNum for F64 {
     type FromStrRadixErr = <OrderedFloat<f64> as NumTraitsNum>::FromStrRadixErr;
 
@@ -176,7 +167,7 @@ impl Hash for F64 {
 impl std::iter::Sum for F64 {
     #[inline]
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
-        iter.map(|x| x.0 .0).sum::<f64>().into()
+        compensated_sum(iter)
     }
 }
 
@@ -243,14 +234,633 @@ impl DivAssign for F64 {
 }
 
 impl Num for F64 {
-    const EXACT_ARITHMETIC: bool = false;
-    const TOL: Self = F64(OrderedFloat(1e-9));
     const ZERO: Self = F64(OrderedFloat(0.));
     const ONE: Self = F64(OrderedFloat(1.));
     const INFINITY: Self = F64(OrderedFloat(f64::INFINITY));
 
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.0.is_nan()
+    }
+
+    /// Interpolates `LANES` points at once on plain `f64` arrays, which the compiler turns into
+    /// SIMD instructions. The operations are the same as in the scalar version, so the results
+    /// are bitwise identical.
+    fn interpolate_into(x0: Self, y0: Self, dy: Self, dx: Self, xs: &[Self], out: &mut Vec<Self>) {
+        const LANES: usize = 4;
+        let (x0, y0, dy, dx) = (x0.0 .0, y0.0 .0, dy.0 .0, dx.0 .0);
+        out.reserve(xs.len());
+        let mut chunks = xs.chunks_exact(LANES);
+        for chunk in &mut chunks {
+            let mut lanes = [0.0; LANES];
+            for (lane, x) in lanes.iter_mut().zip(chunk) {
+                *lane = y0 + (x.0 .0 - x0) * dy / dx;
+            }
+            out.extend(lanes.map(F64::from));
+        }
+        out.extend(
+            chunks
+                .remainder()
+                .iter()
+                .map(|x| F64::from(y0 + (x.0 .0 - x0) * dy / dx)),
+        );
+    }
+}
+
+impl Tolerance for F64 {
+    const EXACT_ARITHMETIC: bool = false;
+    const TOL: Self = F64(OrderedFloat(1e-9));
+}
+
+impl ToF64 for F64 {
     #[inline]
     fn to_f64(self) -> f64 {
         self.0 .0
     }
 }
+
+/// A single precision float for memory-constrained settings.
+/// Its tolerance is much coarser than the one of `F64` to absorb the larger rounding errors.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug)]
+pub struct F32(OrderedFloat<f32>);
+
+impl From<OrderedFloat<f32>> for F32 {
+    #[inline]
+    fn from(val: OrderedFloat<f32>) -> Self {
+        F32(val)
+    }
+}
+
+impl From<f32> for F32 {
+    #[inline]
+    fn from(val: f32) -> Self {
+        OrderedFloat(val).into()
+    }
+}
+
+impl Rem for F32 {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        Self(self.0.rem(rhs.0))
+    }
+}
+
+impl Div for F32 {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        Self(self.0.div(rhs.0))
+    }
+}
+
+impl Sub for F32 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0.sub(rhs.0))
+    }
+}
+
+impl Mul for F32 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0.mul(rhs.0))
+    }
+}
+
+impl Add for F32 {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0.add(rhs.0))
+    }
+}
+
+impl Zero for F32 {
+    #[inline]
+    fn zero() -> Self {
+        Self(OrderedFloat::zero())
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+}
+
+impl One for F32 {
+    #[inline]
+    fn one() -> Self {
+        Self(OrderedFloat::one())
+    }
+}
+
+impl PartialEq<F32> for F32 {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.eq(&other.0)
+    }
+}
+
+impl PartialEq<f32> for F32 {
+    #[inline]
+    fn eq(&self, other: &f32) -> bool {
+        self.0.eq(other)
+    }
+}
+
+impl PartialOrd for F32 {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.total_cmp(other))
+    }
+
+    #[inline]
+    fn lt(&self, other: &Self) -> bool {
+        self.0.lt(&other.0)
+    }
+
+    #[inline]
+    fn le(&self, other: &Self) -> bool {
+        self.0.le(&other.0)
+    }
+
+    #[inline]
+    fn gt(&self, other: &Self) -> bool {
+        self.0.gt(&other.0)
+    }
+
+    #[inline]
+    fn ge(&self, other: &Self) -> bool {
+        self.0.ge(&other.0)
+    }
+}
+
+impl NumTraitsNum for F32 {
+    type FromStrRadixErr = <OrderedFloat<f32> as NumTraitsNum>::FromStrRadixErr;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        OrderedFloat::from_str_radix(str, radix).map(Self)
+    }
+}
+
+impl Display for F32 {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0 .0, f)
+    }
+}
+
+impl Neg for F32 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        self.0.neg().into()
+    }
+}
+
+impl Hash for F32 {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl std::iter::Sum for F32 {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.map(|x| x.0 .0).sum::<f32>().into()
+    }
+}
+
+impl Signed for F32 {
+    #[inline]
+    fn abs(&self) -> Self {
+        self.0.abs().into()
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        self.0.abs_sub(&other.0).into()
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        self.0.signum().into()
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.0.is_positive()
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.0.is_negative()
+    }
+}
+
+impl AddAssign for F32 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        self.0.add_assign(rhs.0);
+    }
+}
+
+impl SubAssign for F32 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0.sub_assign(rhs.0);
+    }
+}
+
+impl MulAssign for F32 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        self.0.mul_assign(rhs.0);
+    }
+}
+
+impl RemAssign for F32 {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        self.0.rem_assign(rhs.0);
+    }
+}
+
+impl DivAssign for F32 {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        self.0.div_assign(rhs.0);
+    }
+}
+
+impl Num for F32 {
+    const ZERO: Self = F32(OrderedFloat(0.));
+    const ONE: Self = F32(OrderedFloat(1.));
+    const INFINITY: Self = F32(OrderedFloat(f32::INFINITY));
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.0.is_nan()
+    }
+}
+
+impl Tolerance for F32 {
+    const EXACT_ARITHMETIC: bool = false;
+    const TOL: Self = F32(OrderedFloat(1e-4));
+}
+
+impl ToF64 for F32 {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.0 .0 as f64
+    }
+}
+
+/// A double precision float without the `OrderedFloat` wrapper, for maximum speed on the loading
+/// hot path. Its comparisons are the plain float comparisons, so NaN compares equal to every value
+/// instead of being ordered above them. Hence, its `total_cmp` is only a total order on NaN-free
+/// values, which the `validate` feature checks. Otherwise, it computes exactly like `F64`.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FastF64(f64);
+
+impl From<f64> for FastF64 {
+    #[inline]
+    fn from(val: f64) -> Self {
+        FastF64(val)
+    }
+}
+
+impl From<F64> for FastF64 {
+    #[inline]
+    fn from(val: F64) -> Self {
+        FastF64(val.0 .0)
+    }
+}
+
+impl Rem for FastF64 {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        Self(self.0 % rhs.0)
+    }
+}
+
+impl Div for FastF64 {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        Self(self.0 / rhs.0)
+    }
+}
+
+impl Sub for FastF64 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Mul for FastF64 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self(self.0 * rhs.0)
+    }
+}
+
+impl Add for FastF64 {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Zero for FastF64 {
+    #[inline]
+    fn zero() -> Self {
+        Self(0.0)
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.0 == 0.0
+    }
+}
+
+impl One for FastF64 {
+    #[inline]
+    fn one() -> Self {
+        Self(1.0)
+    }
+}
+
+impl PartialEq<FastF64> for FastF64 {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl PartialEq<f64> for FastF64 {
+    #[inline]
+    fn eq(&self, other: &f64) -> bool {
+        self.0 == *other
+    }
+}
+
+impl PartialOrd for FastF64 {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.total_cmp(other))
+    }
+
+    #[inline]
+    fn lt(&self, other: &Self) -> bool {
+        self.0 < other.0
+    }
+
+    #[inline]
+    fn le(&self, other: &Self) -> bool {
+        self.0 <= other.0
+    }
+
+    #[inline]
+    fn gt(&self, other: &Self) -> bool {
+        self.0 > other.0
+    }
+
+    #[inline]
+    fn ge(&self, other: &Self) -> bool {
+        self.0 >= other.0
+    }
+}
+
+impl NumTraitsNum for FastF64 {
+    type FromStrRadixErr = <f64 as NumTraitsNum>::FromStrRadixErr;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        f64::from_str_radix(str, radix).map(Self)
+    }
+}
+
+impl Display for FastF64 {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Neg for FastF64 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+impl Hash for FastF64 {
+    /// Hashes both zeros alike, as they are equal.
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let value = if self.0 == 0.0 { 0.0 } else { self.0 };
+        value.to_bits().hash(state)
+    }
+}
+
+impl std::iter::Sum for FastF64 {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        compensated_sum(iter)
+    }
+}
+
+impl Signed for FastF64 {
+    #[inline]
+    fn abs(&self) -> Self {
+        Self(self.0.abs())
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        Self(Signed::abs_sub(&self.0, &other.0))
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        Self(Signed::signum(&self.0))
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        Signed::is_positive(&self.0)
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        Signed::is_negative(&self.0)
+    }
+}
+
+impl AddAssign for FastF64 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for FastF64 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl MulAssign for FastF64 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        self.0 *= rhs.0;
+    }
+}
+
+impl RemAssign for FastF64 {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        self.0 %= rhs.0;
+    }
+}
+
+impl DivAssign for FastF64 {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        self.0 /= rhs.0;
+    }
+}
+
+impl Num for FastF64 {
+    const ZERO: Self = FastF64(0.);
+    const ONE: Self = FastF64(1.);
+    const INFINITY: Self = FastF64(f64::INFINITY);
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        if self.0 < other.0 {
+            std::cmp::Ordering::Less
+        } else if self.0 > other.0 {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.0.is_nan()
+    }
+
+    /// See the implementation of `F64`.
+    fn interpolate_into(x0: Self, y0: Self, dy: Self, dx: Self, xs: &[Self], out: &mut Vec<Self>) {
+        const LANES: usize = 4;
+        let (x0, y0, dy, dx) = (x0.0, y0.0, dy.0, dx.0);
+        out.reserve(xs.len());
+        let mut chunks = xs.chunks_exact(LANES);
+        for chunk in &mut chunks {
+            let mut lanes = [0.0; LANES];
+            for (lane, x) in lanes.iter_mut().zip(chunk) {
+                *lane = y0 + (x.0 - x0) * dy / dx;
+            }
+            out.extend(lanes.map(FastF64));
+        }
+        out.extend(
+            chunks
+                .remainder()
+                .iter()
+                .map(|x| FastF64(y0 + (x.0 - x0) * dy / dx)),
+        );
+    }
+}
+
+impl Tolerance for FastF64 {
+    const EXACT_ARITHMETIC: bool = false;
+    const TOL: Self = FastF64(1e-9);
+}
+
+impl ToF64 for FastF64 {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::nested_merges,
+        num::{max, ToF64, Tolerance},
+        price_of_anarchy::total_travel_times,
+    };
+
+    use super::{FastF64, F32, F64};
+
+    #[test]
+    fn it_loads_networks_in_single_precision() {
+        let single = nested_merges::<F32>();
+        let flow = single.instance.load(&single.path_inflows);
+        let total = total_travel_times(&single.instance, &flow).total;
+        assert!((total.to_f64() - single.total_travel_time.to_f64()).abs() <= F32::TOL.to_f64());
+
+        let double = nested_merges::<F64>();
+        let flow = double.instance.load(&double.path_inflows);
+        let expected = total_travel_times(&double.instance, &flow).total;
+        assert!((total.to_f64() - expected.to_f64()).abs() <= F32::TOL.to_f64());
+    }
+
+    #[test]
+    fn it_loads_networks_like_f64_without_ordered_floats() {
+        let fast = nested_merges::<FastF64>();
+        let flow = fast.instance.load(&fast.path_inflows);
+        let double = nested_merges::<F64>();
+        let expected = double.instance.load(&double.path_inflows);
+        for (queue, expected) in flow.queues().iter().zip(expected.queues()) {
+            let points = queue.points().iter().map(|p| (p.0.to_f64(), p.1.to_f64()));
+            let expected = expected
+                .points()
+                .iter()
+                .map(|p| (p.0.to_f64(), p.1.to_f64()));
+            assert!(points.eq(expected));
+        }
+        assert_eq!(FastF64::from(-0.0), FastF64::from(0.0));
+        assert!(max(FastF64::from(1.0), FastF64::from(2.0)) == 2.0);
+    }
+}
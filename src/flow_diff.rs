@@ -0,0 +1,148 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection},
+    num::{max, Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+    piecewise_linear::PiecewiseLinear,
+    points,
+};
+
+/// The difference `lhs - rhs` of two flows on a single edge.
+#[derive(Debug, Clone)]
+pub struct EdgeDiff<T: Num> {
+    pub queue: PiecewiseLinear<T>,
+    /// Differences of the inflow rates by commodity; commodities missing in one flow count as zero.
+    pub inflow: HashMap<usize, PiecewiseConstant<T>>,
+    pub outflow: HashMap<usize, PiecewiseConstant<T>>,
+    pub queue_sup_norm: T,
+    /// The largest sup-norm of the inflow rate differences over all commodities.
+    pub inflow_sup_norm: T,
+    pub outflow_sup_norm: T,
+}
+
+/// The per-edge differences of two flows on the same network.
+#[derive(Debug, Clone)]
+pub struct FlowDiff<T: Num> {
+    pub edges: Vec<EdgeDiff<T>>,
+    pub queue_sup_norm: T,
+    pub inflow_sup_norm: T,
+    pub outflow_sup_norm: T,
+}
+
+impl<T: Num> FlowDiff<T> {
+    pub fn new(lhs: &DynamicFlow<T>, rhs: &DynamicFlow<T>) -> Self
+    where
+        T: Tolerance,
+    {
+        debug_assert_eq!(
+            lhs.queues().len(),
+            rhs.queues().len(),
+            "The flows must be defined on the same network."
+        );
+        let edges: Vec<EdgeDiff<T>> = (0..lhs.queues().len())
+            .map(|e| {
+                let queue = &*lhs.queues()[e] - &*rhs.queues()[e];
+                let inflow = rates_diff(&lhs.inflow()[e], &rhs.inflow()[e]);
+                let outflow = rates_diff(&lhs.outflow()[e], &rhs.outflow()[e]);
+                EdgeDiff {
+                    queue_sup_norm: queue.sup_norm(),
+                    inflow_sup_norm: max_sup_norm(inflow.values()),
+                    outflow_sup_norm: max_sup_norm(outflow.values()),
+                    queue,
+                    inflow,
+                    outflow,
+                }
+            })
+            .collect();
+        FlowDiff {
+            queue_sup_norm: edges.iter().map(|e| e.queue_sup_norm).fold(T::ZERO, max),
+            inflow_sup_norm: edges.iter().map(|e| e.inflow_sup_norm).fold(T::ZERO, max),
+            outflow_sup_norm: edges.iter().map(|e| e.outflow_sup_norm).fold(T::ZERO, max),
+            edges,
+        }
+    }
+}
+
+fn rates_diff<T: Num + Tolerance>(
+    lhs: &FlowRatesCollection<T>,
+    rhs: &FlowRatesCollection<T>,
+) -> HashMap<usize, PiecewiseConstant<T>> {
+    let lhs = lhs.function_by_comm();
+    let rhs = rhs.function_by_comm();
+    let commodities: HashSet<usize> = lhs.keys().chain(rhs.keys()).copied().collect();
+    commodities
+        .into_iter()
+        .map(|i| {
+            let diff = match (lhs.get(&i), rhs.get(&i)) {
+                (Some(f), Some(g)) => f - g,
+                (Some(f), None) => f - &zero_like(f),
+                (None, Some(g)) => &zero_like(g) - g,
+                (None, None) => unreachable!(),
+            };
+            (i, diff)
+        })
+        .collect()
+}
+
+fn zero_like<T: Num>(f: &PiecewiseConstant<T>) -> PiecewiseConstant<T> {
+    PiecewiseConstant::new(f.domain(), points![(f.points()[0].0, T::ZERO)])
+}
+
+fn max_sup_norm<'a, T: Num + 'a>(functions: impl Iterator<Item = &'a PiecewiseConstant<T>>) -> T {
+    functions.map(|f| f.sup_norm()).fold(T::ZERO, max)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{commodity_vec::CommodityVec, dynamic_flow::DynamicFlow, float::F64, num::Num};
+
+    fn single_edge_flow(inflow: f64) -> DynamicFlow<F64> {
+        let mut flow = DynamicFlow::new(1);
+        flow.extend(
+            HashMap::from([(0, CommodityVec::from([(0, inflow.into())]))]),
+            Some(1.0.into()),
+            &[1.0.into()],
+            &[1.0.into()],
+            &[1.0.into()],
+        );
+        flow.extend(
+            HashMap::from([(0, CommodityVec::new())]),
+            None,
+            &[1.0.into()],
+            &[1.0.into()],
+            &[1.0.into()],
+        );
+        while flow.built_until() < F64::INFINITY {
+            flow.extend(
+                HashMap::new(),
+                None,
+                &[1.0.into()],
+                &[1.0.into()],
+                &[1.0.into()],
+            );
+        }
+        flow
+    }
+
+    #[test]
+    fn it_diffs_two_flows() {
+        let lhs = single_edge_flow(2.0);
+        let rhs = single_edge_flow(1.0);
+        let diff = lhs.diff(&rhs);
+        assert_eq!(diff.edges.len(), 1);
+        assert_eq!(diff.edges[0].queue.eval(1.0), 1.0);
+        assert_eq!(diff.edges[0].inflow[&0].eval(0.5), 1.0);
+        assert_eq!(diff.edges[0].outflow[&0].eval(1.5), 0.0);
+        assert_eq!(diff.edges[0].outflow[&0].eval(2.5), 1.0);
+        assert_eq!(diff.queue_sup_norm, 1.0);
+        assert_eq!(diff.inflow_sup_norm, 1.0);
+        assert_eq!(diff.outflow_sup_norm, 1.0);
+
+        let no_diff = lhs.diff(&lhs);
+        assert_eq!(no_diff.queue_sup_norm, F64::ZERO);
+        assert_eq!(no_diff.outflow_sup_norm, F64::ZERO);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{
+    fmt::{Display, Formatter},
+    io,
+    str::FromStr,
+};
+
+/// An error while reading or writing instances or flows in some file format.
+#[derive(Debug)]
+pub enum FormatError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Toml(toml::de::Error),
+    Yaml(serde_yaml::Error),
+    Xml(quick_xml::Error),
+    MessagePack(rmp_serde::encode::Error),
+    MessagePackDecode(rmp_serde::decode::Error),
+    #[cfg(feature = "parquet")]
+    Parquet(parquet::errors::ParquetError),
+    /// A call of the HDF5 library failed, which prints the details to stderr.
+    #[cfg(feature = "hdf5")]
+    Hdf5(String),
+    /// The input is well-formed, but does not describe a valid instance or flow.
+    Invalid(String),
+}
+
+impl FormatError {
+    /// Returns an `Invalid` error about the line `number` of a text format, counted from 1.
+    pub fn at_line(number: usize, message: &str) -> Self {
+        FormatError::Invalid(format!("Line {}: {}", number, message))
+    }
+}
+
+/// Parses a whitespace-trimmed token of the line `number` of a text format.
+pub fn parse_token<V: FromStr>(number: usize, token: &str) -> Result<V, FormatError> {
+    token
+        .trim()
+        .parse()
+        .map_err(|_| FormatError::at_line(number, &format!("Cannot parse `{}`.", token.trim())))
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FormatError::Io(err) => write!(f, "I/O error: {}", err),
+            FormatError::Json(err) => write!(f, "Malformed JSON: {}", err),
+            FormatError::Toml(err) => write!(f, "Malformed TOML: {}", err),
+            FormatError::Yaml(err) => write!(f, "Malformed YAML: {}", err),
+            FormatError::Xml(err) => write!(f, "Malformed XML: {}", err),
+            FormatError::MessagePack(err) => write!(f, "MessagePack error: {}", err),
+            FormatError::MessagePackDecode(err) => write!(f, "Malformed MessagePack: {}", err),
+            #[cfg(feature = "parquet")]
+            FormatError::Parquet(err) => write!(f, "Parquet error: {}", err),
+            #[cfg(feature = "hdf5")]
+            FormatError::Hdf5(message) => write!(f, "HDF5 error: {}", message),
+            FormatError::Invalid(message) => write!(f, "Invalid input: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for FormatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FormatError::Io(err) => Some(err),
+            FormatError::Json(err) => Some(err),
+            FormatError::Toml(err) => Some(err),
+            FormatError::Yaml(err) => Some(err),
+            FormatError::Xml(err) => Some(err),
+            FormatError::MessagePack(err) => Some(err),
+            FormatError::MessagePackDecode(err) => Some(err),
+            #[cfg(feature = "parquet")]
+            FormatError::Parquet(err) => Some(err),
+            #[cfg(feature = "hdf5")]
+            FormatError::Hdf5(_) => None,
+            FormatError::Invalid(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        FormatError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for FormatError {
+    fn from(err: serde_json::Error) -> Self {
+        FormatError::Json(err)
+    }
+}
+
+impl From<toml::de::Error> for FormatError {
+    fn from(err: toml::de::Error) -> Self {
+        FormatError::Toml(err)
+    }
+}
+
+impl From<serde_yaml::Error> for FormatError {
+    fn from(err: serde_yaml::Error) -> Self {
+        FormatError::Yaml(err)
+    }
+}
+
+impl From<quick_xml::Error> for FormatError {
+    fn from(err: quick_xml::Error) -> Self {
+        FormatError::Xml(err)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for FormatError {
+    fn from(err: rmp_serde::encode::Error) -> Self {
+        FormatError::MessagePack(err)
+    }
+}
+
+impl From<rmp_serde::decode::Error> for FormatError {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        FormatError::MessagePackDecode(err)
+    }
+}
+
+#[cfg(feature = "parquet")]
+impl From<parquet::errors::ParquetError> for FormatError {
+    fn from(err: parquet::errors::ParquetError) -> Self {
+        FormatError::Parquet(err)
+    }
+}
@@ -0,0 +1,250 @@
+use std::{collections::HashMap, fmt::Write};
+
+use quick_xml::{events::Event, Reader};
+
+use crate::{
+    format_error::FormatError,
+    network::Network,
+    num::{Num, ToF64},
+    xml::{attributes, required},
+};
+
+/// A network read from a GraphML file together with the GraphML ids of its nodes.
+/// Nodes and edges are numbered in the order of their elements.
+#[derive(Debug, Clone)]
+pub struct GraphmlNetwork<T: Num> {
+    pub network: Network<T>,
+    pub node_ids: Vec<String>,
+}
+
+/// An edge whose `data` elements are still being read.
+struct GraphmlEdge {
+    source: usize,
+    target: usize,
+    data: HashMap<String, String>,
+}
+
+/// Parses a directed GraphML graph whose edges have the attributes `capacity` and `travelTime`,
+/// e.g. as written by `Network::to_graphml` or by networkx. Attributes are matched by the
+/// `attr.name` of their key, and keys may provide a default value. Keys `for` other elements than
+/// edges and all other attributes are ignored.
+pub fn parse_graphml<T: Num + From<f64>>(xml: &str) -> Result<GraphmlNetwork<T>, FormatError> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut network = Network::new();
+    let mut node_ids = Vec::new();
+    let mut nodes_by_id: HashMap<String, usize> = HashMap::new();
+    // The attribute name of each key, which is `None` for keys of other elements than edges, and
+    // the defaults by attribute name
+    let mut key_names: HashMap<String, Option<String>> = HashMap::new();
+    let mut defaults: HashMap<String, String> = HashMap::new();
+    // The key of the `default` or `data` element whose text is read next
+    let mut text_key: Option<String> = None;
+    let mut current_key: Option<String> = None;
+    let mut in_default = false;
+    let mut edge: Option<GraphmlEdge> = None;
+    loop {
+        let (element, is_empty) = match reader.read_event()? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::Text(text) => {
+                if let Some(key) = text_key.take() {
+                    let Some(name) = key_names.get(&key).cloned().unwrap_or(Some(key)) else {
+                        continue;
+                    };
+                    let value = text.unescape()?.into_owned();
+                    if in_default {
+                        defaults.insert(name, value);
+                    } else if let Some(edge) = edge.as_mut() {
+                        edge.data.insert(name, value);
+                    }
+                }
+                continue;
+            }
+            Event::End(e) => {
+                match e.name().as_ref() {
+                    b"edge" => add_edge(&mut network, edge.take(), &defaults)?,
+                    b"default" => in_default = false,
+                    _ => {}
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        let attributes = attributes(&element)?;
+        match element.name().as_ref() {
+            b"key" => {
+                let id: String = required(&attributes, "key", "id")?;
+                let name = attributes.get("attr.name").cloned().unwrap_or(id.clone());
+                let for_edges = attributes
+                    .get("for")
+                    .is_none_or(|element| element == "edge" || element == "all");
+                key_names.insert(id.clone(), for_edges.then_some(name));
+                current_key = Some(id);
+            }
+            b"default" if !is_empty => {
+                in_default = true;
+                text_key = current_key.clone();
+            }
+            b"graph"
+                if attributes
+                    .get("edgedefault")
+                    .is_some_and(|d| d != "directed") =>
+            {
+                return Err(FormatError::Invalid(String::from(
+                    "Only directed graphs are supported.",
+                )));
+            }
+            b"node" => {
+                let id: String = required(&attributes, "node", "id")?;
+                if nodes_by_id.insert(id.clone(), network.add_node()).is_some() {
+                    return Err(FormatError::Invalid(format!(
+                        "The node {} is repeated.",
+                        id
+                    )));
+                }
+                node_ids.push(id);
+            }
+            b"edge" => {
+                let node = |key: &str| -> Result<usize, FormatError> {
+                    let id: String = required(&attributes, "edge", key)?;
+                    nodes_by_id.get(&id).copied().ok_or_else(|| {
+                        FormatError::Invalid(format!("An edge uses the unknown node {}.", id))
+                    })
+                };
+                edge = Some(GraphmlEdge {
+                    source: node("source")?,
+                    target: node("target")?,
+                    data: HashMap::new(),
+                });
+                if is_empty {
+                    add_edge(&mut network, edge.take(), &defaults)?;
+                }
+            }
+            b"data" => text_key = Some(required(&attributes, "data", "key")?),
+            _ => {}
+        }
+    }
+    Ok(GraphmlNetwork { network, node_ids })
+}
+
+fn add_edge<T: Num + From<f64>>(
+    network: &mut Network<T>,
+    edge: Option<GraphmlEdge>,
+    defaults: &HashMap<String, String>,
+) -> Result<(), FormatError> {
+    let Some(edge) = edge else {
+        return Ok(());
+    };
+    let value = |name: &str| -> Result<f64, FormatError> {
+        let value = edge.data.get(name).or(defaults.get(name)).ok_or_else(|| {
+            FormatError::Invalid(format!("The edge {} has no {}.", network.num_edges(), name))
+        })?;
+        value
+            .trim()
+            .parse()
+            .map_err(|_| FormatError::Invalid(format!("Cannot parse the {} `{}`.", name, value)))
+    };
+    let (capacity, travel_time) = (value("capacity")?, value("travelTime")?);
+    if capacity.is_nan() || capacity <= 0.0 || travel_time.is_nan() || travel_time < 0.0 {
+        return Err(FormatError::Invalid(format!(
+            "The edge {} has an invalid capacity or travel time.",
+            network.num_edges()
+        )));
+    }
+    network.add_edge(
+        edge.source,
+        edge.target,
+        capacity.into(),
+        travel_time.into(),
+    );
+    Ok(())
+}
+
+impl<T: Num + ToF64> Network<T> {
+    /// Returns the network as a directed GraphML graph with nodes `n0, n1, ...` and edges
+    /// `e0, e1, ...` that have the attributes `capacity` and `travelTime`.
+    pub fn to_graphml(&self) -> String {
+        let mut xml = String::from(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="capacity" for="edge" attr.name="capacity" attr.type="double"/>
+  <key id="travelTime" for="edge" attr.name="travelTime" attr.type="double"/>
+  <graph id="G" edgedefault="directed">
+"#,
+        );
+        for node in 0..self.num_nodes() {
+            writeln!(xml, r#"    <node id="n{}"/>"#, node).unwrap();
+        }
+        for edge in 0..self.num_edges() {
+            writeln!(
+                xml,
+                r#"    <edge id="e{}" source="n{}" target="n{}"><data key="capacity">{}</data><data key="travelTime">{}</data></edge>"#,
+                edge,
+                self.tail(edge),
+                self.head(edge),
+                self.capacity()[edge].to_f64(),
+                self.travel_time()[edge].to_f64()
+            )
+            .unwrap();
+        }
+        xml.push_str("  </graph>\n</graphml>\n");
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{example_instances::nested_merges, float::F64};
+
+    use super::parse_graphml;
+
+    #[test]
+    fn it_round_trips_networks() {
+        let network = nested_merges::<F64>().instance.network;
+        let graphml = parse_graphml::<F64>(&network.to_graphml()).unwrap();
+        assert_eq!(graphml.node_ids[1], "n1");
+        assert_eq!(graphml.network.num_nodes(), network.num_nodes());
+        assert_eq!(graphml.network.capacity(), network.capacity());
+        assert_eq!(graphml.network.travel_time(), network.travel_time());
+        for edge in 0..network.num_edges() {
+            assert_eq!(graphml.network.tail(edge), network.tail(edge));
+            assert_eq!(graphml.network.head(edge), network.head(edge));
+        }
+    }
+
+    #[test]
+    fn it_reads_networkx_graphs() {
+        let xml = r#"<?xml version='1.0' encoding='utf-8'?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d1" for="edge" attr.name="travelTime" attr.type="double" />
+  <key id="d0" for="edge" attr.name="capacity" attr.type="double">
+    <default>2.0</default>
+  </key>
+  <key id="d2" for="node" attr.name="capacity" attr.type="double">
+    <default>5.0</default>
+  </key>
+  <graph edgedefault="directed">
+    <node id="s"><data key="d2">4.0</data></node>
+    <node id="t" />
+    <edge source="s" target="t">
+      <data key="d1">3.5</data>
+    </edge>
+    <edge source="t" target="s">
+      <data key="d0">1.0</data>
+      <data key="d1">1</data>
+    </edge>
+  </graph>
+</graphml>
+"#;
+        let graphml = parse_graphml::<F64>(xml).unwrap();
+        assert_eq!(graphml.node_ids, vec!["s", "t"]);
+        assert_eq!(graphml.network.capacity(), &[F64::from(2.0), 1.0.into()]);
+        assert_eq!(graphml.network.travel_time()[0], 3.5);
+        assert!(parse_graphml::<F64>(&xml.replace("<data key=\"d1\">1</data>", "")).is_err());
+        // Only the defaults of edge keys apply to edges.
+        let without_default = xml.replace("<default>2.0</default>", "");
+        assert!(parse_graphml::<F64>(&without_default).is_err());
+    }
+}
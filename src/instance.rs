@@ -0,0 +1,91 @@
+use crate::{
+    dynamic_flow::DynamicFlow,
+    network::Network,
+    network_loader::PathInflow,
+    num::{Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+};
+
+/// A commodity distributes its network inflow rate `inflow` over a fixed set of `paths`.
+#[derive(Debug, Clone)]
+pub struct Commodity<T: Num> {
+    pub paths: Vec<Vec<usize>>,
+    pub inflow: PiecewiseConstant<T>,
+}
+
+/// A network together with its commodities.
+/// This is the common input of all solvers operating on path inflows.
+#[derive(Debug, Clone)]
+pub struct Instance<T: Num> {
+    pub network: Network<T>,
+    pub commodities: Vec<Commodity<T>>,
+}
+
+impl<T: Num> Instance<T> {
+    pub fn new(network: Network<T>, commodities: Vec<Commodity<T>>) -> Self {
+        debug_assert!(
+            commodities
+                .iter()
+                .all(|c| !c.paths.is_empty() && c.paths.iter().all(|p| network.is_path(p))),
+            "Every commodity needs at least one path and all paths must exist in the network."
+        );
+        Self {
+            network,
+            commodities,
+        }
+    }
+
+    /// Returns the commodity of each path, where paths are numbered consecutively by commodity.
+    /// The flow of a path is stored under this number in the functions of a loaded `DynamicFlow`.
+    pub fn commodity_by_path(&self) -> Vec<usize> {
+        self.commodities
+            .iter()
+            .enumerate()
+            .flat_map(|(i, c)| c.paths.iter().map(move |_| i))
+            .collect()
+    }
+
+    /// Loads the network with the given inflows, where path_inflows[i][j] is the inflow rate of the
+    /// j-th path of commodity i.
+    pub fn load(&self, path_inflows: &[Vec<PiecewiseConstant<T>>]) -> DynamicFlow<T>
+    where
+        T: Tolerance,
+    {
+        self.network.load(&self.flat_path_inflows(path_inflows))
+    }
+
+    /// Loads the network like `load`, but on `num_parts` parts of the network concurrently.
+    pub fn load_partitioned(
+        &self,
+        path_inflows: &[Vec<PiecewiseConstant<T>>],
+        num_parts: usize,
+    ) -> DynamicFlow<T>
+    where
+        T: Tolerance,
+    {
+        self.network
+            .load_partitioned(&self.flat_path_inflows(path_inflows), num_parts)
+    }
+
+    /// Pairs the paths numbered consecutively by commodity with their inflows.
+    pub fn flat_path_inflows<'a>(
+        &'a self,
+        path_inflows: &'a [Vec<PiecewiseConstant<T>>],
+    ) -> Vec<PathInflow<'a, T>> {
+        debug_assert_eq!(path_inflows.len(), self.commodities.len());
+        self.commodities
+            .iter()
+            .zip(path_inflows.iter())
+            .flat_map(|(c, inflows)| {
+                debug_assert_eq!(c.paths.len(), inflows.len());
+                c.paths
+                    .iter()
+                    .zip(inflows.iter())
+                    .map(|(path, inflow)| PathInflow {
+                        path: path.as_slice(),
+                        inflow,
+                    })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,307 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    format_error::FormatError,
+    instance::{Commodity, Instance},
+    network::Network,
+    num::{Num, ToF64},
+    piecewise_constant::PiecewiseConstant,
+    point::Point,
+};
+
+/// The JSON representation of an instance, e.g.
+/// ```json
+/// {
+///   "numNodes": 3,
+///   "edges": [
+///     { "tail": 0, "head": 1, "capacity": 1.0, "travelTime": 2.0 },
+///     { "tail": 1, "head": 2, "capacity": 2.0, "travelTime": 1.0 }
+///   ],
+///   "commodities": [
+///     { "paths": [[0, 1]], "inflow": { "times": [0.0, 3.0], "values": [2.0, 0.0] } }
+///   ]
+/// }
+/// ```
+/// Nodes and edges are identified by their index, and paths are given as lists of edges.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct InstanceJson {
+    pub num_nodes: usize,
+    pub edges: Vec<EdgeJson>,
+    pub commodities: Vec<CommodityJson>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeJson {
+    pub tail: usize,
+    pub head: usize,
+    pub capacity: f64,
+    pub travel_time: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CommodityJson {
+    pub paths: Vec<Vec<usize>>,
+    pub inflow: StepFunctionJson,
+}
+
+/// A step function with the value `values[i]` from `times[i]` until the next time.
+/// Its `domain` is only written if it is bounded, where `null` stands for an unbounded side, e.g.
+/// `"domain": [0.0, null]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StepFunctionJson {
+    pub times: Vec<f64>,
+    pub values: Vec<f64>,
+    #[serde(default, skip_serializing_if = "is_real_line")]
+    pub domain: [Option<f64>; 2],
+}
+
+fn is_real_line(domain: &[Option<f64>; 2]) -> bool {
+    domain == &[None, None]
+}
+
+impl StepFunctionJson {
+    pub fn from_function<T: Num + ToF64>(f: &PiecewiseConstant<T>) -> Self {
+        StepFunctionJson {
+            times: f.points().iter().map(|p| p.0.to_f64()).collect(),
+            values: f.points().iter().map(|p| p.1.to_f64()).collect(),
+            domain: f
+                .domain()
+                .map(|x| (x > -T::INFINITY && x < T::INFINITY).then(|| x.to_f64())),
+        }
+    }
+
+    /// Returns the step function on its domain, or an error naming `what` if the times are not
+    /// finite, increasing and within the domain, or if some value is negative.
+    pub fn to_function<T: Num + From<f64>>(
+        &self,
+        what: &str,
+    ) -> Result<PiecewiseConstant<T>, FormatError> {
+        if self.times.is_empty() || self.times.len() != self.values.len() {
+            return Err(FormatError::Invalid(format!(
+                "{} needs equally many times and values, and at least one of each.",
+                what
+            )));
+        }
+        if let Some(time) = self.times.iter().find(|time| !time.is_finite()) {
+            return Err(FormatError::Invalid(format!(
+                "{} has the invalid time {}.",
+                what, time
+            )));
+        }
+        if !self.times.windows(2).all(|w| w[0] < w[1]) {
+            return Err(FormatError::Invalid(format!(
+                "The times of {} are not increasing.",
+                what
+            )));
+        }
+        let [lower, upper] = self.domain;
+        let (first, last) = (self.times[0], self.times[self.times.len() - 1]);
+        if lower.is_some_and(|lower| lower.is_nan() || lower > first)
+            || upper.is_some_and(|upper| upper.is_nan() || upper < last)
+        {
+            return Err(FormatError::Invalid(format!(
+                "The times of {} are not within its domain.",
+                what
+            )));
+        }
+        if let Some(value) = self
+            .values
+            .iter()
+            .find(|value| value.is_nan() || **value < 0.0)
+        {
+            return Err(FormatError::Invalid(format!(
+                "{} has the invalid value {}.",
+                what, value
+            )));
+        }
+        let points = self
+            .times
+            .iter()
+            .zip(&self.values)
+            .map(|(&time, &value)| Point(time.into(), value.into()));
+        let domain = [
+            lower.map_or(-T::INFINITY, T::from),
+            upper.map_or(T::INFINITY, T::from),
+        ];
+        Ok(PiecewiseConstant::new(domain, points))
+    }
+}
+
+impl InstanceJson {
+    pub fn from_instance<T: Num + ToF64>(instance: &Instance<T>) -> Self {
+        let network = &instance.network;
+        InstanceJson {
+            num_nodes: network.num_nodes(),
+            edges: (0..network.num_edges())
+                .map(|edge| EdgeJson {
+                    tail: network.tail(edge),
+                    head: network.head(edge),
+                    capacity: network.capacity()[edge].to_f64(),
+                    travel_time: network.travel_time()[edge].to_f64(),
+                })
+                .collect(),
+            commodities: instance
+                .commodities
+                .iter()
+                .map(|c| CommodityJson {
+                    paths: c.paths.clone(),
+                    inflow: StepFunctionJson::from_function(&c.inflow),
+                })
+                .collect(),
+        }
+    }
+
+    /// Builds the instance, checking everything that `Instance::new` and `Network::add_edge` only
+    /// assert in debug builds.
+    pub fn to_instance<T: Num + From<f64>>(&self) -> Result<Instance<T>, FormatError> {
+        let mut network = Network::new();
+        for _ in 0..self.num_nodes {
+            network.add_node();
+        }
+        for (edge, e) in self.edges.iter().enumerate() {
+            if e.tail >= self.num_nodes || e.head >= self.num_nodes {
+                return Err(FormatError::Invalid(format!(
+                    "Edge {} connects the unknown nodes {} and {}.",
+                    edge, e.tail, e.head
+                )));
+            }
+            if e.capacity.is_nan() || e.capacity <= 0.0 {
+                return Err(FormatError::Invalid(format!(
+                    "Edge {} has the invalid capacity {}.",
+                    edge, e.capacity
+                )));
+            }
+            if e.travel_time.is_nan() || e.travel_time < 0.0 {
+                return Err(FormatError::Invalid(format!(
+                    "Edge {} has the invalid travel time {}.",
+                    edge, e.travel_time
+                )));
+            }
+            network.add_edge(e.tail, e.head, e.capacity.into(), e.travel_time.into());
+        }
+
+        let mut commodities = Vec::with_capacity(self.commodities.len());
+        for (i, c) in self.commodities.iter().enumerate() {
+            if c.paths.is_empty() {
+                return Err(FormatError::Invalid(format!(
+                    "Commodity {} has no paths.",
+                    i
+                )));
+            }
+            if let Some(path) = c.paths.iter().find(|path| !network.is_path(path)) {
+                return Err(FormatError::Invalid(format!(
+                    "Commodity {} has the invalid path {:?}.",
+                    i, path
+                )));
+            }
+            commodities.push(Commodity {
+                paths: c.paths.clone(),
+                inflow: c
+                    .inflow
+                    .to_function(&format!("The inflow of commodity {}", i))?,
+            });
+        }
+        Ok(Instance::new(network, commodities))
+    }
+}
+
+impl<T: Num + ToF64> Instance<T> {
+    /// Returns the instance in the format of `InstanceJson`.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&InstanceJson::from_instance(self)).unwrap()
+    }
+}
+
+impl<T: Num + From<f64>> Instance<T> {
+    /// Reads an instance in the format of `InstanceJson`.
+    pub fn from_json(json: &str) -> Result<Self, FormatError> {
+        serde_json::from_str::<InstanceJson>(json)?.to_instance()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::nested_merges, float::F64, format_error::FormatError,
+        instance::Instance, num::Num, piecewise_constant::PiecewiseConstant, points,
+    };
+
+    use super::StepFunctionJson;
+
+    #[test]
+    fn it_reads_its_own_json() {
+        let example = nested_merges::<F64>();
+        let json = example.instance.to_json();
+        let instance: Instance<F64> = Instance::from_json(&json).unwrap();
+        let network = &instance.network;
+        assert_eq!(network.num_nodes(), example.instance.network.num_nodes());
+        assert_eq!(network.capacity(), example.instance.network.capacity());
+        assert_eq!(
+            network.travel_time(),
+            example.instance.network.travel_time()
+        );
+        for (c, expected) in instance
+            .commodities
+            .iter()
+            .zip(&example.instance.commodities)
+        {
+            assert_eq!(c.paths, expected.paths);
+            assert_eq!(c.inflow.points(), expected.inflow.points());
+            assert_eq!(c.inflow.domain(), expected.inflow.domain());
+        }
+        assert_eq!(instance.to_json(), json);
+    }
+
+    #[test]
+    fn it_keeps_bounded_domains() {
+        let inflow: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 2.0), (3.0, 0.0)]);
+        let json = StepFunctionJson::from_function(&inflow);
+        assert_eq!(json.domain, [Some(0.0), None]);
+        let text = serde_json::to_string(&json).unwrap();
+        assert!(text.contains(r#""domain":[0.0,null]"#));
+        let read: StepFunctionJson = serde_json::from_str(&text).unwrap();
+        assert_eq!(read.to_function::<F64>("f").unwrap(), inflow);
+
+        let real_line = StepFunctionJson {
+            domain: [None, None],
+            ..json.clone()
+        };
+        assert!(!serde_json::to_string(&real_line)
+            .unwrap()
+            .contains("domain"));
+        let before_domain = StepFunctionJson {
+            domain: [Some(1.0), None],
+            ..json.clone()
+        };
+        assert!(before_domain.to_function::<F64>("f").is_err());
+        let nan_time = StepFunctionJson {
+            times: vec![f64::NAN],
+            values: vec![1.0],
+            domain: [None, None],
+        };
+        assert!(nan_time.to_function::<F64>("f").is_err());
+    }
+
+    #[test]
+    fn it_rejects_invalid_instances() {
+        let json = r#"{
+            "numNodes": 3,
+            "edges": [
+                { "tail": 0, "head": 1, "capacity": 1.0, "travelTime": 2.0 },
+                { "tail": 1, "head": 2, "capacity": 2.0, "travelTime": 1.0 }
+            ],
+            "commodities": [
+                { "paths": [[1, 0]], "inflow": { "times": [0.0, 3.0], "values": [2.0, 0.0] } }
+            ]
+        }"#;
+        let result: Result<Instance<F64>, _> = Instance::from_json(json);
+        assert!(matches!(result, Err(FormatError::Invalid(_))));
+        let result: Result<Instance<F64>, _> = Instance::from_json(&json.replace("1, 0", "0, 1"));
+        assert_eq!(result.unwrap().commodities[0].inflow.eval(1.0), 2.0);
+        let result: Result<Instance<F64>, _> = Instance::from_json("{}");
+        assert!(matches!(result, Err(FormatError::Json(_))));
+    }
+}
@@ -0,0 +1,302 @@
+use std::{
+    fmt::{Debug, Display},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+};
+
+use num_traits::{Num as NumTraitsNum, One, Signed, Zero};
+
+use crate::num::{Num, ToF64, Tolerance};
+
+/// An integer number of fixed units of time or flow, extended by ±∞.
+///
+/// All operations are exact: Overflows and quotients that are not integral panic.
+/// This is much faster than rational arithmetic, but only applicable to instances whose loading
+/// keeps all intermediate values integral, e.g. unit capacities with integral rates and times.
+/// The largest and smallest raw values represent ±∞.
+#[repr(transparent)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct I64Time(i64);
+
+const RAW_INFINITY: i64 = i64::MAX;
+
+impl I64Time {
+    pub fn raw(self) -> i64 {
+        self.0
+    }
+
+    #[inline]
+    fn is_infinite(self) -> bool {
+        self.0 == RAW_INFINITY || self.0 == -RAW_INFINITY
+    }
+
+    #[inline]
+    fn checked(result: Option<i64>) -> Self {
+        match result {
+            Some(raw) if raw.abs() < RAW_INFINITY => I64Time(raw),
+            _ => panic!("The integer overflowed."),
+        }
+    }
+}
+
+impl From<i64> for I64Time {
+    #[inline]
+    fn from(val: i64) -> Self {
+        I64Time::checked(Some(val))
+    }
+}
+
+impl Rem for I64Time {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        debug_assert!(!self.is_infinite() && !rhs.is_infinite());
+        I64Time(self.0 % rhs.0)
+    }
+}
+
+impl Div for I64Time {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        assert!(!rhs.is_zero(), "Division by zero.");
+        match (self.is_infinite(), rhs.is_infinite()) {
+            (false, false) => {
+                assert!(
+                    self.0 % rhs.0 == 0,
+                    "The quotient {} / {} is not integral.",
+                    self,
+                    rhs
+                );
+                I64Time(self.0 / rhs.0)
+            }
+            (false, true) => I64Time::ZERO,
+            (true, false) if rhs.is_positive() => self,
+            (true, false) => -self,
+            (true, true) => panic!("The quotient {} / {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Sub for I64Time {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for I64Time {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        if !self.is_infinite() && !rhs.is_infinite() {
+            I64Time::checked(self.0.checked_mul(rhs.0))
+        } else if self.is_zero() || rhs.is_zero() {
+            // We use the convention 0·∞ = 0.
+            I64Time::ZERO
+        } else if self.is_positive() == rhs.is_positive() {
+            I64Time::INFINITY
+        } else {
+            -I64Time::INFINITY
+        }
+    }
+}
+
+impl Add for I64Time {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self.is_infinite(), rhs.is_infinite()) {
+            (false, false) => I64Time::checked(self.0.checked_add(rhs.0)),
+            (false, true) => rhs,
+            (true, false) => self,
+            (true, true) if self == rhs => self,
+            (true, true) => panic!("The sum {} + {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Zero for I64Time {
+    #[inline]
+    fn zero() -> Self {
+        I64Time::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl One for I64Time {
+    #[inline]
+    fn one() -> Self {
+        I64Time::ONE
+    }
+}
+
+impl Display for I64Time {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            RAW_INFINITY => write!(f, "inf"),
+            raw if raw == -RAW_INFINITY => write!(f, "-inf"),
+            raw => Display::fmt(&raw, f),
+        }
+    }
+}
+
+impl NumTraitsNum for I64Time {
+    type FromStrRadixErr = <i64 as NumTraitsNum>::FromStrRadixErr;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        i64::from_str_radix(str, radix).map(I64Time)
+    }
+}
+
+impl Neg for I64Time {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        I64Time(-self.0)
+    }
+}
+
+impl std::iter::Sum for I64Time {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(I64Time::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Signed for I64Time {
+    #[inline]
+    fn abs(&self) -> Self {
+        I64Time(self.0.abs())
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            I64Time::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        I64Time(self.0.signum())
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.0 > 0
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+}
+
+impl AddAssign for I64Time {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for I64Time {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for I64Time {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl RemAssign for I64Time {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl DivAssign for I64Time {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Num for I64Time {
+    const ZERO: Self = I64Time(0);
+    const ONE: Self = I64Time(1);
+    const INFINITY: Self = I64Time(RAW_INFINITY);
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.cmp(other)
+    }
+}
+
+impl Tolerance for I64Time {}
+
+impl ToF64 for I64Time {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        if self.is_infinite() {
+            self.0.signum() as f64 * f64::INFINITY
+        } else {
+            self.0 as f64
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::{braess, vickrey_bottleneck},
+        num::Num,
+    };
+
+    use super::I64Time;
+
+    #[test]
+    fn it_loads_integral_networks() {
+        let example = braess(I64Time::ONE, I64Time::from(4));
+        let flow = example.instance.load(&example.path_inflows);
+        assert!(flow.queues().iter().all(|q| q.sup_norm() == I64Time::ZERO));
+        // The zig-zag path is the third path and takes two time units.
+        let arrivals = &flow.outflow()[2].function_by_comm()[&2];
+        assert_eq!(arrivals.eval(I64Time::ONE), I64Time::ZERO);
+        assert_eq!(arrivals.eval(I64Time::from(2)), I64Time::ONE);
+        assert_eq!(arrivals.eval(I64Time::from(6)), I64Time::ZERO);
+
+        let example = vickrey_bottleneck(I64Time::from(3), I64Time::ONE, 2.into(), 1.into());
+        let flow = example.instance.load(&example.path_inflows);
+        assert_eq!(flow.queues()[0].sup_norm(), example.max_queues[0]);
+        assert_eq!(flow.queues()[0].eval(I64Time::from(3)), I64Time::ZERO);
+        assert_eq!(-I64Time::INFINITY * I64Time::from(2), -I64Time::INFINITY);
+        assert_eq!(I64Time::INFINITY.to_string(), "inf");
+    }
+
+    #[test]
+    #[should_panic(expected = "not integral")]
+    fn it_rejects_inexact_quotients() {
+        let _ = I64Time::ONE / I64Time::from(2);
+    }
+}
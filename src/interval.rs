@@ -0,0 +1,422 @@
+use std::{
+    cmp::Ordering,
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+};
+
+use num_traits::{Num as NumTraitsNum, One, Signed, Zero};
+use ordered_float::OrderedFloat;
+
+use crate::num::{Num, ToF64, Tolerance};
+
+/// An interval `[lo, hi]` of floats that is guaranteed to contain the exact result.
+///
+/// Every operation rounds the lower bound down and the upper bound up, so after running a
+/// loading, the `width` of a value bounds the rounding error the `F64` backend could have made.
+/// Comparisons only look at the midpoints, such that the piecewise algorithms branch just like
+/// they would for floats.
+#[derive(Copy, Clone, Debug)]
+pub struct Interval {
+    lo: f64,
+    hi: f64,
+}
+
+impl Interval {
+    pub fn new(lo: f64, hi: f64) -> Self {
+        debug_assert!(lo <= hi, "The interval [{}, {}] is empty.", lo, hi);
+        Interval { lo, hi }
+    }
+
+    pub fn lo(self) -> f64 {
+        self.lo
+    }
+
+    pub fn hi(self) -> f64 {
+        self.hi
+    }
+
+    pub fn width(self) -> f64 {
+        if self.lo == self.hi {
+            0.0
+        } else {
+            self.hi - self.lo
+        }
+    }
+
+    pub fn midpoint(self) -> f64 {
+        if self.lo == self.hi {
+            self.lo
+        } else {
+            self.lo / 2.0 + self.hi / 2.0
+        }
+    }
+
+    pub fn contains(self, value: f64) -> bool {
+        self.lo <= value && value <= self.hi
+    }
+
+    fn key(self) -> (OrderedFloat<f64>, OrderedFloat<f64>, OrderedFloat<f64>) {
+        (
+            OrderedFloat(self.midpoint()),
+            OrderedFloat(self.lo),
+            OrderedFloat(self.hi),
+        )
+    }
+}
+
+/// Returns the bounds of `approx + error`, where `error` has the given sign.
+#[inline]
+fn enclose(approx: f64, error: f64) -> (f64, f64) {
+    if !approx.is_finite() || error == 0.0 {
+        (approx, approx)
+    } else if error < 0.0 {
+        (approx.next_down(), approx)
+    } else {
+        (approx, approx.next_up())
+    }
+}
+
+/// Encloses `a + b` using the error-free two-sum transformation.
+#[inline]
+fn enclose_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let error = (a - (sum - b_virtual)) + (b - b_virtual);
+    enclose(sum, error)
+}
+
+/// Encloses `a * b` using a fused multiply-add for the exact rounding error.
+/// We use the convention 0·∞ = 0.
+#[inline]
+fn enclose_product(a: f64, b: f64) -> (f64, f64) {
+    if a == 0.0 || b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let product = a * b;
+    enclose(product, a.mul_add(b, -product))
+}
+
+/// Encloses `a / b` using the exact remainder `a - q·b`.
+#[inline]
+fn enclose_quotient(a: f64, b: f64) -> (f64, f64) {
+    let quotient = a / b;
+    if !b.is_finite() {
+        return (quotient, quotient);
+    }
+    let remainder = (-quotient).mul_add(b, a);
+    enclose(quotient, remainder * b.signum())
+}
+
+/// Combines the enclosures of the four combinations of bounds.
+#[inline]
+fn hull(a: Interval, b: Interval, op: fn(f64, f64) -> (f64, f64)) -> Interval {
+    let corners = [
+        op(a.lo, b.lo),
+        op(a.lo, b.hi),
+        op(a.hi, b.lo),
+        op(a.hi, b.hi),
+    ];
+    Interval {
+        lo: corners.iter().map(|c| c.0).fold(f64::INFINITY, f64::min),
+        hi: corners
+            .iter()
+            .map(|c| c.1)
+            .fold(f64::NEG_INFINITY, f64::max),
+    }
+}
+
+impl From<f64> for Interval {
+    #[inline]
+    fn from(val: f64) -> Self {
+        Interval { lo: val, hi: val }
+    }
+}
+
+impl Rem for Interval {
+    type Output = Self;
+
+    /// The remainder is only computed for the midpoints and hence not certified.
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        (self.midpoint() % rhs.midpoint()).into()
+    }
+}
+
+impl Div for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        if rhs.lo <= 0.0 && 0.0 <= rhs.hi {
+            Interval::new(f64::NEG_INFINITY, f64::INFINITY)
+        } else {
+            hull(self, rhs, enclose_quotient)
+        }
+    }
+}
+
+impl Sub for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + -rhs
+    }
+}
+
+impl Mul for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        hull(self, rhs, enclose_product)
+    }
+}
+
+impl Add for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        Interval {
+            lo: enclose_sum(self.lo, rhs.lo).0,
+            hi: enclose_sum(self.hi, rhs.hi).1,
+        }
+    }
+}
+
+impl Zero for Interval {
+    #[inline]
+    fn zero() -> Self {
+        Interval::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        *self == Interval::ZERO
+    }
+}
+
+impl One for Interval {
+    #[inline]
+    fn one() -> Self {
+        Interval::ONE
+    }
+}
+
+impl PartialEq for Interval {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.total_cmp(other) == Ordering::Equal
+    }
+}
+
+impl PartialOrd for Interval {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.total_cmp(other))
+    }
+}
+
+impl Hash for Interval {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        OrderedFloat(self.lo).hash(state);
+        OrderedFloat(self.hi).hash(state);
+    }
+}
+
+impl NumTraitsNum for Interval {
+    type FromStrRadixErr = <f64 as NumTraitsNum>::FromStrRadixErr;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        f64::from_str_radix(str, radix).map(Interval::from)
+    }
+}
+
+impl Display for Interval {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.lo == self.hi {
+            Display::fmt(&self.lo, f)
+        } else {
+            write!(f, "[{}, {}]", self.lo, self.hi)
+        }
+    }
+}
+
+impl Neg for Interval {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        Interval {
+            lo: -self.hi,
+            hi: -self.lo,
+        }
+    }
+}
+
+impl std::iter::Sum for Interval {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Interval::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl Signed for Interval {
+    #[inline]
+    fn abs(&self) -> Self {
+        if self.lo >= 0.0 {
+            *self
+        } else if self.hi <= 0.0 {
+            -*self
+        } else {
+            Interval::new(0.0, self.hi.max(-self.lo))
+        }
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Interval::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        self.midpoint().signum().into()
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        self.midpoint() > 0.0
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        self.midpoint() < 0.0
+    }
+}
+
+impl AddAssign for Interval {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Interval {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Interval {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl RemAssign for Interval {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl DivAssign for Interval {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Num for Interval {
+    const ZERO: Self = Interval { lo: 0., hi: 0. };
+    const ONE: Self = Interval { lo: 1., hi: 1. };
+    const INFINITY: Self = Interval {
+        lo: f64::INFINITY,
+        hi: f64::INFINITY,
+    };
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self.lo.is_nan() || self.hi.is_nan()
+    }
+}
+
+impl Tolerance for Interval {
+    const EXACT_ARITHMETIC: bool = false;
+    const TOL: Self = Interval { lo: 1e-9, hi: 1e-9 };
+}
+
+impl ToF64 for Interval {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        self.midpoint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_traits::Signed;
+
+    use crate::{
+        example_instances::vickrey_bottleneck,
+        float::F64,
+        num::{Num, ToF64},
+        price_of_anarchy::total_travel_times,
+    };
+
+    use super::Interval;
+
+    #[test]
+    fn it_encloses_rounding_errors() {
+        let tenth = Interval::ONE / Interval::from(10.0);
+        assert!(tenth.contains(0.1) && tenth.width() > 0.0);
+        let sum = tenth + tenth + tenth;
+        assert!(sum.lo() < 0.3 && 0.3 < sum.hi());
+        assert_eq!(Interval::ONE + Interval::ONE, Interval::from(2.0));
+        assert_eq!(Interval::INFINITY * Interval::ZERO, Interval::ZERO);
+        assert_eq!(
+            Interval::new(-1.0, 2.0) * Interval::new(-3.0, 1.0),
+            Interval::new(-6.0, 3.0)
+        );
+        assert_eq!(Interval::new(-1.0, 2.0).abs(), Interval::new(0.0, 2.0));
+    }
+
+    #[test]
+    fn it_bounds_the_error_of_a_loading() {
+        let example = vickrey_bottleneck(
+            Interval::from(3.0),
+            Interval::from(2.0 / 3.0),
+            Interval::ONE,
+            Interval::from(1.0 / 3.0),
+        );
+        let flow = example.instance.load(&example.path_inflows);
+        let total = total_travel_times(&example.instance, &flow).total;
+        assert!(total.width() < 1e-12);
+
+        let float = vickrey_bottleneck::<F64>(
+            3.0.into(),
+            (2.0 / 3.0).into(),
+            1.0.into(),
+            (1.0 / 3.0).into(),
+        );
+        let float_flow = float.instance.load(&float.path_inflows);
+        let float_total = total_travel_times(&float.instance, &float_flow).total;
+        assert!(total.contains(float_total.to_f64()));
+    }
+}
@@ -0,0 +1,56 @@
+#![allow(dead_code)]
+
+pub mod bench;
+pub mod chunked_points;
+pub mod commodity_vec;
+pub mod compression;
+pub mod csv_import;
+pub mod decimal;
+pub mod departure_time_choice;
+pub mod depletion_queue;
+pub mod dimacs;
+pub mod double_double;
+pub mod dual;
+pub mod dynamic_flow;
+pub mod example_instances;
+pub mod export_csv;
+#[cfg(feature = "hdf5")]
+pub mod export_hdf5;
+#[cfg(feature = "parquet")]
+pub mod export_parquet;
+pub mod export_protobuf;
+pub mod export_visualization;
+pub mod fixed_point;
+pub mod float;
+pub mod flow_diff;
+pub mod format_error;
+pub mod graphml;
+pub mod instance;
+pub mod instance_json;
+pub mod integer;
+pub mod interval;
+pub mod matsim;
+pub mod network;
+pub mod network_loader;
+pub mod node_flow;
+pub mod num;
+pub mod od_demand;
+pub mod option_ext;
+pub mod outflow_change_queue;
+pub mod path_decomposition;
+pub mod piecewise_constant;
+pub mod piecewise_linear;
+pub mod piecewise_quadratic;
+pub mod plot;
+pub mod point;
+pub mod price_of_anarchy;
+pub mod profiling;
+pub mod rational;
+pub mod run_config;
+pub mod scenario;
+pub mod sumo;
+pub mod time_space;
+pub mod tntp;
+pub mod tolerance;
+pub mod validation;
+pub mod xml;
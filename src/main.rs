@@ -1,21 +1,23 @@
-#![allow(dead_code)]
+use std::{env, path::Path, process};
 
-mod depletion_queue;
-mod dynamic_flow;
-mod export_visualization;
-mod float;
-mod network_loader;
-mod num;
-mod option_ext;
-mod piecewise_constant;
-mod piecewise_linear;
-mod plot;
-mod point;
-
-use crate::{float::F64, num::Num};
-use piecewise_linear::PiecewiseLinear;
+use dynamic_flows_rs::{
+    float::F64, num::Num, piecewise_linear::PiecewiseLinear, plot, points,
+    run_config::run_config_file,
+};
 
 fn main() {
+    // With a TOML or YAML config file as argument, run it instead of the demo below.
+    if let Some(path) = env::args().nth(1) {
+        match run_config_file(Path::new(&path)) {
+            Ok(report) => println!("{:?}", report),
+            Err(err) => {
+                eprintln!("{}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+
     let f1: PiecewiseLinear<F64> = PiecewiseLinear::new(
         [-F64::INFINITY, F64::INFINITY],
         1.0,
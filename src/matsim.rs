@@ -0,0 +1,314 @@
+use std::collections::{BTreeMap, HashMap};
+
+use quick_xml::{events::Event, Reader};
+
+use crate::{
+    format_error::FormatError,
+    instance::{Commodity, Instance},
+    network::Network,
+    num::Num,
+    piecewise_constant::PiecewiseConstant,
+    point::Point,
+    xml::{attributes, required},
+};
+
+/// A network read from a MATSim `network.xml` file, in which time is measured in seconds.
+#[derive(Debug, Clone)]
+pub struct MatsimNetwork<T: Num> {
+    pub network: Network<T>,
+    /// The MATSim id of each node.
+    pub node_ids: Vec<String>,
+    /// The MATSim id of the link of each edge.
+    pub link_ids: Vec<String>,
+    edges_by_link: HashMap<String, usize>,
+}
+
+/// Parses a MATSim time `hh:mm:ss` (where hours may exceed 24) or a plain number of seconds.
+fn parse_time(time: &str) -> Result<f64, FormatError> {
+    let invalid = || FormatError::Invalid(format!("Cannot parse the time `{}`.", time));
+    let mut seconds = 0.0;
+    for part in time.trim().split(':') {
+        seconds = 60.0 * seconds + part.parse::<f64>().map_err(|_| invalid())?;
+    }
+    Ok(seconds)
+}
+
+/// Parses a MATSim network. The capacity of a link is given per `capperiod` of the `links` element
+/// (one hour by default) and becomes a capacity per second, while the travel time of an edge is
+/// the length of its link divided by its free speed.
+pub fn parse_matsim_network<T: Num + From<f64>>(
+    xml: &str,
+) -> Result<MatsimNetwork<T>, FormatError> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut network = Network::new();
+    let mut node_ids = Vec::new();
+    let mut nodes_by_id: HashMap<String, usize> = HashMap::new();
+    let mut link_ids = Vec::new();
+    let mut edges_by_link = HashMap::new();
+    let mut capacity_period = 3600.0;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) => match e.name().as_ref() {
+                b"node" => {
+                    let id: String = required(&attributes(&e)?, "node", "id")?;
+                    nodes_by_id.insert(id.clone(), network.add_node());
+                    node_ids.push(id);
+                }
+                b"links" => {
+                    if let Some(period) = attributes(&e)?.get("capperiod") {
+                        capacity_period = parse_time(period)?;
+                    }
+                }
+                b"link" => {
+                    let attributes = attributes(&e)?;
+                    let id: String = required(&attributes, "link", "id")?;
+                    let node = |key: &str| -> Result<usize, FormatError> {
+                        let node_id: String = required(&attributes, "link", key)?;
+                        nodes_by_id.get(&node_id).copied().ok_or_else(|| {
+                            FormatError::Invalid(format!(
+                                "The link {} uses the unknown node {}.",
+                                id, node_id
+                            ))
+                        })
+                    };
+                    let (tail, head) = (node("from")?, node("to")?);
+                    let length: f64 = required(&attributes, "link", "length")?;
+                    let free_speed: f64 = required(&attributes, "link", "freespeed")?;
+                    let capacity: f64 = required(&attributes, "link", "capacity")?;
+                    if capacity.is_nan()
+                        || capacity <= 0.0
+                        || free_speed.is_nan()
+                        || free_speed <= 0.0
+                    {
+                        return Err(FormatError::Invalid(format!(
+                            "The link {} needs a positive capacity and free speed.",
+                            id
+                        )));
+                    }
+                    if length.is_nan() || length < 0.0 {
+                        return Err(FormatError::Invalid(format!(
+                            "The link {} has a negative length.",
+                            id
+                        )));
+                    }
+                    let edge = network.add_edge(
+                        tail,
+                        head,
+                        (capacity / capacity_period).into(),
+                        (length / free_speed).into(),
+                    );
+                    edges_by_link.insert(id.clone(), edge);
+                    link_ids.push(id);
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(MatsimNetwork {
+        network,
+        node_ids,
+        link_ids,
+        edges_by_link,
+    })
+}
+
+fn missing_route() -> FormatError {
+    FormatError::Invalid(String::from("A car leg of a selected plan has no route."))
+}
+
+impl<T: Num + From<f64>> MatsimNetwork<T> {
+    /// Returns the edge of the link with the given MATSim id.
+    pub fn edge(&self, link_id: &str) -> Option<usize> {
+        self.edges_by_link.get(link_id).copied()
+    }
+
+    /// Reads the car legs of the selected plans of a MATSim `plans.xml` file and returns an
+    /// instance with one commodity per distinct route. The departures on a route are counted in
+    /// bins of `bin_width` seconds, giving a network inflow rate of `count / bin_width` per bin.
+    /// A leg departs at its `dep_time`, or else at the `end_time` of the preceding activity.
+    /// Plans without a `selected` attribute are considered selected.
+    pub fn instance_from_plans(
+        &self,
+        xml: &str,
+        bin_width: f64,
+    ) -> Result<Instance<T>, FormatError> {
+        debug_assert!(bin_width > 0.0, "The bins need a positive width.");
+        let mut reader = Reader::from_str(xml);
+        reader.trim_text(true);
+        // The number of departures per bin of each route
+        let mut departures: BTreeMap<Vec<usize>, BTreeMap<i64, usize>> = BTreeMap::new();
+        let mut in_selected_plan = false;
+        let mut last_end_time: Option<f64> = None;
+        // The departure time of the current car leg, whose route is read next
+        let mut car_leg: Option<f64> = None;
+        let mut in_route = false;
+        loop {
+            let event = reader.read_event()?;
+            let is_empty = matches!(event, Event::Empty(_));
+            match event {
+                Event::Start(e) | Event::Empty(e) => {
+                    let attributes = attributes(&e)?;
+                    match e.name().as_ref() {
+                        b"plan" => {
+                            in_selected_plan =
+                                attributes.get("selected").is_none_or(|s| s == "yes");
+                            last_end_time = None;
+                        }
+                        b"act" | b"activity" if in_selected_plan => {
+                            last_end_time = attributes
+                                .get("end_time")
+                                .map(|time| parse_time(time))
+                                .transpose()?;
+                        }
+                        b"leg" if in_selected_plan => {
+                            car_leg = None;
+                            if attributes.get("mode").is_some_and(|mode| mode == "car") {
+                                let dep_time = attributes
+                                    .get("dep_time")
+                                    .map(|t| parse_time(t))
+                                    .transpose()?;
+                                car_leg = Some(dep_time.or(last_end_time).ok_or_else(|| {
+                                    FormatError::Invalid(String::from(
+                                        "A car leg has neither a dep_time nor a preceding end_time.",
+                                    ))
+                                })?);
+                                if is_empty {
+                                    return Err(missing_route());
+                                }
+                            }
+                        }
+                        b"route" => in_route = car_leg.is_some(),
+                        _ => {}
+                    }
+                }
+                Event::Text(text) if in_route => {
+                    let departure = car_leg.take().unwrap();
+                    let path = text
+                        .unescape()?
+                        .split_whitespace()
+                        .map(|link| {
+                            self.edge(link).ok_or_else(|| {
+                                FormatError::Invalid(format!(
+                                    "A route uses the unknown link {}.",
+                                    link
+                                ))
+                            })
+                        })
+                        .collect::<Result<Vec<usize>, FormatError>>()?;
+                    if path.is_empty() || !self.network.is_path(&path) {
+                        return Err(FormatError::Invalid(format!(
+                            "The route {:?} is not a path.",
+                            text.unescape()?
+                        )));
+                    }
+                    let bin = (departure / bin_width).floor() as i64;
+                    *departures.entry(path).or_default().entry(bin).or_default() += 1;
+                }
+                Event::End(e) if e.name().as_ref() == b"route" => in_route = false,
+                // Other children of a car leg, e.g. its attributes, are skipped until its end.
+                Event::End(e) if e.name().as_ref() == b"leg" && car_leg.is_some() => {
+                    return Err(missing_route());
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        let commodities = departures
+            .into_iter()
+            .map(|(path, bins)| {
+                let mut points: Vec<Point<T>> = Vec::with_capacity(2 * bins.len());
+                for (&bin, &count) in &bins {
+                    let start = bin as f64 * bin_width;
+                    if points.last().is_some_and(|p| p.0 == T::from(start)) {
+                        points.pop();
+                    }
+                    points.push(Point(start.into(), (count as f64 / bin_width).into()));
+                    points.push(Point((start + bin_width).into(), T::ZERO));
+                }
+                Commodity {
+                    paths: vec![path],
+                    inflow: PiecewiseConstant::new([-T::INFINITY, T::INFINITY], points),
+                }
+            })
+            .collect();
+        Ok(Instance::new(self.network.clone(), commodities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::F64;
+
+    use super::parse_matsim_network;
+
+    const NETWORK: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE network SYSTEM "http://www.matsim.org/files/dtd/network_v1.dtd">
+<network name="corridor">
+  <nodes>
+    <node id="a" x="0" y="0"/>
+    <node id="b" x="100" y="0"/>
+    <node id="c" x="200" y="0"/>
+  </nodes>
+  <links capperiod="00:30:00">
+    <link id="1" from="a" to="b" length="100" capacity="3600" freespeed="10" permlanes="1"/>
+    <link id="2" from="b" to="c" length="50" capacity="900" freespeed="5" permlanes="1"/>
+  </links>
+</network>
+"#;
+
+    const PLANS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<population>
+  <person id="1">
+    <plan selected="no">
+      <act type="h" link="1" end_time="00:00:00"/>
+      <leg mode="car"><route type="links">1</route></leg>
+    </plan>
+    <plan selected="yes">
+      <act type="h" link="1" end_time="00:00:05"/>
+      <leg mode="car"><route type="links">1 2</route></leg>
+      <act type="w" link="2"/>
+    </plan>
+  </person>
+  <person id="2">
+    <plan>
+      <act type="h" link="1" end_time="00:00:07"/>
+      <leg mode="car" dep_time="00:00:12">
+        <attributes>
+          <attribute name="routingMode" class="java.lang.String">car</attribute>
+        </attributes>
+        <route type="links">1 2</route>
+      </leg>
+      <act type="w" link="2" end_time="01:00:00"/>
+      <leg mode="walk"/>
+    </plan>
+  </person>
+</population>
+"#;
+
+    #[test]
+    fn it_reads_networks_and_plans() {
+        let matsim = parse_matsim_network::<F64>(NETWORK).unwrap();
+        assert_eq!(matsim.node_ids, vec!["a", "b", "c"]);
+        assert_eq!(matsim.edge("2"), Some(1));
+        assert_eq!(matsim.network.capacity()[0], 2.0);
+        assert_eq!(matsim.network.capacity()[1], 0.5);
+        assert_eq!(matsim.network.travel_time()[1], 10.0);
+
+        let instance = matsim.instance_from_plans(PLANS, 10.0).unwrap();
+        assert_eq!(instance.commodities.len(), 1);
+        let commodity = &instance.commodities[0];
+        assert_eq!(commodity.paths, vec![vec![0, 1]]);
+        assert_eq!(commodity.inflow.eval(5.0), 0.1);
+        assert_eq!(commodity.inflow.eval(15.0), 0.1);
+        assert_eq!(commodity.inflow.eval(20.0), 0.0);
+        assert!(matsim
+            .instance_from_plans(&PLANS.replace("1 2", "2 1"), 10.0)
+            .is_err());
+        let without_route = PLANS.replace(r#"<route type="links">1 2</route>"#, "");
+        assert!(matsim.instance_from_plans(&without_route, 10.0).is_err());
+    }
+}
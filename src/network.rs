@@ -0,0 +1,175 @@
+use std::cmp::max;
+
+use petgraph::{
+    graph::{DiGraph, EdgeIndex, NodeIndex},
+    visit::EdgeRef,
+    Direction,
+};
+
+use crate::{
+    dynamic_flow::DynamicFlow,
+    network_loader::{NetworkLoader, PathInflow},
+    num::{Num, Tolerance},
+    od_demand::OdDemand,
+};
+
+/// A directed network whose edges carry a capacity and a free-flow travel time.
+/// Nodes and edges are identified by their insertion index.
+#[derive(Debug, Clone)]
+pub struct Network<T: Num> {
+    graph: DiGraph<(), ()>,
+    capacity: Vec<T>,
+    inv_capacity: Vec<T>,
+    travel_time: Vec<T>,
+}
+
+impl<T: Num> Network<T> {
+    pub fn new() -> Self {
+        Network {
+            graph: DiGraph::new(),
+            capacity: Vec::new(),
+            inv_capacity: Vec::new(),
+            travel_time: Vec::new(),
+        }
+    }
+
+    pub fn add_node(&mut self) -> usize {
+        self.graph.add_node(()).index()
+    }
+
+    pub fn add_edge(&mut self, tail: usize, head: usize, capacity: T, travel_time: T) -> usize {
+        debug_assert!(capacity > T::ZERO, "The capacity must be positive.");
+        debug_assert!(
+            travel_time >= T::ZERO,
+            "The travel time must be non-negative."
+        );
+        while self.graph.node_count() <= max(tail, head) {
+            self.graph.add_node(());
+        }
+        let edge = self
+            .graph
+            .add_edge(NodeIndex::new(tail), NodeIndex::new(head), ())
+            .index();
+        self.capacity.push(capacity);
+        self.inv_capacity.push(T::ONE / capacity);
+        self.travel_time.push(travel_time);
+        edge
+    }
+
+    pub fn num_nodes(&self) -> usize {
+        self.graph.node_count()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    pub fn capacity(&self) -> &[T] {
+        &self.capacity
+    }
+
+    pub fn inv_capacity(&self) -> &[T] {
+        &self.inv_capacity
+    }
+
+    pub fn travel_time(&self) -> &[T] {
+        &self.travel_time
+    }
+
+    pub fn set_capacity(&mut self, edge: usize, capacity: T) {
+        debug_assert!(capacity > T::ZERO, "The capacity must be positive.");
+        self.capacity[edge] = capacity;
+        self.inv_capacity[edge] = T::ONE / capacity;
+    }
+
+    pub fn tail(&self, edge: usize) -> usize {
+        self.graph
+            .edge_endpoints(EdgeIndex::new(edge))
+            .unwrap()
+            .0
+            .index()
+    }
+
+    pub fn head(&self, edge: usize) -> usize {
+        self.graph
+            .edge_endpoints(EdgeIndex::new(edge))
+            .unwrap()
+            .1
+            .index()
+    }
+
+    pub fn outgoing_edges(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.graph
+            .edges_directed(NodeIndex::new(node), Direction::Outgoing)
+            .map(|e| e.id().index())
+    }
+
+    pub fn incoming_edges(&self, node: usize) -> impl Iterator<Item = usize> + '_ {
+        self.graph
+            .edges_directed(NodeIndex::new(node), Direction::Incoming)
+            .map(|e| e.id().index())
+    }
+
+    /// Returns true, if consecutive edges of `path` share their head and tail respectively.
+    pub fn is_path(&self, path: &[usize]) -> bool {
+        path.iter().all(|&e| e < self.num_edges())
+            && path.windows(2).all(|w| self.head(w[0]) == self.tail(w[1]))
+    }
+
+    /// Loads the network with the given path inflows until all queues have depleted.
+    pub fn load(&self, path_inflows: &[PathInflow<T>]) -> DynamicFlow<T>
+    where
+        T: Tolerance,
+    {
+        NetworkLoader::new(path_inflows).build_flow(
+            self.num_edges(),
+            &self.capacity,
+            &self.inv_capacity,
+            &self.travel_time,
+        )
+    }
+
+    /// Loads the network like `load`, but splits it into `num_parts` parts that are loaded
+    /// concurrently, see `NetworkLoader::build_flow_partitioned`.
+    pub fn load_partitioned(
+        &self,
+        path_inflows: &[PathInflow<T>],
+        num_parts: usize,
+    ) -> DynamicFlow<T>
+    where
+        T: Tolerance,
+    {
+        let loader = NetworkLoader::new(path_inflows);
+        let partition = loader.partition_edges(self.num_edges(), num_parts);
+        loader.build_flow_partitioned(
+            &partition,
+            self.num_edges(),
+            &self.capacity,
+            &self.inv_capacity,
+            &self.travel_time,
+        )
+    }
+
+    /// Loads the network with the given OD demands, expanding each into its path inflows.
+    pub fn load_demands(&self, demands: &[OdDemand<T>]) -> DynamicFlow<T>
+    where
+        T: Tolerance,
+    {
+        debug_assert!(
+            demands.iter().all(|d| d.is_valid(self)),
+            "Every demand needs valid paths from its origin to its destination."
+        );
+        NetworkLoader::from_demands(demands).build_flow(
+            self.num_edges(),
+            &self.capacity,
+            &self.inv_capacity,
+            &self.travel_time,
+        )
+    }
+}
+
+impl<T: Num> Default for Network<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
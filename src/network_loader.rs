@@ -1,20 +1,216 @@
-use std::{cmp::Reverse, collections::HashMap};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
-use itertools::Itertools;
 use priority_queue::PriorityQueue;
+use rayon::prelude::*;
 
 use crate::{
-    dynamic_flow::DynamicFlow, num::Num, piecewise_constant::PiecewiseConstant, point::Point,
+    commodity_vec::CommodityVec,
+    dynamic_flow::{CapacityHints, DynamicFlow, RateStorage},
+    export_visualization::FlowExportSink,
+    format_error::FormatError,
+    num::{max, min, Num, ToF64, Tolerance, Total},
+    od_demand::OdDemand,
+    piecewise_constant::PiecewiseConstant,
+    piecewise_linear::PiecewiseLinear,
+    point::Point,
+    tolerance::TolerancePolicy,
 };
 
+/// The largest number of breakpoints of the queue of an edge estimated by
+/// `NetworkLoader::estimate_capacity_hints`.
+pub const MAX_ESTIMATED_BREAKPOINTS: usize = 1024;
+
+/// A change of the network inflow rate of a path to `value` at time `time`.
+/// Changes are compared by `Num::total_cmp`, so that they can be kept in a priority queue.
+#[derive(Debug, Clone)]
+pub struct PathInflowRateChange<T: Num> {
+    pub path: usize,
+    pub time: T,
+    pub value: T,
+}
+
+impl<T: Num> PartialEq for PathInflowRateChange<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+            && Total(self.time) == Total(other.time)
+            && Total(self.value) == Total(other.value)
+    }
+}
+
+impl<T: Num> Eq for PathInflowRateChange<T> {}
+
+impl<T: Num> Hash for PathInflowRateChange<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.path.hash(state);
+        Total(self.time).hash(state);
+        Total(self.value).hash(state);
+    }
+}
+
+/// The outcome of a single step of the network loader.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoaderEvent<T: Num> {
+    /// The flow has been extended until `built_until`, where the outflow of `changed_edges` changes.
+    Extended {
+        built_until: T,
+        changed_edges: Vec<usize>,
+    },
+    /// The flow has been built until infinity.
+    Finished,
+}
+
+/// The state of a running loading, reported after each extension phase.
+pub struct LoadingProgress<'a, T: Num> {
+    pub built_until: T,
+    /// The number of extension phases so far.
+    pub phases: usize,
+    /// The number of network inflow changes and edge outflow changes processed so far.
+    pub processed_events: usize,
+    pub flow: &'a DynamicFlow<T>,
+}
+
+impl<T: Num> LoadingProgress<'_, T> {
+    /// Returns the number of breakpoints of the flow built so far.
+    /// This takes time linear in the size of the flow, so callers may want to throttle it.
+    pub fn breakpoints(&self) -> usize {
+        self.flow.num_breakpoints()
+    }
+}
+
+/// A token that allows aborting a loading, e.g. from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The flow of a loading that may have been cancelled.
+// Both variants hold a flow, and a result is only returned once per loading.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug)]
+pub enum LoadingResult<T: Num> {
+    Completed(DynamicFlow<T>),
+    Cancelled(PartialFlow<T>),
+}
+
+/// A flow built until some horizon together with the network inflow changes not yet applied.
+/// The loading can be resumed from the horizon by `resume` or `resume_until`.
+#[derive(Debug)]
+pub struct PartialFlow<T: Num> {
+    pub flow: DynamicFlow<T>,
+    /// The pending network inflow changes, sorted by time.
+    pub pending_changes: Vec<PathInflowRateChange<T>>,
+    // The loader with the edge inflow changes at the horizon, and the flow it builds, which keeps
+    // repeated edge visits under their own commodities
+    loader: NetworkLoader<T>,
+    unmerged: DynamicFlow<T>,
+}
+
+impl<T: Num + Tolerance> PartialFlow<T> {
+    /// Continues the loading from the horizon and returns the complete flow.
+    pub fn resume(self, capacity: &[T], inv_capacity: &[T], travel_time: &[T]) -> DynamicFlow<T> {
+        let PartialFlow {
+            flow: merged,
+            mut loader,
+            unmerged: mut flow,
+            ..
+        } = self;
+        if flow.built_until() >= T::INFINITY {
+            return merged;
+        }
+        // Drops the shared rates, so that extending does not copy them.
+        drop(merged);
+        while loader.step(&mut flow, capacity, inv_capacity, travel_time) != LoaderEvent::Finished {
+        }
+        flow
+    }
+
+    /// Continues the loading from the horizon until the later `horizon`, see
+    /// `NetworkLoader::build_flow_until`.
+    pub fn resume_until(
+        self,
+        horizon: T,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) -> PartialFlow<T> {
+        let PartialFlow {
+            flow: merged,
+            mut loader,
+            unmerged: mut flow,
+            ..
+        } = self;
+        drop(merged);
+        loader.extend_until(&mut flow, horizon, capacity, inv_capacity, travel_time);
+        loader.into_partial_flow(flow)
+    }
+}
+
 #[derive(Debug)]
 pub struct NetworkLoader<T: Num> {
-    // Describes the path by mapping (Commodity, Edge?) -> Edge?
-    next_edge: HashMap<(usize, Option<usize>), usize>,
+    // The edges of each path
+    paths: Vec<Vec<usize>>,
+
+    // The flow of a path is tracked separately for each position along the path:
+    // keys[p][i] is the commodity under which the flow stores path p on its i-th edge.
+    // This is p itself, unless the edge was already visited earlier on the path.
+    keys: Vec<Vec<usize>>,
+
+    // Maps the additional commodities of repeated edge visits to their path
+    repeated_visits: HashMap<usize, usize>,
+
+    // Maps an edge to all (path, position) that continue on their path after this edge
+    continuing: HashMap<usize, Vec<(usize, usize)>>,
 
-    // The changes of the inflow rate of all paths:
-    // The key is the time of the change, the value is the path that changes and the new inflow rate
-    path_inflow_rate_changes: PriorityQueue<(usize, T), Reverse<T>>,
+    // The changes of the inflow rate of all paths, prioritized by the time of the change
+    path_inflow_rate_changes: PriorityQueue<PathInflowRateChange<T>, Reverse<Total<T>>>,
+
+    // The number of inflow rate changes of each path that have not been processed yet
+    pending_changes: Vec<usize>,
+
+    // Paths without network inflow from now on, whose particles may still be in the network
+    draining_paths: Vec<usize>,
+
+    // The inflow of edges that changes at the current time, by edge, by commodity
+    new_inflow: HashMap<usize, CommodityVec<T>>,
+
+    // The number of commodities of the flow, i.e. paths plus repeated edge visits
+    num_commodities: usize,
+
+    // The number of network inflow changes and edge outflow changes processed so far
+    processed_events: usize,
+
+    // The expected sizes of the flow to reserve, if given
+    capacity_hints: Option<CapacityHints>,
+
+    // Which rate functions the built flows record
+    rate_storage: RateStorage,
+
+    // Whether the built flows recycle their short-lived rate maps
+    rate_pooling: bool,
+
+    // The tolerance policy of the built flows
+    tolerance: TolerancePolicy<T>,
 }
 
 pub struct PathInflow<'a, T: Num> {
@@ -22,32 +218,179 @@ pub struct PathInflow<'a, T: Num> {
     pub inflow: &'a PiecewiseConstant<T>,
 }
 
-impl<T: Num> NetworkLoader<T> {
+/// A path inflow whose rate is piecewise linear instead of piecewise constant.
+pub struct LinearPathInflow<'a, T: Num> {
+    pub path: &'a [usize],
+    pub inflow: &'a PiecewiseLinear<T>,
+}
+
+impl<T: Num + Tolerance> NetworkLoader<T> {
     pub fn new<'a>(path_inflows: &'a [PathInflow<'a, T>]) -> Self {
-        let mut next_edge_map: HashMap<(usize, Option<usize>), usize> =
-            HashMap::with_capacity(path_inflows.iter().map(|p| p.path.len() + 1).sum());
+        let mut keys: Vec<Vec<usize>> = Vec::with_capacity(path_inflows.len());
+        let mut repeated_visits: HashMap<usize, usize> = HashMap::new();
+        let mut continuing: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
         let mut path_inflow_rate_changes = PriorityQueue::with_capacity(
             path_inflows.iter().map(|p| p.inflow.points().len()).sum(),
         );
         for (i, path_inflow) in path_inflows.iter().enumerate() {
-            if let Some(&edge) = path_inflow.path.first() {
-                next_edge_map.insert((i, None), edge);
-            }
-            for (&edge, &next_edge) in path_inflow.path.iter().tuple_windows() {
-                next_edge_map.insert((i, Some(edge)), next_edge);
+            let mut visited: HashSet<usize> = HashSet::with_capacity(path_inflow.path.len());
+            let keys_i: Vec<usize> = path_inflow
+                .path
+                .iter()
+                .map(|&edge| {
+                    if visited.insert(edge) {
+                        i
+                    } else {
+                        let key = path_inflows.len() + repeated_visits.len();
+                        repeated_visits.insert(key, i);
+                        key
+                    }
+                })
+                .collect();
+            let num_continuing = path_inflow.path.len().saturating_sub(1);
+            for (position, &edge) in path_inflow.path.iter().enumerate().take(num_continuing) {
+                continuing.entry(edge).or_default().push((i, position));
             }
+            keys.push(keys_i);
 
             for &Point(time, value) in path_inflow.inflow.points().iter() {
-                path_inflow_rate_changes.push((i, value), Reverse(time));
+                path_inflow_rate_changes.push(
+                    PathInflowRateChange {
+                        path: i,
+                        time,
+                        value,
+                    },
+                    Reverse(Total(time)),
+                );
             }
         }
 
+        let num_commodities = path_inflows.len() + repeated_visits.len();
         Self {
-            next_edge: next_edge_map,
+            paths: path_inflows.iter().map(|p| p.path.to_vec()).collect(),
+            keys,
+            repeated_visits,
+            continuing,
             path_inflow_rate_changes,
+            pending_changes: path_inflows
+                .iter()
+                .map(|p| p.inflow.points().len())
+                .collect(),
+            draining_paths: Vec::new(),
+            new_inflow: HashMap::new(),
+            num_commodities,
+            processed_events: 0,
+            capacity_hints: None,
+            rate_storage: RateStorage::PerCommodity,
+            rate_pooling: true,
+            tolerance: TolerancePolicy::default(),
         }
     }
 
+    /// Reserves memory in the built flows according to `hints`. Without hints, the functions of
+    /// the flows grow as they are built.
+    pub fn with_capacity_hints(mut self, hints: CapacityHints) -> Self {
+        self.capacity_hints = Some(hints);
+        self
+    }
+
+    /// Reserves memory in the built flows according to `estimate_capacity_hints`.
+    pub fn with_estimated_capacity_hints(self, num_edges: usize) -> Self {
+        let hints = self.estimate_capacity_hints(num_edges);
+        self.with_capacity_hints(hints)
+    }
+
+    /// Builds flows that only record the rate functions given by `storage`. With
+    /// `RateStorage::Aggregate`, paths are never considered finished, as the flow of a path is not
+    /// recorded anymore.
+    pub fn with_rate_storage(mut self, storage: RateStorage) -> Self {
+        self.rate_storage = storage;
+        self
+    }
+
+    /// Builds flows that drop their short-lived rate maps instead of recycling them, see
+    /// `DynamicFlow::without_rate_pooling`.
+    pub fn with_rate_pooling(mut self, pooling: bool) -> Self {
+        self.rate_pooling = pooling;
+        self
+    }
+
+    /// Builds flows with the given tolerance policy instead of the default one.
+    pub fn with_tolerance(mut self, tolerance: TolerancePolicy<T>) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Estimates the sizes of the flow from the path inflows: An edge is used by one commodity per
+    /// path visiting it, and each network inflow change of these paths likely causes a breakpoint
+    /// of the queue of the edge. The breakpoints are capped at `MAX_ESTIMATED_BREAKPOINTS`, as
+    /// changes of many paths rarely all reach an edge.
+    pub fn estimate_capacity_hints(&self, num_edges: usize) -> CapacityHints {
+        let mut hints = CapacityHints::uniform(num_edges, 0, 0);
+        for (path, &changes) in self.paths.iter().zip(&self.pending_changes) {
+            let edges: HashSet<usize> = path.iter().copied().collect();
+            for edge in edges {
+                hints.commodities[edge] += 1;
+                hints.breakpoints[edge] =
+                    (hints.breakpoints[edge] + changes).min(MAX_ESTIMATED_BREAKPOINTS);
+            }
+        }
+        hints
+    }
+
+    /// Creates the flow to be built by this loader with reserved memory.
+    fn new_flow(&self, num_edges: usize) -> DynamicFlow<T> {
+        let mut flow = DynamicFlow::new(num_edges)
+            .with_rate_storage(self.rate_storage)
+            .with_tolerance(self.tolerance);
+        if let Some(hints) = &self.capacity_hints {
+            flow.reserve(hints);
+        }
+        if !self.rate_pooling {
+            flow = flow.without_rate_pooling();
+        }
+        flow
+    }
+
+    /// Creates a loader for piecewise linear path inflow rates, each of which is replaced by a step
+    /// function deviating by at most `max_error` (see `PiecewiseLinear::to_step_function`).
+    pub fn new_linear<'a>(path_inflows: &'a [LinearPathInflow<'a, T>], max_error: T) -> Self {
+        let step_functions: Vec<PiecewiseConstant<T>> = path_inflows
+            .iter()
+            .map(|p| p.inflow.to_step_function(max_error))
+            .collect();
+        let path_inflows: Vec<PathInflow<T>> = path_inflows
+            .iter()
+            .zip(step_functions.iter())
+            .map(|(p, inflow)| PathInflow {
+                path: p.path,
+                inflow,
+            })
+            .collect();
+        Self::new(&path_inflows)
+    }
+
+    /// Creates a loader for the given OD demands, where the paths are numbered consecutively by
+    /// demand. The flow of a path is stored under this number in the loaded `DynamicFlow`.
+    pub fn from_demands(demands: &[OdDemand<T>]) -> Self {
+        let inflows: Vec<Vec<PiecewiseConstant<T>>> =
+            demands.iter().map(|d| d.path_inflows()).collect();
+        let path_inflows: Vec<PathInflow<T>> = demands
+            .iter()
+            .zip(inflows.iter())
+            .flat_map(|(d, inflows)| {
+                d.paths
+                    .iter()
+                    .zip(inflows.iter())
+                    .map(|((path, _), inflow)| PathInflow {
+                        path: path.as_slice(),
+                        inflow,
+                    })
+            })
+            .collect();
+        Self::new(&path_inflows)
+    }
+
     pub fn build_flow(
         mut self,
         num_edges: usize,
@@ -55,71 +398,687 @@ impl<T: Num> NetworkLoader<T> {
         inv_capacity: &[T],
         travel_time: &[T],
     ) -> DynamicFlow<T> {
-        let mut flow: DynamicFlow<T> = DynamicFlow::new(num_edges);
-
-        // By edge, by path
-        let mut new_inflow: HashMap<usize, HashMap<usize, T>> = HashMap::new();
-        while flow.built_until() < T::INFINITY {
-            while self
-                .path_inflow_rate_changes
-                .peek()
-                .is_some_and(|(_, Reverse(time))| *time <= flow.built_until())
-            {
-                let ((path, new_value), _) = self.path_inflow_rate_changes.pop().unwrap();
-                new_inflow
-                    .entry(self.next_edge[&(path, None)])
-                    .or_insert(HashMap::new())
-                    .entry(path)
-                    .and_modify(|v| {
-                        *v += new_value;
-                    })
-                    .or_insert(new_value);
+        let mut flow: DynamicFlow<T> = self.new_flow(num_edges);
+        while self.step(&mut flow, capacity, inv_capacity, travel_time) != LoaderEvent::Finished {}
+        flow
+    }
+
+    /// Performs a single extension phase on `flow`, which must have been created with
+    /// `DynamicFlow::new` and only been extended by this loader.
+    /// In between steps, callers may inspect the flow built so far.
+    pub fn step(
+        &mut self,
+        flow: &mut DynamicFlow<T>,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) -> LoaderEvent<T> {
+        if flow.built_until() >= T::INFINITY {
+            return LoaderEvent::Finished;
+        }
+        let mut changed_edges: Vec<usize> = self
+            .extend_phase(flow, None, capacity, inv_capacity, travel_time)
+            .into_iter()
+            .collect();
+        if flow.built_until() >= T::INFINITY {
+            self.merge_repeated_visits(flow);
+            return LoaderEvent::Finished;
+        }
+        changed_edges.sort();
+        LoaderEvent::Extended {
+            built_until: flow.built_until(),
+            changed_edges,
+        }
+    }
+
+    /// Builds the flow like `build_flow`, but calls `on_progress` after each extension phase and
+    /// checks `cancellation` before each phase. If the loading is cancelled, the flow built so far
+    /// is returned as a partial flow.
+    pub fn build_flow_with_progress(
+        mut self,
+        num_edges: usize,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+        mut on_progress: impl FnMut(&LoadingProgress<T>),
+        cancellation: &CancellationToken,
+    ) -> LoadingResult<T> {
+        let mut flow: DynamicFlow<T> = self.new_flow(num_edges);
+        let mut phases = 0;
+        loop {
+            if cancellation.is_cancelled() {
+                return LoadingResult::Cancelled(self.into_partial_flow(flow));
+            }
+            let event = self.step(&mut flow, capacity, inv_capacity, travel_time);
+            phases += 1;
+            on_progress(&LoadingProgress {
+                built_until: flow.built_until(),
+                phases,
+                processed_events: self.processed_events,
+                flow: &flow,
+            });
+            if event == LoaderEvent::Finished {
+                return LoadingResult::Completed(flow);
             }
+        }
+    }
 
-            let max_extension_time = self
-                .path_inflow_rate_changes
-                .peek()
-                .map(|(_, Reverse(change_time))| *change_time);
+    /// Builds the flow like `build_flow`, but writes the data built by each extension phase to
+    /// `sink` while the loading runs.
+    pub fn build_flow_streaming<W: Write>(
+        mut self,
+        num_edges: usize,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+        sink: &mut FlowExportSink<W, T>,
+    ) -> Result<DynamicFlow<T>, FormatError>
+    where
+        T: ToF64,
+    {
+        let mut flow: DynamicFlow<T> = self.new_flow(num_edges);
+        loop {
+            let event = self.step(&mut flow, capacity, inv_capacity, travel_time);
+            sink.write(&flow)?;
+            if event == LoaderEvent::Finished {
+                return Ok(flow);
+            }
+        }
+    }
 
-            let changed_edges = flow.extend(
-                new_inflow,
-                max_extension_time,
-                capacity,
-                inv_capacity,
-                travel_time,
-            );
-            new_inflow = HashMap::new();
-            for edge in changed_edges {
-                let values = flow.outflow_at_built_until(edge);
-                match values {
-                    None => {}
-                    Some(outflow_map) => {
-                        for (&path, &outflow) in outflow_map.iter() {
-                            let next_edge = self.next_edge.get(&(path, Some(edge)));
-                            if let Some(&next_edge) = next_edge {
-                                new_inflow
-                                    .entry(next_edge)
-                                    .or_insert(HashMap::new())
-                                    .entry(path)
-                                    .and_modify(|v| {
-                                        *v += outflow;
-                                    })
-                                    .or_insert(outflow);
-                            }
-                        }
+    /// Builds the flow exactly until `horizon`, i.e. the returned flow is defined on `[0, horizon)`.
+    /// Network inflow changes of paths at or after `horizon` are returned as pending changes.
+    pub fn build_flow_until(
+        mut self,
+        horizon: T,
+        num_edges: usize,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) -> PartialFlow<T> {
+        let mut flow: DynamicFlow<T> = self.new_flow(num_edges);
+        self.extend_until(&mut flow, horizon, capacity, inv_capacity, travel_time);
+        self.into_partial_flow(flow)
+    }
+
+    fn extend_until(
+        &mut self,
+        flow: &mut DynamicFlow<T>,
+        horizon: T,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) {
+        while flow.built_until() < horizon {
+            self.extend_phase(flow, Some(horizon), capacity, inv_capacity, travel_time);
+        }
+    }
+
+    /// Keeps the loader and the flow for resuming. The rates of the flow are shared with its copy
+    /// that reports repeated visits under their path.
+    fn into_partial_flow(self, flow: DynamicFlow<T>) -> PartialFlow<T> {
+        let mut pending_changes: Vec<PathInflowRateChange<T>> = self
+            .path_inflow_rate_changes
+            .iter()
+            .map(|(change, _)| change.clone())
+            .collect();
+        pending_changes.sort_by(|a, b| a.time.total_cmp(&b.time).then(a.path.cmp(&b.path)));
+        let mut merged = flow.clone();
+        self.merge_repeated_visits(&mut merged);
+        PartialFlow {
+            flow: merged,
+            pending_changes,
+            loader: self,
+            unmerged: flow,
+        }
+    }
+
+    /// Applies all network inflow changes up to `flow.built_until()` and extends the flow until the
+    /// next change of some edge inflow, the next network inflow change, or `horizon`.
+    fn extend_phase(
+        &mut self,
+        flow: &mut DynamicFlow<T>,
+        horizon: Option<T>,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) -> HashSet<usize> {
+        while self
+            .path_inflow_rate_changes
+            .peek()
+            .is_some_and(|(_, Reverse(Total(time)))| *time <= flow.built_until())
+        {
+            let (change, _) = self.path_inflow_rate_changes.pop().unwrap();
+            self.processed_events += 1;
+            let Some(&edge) = self.paths[change.path].first() else {
+                continue;
+            };
+            inflow_entry(&mut self.new_inflow, flow, edge, self.num_commodities)
+                .insert(change.path, change.value);
+            self.pending_changes[change.path] -= 1;
+            if self.pending_changes[change.path] == 0 && change.value == T::ZERO {
+                self.draining_paths.push(change.path);
+            }
+        }
+
+        for inflow_e in self.new_inflow.values_mut() {
+            inflow_e.retain(|_, v| *v != T::ZERO);
+        }
+
+        let next_change_time = self
+            .path_inflow_rate_changes
+            .peek()
+            .map(|(_, Reverse(Total(change_time)))| *change_time);
+        let max_extension_time = match (next_change_time, horizon) {
+            (Some(time), Some(horizon)) => Some(min(time, horizon)),
+            (time, horizon) => time.or(horizon),
+        };
+
+        let changed_edges = flow.extend(
+            self.new_inflow.drain(),
+            max_extension_time,
+            capacity,
+            inv_capacity,
+            travel_time,
+        );
+        self.remove_finished_paths(flow, inv_capacity, travel_time);
+        self.processed_events += changed_edges.len();
+
+        for &edge in &changed_edges {
+            let Some(continuing) = self.continuing.get(&edge) else {
+                continue;
+            };
+            let outflow_map = flow.copy_outflow_at_built_until(edge);
+            for &(path, position) in continuing {
+                let key = self.keys[path][position];
+                let next_edge = self.paths[path][position + 1];
+                let next_key = self.keys[path][position + 1];
+                let outflow = outflow_map.get(key).copied().unwrap_or(T::ZERO);
+                inflow_entry(&mut self.new_inflow, flow, next_edge, self.num_commodities)
+                    .insert(next_key, outflow);
+            }
+            flow.recycle(outflow_map);
+        }
+        changed_edges
+    }
+
+    /// Assigns each edge to one of `num_parts` parts for `build_flow_partitioned`, such that the
+    /// parts are visited by about equally many paths and consecutive edges of paths mostly lie in
+    /// the same part. The edges are cut into parts in the order of a breadth-first search along
+    /// the paths.
+    pub fn partition_edges(&self, num_edges: usize, num_parts: usize) -> Vec<usize> {
+        debug_assert!(num_parts > 0, "There must be at least one part.");
+        let mut neighbours: Vec<Vec<usize>> = vec![Vec::new(); num_edges];
+        for path in &self.paths {
+            for pair in path.windows(2) {
+                neighbours[pair[0]].push(pair[1]);
+                neighbours[pair[1]].push(pair[0]);
+            }
+        }
+        // Edges without paths count as well, as they still need to be built.
+        let weight: Vec<usize> = self
+            .estimate_capacity_hints(num_edges)
+            .commodities
+            .iter()
+            .map(|visits| visits + 1)
+            .collect();
+        let total_weight: usize = weight.iter().sum();
+
+        let mut partition = vec![usize::MAX; num_edges];
+        let mut queue: VecDeque<usize> = VecDeque::new();
+        let mut assigned_weight = 0;
+        for start in 0..num_edges {
+            if partition[start] != usize::MAX {
+                continue;
+            }
+            partition[start] = 0;
+            queue.push_back(start);
+            while let Some(edge) = queue.pop_front() {
+                partition[edge] = (num_parts - 1).min(assigned_weight * num_parts / total_weight);
+                assigned_weight += weight[edge];
+                for &next in &neighbours[edge] {
+                    if partition[next] == usize::MAX {
+                        // Marks the edge as queued, its part is assigned when it is dequeued.
+                        partition[next] = 0;
+                        queue.push_back(next);
                     }
                 }
             }
         }
+        partition
+    }
+
+    /// Builds the flow like `build_flow`, but loads the parts of the network given by
+    /// `partition[edge]` concurrently, each by its own loader and on its own thread.
+    ///
+    /// The parts are synchronized in rounds: Flow leaving a part through an edge with travel time
+    /// `τ` is known for `τ` time units ahead, so all parts can be built independently until the
+    /// next event time plus the smallest travel time of edges crossing between parts. Afterwards,
+    /// the flow crossing between parts is exchanged. Hence, partitions whose crossing edges have
+    /// long travel times need few rounds. If some crossing edge has no travel time, the flow is
+    /// built sequentially.
+    pub fn build_flow_partitioned(
+        self,
+        partition: &[usize],
+        num_edges: usize,
+        capacity: &[T],
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) -> DynamicFlow<T> {
+        debug_assert_eq!(partition.len(), num_edges);
+        let num_parts = partition.iter().max().map_or(0, |&part| part + 1);
+        let mut networks: Vec<PartNetwork<T>> =
+            (0..num_parts).map(|_| PartNetwork::default()).collect();
+        // local[edge] is the number of edge within its part
+        let mut local = vec![0; num_edges];
+        for edge in 0..num_edges {
+            let network = &mut networks[partition[edge]];
+            local[edge] = network.edges.len();
+            network.edges.push(edge);
+            network.capacity.push(capacity[edge]);
+            network.inv_capacity.push(inv_capacity[edge]);
+            network.travel_time.push(travel_time[edge]);
+        }
+
+        // Splits the paths into segments of consecutive edges in the same part.
+        let mut links: Vec<PartLink<T>> = Vec::new();
+        for (path, edges) in self.paths.iter().enumerate() {
+            let mut start = 0;
+            while start < edges.len() {
+                let part = partition[edges[start]];
+                let len = edges[start..]
+                    .iter()
+                    .take_while(|&&edge| partition[edge] == part)
+                    .count();
+                let segment = networks[part].segments.len();
+                networks[part].segments.push(Segment {
+                    path,
+                    start,
+                    edges: edges[start..start + len]
+                        .iter()
+                        .map(|&edge| local[edge])
+                        .collect(),
+                    keys: self.keys[path][start..start + len].to_vec(),
+                    local_keys: Vec::new(),
+                });
+                if start > 0 {
+                    let prev_edge = edges[start - 1];
+                    let from = partition[prev_edge];
+                    links.push(PartLink {
+                        from,
+                        edge: local[prev_edge],
+                        from_segment: networks[from].segments.len() - 1,
+                        to: part,
+                        segment,
+                        travel_time: travel_time[prev_edge],
+                        sent_until: -T::INFINITY,
+                    });
+                }
+                start += len;
+            }
+        }
+        for network in networks.iter_mut() {
+            network.number_commodities();
+        }
+        let lookahead = links
+            .iter()
+            .map(|link| link.travel_time)
+            .fold(T::INFINITY, min);
+        if lookahead <= T::ZERO {
+            return self.build_flow(num_edges, capacity, inv_capacity, travel_time);
+        }
+        let mut parts: Vec<Part<T>> = networks
+            .into_iter()
+            .map(|network| {
+                let loader = self.part_loader(&network);
+                let flow = loader.new_flow(network.edges.len());
+                Part {
+                    network,
+                    loader,
+                    flow,
+                }
+            })
+            .collect();
+
+        loop {
+            // The outflow of an edge is final until its travel time after the time the part is
+            // built until, as later phases only add outflow changes after that.
+            for link in links.iter_mut() {
+                let from = &parts[link.from];
+                let known_until = from.flow.built_until() + link.travel_time;
+                let key = *from.network.segments[link.from_segment]
+                    .local_keys
+                    .last()
+                    .unwrap();
+                let changes: Vec<Point<T>> = from.flow.outflow()[link.edge]
+                    .function_by_comm()
+                    .get(&key)
+                    .map_or(&[][..], |f| f.points())
+                    .iter()
+                    .filter(|p| p.0 >= link.sent_until && p.0 < known_until)
+                    .cloned()
+                    .collect();
+                link.sent_until = known_until;
+                let loader = &mut parts[link.to].loader;
+                for Point(time, value) in changes {
+                    loader.push_inflow_change(link.segment, time, value);
+                }
+            }
+            let Some(next_event_time) = parts.iter().filter_map(Part::next_event_time).reduce(min)
+            else {
+                break;
+            };
+            // All inflow of the parts before the smallest `sent_until` has been passed on. Outflow
+            // not passed on yet changes at the time of a pending outflow event, so nothing crosses
+            // between parts before the next event either.
+            let known_until = links
+                .iter()
+                .map(|link| link.sent_until)
+                .fold(T::INFINITY, min);
+            let horizon = max(next_event_time, known_until);
+            parts.par_iter_mut().for_each(|part| {
+                while part.flow.built_until() < horizon {
+                    part.extend_phase(Some(horizon));
+                }
+            });
+        }
+
+        let mut flow = self.new_flow(num_edges);
+        for mut part in parts {
+            while part.flow.built_until() < T::INFINITY {
+                part.extend_phase(None);
+            }
+            part.flow.rename_commodities(&part.network.global_keys);
+            flow.insert_part(part.flow, &part.network.edges);
+        }
+        self.merge_repeated_visits(&mut flow);
         flow
     }
+
+    /// Creates the loader of the segments of a part. Segments at the start of a path get the
+    /// network inflow of the path, the others get their inflow from other parts while loading.
+    /// The flow of each segment is stored under the local commodities of the part, see
+    /// `PartNetwork::number_commodities`.
+    fn part_loader(&self, part: &PartNetwork<T>) -> NetworkLoader<T> {
+        let mut continuing: HashMap<usize, Vec<(usize, usize)>> = HashMap::new();
+        for (i, segment) in part.segments.iter().enumerate() {
+            let num_continuing = segment.edges.len() - 1;
+            for (position, &edge) in segment.edges.iter().enumerate().take(num_continuing) {
+                continuing.entry(edge).or_default().push((i, position));
+            }
+        }
+        // first_segment[path] is the segment of this part at the start of the path, if any
+        let first_segment: HashMap<usize, usize> = part
+            .segments
+            .iter()
+            .enumerate()
+            .filter(|(_, segment)| segment.start == 0)
+            .map(|(i, segment)| (segment.path, i))
+            .collect();
+        let mut path_inflow_rate_changes = PriorityQueue::new();
+        for (change, &priority) in self.path_inflow_rate_changes.iter() {
+            if let Some(&i) = first_segment.get(&change.path) {
+                let change = PathInflowRateChange {
+                    path: i,
+                    ..change.clone()
+                };
+                path_inflow_rate_changes.push(change, priority);
+            }
+        }
+        // Segments fed by other parts keep a pending change, so that they are never considered
+        // finished, as more flow may arrive in later rounds.
+        let pending_changes: Vec<usize> = part
+            .segments
+            .iter()
+            .map(|segment| match segment.start {
+                0 => self.pending_changes[segment.path],
+                _ => 1,
+            })
+            .collect();
+        NetworkLoader {
+            paths: part.segments.iter().map(|s| s.edges.clone()).collect(),
+            keys: part.segments.iter().map(|s| s.local_keys.clone()).collect(),
+            repeated_visits: HashMap::new(),
+            continuing,
+            path_inflow_rate_changes,
+            pending_changes,
+            draining_paths: Vec::new(),
+            new_inflow: HashMap::new(),
+            num_commodities: part.global_keys.len(),
+            processed_events: 0,
+            capacity_hints: self.capacity_hints.as_ref().map(|hints| CapacityHints {
+                breakpoints: part.edges.iter().map(|&e| hints.breakpoints[e]).collect(),
+                commodities: part.edges.iter().map(|&e| hints.commodities[e]).collect(),
+            }),
+            rate_storage: self.rate_storage,
+            rate_pooling: self.rate_pooling,
+            tolerance: self.tolerance,
+        }
+    }
+
+    /// Schedules a change of the inflow rate of `path` to `value` at `time`.
+    fn push_inflow_change(&mut self, path: usize, time: T, value: T) {
+        self.pending_changes[path] += 1;
+        self.path_inflow_rate_changes.push(
+            PathInflowRateChange { path, time, value },
+            Reverse(Total(time)),
+        );
+    }
+
+    /// Returns the time of the next event of the loading of `flow`, if any.
+    fn next_event_time(&self, flow: &DynamicFlow<T>) -> Option<T> {
+        if !self.new_inflow.is_empty() {
+            return Some(flow.built_until());
+        }
+        let next_change = self
+            .path_inflow_rate_changes
+            .peek()
+            .map(|(_, Reverse(Total(time)))| *time);
+        next_change
+            .into_iter()
+            .chain(flow.next_event_time())
+            .reduce(min)
+    }
+
+    /// Reports the flow of repeated edge visits under the path itself.
+    fn merge_repeated_visits(&self, flow: &mut DynamicFlow<T>) {
+        if !self.repeated_visits.is_empty() {
+            flow.merge_commodities(&self.repeated_visits);
+        }
+    }
+
+    /// Drops draining paths whose particles have all left the network, so that their (vanishing)
+    /// rates are no longer propagated along the path.
+    fn remove_finished_paths(
+        &mut self,
+        flow: &DynamicFlow<T>,
+        inv_capacity: &[T],
+        travel_time: &[T],
+    ) {
+        if flow.rate_storage() == RateStorage::Aggregate {
+            return;
+        }
+        let mut i = 0;
+        while i < self.draining_paths.len() {
+            let path = self.draining_paths[i];
+            let (edges, keys) = (&self.paths[path], &self.keys[path]);
+            if !is_finished(flow, edges, keys, inv_capacity, travel_time) {
+                i += 1;
+                continue;
+            }
+            self.draining_paths.swap_remove(i);
+            for edge in edges.iter() {
+                if let Some(continuing) = self.continuing.get_mut(edge) {
+                    continuing.retain(|&(p, _)| p != path);
+                    if continuing.is_empty() {
+                        self.continuing.remove(edge);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The edges of a part of the network, see `NetworkLoader::build_flow_partitioned`.
+/// The edges of the part are numbered locally, `edges[i]` is the edge of the network.
+struct PartNetwork<T: Num> {
+    edges: Vec<usize>,
+    capacity: Vec<T>,
+    inv_capacity: Vec<T>,
+    travel_time: Vec<T>,
+    segments: Vec<Segment>,
+    // global_keys[i] is the commodity of the network of the local commodity i
+    global_keys: Vec<usize>,
+}
+
+impl<T: Num> Default for PartNetwork<T> {
+    fn default() -> Self {
+        PartNetwork {
+            edges: Vec::new(),
+            capacity: Vec::new(),
+            inv_capacity: Vec::new(),
+            travel_time: Vec::new(),
+            segments: Vec::new(),
+            global_keys: Vec::new(),
+        }
+    }
+}
+
+impl<T: Num> PartNetwork<T> {
+    /// Numbers the commodities of the part like a loader numbers those of its paths: Segment `i`
+    /// enters its first edge as commodity `i`, and each further edge of a segment gets a
+    /// commodity of its own.
+    fn number_commodities(&mut self) {
+        self.global_keys = self.segments.iter().map(|s| s.keys[0]).collect();
+        for (i, segment) in self.segments.iter_mut().enumerate() {
+            segment.local_keys = Vec::with_capacity(segment.keys.len());
+            segment.local_keys.push(i);
+            for &key in &segment.keys[1..] {
+                segment.local_keys.push(self.global_keys.len());
+                self.global_keys.push(key);
+            }
+        }
+    }
+}
+
+/// A part of the network loaded by its own loader into its own flow.
+struct Part<T: Num> {
+    network: PartNetwork<T>,
+    loader: NetworkLoader<T>,
+    flow: DynamicFlow<T>,
+}
+
+impl<T: Num + Tolerance> Part<T> {
+    fn next_event_time(&self) -> Option<T> {
+        self.loader.next_event_time(&self.flow)
+    }
+
+    fn extend_phase(&mut self, horizon: Option<T>) {
+        let network = &self.network;
+        self.loader.extend_phase(
+            &mut self.flow,
+            horizon,
+            &network.capacity,
+            &network.inv_capacity,
+            &network.travel_time,
+        );
+    }
+}
+
+/// The consecutive edges of a path within a single part, with the commodity of each edge visit.
+struct Segment {
+    path: usize,
+    // The position of the first edge of the segment on the path
+    start: usize,
+    edges: Vec<usize>,
+    keys: Vec<usize>,
+    // The commodities of the edge visits within the part
+    local_keys: Vec<usize>,
+}
+
+/// Passes the outflow of segment `from_segment` from its last edge `edge` of part `from` on as
+/// inflow of segment `segment` of part `to`.
+struct PartLink<T: Num> {
+    from: usize,
+    edge: usize,
+    from_segment: usize,
+    to: usize,
+    segment: usize,
+    travel_time: T,
+    // The outflow before this time has been passed on
+    sent_until: T,
+}
+
+/// Returns whether all particles on `path` have left the network by `flow.built_until()`,
+/// assuming that no more particles enter the path. `keys[i]` is the commodity of the path's
+/// flow on its i-th edge.
+/// This is the case if on each edge of the path, the inflow of the path has vanished early enough
+/// for its last particle to exit before `built_until`, and the outflow has vanished as well.
+/// Rates vanish if they are zero up to the tolerance policy of the flow.
+fn is_finished<T: Num>(
+    flow: &DynamicFlow<T>,
+    path: &[usize],
+    keys: &[usize],
+    inv_capacity: &[T],
+    travel_time: &[T],
+) -> bool {
+    let built_until = flow.built_until();
+    let tol = flow.tolerance();
+    path.iter().zip(keys.iter()).all(|(&edge, key)| {
+        let last_inflow_change = flow.inflow()[edge]
+            .function_by_comm()
+            .get(key)
+            .map(|f| f.points().last().unwrap());
+        let last_outflow_change = flow.outflow()[edge]
+            .function_by_comm()
+            .get(key)
+            .map(|f| f.points().last().unwrap());
+        let inflow_vanished = last_inflow_change.is_none_or(|p| {
+            tol.is_close(p.1, T::ZERO)
+                && flow.exit_time(edge, p.0, inv_capacity[edge], travel_time[edge]) <= built_until
+        });
+        let outflow_vanished =
+            last_outflow_change.is_none_or(|p| tol.is_close(p.1, T::ZERO) && p.0 <= built_until);
+        inflow_vanished && outflow_vanished
+    })
+}
+
+/// Returns the new inflow map of `edge`, initialized with its current inflow if not yet present.
+/// This makes sure that paths whose inflow did not change are carried over into the new map.
+fn inflow_entry<'b, T: Num>(
+    new_inflow: &'b mut HashMap<usize, CommodityVec<T>>,
+    flow: &mut DynamicFlow<T>,
+    edge: usize,
+    num_commodities: usize,
+) -> &'b mut CommodityVec<T> {
+    new_inflow.entry(edge).or_insert_with(|| {
+        let mut map = flow.copy_inflow_at_built_until(edge);
+        map.reserve(num_commodities);
+        map
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{float::F64, num::Num, piecewise_constant::PiecewiseConstant, points};
+    use std::collections::HashMap;
 
-    use super::{NetworkLoader, PathInflow};
+    use crate::{
+        bench::{grid_instance, DemandPattern},
+        commodity_vec::CommodityVec,
+        dynamic_flow::{CapacityHints, DynamicFlow, RateStorage, AGGREGATE_COMMODITY},
+        float::F64,
+        num::Num,
+        piecewise_constant::PiecewiseConstant,
+        piecewise_linear::PiecewiseLinear,
+        point::Point,
+        points,
+        tolerance::TolerancePolicy,
+    };
+
+    use super::{
+        is_finished, CancellationToken, LinearPathInflow, LoaderEvent, LoadingResult,
+        NetworkLoader, PathInflow, MAX_ESTIMATED_BREAKPOINTS,
+    };
 
     #[test]
     fn it_should_do_a_correct_network_loading() {
@@ -147,4 +1106,560 @@ mod tests {
         );
         assert_eq!(flow.built_until(), F64::INFINITY);
     }
+
+    #[test]
+    fn it_should_keep_the_inflow_of_paths_sharing_an_edge() {
+        let network_loader: NetworkLoader<F64> = NetworkLoader::new(&[
+            PathInflow {
+                path: &[0, 1],
+                inflow: &PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 1.0), (2.0, 0.0)],
+                ),
+            },
+            PathInflow {
+                path: &[0, 2],
+                inflow: &PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 0.0), (1.0, 1.0), (3.0, 0.0)],
+                ),
+            },
+        ]);
+        let flow = network_loader.build_flow(
+            3,
+            &[2.0.into(), 2.0.into(), 2.0.into()],
+            &[0.5.into(), 0.5.into(), 0.5.into()],
+            &[1.0.into(), 1.0.into(), 1.0.into()],
+        );
+        let inflow_0 = flow.inflow()[0].function_by_comm();
+        assert_eq!(inflow_0[&0].eval(1.5), 1.0);
+        assert_eq!(inflow_0[&1].eval(1.5), 1.0);
+        assert_eq!(inflow_0[&0].eval(2.5), 0.0);
+        let inflow_1 = flow.inflow()[1].function_by_comm();
+        assert_eq!(inflow_1[&0].eval(2.5), 1.0);
+        assert_eq!(inflow_1[&0].eval(3.5), 0.0);
+        assert!(!inflow_1.contains_key(&1));
+    }
+
+    #[test]
+    fn it_should_process_repeated_inflow_values() {
+        let network_loader: NetworkLoader<F64> = NetworkLoader::new(&[PathInflow {
+            path: &[0],
+            inflow: &PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 1.0), (1.0, 0.0), (2.0, 1.0), (3.0, 0.0)],
+            ),
+        }]);
+        let flow = network_loader.build_flow(1, &[1.0.into()], &[1.0.into()], &[1.0.into()]);
+        let inflow = &flow.inflow()[0].function_by_comm()[&0];
+        assert_eq!(inflow.eval(0.5), 1.0);
+        assert_eq!(inflow.eval(1.5), 0.0);
+        assert_eq!(inflow.eval(2.5), 1.0);
+        assert_eq!(inflow.eval(3.5), 0.0);
+    }
+
+    #[test]
+    fn it_should_detect_finished_paths() {
+        let mut flow: DynamicFlow<F64> = DynamicFlow::new(2);
+        let capacity = [1.0.into(), 1.0.into()];
+        let travel_time = [1.0.into(), 1.0.into()];
+        let changes: Vec<HashMap<usize, CommodityVec<F64>>> = vec![
+            HashMap::from([(0, CommodityVec::from([(0, 2.0.into())]))]),
+            HashMap::from([(0, CommodityVec::new())]),
+        ];
+        for (i, new_inflow) in changes.into_iter().enumerate() {
+            flow.extend(
+                new_inflow,
+                Some(F64::from(i as f64 + 1.0)),
+                &capacity,
+                &capacity,
+                &travel_time,
+            );
+        }
+        // The last particle enters edge 0 at time 1 and waits in its queue until time 2.
+        assert!(!is_finished(&flow, &[0], &[0], &capacity, &travel_time));
+        while flow.built_until() < F64::from(3.0) {
+            flow.extend(HashMap::new(), None, &capacity, &capacity, &travel_time);
+        }
+        assert_eq!(flow.built_until(), 3.0);
+        assert!(is_finished(&flow, &[0], &[0], &capacity, &travel_time));
+        assert!(is_finished(
+            &flow,
+            &[0, 1],
+            &[1, 1],
+            &capacity,
+            &travel_time
+        ));
+    }
+
+    #[test]
+    fn it_should_detect_finished_paths_with_the_tolerance_of_the_flow() {
+        let capacity = [1.0.into()];
+        let travel_time = [1.0.into()];
+        let tolerance = TolerancePolicy::new(1e-4.into(), F64::ZERO);
+        let load = |tolerance| {
+            let mut flow: DynamicFlow<F64> = DynamicFlow::new(1).with_tolerance(tolerance);
+            let changes: Vec<HashMap<usize, CommodityVec<F64>>> = vec![
+                HashMap::from([(0, CommodityVec::from([(0, 1.0.into())]))]),
+                HashMap::from([(0, CommodityVec::from([(0, 1e-6.into())]))]),
+            ];
+            for (i, new_inflow) in changes.into_iter().enumerate() {
+                flow.extend(
+                    new_inflow,
+                    Some(F64::from(i as f64 + 1.0)),
+                    &capacity,
+                    &capacity,
+                    &travel_time,
+                );
+            }
+            while flow.built_until() < F64::from(4.0) {
+                flow.extend(HashMap::new(), None, &capacity, &capacity, &travel_time);
+            }
+            flow
+        };
+        // A residual rate of 1e-6 only vanishes for the coarser tolerance policy.
+        assert!(!is_finished(
+            &load(TolerancePolicy::default()),
+            &[0],
+            &[0],
+            &capacity,
+            &travel_time
+        ));
+        assert!(is_finished(
+            &load(tolerance),
+            &[0],
+            &[0],
+            &capacity,
+            &travel_time
+        ));
+    }
+
+    #[test]
+    fn it_should_keep_loading_after_a_path_has_finished() {
+        let network_loader: NetworkLoader<F64> = NetworkLoader::new(&[
+            PathInflow {
+                path: &[0, 1],
+                inflow: &PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 1.0), (1.0, 0.0)],
+                ),
+            },
+            PathInflow {
+                path: &[0, 1],
+                inflow: &PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 1.0), (10.0, 2.0), (20.0, 0.0)],
+                ),
+            },
+        ]);
+        let flow = network_loader.build_flow(
+            2,
+            &[2.0.into(), 2.0.into()],
+            &[0.5.into(), 0.5.into()],
+            &[1.0.into(), 1.0.into()],
+        );
+        let inflow_1 = flow.inflow()[1].function_by_comm();
+        assert_eq!(inflow_1[&0].eval(1.5), 1.0);
+        assert_eq!(inflow_1[&0].eval(2.5), 0.0);
+        assert_eq!(inflow_1[&1].eval(5.0), 1.0);
+        assert_eq!(inflow_1[&1].eval(15.0), 2.0);
+        assert_eq!(inflow_1[&1].eval(25.0), 0.0);
+    }
+
+    #[test]
+    fn it_should_load_paths_with_repeated_edges() {
+        let network_loader: NetworkLoader<F64> = NetworkLoader::new(&[PathInflow {
+            path: &[0, 1, 0],
+            inflow: &PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 1.0), (1.0, 0.0)],
+            ),
+        }]);
+        let flow = network_loader.build_flow(
+            2,
+            &[2.0.into(), 2.0.into()],
+            &[0.5.into(), 0.5.into()],
+            &[1.0.into(), 1.0.into()],
+        );
+        assert_eq!(flow.inflow()[0].function_by_comm().len(), 1);
+        let inflow_0 = &flow.inflow()[0].function_by_comm()[&0];
+        assert_eq!(inflow_0.eval(0.5), 1.0);
+        assert_eq!(inflow_0.eval(1.5), 0.0);
+        assert_eq!(inflow_0.eval(2.5), 1.0);
+        assert_eq!(inflow_0.eval(3.5), 0.0);
+        let outflow_0 = &flow.outflow()[0].function_by_comm()[&0];
+        assert_eq!(outflow_0.eval(1.5), 1.0);
+        assert_eq!(outflow_0.eval(3.5), 1.0);
+        assert_eq!(outflow_0.eval(4.5), 0.0);
+        assert_eq!(flow.outflow()[1].function_by_comm()[&0].eval(2.5), 1.0);
+    }
+
+    #[test]
+    fn it_should_reserve_capacity_from_hints() {
+        let inflow: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 3.0), (1.0, 0.0)],
+        );
+        let path_inflows = [PathInflow {
+            path: &[0, 1, 0],
+            inflow: &inflow,
+        }];
+        let hints = NetworkLoader::new(&path_inflows).estimate_capacity_hints(2);
+        assert_eq!(hints.commodities, vec![1, 1]);
+        assert_eq!(hints.breakpoints, vec![2, 2]);
+
+        let many_changes: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            (0..600).map(|i| Point(F64::from(i as f64), F64::from((i % 2) as f64))),
+        );
+        let path_inflows = [0, 1].map(|_| PathInflow {
+            path: &[0],
+            inflow: &many_changes,
+        });
+        let hints = NetworkLoader::new(&path_inflows).estimate_capacity_hints(1);
+        assert_eq!(hints.commodities, vec![2]);
+        assert_eq!(hints.breakpoints, vec![MAX_ESTIMATED_BREAKPOINTS]);
+
+        let (capacity, inv_capacity, travel_time) = (
+            [2.0.into(), 2.0.into()],
+            [0.5.into(), 0.5.into()],
+            [1.0.into(), 1.0.into()],
+        );
+        let estimated = NetworkLoader::new(&path_inflows)
+            .with_estimated_capacity_hints(2)
+            .build_flow(2, &capacity, &inv_capacity, &travel_time);
+        let hinted = NetworkLoader::new(&path_inflows)
+            .with_capacity_hints(CapacityHints::uniform(2, 100, 10))
+            .build_flow(2, &capacity, &inv_capacity, &travel_time);
+        assert_eq!(estimated.queues(), hinted.queues());
+        assert_eq!(
+            estimated.outflow()[0].function_by_comm(),
+            hinted.outflow()[0].function_by_comm()
+        );
+    }
+
+    #[test]
+    fn it_should_load_piecewise_linear_inflows() {
+        let network_loader: NetworkLoader<F64> = NetworkLoader::new_linear(
+            &[LinearPathInflow {
+                path: &[0],
+                inflow: &PiecewiseLinear::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    0.0,
+                    0.0,
+                    points![(0.0, 0.0), (1.0, 2.0), (2.0, 0.0)],
+                ),
+            }],
+            0.1.into(),
+        );
+        let flow = network_loader.build_flow(1, &[1.0.into()], &[1.0.into()], &[1.0.into()]);
+        let inflow = &flow.inflow()[0].function_by_comm()[&0];
+        assert!(num_traits::abs(inflow.eval(0.5) - F64::from(1.0)) <= F64::from(0.11));
+        assert!(num_traits::abs(inflow.eval(1.5) - F64::from(1.0)) <= F64::from(0.11));
+        assert_eq!(inflow.eval(2.5), 0.0);
+        // The inflow volume 2 is preserved, so the queue of the unit capacity edge depletes at 2.
+        assert_eq!(flow.queues()[0].eval(2.5), 0.0);
+        assert!(flow.queues()[0].eval(1.5) > F64::ZERO);
+    }
+
+    #[test]
+    fn it_should_build_the_flow_until_a_horizon() {
+        let network_loader: NetworkLoader<F64> = NetworkLoader::new(&[
+            PathInflow {
+                path: &[0, 1],
+                inflow: &PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 2.0), (1.0, 0.0)],
+                ),
+            },
+            PathInflow {
+                path: &[1],
+                inflow: &PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 0.0), (2.5, 1.0), (4.0, 0.0)],
+                ),
+            },
+        ]);
+        let partial = network_loader.build_flow_until(
+            2.5.into(),
+            2,
+            &[1.0.into(), 1.0.into()],
+            &[1.0.into(), 1.0.into()],
+            &[1.0.into(), 1.0.into()],
+        );
+        assert_eq!(partial.flow.built_until(), 2.5);
+        assert_eq!(partial.flow.queues()[0].eval(2.0), 0.0);
+        assert_eq!(
+            partial.flow.inflow()[1].function_by_comm()[&0].eval(2.25),
+            1.0
+        );
+        let pending: Vec<(usize, F64, F64)> = partial
+            .pending_changes
+            .iter()
+            .map(|c| (c.path, c.time, c.value))
+            .collect();
+        assert_eq!(
+            pending,
+            vec![(1, 2.5.into(), 1.0.into()), (1, 4.0.into(), 0.0.into())]
+        );
+    }
+
+    #[test]
+    fn it_should_resume_partial_flows() {
+        let inflows = [
+            PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 2.0), (1.5, 0.5), (3.0, 0.0)],
+            ),
+            PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+            ),
+        ];
+        let path_inflows = [
+            PathInflow {
+                path: &[0, 1, 0],
+                inflow: &inflows[0],
+            },
+            PathInflow {
+                path: &[0, 1],
+                inflow: &inflows[1],
+            },
+        ];
+        let capacity = [F64::from(1.0), F64::from(2.0)];
+        let inv_capacity = [F64::from(1.0), F64::from(0.5)];
+        let travel_time = [F64::from(1.0), F64::from(1.0)];
+        let expected =
+            NetworkLoader::new(&path_inflows).build_flow(2, &capacity, &inv_capacity, &travel_time);
+        let assert_same_flow = |flow: &DynamicFlow<F64>| {
+            assert_eq!(flow.queues(), expected.queues());
+            for edge in 0..2 {
+                assert_eq!(
+                    flow.inflow()[edge].function_by_comm(),
+                    expected.inflow()[edge].function_by_comm()
+                );
+                assert_eq!(
+                    flow.outflow()[edge].function_by_comm(),
+                    expected.outflow()[edge].function_by_comm()
+                );
+            }
+        };
+        // Edge inflows change at the horizons 2 and 3.
+        for horizon in [0.5, 2.0, 3.0, 10.0] {
+            let partial = NetworkLoader::new(&path_inflows).build_flow_until(
+                horizon.into(),
+                2,
+                &capacity,
+                &inv_capacity,
+                &travel_time,
+            );
+            assert_same_flow(&partial.resume(&capacity, &inv_capacity, &travel_time));
+
+            let partial = NetworkLoader::new(&path_inflows)
+                .build_flow_until(horizon.into(), 2, &capacity, &inv_capacity, &travel_time)
+                .resume_until(
+                    (horizon + 1.0).into(),
+                    &capacity,
+                    &inv_capacity,
+                    &travel_time,
+                );
+            assert_eq!(partial.flow.built_until(), horizon + 1.0);
+            assert_same_flow(&partial.resume(&capacity, &inv_capacity, &travel_time));
+        }
+    }
+
+    #[test]
+    fn it_should_step_through_the_extension_phases() {
+        let mut network_loader: NetworkLoader<F64> = NetworkLoader::new(&[PathInflow {
+            path: &[0, 1],
+            inflow: &PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 1.0), (1.0, 0.0)],
+            ),
+        }]);
+        let capacity = [1.0.into(), 1.0.into()];
+        let travel_time = [1.0.into(), 2.0.into()];
+        let mut flow = DynamicFlow::new(2);
+        let mut events = Vec::new();
+        loop {
+            let event = network_loader.step(&mut flow, &capacity, &capacity, &travel_time);
+            if event == LoaderEvent::Finished {
+                break;
+            }
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                LoaderEvent::Extended {
+                    built_until: 1.0.into(),
+                    changed_edges: vec![0]
+                },
+                LoaderEvent::Extended {
+                    built_until: 2.0.into(),
+                    changed_edges: vec![0]
+                },
+                LoaderEvent::Extended {
+                    built_until: 3.0.into(),
+                    changed_edges: vec![1]
+                },
+                LoaderEvent::Extended {
+                    built_until: 4.0.into(),
+                    changed_edges: vec![1]
+                },
+            ]
+        );
+        assert_eq!(flow.built_until(), F64::INFINITY);
+        assert_eq!(
+            network_loader.step(&mut flow, &capacity, &capacity, &travel_time),
+            LoaderEvent::Finished
+        );
+    }
+
+    #[test]
+    fn it_should_report_progress_and_cancel() {
+        let path_inflows: [PathInflow<F64>; 1] = [PathInflow {
+            path: &[0, 1],
+            inflow: &PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 2.0), (1.0, 0.0), (5.0, 1.0), (6.0, 0.0)],
+            ),
+        }];
+        let capacity = [1.0.into(), 1.0.into()];
+
+        let mut built_until = Vec::new();
+        let result = NetworkLoader::new(&path_inflows).build_flow_with_progress(
+            2,
+            &capacity,
+            &capacity,
+            &capacity,
+            |progress| built_until.push(progress.built_until),
+            &CancellationToken::new(),
+        );
+        let LoadingResult::Completed(flow) = result else {
+            panic!("The loading was not cancelled.");
+        };
+        assert_eq!(flow.built_until(), F64::INFINITY);
+        assert_eq!(*built_until.last().unwrap(), F64::INFINITY);
+        assert!(built_until.windows(2).all(|w| w[0] < w[1]));
+
+        let cancellation = CancellationToken::new();
+        let result = NetworkLoader::new(&path_inflows).build_flow_with_progress(
+            2,
+            &capacity,
+            &capacity,
+            &capacity,
+            |progress| {
+                assert!(progress.breakpoints() > 0);
+                if progress.built_until >= F64::from(2.0) {
+                    cancellation.cancel();
+                }
+            },
+            &cancellation,
+        );
+        let LoadingResult::Cancelled(partial) = result else {
+            panic!("The loading should have been cancelled.");
+        };
+        assert_eq!(partial.flow.built_until(), 2.0);
+        assert_eq!(partial.pending_changes.len(), 2);
+    }
+
+    #[test]
+    fn it_should_load_partitions_concurrently() {
+        let instance =
+            grid_instance::<F64>(6, 6, 8, 2, DemandPattern::Random { changes: 3 }, 20.0, 3);
+        let network = &instance.instance.network;
+        let path_inflows = instance.instance.flat_path_inflows(&instance.path_inflows);
+        let sequential = network.load(&path_inflows);
+        for num_parts in [1, 2, 4] {
+            let partition =
+                NetworkLoader::new(&path_inflows).partition_edges(network.num_edges(), num_parts);
+            assert_eq!(partition.iter().max(), Some(&(num_parts - 1)));
+            let partitioned = network.load_partitioned(&path_inflows, num_parts);
+            assert_eq!(partitioned.built_until(), F64::INFINITY);
+            // The rounds split the extension phases differently, which only affects rounding.
+            // Rates are compared in L1 as breakpoints may be shifted by rounding errors.
+            let tol = F64::from(1e-9);
+            for edge in 0..network.num_edges() {
+                assert!(partitioned.queues()[edge].approx_eq(&sequential.queues()[edge], tol));
+                for (lhs, rhs) in [
+                    (&partitioned.inflow()[edge], &sequential.inflow()[edge]),
+                    (&partitioned.outflow()[edge], &sequential.outflow()[edge]),
+                ] {
+                    let (lhs, rhs) = (lhs.function_by_comm(), rhs.function_by_comm());
+                    assert_eq!(lhs.len(), rhs.len());
+                    assert!(lhs.iter().all(|(comm, f)| f.l1_distance(&rhs[comm]) <= tol));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_forward_the_outflow_along_a_chain_of_parts() {
+        let inflow = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (10.0, 0.0)],
+        );
+        let path_inflows = [PathInflow {
+            path: &[0, 1, 2],
+            inflow: &inflow,
+        }];
+        let capacity = [F64::from(5.0); 3];
+        let inv_capacity = [F64::from(0.2); 3];
+        let travel_time = [F64::from(1.0); 3];
+        let sequential =
+            NetworkLoader::new(&path_inflows).build_flow(3, &capacity, &inv_capacity, &travel_time);
+        let partitioned = NetworkLoader::new(&path_inflows).build_flow_partitioned(
+            &[0, 1, 2],
+            3,
+            &capacity,
+            &inv_capacity,
+            &travel_time,
+        );
+        assert_eq!(
+            sequential.outflow()[1].function_by_comm()[&0].points(),
+            points![(0.0, 0.0), (2.0, 1.0), (12.0, 0.0)]
+        );
+        for edge in 0..3 {
+            for (lhs, rhs) in [
+                (&partitioned.inflow()[edge], &sequential.inflow()[edge]),
+                (&partitioned.outflow()[edge], &sequential.outflow()[edge]),
+            ] {
+                assert_eq!(lhs.function_by_comm(), rhs.function_by_comm());
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_only_record_aggregate_rates() {
+        let instance = grid_instance::<F64>(5, 5, 6, 2, DemandPattern::Peak { steps: 4 }, 20.0, 1);
+        let network = &instance.instance.network;
+        let path_inflows = instance.instance.flat_path_inflows(&instance.path_inflows);
+        let build = |storage| {
+            NetworkLoader::new(&path_inflows)
+                .with_rate_storage(storage)
+                .build_flow(
+                    network.num_edges(),
+                    network.capacity(),
+                    network.inv_capacity(),
+                    network.travel_time(),
+                )
+        };
+        let detailed = build(RateStorage::PerCommodity);
+        let aggregate = build(RateStorage::Aggregate);
+        assert_eq!(aggregate.queues(), detailed.queues());
+        for edge in 0..network.num_edges() {
+            let rates = aggregate.outflow()[edge].function_by_comm();
+            assert!(rates.len() <= 1);
+            let Some(total) = rates.get(&AGGREGATE_COMMODITY) else {
+                continue;
+            };
+            let detailed_total = detailed.outflow()[edge].function_by_comm().values().fold(
+                PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 0.0)]),
+                |sum, f| &sum + f,
+            );
+            assert!(total.l1_distance(&detailed_total) <= 1e-9.into());
+        }
+        assert!(aggregate.stats().heap_bytes < detailed.stats().heap_bytes);
+    }
 }
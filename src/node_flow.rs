@@ -0,0 +1,113 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection},
+    network::Network,
+    num::{Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+};
+
+/// The flow rates of a dynamic flow aggregated to the nodes of its network, e.g. for dashboards
+/// that show the throughput of intersections instead of the details of each edge.
+/// Only flow on edges is counted: The network inflow of a path leaves its origin without entering
+/// it, and the flow of a path enters its destination without leaving it.
+#[derive(Debug, Clone)]
+pub struct NodeFlow<T: Num> {
+    /// The total rate with which each commodity enters a node, i.e. the sum of the outflow rates of
+    /// the incoming edges of the node.
+    pub inflow: Vec<Arc<FlowRatesCollection<T>>>,
+    /// The total rate with which each commodity leaves a node, i.e. the sum of the inflow rates of
+    /// the outgoing edges of the node.
+    pub outflow: Vec<Arc<FlowRatesCollection<T>>>,
+}
+
+impl<T: Num> NodeFlow<T> {
+    pub fn new(network: &Network<T>, flow: &DynamicFlow<T>) -> Self
+    where
+        T: Tolerance,
+    {
+        NodeFlow {
+            inflow: (0..network.num_nodes())
+                .map(|node| Arc::new(sum_rates(network.incoming_edges(node), flow.outflow())))
+                .collect(),
+            outflow: (0..network.num_nodes())
+                .map(|node| Arc::new(sum_rates(network.outgoing_edges(node), flow.inflow())))
+                .collect(),
+        }
+    }
+}
+
+/// Adds up the rate functions of `edges` by commodity.
+fn sum_rates<T: Num + Tolerance>(
+    edges: impl Iterator<Item = usize>,
+    rates: &[Arc<FlowRatesCollection<T>>],
+) -> FlowRatesCollection<T> {
+    let mut function_by_comm: HashMap<usize, PiecewiseConstant<T>> = HashMap::new();
+    for edge in edges {
+        for (&comm, f) in rates[edge].function_by_comm() {
+            match function_by_comm.get_mut(&comm) {
+                Some(g) => *g = &*g + f,
+                None => {
+                    function_by_comm.insert(comm, f.clone());
+                }
+            }
+        }
+    }
+    FlowRatesCollection::from_functions(function_by_comm)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        export_csv::write_node_rates_csv, export_visualization::VisualizationNodeFlow, float::F64,
+        network::Network, network_loader::PathInflow, num::Num,
+        piecewise_constant::PiecewiseConstant, points,
+    };
+
+    use super::NodeFlow;
+
+    #[test]
+    fn it_aggregates_rates_to_nodes() {
+        let mut network = Network::new();
+        network.add_edge(0, 2, 1.0.into(), 1.0.into());
+        network.add_edge(1, 2, 1.0.into(), 2.0.into());
+        network.add_edge(2, 3, 4.0.into(), 1.0.into());
+        let inflow: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (3.0, 0.0)],
+        );
+        let path_inflows = [
+            PathInflow {
+                path: &[0, 2],
+                inflow: &inflow,
+            },
+            PathInflow {
+                path: &[1, 2],
+                inflow: &inflow,
+            },
+        ];
+        let flow = network.load(&path_inflows);
+        let nodes = NodeFlow::new(&network, &flow);
+
+        assert!(nodes.inflow[0].function_by_comm().is_empty());
+        let inflow = nodes.inflow[2].function_by_comm();
+        assert_eq!(inflow[&0].eval(1.5), 1.0);
+        assert_eq!(inflow[&1].eval(1.5), 0.0);
+        assert_eq!(inflow[&1].eval(2.5), 1.0);
+        let outflow = nodes.outflow[2].function_by_comm();
+        assert_eq!(outflow[&0], flow.inflow()[2].function_by_comm()[&0]);
+        let total = nodes.inflow[3].function_by_comm();
+        assert_eq!(total[&0].eval(3.5) + total[&1].eval(3.5), 2.0);
+
+        let mut csv = Vec::new();
+        write_node_rates_csv(&nodes.outflow, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("node,commodity,time,value\n0,0,0,1\n0,0,3,0\n1,1,0,1\n"));
+        let json = serde_json::to_value(VisualizationNodeFlow(&nodes)).unwrap();
+        assert_eq!(json["inflow"].as_array().unwrap().len(), 4);
+        assert_eq!(
+            json["outflow"][1]["1"]["values"],
+            serde_json::json!([1.0, 0.0])
+        );
+    }
+}
@@ -1,37 +1,173 @@
 use core::fmt::Debug;
-use num_traits::{Num as num_traits_Num, NumAssignOps, Signed};
-use std::{fmt::Display, hash::Hash, ops::Neg};
+use num_traits::{abs, Num as num_traits_Num, NumAssignOps, Signed};
+use std::{cmp::Ordering, fmt::Display, hash::Hash, ops::Neg};
 
+/// The arithmetic of a numeric backend.
+/// Backends are only partially ordered, as float backends can be NaN. Sorting and priority queues
+/// use the total order `total_cmp` instead, e.g. via `Total`.
 pub trait Num:
     num_traits_Num
     + Neg<Output = Self>
     + Signed
-    + Ord
+    + PartialOrd
     + Copy
     + Debug
     + Display
     + Hash
     + NumAssignOps
     + std::iter::Sum
+    + Send
+    + Sync
 {
-    const EXACT_ARITHMETIC: bool;
     const ZERO: Self;
     const ONE: Self;
-    const TOL: Self;
     const INFINITY: Self;
+
+    /// A total order that agrees with `<` on all numbers. Float backends order NaN above all other
+    /// values.
+    fn total_cmp(&self, other: &Self) -> Ordering;
+
+    /// Returns whether the value is not a number, which only float backends can represent.
+    #[inline]
+    fn is_nan(self) -> bool {
+        false
+    }
+
+    /// Appends `y0 + (x - x0) · dy / dx` for all `xs` to `out`, i.e. interpolates a linear piece.
+    /// Float backends override this with a vectorized version.
+    #[inline]
+    fn interpolate_into(x0: Self, y0: Self, dy: Self, dx: Self, xs: &[Self], out: &mut Vec<Self>) {
+        out.extend(xs.iter().map(|&x| y0 + (x - x0) * dy / dx));
+    }
+}
+
+/// The larger of two numbers with respect to `Num::total_cmp`, which is `b` if they are equal.
+#[inline]
+pub fn max<T: Num>(a: T, b: T) -> T {
+    match a.total_cmp(&b) {
+        Ordering::Greater => a,
+        _ => b,
+    }
+}
+
+/// The smaller of two numbers with respect to `Num::total_cmp`, which is `a` if they are equal.
+#[inline]
+pub fn min<T: Num>(a: T, b: T) -> T {
+    match a.total_cmp(&b) {
+        Ordering::Greater => b,
+        _ => a,
+    }
+}
+
+/// A number ordered by `Num::total_cmp`, e.g. as the key of a sorted collection or as the
+/// priority in a queue.
+#[derive(Copy, Clone, Debug)]
+pub struct Total<T: Num>(pub T);
+
+impl<T: Num> PartialEq for Total<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.total_cmp(&other.0) == Ordering::Equal
+    }
+}
+
+impl<T: Num> Eq for Total<T> {}
+
+impl<T: Num> PartialOrd for Total<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Num> Ord for Total<T> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+impl<T: Num> Hash for Total<T> {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+/// How a backend deals with rounding errors, which the piecewise algebra and the network loading
+/// need to compare numbers.
+/// Since `EXACT_ARITHMETIC` is a constant, branches on it are resolved at compile time, which
+/// lets the piecewise algorithms skip tolerance handling for exact backends. Exact backends can
+/// keep the defaults.
+pub trait Tolerance: Num {
+    const EXACT_ARITHMETIC: bool = true;
+    /// The default absolute tolerance, which is zero for exact backends.
+    const TOL: Self = Self::ZERO;
+}
+
+/// The conversion to floats used for plotting, exporting and comparing backends.
+pub trait ToF64 {
     fn to_f64(self) -> f64;
 }
 
 pub trait Sum: for<'a> Iterator {
-    fn sum_iter<'a, T: Num + 'a>(self) -> T
+    fn sum_iter<'a, T: Num + Tolerance + 'a>(self) -> T
     where
         Self: Iterator<Item = &'a T> + Sized,
     {
-        self.fold(T::ZERO, |mut acc, x| {
-            acc += *x;
-            acc
-        })
+        if T::EXACT_ARITHMETIC {
+            self.fold(T::ZERO, |mut acc, x| {
+                acc += *x;
+                acc
+            })
+        } else {
+            compensated_sum(self.copied())
+        }
     }
 }
 
 impl<'a, T: Num + 'a, I: Iterator<Item = &'a T>> Sum for I {}
+
+/// Sums the values with Neumaier's variant of Kahan summation:
+/// The rounding error of each addition is accumulated separately and added in the end.
+/// Infinite partial sums are propagated without compensation.
+pub fn compensated_sum<T: Num>(values: impl Iterator<Item = T>) -> T {
+    let mut sum = T::ZERO;
+    let mut compensation = T::ZERO;
+    for x in values {
+        let next = sum + x;
+        if abs(next) < T::INFINITY {
+            compensation += if abs(sum) >= abs(x) {
+                (sum - next) + x
+            } else {
+                (x - next) + sum
+            };
+        }
+        sum = next;
+    }
+    if abs(sum) < T::INFINITY {
+        sum + compensation
+    } else {
+        sum
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::F64;
+
+    use super::{compensated_sum, Sum};
+
+    #[test]
+    fn it_sums_without_drift() {
+        let values: Vec<F64> = [1.0, 1e100, 1.0, -1e100].map(F64::from).to_vec();
+        assert_eq!(values.iter().sum_iter::<F64>(), 2.0);
+
+        let tenths = vec![F64::from(0.1); 10];
+        assert_eq!(compensated_sum(tenths.into_iter()), 1.0);
+        assert_eq!(
+            compensated_sum([F64::from(1.0), F64::from(f64::INFINITY)].into_iter()),
+            f64::INFINITY
+        );
+    }
+}
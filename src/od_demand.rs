@@ -0,0 +1,91 @@
+use num_traits::abs;
+
+use crate::{
+    network::Network,
+    num::{Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+};
+
+/// The demand between an origin and a destination, which departs with the rate `profile` and is
+/// split over `paths` by fixed shares.
+#[derive(Debug, Clone)]
+pub struct OdDemand<T: Num> {
+    pub origin: usize,
+    pub destination: usize,
+    pub profile: PiecewiseConstant<T>,
+    /// Pairs of a path from `origin` to `destination` and the share of the demand it receives.
+    pub paths: Vec<(Vec<usize>, T)>,
+}
+
+impl<T: Num> OdDemand<T> {
+    /// Returns the inflow rate of each path, i.e. the profile scaled by the path's share.
+    pub fn path_inflows(&self) -> Vec<PiecewiseConstant<T>> {
+        self.paths
+            .iter()
+            .map(|&(_, share)| &self.profile * share)
+            .collect()
+    }
+
+    /// Returns true, if all paths lead from `origin` to `destination` in `network` and the shares
+    /// are non-negative and sum up to one.
+    pub fn is_valid(&self, network: &Network<T>) -> bool
+    where
+        T: Tolerance,
+    {
+        let share_sum: T = self.paths.iter().map(|&(_, share)| share).sum();
+        !self.paths.is_empty()
+            && abs(share_sum - T::ONE) <= T::TOL
+            && self.paths.iter().all(|(path, share)| {
+                *share >= T::ZERO
+                    && network.is_path(path)
+                    && path
+                        .first()
+                        .is_some_and(|&e| network.tail(e) == self.origin)
+                    && path
+                        .last()
+                        .is_some_and(|&e| network.head(e) == self.destination)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::two_parallel_edges, float::F64, num::Num,
+        piecewise_constant::PiecewiseConstant, points,
+    };
+
+    use super::OdDemand;
+
+    fn demand(shares: [f64; 2]) -> OdDemand<F64> {
+        OdDemand {
+            origin: 0,
+            destination: 1,
+            profile: PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 2.0), (1.0, 0.0)],
+            ),
+            paths: vec![(vec![0], shares[0].into()), (vec![1], shares[1].into())],
+        }
+    }
+
+    #[test]
+    fn it_validates_demands() {
+        let network = two_parallel_edges(F64::ONE).instance.network;
+        assert!(demand([0.25, 0.75]).is_valid(&network));
+        assert!(!demand([0.5, 0.75]).is_valid(&network));
+        let mut wrong_destination = demand([0.5, 0.5]);
+        wrong_destination.destination = 0;
+        assert!(!wrong_destination.is_valid(&network));
+    }
+
+    #[test]
+    fn it_splits_the_profile_over_the_paths() {
+        let network = two_parallel_edges(F64::ONE).instance.network;
+        let flow = network.load_demands(&[demand([0.25, 0.75])]);
+        assert_eq!(flow.inflow()[0].function_by_comm()[&0].eval(0.5), 0.5);
+        assert_eq!(flow.inflow()[1].function_by_comm()[&1].eval(0.5), 1.5);
+        assert_eq!(flow.queues()[1].eval(1.0), 0.5);
+        assert_eq!(flow.queues()[0].eval(1.0), 0.0);
+    }
+}
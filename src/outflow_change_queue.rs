@@ -0,0 +1,190 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+};
+
+use priority_queue::PriorityQueue;
+
+use crate::{
+    num::{max, Num, Total},
+    tolerance::TolerancePolicy,
+};
+
+/// Keeps track of the times at which the outflow of edges changes in the future.
+/// The outflow rate functions of the edges have already been extended by these changes,
+/// so only the times are kept.
+///
+/// Only the earliest pending change of each edge is held in the priority queue, the later ones
+/// wait in a sorted list per edge. A newer change replaces a pending change of the edge at the
+/// same time up to the tolerance, as extending the outflow rates overwrites their last breakpoint
+/// in that case. Hence, the priority queue never holds more entries than there are edges, and
+/// the pending changes of an edge are distinct breakpoints of its outflow, even on long runs.
+#[derive(Clone, Debug)]
+pub struct OutflowChangeQueue<T: Num> {
+    next_changes: PriorityQueue<usize, Reverse<Total<T>>>,
+    later_changes: HashMap<usize, VecDeque<T>>,
+}
+
+impl<T: Num> OutflowChangeQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            next_changes: PriorityQueue::new(),
+            later_changes: HashMap::new(),
+        }
+    }
+
+    /// Makes sure that `num_edges` edges can have pending changes without reallocating.
+    pub fn reserve(&mut self, num_edges: usize) {
+        self.next_changes
+            .reserve(num_edges.saturating_sub(self.next_changes.len()));
+    }
+
+    pub fn push(&mut self, edge: usize, change_time: T, tol: &TolerancePolicy<T>) {
+        let Some(&Reverse(Total(next_time))) = self.next_changes.get_priority(&edge) else {
+            self.next_changes.push(edge, Reverse(Total(change_time)));
+            return;
+        };
+        let later = self.later_changes.entry(edge).or_default();
+        // Only the pending changes next to the new one can be close to it. The later time of
+        // both is kept, as the rates are only final from there on.
+        let mut change_time = change_time;
+        let pos = later.partition_point(|&time| time < change_time);
+        if later
+            .get(pos)
+            .is_some_and(|&time| tol.is_close(time, change_time))
+        {
+            change_time = later.remove(pos).unwrap();
+        }
+        let pos = if pos > 0 && tol.is_close(later[pos - 1], change_time) {
+            later.remove(pos - 1);
+            pos - 1
+        } else {
+            pos
+        };
+        if tol.is_close(next_time, change_time) {
+            self.next_changes
+                .change_priority(&edge, Reverse(Total(max(next_time, change_time))));
+        } else if change_time < next_time {
+            // Changes are usually pushed in order, but a depletion may schedule an earlier one.
+            self.next_changes
+                .change_priority(&edge, Reverse(Total(change_time)));
+            later.push_front(next_time);
+        } else {
+            later.insert(pos, change_time);
+        }
+        if later.is_empty() {
+            self.later_changes.remove(&edge);
+        }
+    }
+
+    /// Removes the earliest change and returns its edge and time.
+    pub fn pop(&mut self) -> Option<(usize, T)> {
+        let (edge, Reverse(Total(change_time))) = self.next_changes.pop()?;
+        if let Some(later) = self.later_changes.get_mut(&edge) {
+            if let Some(next_time) = later.pop_front() {
+                self.next_changes.push(edge, Reverse(Total(next_time)));
+            }
+            if later.is_empty() {
+                self.later_changes.remove(&edge);
+            }
+        }
+        Some((edge, change_time))
+    }
+
+    pub fn min_change_time(&self) -> Option<&T> {
+        self.next_changes
+            .peek()
+            .map(|(_, Reverse(Total(time)))| time)
+    }
+
+    /// Returns the number of pending changes of all edges.
+    pub fn len(&self) -> usize {
+        self.next_changes.len()
+            + self
+                .later_changes
+                .values()
+                .map(VecDeque::len)
+                .sum::<usize>()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.next_changes.is_empty()
+    }
+
+    pub fn map_num<U: Num>(&self, convert: impl Fn(T) -> U) -> OutflowChangeQueue<U> {
+        OutflowChangeQueue {
+            next_changes: self
+                .next_changes
+                .iter()
+                .map(|(&edge, &Reverse(Total(time)))| (edge, Reverse(Total(convert(time)))))
+                .collect(),
+            later_changes: self
+                .later_changes
+                .iter()
+                .map(|(&edge, times)| (edge, times.iter().map(|&time| convert(time)).collect()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Num> Default for OutflowChangeQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{float::F64, tolerance::TolerancePolicy};
+
+    use super::OutflowChangeQueue;
+
+    #[test]
+    fn it_coalesces_changes_per_edge() {
+        let mut q: OutflowChangeQueue<F64> = OutflowChangeQueue::new();
+        let tol = TolerancePolicy::default();
+        for (edge, time) in [(0, 2.0), (0, 2.0), (1, 3.0), (0, 4.0), (0, 1.0), (0, 4.0)] {
+            q.push(edge, time.into(), &tol);
+        }
+        assert_eq!(q.len(), 4);
+        assert_eq!(q.min_change_time(), Some(&1.0.into()));
+        let popped: Vec<(usize, F64)> = std::iter::from_fn(|| q.pop()).collect();
+        assert_eq!(
+            popped,
+            vec![
+                (0, 1.0.into()),
+                (0, 2.0.into()),
+                (1, 3.0.into()),
+                (0, 4.0.into())
+            ]
+        );
+        assert!(q.is_empty());
+    }
+
+    #[test]
+    fn it_replaces_superseded_changes() {
+        let mut q: OutflowChangeQueue<F64> = OutflowChangeQueue::new();
+        let tol = TolerancePolicy::new(0.01.into(), 0.0.into());
+        for (edge, time) in [
+            (0, 1.0),
+            (0, 2.0),
+            (0, 2.005),
+            (0, 3.0),
+            (0, 0.999),
+            (1, 1.0),
+        ] {
+            q.push(edge, time.into(), &tol);
+        }
+        assert_eq!(q.len(), 4);
+        let popped: Vec<(usize, F64)> = std::iter::from_fn(|| q.pop()).collect();
+        assert_eq!(
+            popped,
+            vec![
+                (0, 1.0.into()),
+                (1, 1.0.into()),
+                (0, 2.005.into()),
+                (0, 3.0.into())
+            ]
+        );
+    }
+}
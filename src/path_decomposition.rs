@@ -0,0 +1,279 @@
+use std::{collections::HashMap, rc::Rc};
+
+use crate::{
+    dynamic_flow::{DynamicFlow, FlowRatesCollection},
+    fixed_point::to_step_function,
+    network::Network,
+    num::{min, Num, Tolerance, Total},
+    piecewise_constant::PiecewiseConstant,
+};
+
+/// The rate at which particles of a commodity enter the network and follow `path`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathRate<T: Num> {
+    pub path: Vec<usize>,
+    pub rate: T,
+}
+
+/// Decomposes the particles of `commodity` entering the network at `departure_time` by the paths
+/// they take. Particles enter the network at a node whenever the commodity's inflow into the
+/// outgoing edges exceeds the outflow of the incoming edges, and they leave it at a node in the
+/// opposite case. At every node, particles are split proportionally to the inflow rates of the
+/// outgoing edges at the time they arrive.
+/// The returned rates sum up to the total network inflow rate of the commodity at `departure_time`.
+pub fn decompose_at<T: Num + Tolerance>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    commodity: usize,
+    departure_time: T,
+) -> Vec<PathRate<T>> {
+    let mut entering: Vec<(Vec<usize>, T, T)> = Vec::new();
+    for node in 0..network.num_nodes() {
+        let arriving = arriving_rate(network, flow, commodity, node, departure_time);
+        let leaving = leaving_rates(network, flow, commodity, node, departure_time);
+        let leaving_total: T = leaving.iter().map(|&(_, r)| r).sum();
+        let source = leaving_total - arriving;
+        if source <= T::TOL {
+            continue;
+        }
+        for (edge, rate) in leaving {
+            entering.push((vec![edge], departure_time, source * rate / leaving_total));
+        }
+    }
+    trace(network, flow, commodity, entering)
+}
+
+/// Decomposes the particles of `commodity` entering at the times of `grid` and returns for each
+/// used path the step function of its network inflow rate, which vanishes from `horizon` on.
+pub fn decompose_on_grid<T: Num + Tolerance>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    commodity: usize,
+    grid: &[T],
+    horizon: T,
+) -> Vec<(Vec<usize>, PiecewiseConstant<T>)> {
+    let mut rates_by_path: HashMap<Vec<usize>, Vec<T>> = HashMap::new();
+    for (k, &time) in grid.iter().enumerate() {
+        for path_rate in decompose_at(network, flow, commodity, time) {
+            rates_by_path
+                .entry(path_rate.path)
+                .or_insert_with(|| vec![T::ZERO; grid.len()])[k] += path_rate.rate;
+        }
+    }
+    let mut result: Vec<(Vec<usize>, PiecewiseConstant<T>)> = rates_by_path
+        .into_iter()
+        .map(|(path, rates)| (path, to_step_function(grid, &rates, horizon)))
+        .collect();
+    result.sort_by(|a, b| a.0.cmp(&b.0));
+    result
+}
+
+/// How the particles leaving an edge at some time continue at its head.
+struct Split<T: Num> {
+    exit_time: T,
+    // The share of the particles that continue on the outgoing edges
+    continuing: T,
+    leaving: Vec<(usize, T)>,
+    leaving_total: T,
+}
+
+/// Follows the particles entering each path at its entrance time with its rate until they leave
+/// the network. The splits are memoized per edge and entrance time, as particles reach the same
+/// edge at the same time along many paths, and the paths are extended iteratively.
+fn trace<T: Num + Tolerance>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    commodity: usize,
+    entering: Vec<(Vec<usize>, T, T)>,
+) -> Vec<PathRate<T>> {
+    let mut result: Vec<PathRate<T>> = Vec::new();
+    let mut splits: HashMap<(usize, Total<T>), Rc<Split<T>>> = HashMap::new();
+    let mut stack = entering;
+    stack.reverse();
+    while let Some((path, entrance_time, rate)) = stack.pop() {
+        if rate <= T::TOL {
+            continue;
+        }
+        let edge = *path.last().unwrap();
+        let split = splits
+            .entry((edge, Total(entrance_time)))
+            .or_insert_with(|| Rc::new(split_at(network, flow, commodity, edge, entrance_time)))
+            .clone();
+        // A path visiting more edges than the network has cannot be part of a finite
+        // decomposition.
+        let continuing = if path.len() < network.num_edges() {
+            split.continuing
+        } else {
+            T::ZERO
+        };
+
+        if continuing < T::ONE {
+            result.push(PathRate {
+                path: path.clone(),
+                rate: rate * (T::ONE - continuing),
+            });
+        }
+        if continuing == T::ZERO {
+            continue;
+        }
+        for &(next_edge, next_rate) in split.leaving.iter().rev() {
+            let mut next_path = path.clone();
+            next_path.push(next_edge);
+            stack.push((
+                next_path,
+                split.exit_time,
+                rate * continuing * next_rate / split.leaving_total,
+            ));
+        }
+    }
+    result
+}
+
+fn split_at<T: Num + Tolerance>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    commodity: usize,
+    edge: usize,
+    entrance_time: T,
+) -> Split<T> {
+    let exit_time = flow.exit_time(
+        edge,
+        entrance_time,
+        network.inv_capacity()[edge],
+        network.travel_time()[edge],
+    );
+    let node = network.head(edge);
+    let arriving = arriving_rate(network, flow, commodity, node, exit_time);
+    let leaving = leaving_rates(network, flow, commodity, node, exit_time);
+    let leaving_total: T = leaving.iter().map(|&(_, r)| r).sum();
+    let continuing = if arriving > T::ZERO {
+        min(leaving_total / arriving, T::ONE)
+    } else {
+        T::ZERO
+    };
+    Split {
+        exit_time,
+        continuing,
+        leaving,
+        leaving_total,
+    }
+}
+
+fn arriving_rate<T: Num>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    commodity: usize,
+    node: usize,
+    time: T,
+) -> T {
+    network
+        .incoming_edges(node)
+        .map(|e| rate_at(&flow.outflow()[e], commodity, time))
+        .sum()
+}
+
+fn leaving_rates<T: Num + Tolerance>(
+    network: &Network<T>,
+    flow: &DynamicFlow<T>,
+    commodity: usize,
+    node: usize,
+    time: T,
+) -> Vec<(usize, T)> {
+    network
+        .outgoing_edges(node)
+        .map(|e| (e, rate_at(&flow.inflow()[e], commodity, time)))
+        .filter(|&(_, r)| r > T::TOL)
+        .collect()
+}
+
+fn rate_at<T: Num>(rates: &FlowRatesCollection<T>, commodity: usize, time: T) -> T {
+    rates
+        .function_by_comm()
+        .get(&commodity)
+        .map_or(T::ZERO, |f| f.eval(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{
+        commodity_vec::CommodityVec, dynamic_flow::DynamicFlow, float::F64, network::Network,
+        num::Num,
+    };
+
+    use super::{decompose_at, decompose_on_grid, PathRate};
+
+    /// A single commodity enters edge 0 with rate 3 during [0, 2) and is split 1:2 onto the
+    /// parallel edges 1 and 2 behind it.
+    fn split_flow() -> (Network<F64>, DynamicFlow<F64>) {
+        let mut network = Network::new();
+        network.add_edge(0, 1, 3.0.into(), 1.0.into());
+        network.add_edge(1, 2, 3.0.into(), 1.0.into());
+        network.add_edge(1, 2, 3.0.into(), 1.0.into());
+
+        let mut flow = DynamicFlow::new(3);
+        let changes: Vec<HashMap<usize, CommodityVec<F64>>> = vec![
+            HashMap::from([(0, CommodityVec::from([(0, 3.0.into())]))]),
+            HashMap::from([
+                (1, CommodityVec::from([(0, 1.0.into())])),
+                (2, CommodityVec::from([(0, 2.0.into())])),
+            ]),
+            HashMap::from([(0, CommodityVec::new())]),
+            HashMap::from([(1, CommodityVec::new()), (2, CommodityVec::new())]),
+        ];
+        for (i, new_inflow) in changes.into_iter().enumerate() {
+            flow.extend(
+                new_inflow,
+                Some(F64::from(i as f64 + 1.0)),
+                network.capacity(),
+                network.inv_capacity(),
+                network.travel_time(),
+            );
+        }
+        while flow.built_until() < F64::INFINITY {
+            flow.extend(
+                HashMap::new(),
+                None,
+                network.capacity(),
+                network.inv_capacity(),
+                network.travel_time(),
+            );
+        }
+        (network, flow)
+    }
+
+    #[test]
+    fn it_decomposes_a_split_flow_into_paths() {
+        let (network, flow) = split_flow();
+        let mut decomposition = decompose_at(&network, &flow, 0, 0.5.into());
+        decomposition.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(
+            decomposition,
+            vec![
+                PathRate {
+                    path: vec![0, 1],
+                    rate: 1.0.into()
+                },
+                PathRate {
+                    path: vec![0, 2],
+                    rate: 2.0.into()
+                }
+            ]
+        );
+        assert_eq!(decompose_at(&network, &flow, 0, 2.5.into()), vec![]);
+    }
+
+    #[test]
+    fn it_decomposes_on_a_grid() {
+        let (network, flow) = split_flow();
+        let grid: Vec<F64> = vec![0.0.into(), 1.0.into()];
+        let decomposition = decompose_on_grid(&network, &flow, 0, &grid, 2.0.into());
+        assert_eq!(decomposition.len(), 2);
+        assert_eq!(decomposition[0].0, vec![0, 1]);
+        assert_eq!(decomposition[0].1.eval(1.5), 1.0);
+        assert_eq!(decomposition[1].0, vec![0, 2]);
+        assert_eq!(decomposition[1].1.eval(0.5), 2.0);
+        assert_eq!(decomposition[1].1.eval(2.5), 0.0);
+    }
+}
@@ -1,17 +1,134 @@
+use itertools::Itertools;
 use num_traits::abs;
+use smallvec::SmallVec;
+use std::iter;
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
-use crate::num::Num;
-use crate::point::Point;
+use crate::chunked_points::ChunkedPoints;
+use crate::num::{max, min, Num, ToF64, Tolerance};
+use crate::piecewise_linear::PiecewiseLinear;
+use crate::point::{Point, PointStorage};
+use crate::tolerance::TolerancePolicy;
+use crate::validation::{check_not_nan, check_points};
 
+/// Determines the value of a step function at its breakpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Continuity {
+    /// At a breakpoint, the function takes the value of the step starting there.
+    /// This is the natural convention for rate functions.
+    #[default]
+    Right,
+    /// At a breakpoint, the function takes the value of the step ending there.
+    Left,
+}
+
+/// The number of points stored inline, without a heap allocation.
+/// Most rate functions of single commodities change only a few times.
+const INLINE_POINTS: usize = 4;
+type Points<T> = SmallVec<[Point<T>; INLINE_POINTS]>;
+
+/// A step function. Each point starts a step with its value that lasts until the next point.
+/// Before the first point, the function takes the value of the first point.
+/// The value at the points themselves is determined by the `Continuity` (right by default).
+/// The points are stored in a `SmallVec` unless another `PointStorage` is chosen.
 #[derive(Debug, Clone, PartialEq)]
-pub struct PiecewiseConstant<T: Num> {
+pub struct PiecewiseConstant<T: Num, P: PointStorage<T> = Points<T>> {
     domain: [T; 2],
-    points: Vec<Point<T>>, // TODO: Maybe use a NonEmptyVec here
+    points: P, // TODO: Maybe use a NonEmptyVec here
+    continuity: Continuity,
+}
+
+impl<T: Num, P: PointStorage<T>> PiecewiseConstant<T, P> {
+    /// Returns the same function with the given convention at its breakpoints.
+    pub fn with_continuity(mut self, continuity: Continuity) -> Self {
+        self.continuity = continuity;
+        self
+    }
+
+    pub fn continuity(&self) -> Continuity {
+        self.continuity
+    }
+
+    pub fn domain(&self) -> [T; 2] {
+        self.domain
+    }
+
+    /// Returns the function with its points in the storage `Q`, e.g. to record a long history in
+    /// `ChunkedPoints`, or to convert such a function back for the operations on `SmallVec` points.
+    pub fn to_storage<Q: PointStorage<T>>(&self) -> PiecewiseConstant<T, Q> {
+        PiecewiseConstant {
+            domain: self.domain,
+            points: Q::from_points(self.points.iter_points().cloned()),
+            continuity: self.continuity,
+        }
+    }
+
+    pub fn get_rnk(&self, at: T) -> Result<usize, usize> {
+        self.points.rank(&at)
+    }
+
+    /// Evaluates the function at `at`, which must not lie before dropped points of the storage.
+    pub fn eval(&self, at: impl Into<T>) -> T {
+        let at = at.into();
+        if let Some(from) = self.points.valid_from() {
+            assert!(at >= from, "The points before {} were dropped.", from);
+        }
+        let points = &self.points;
+        match self.get_rnk(at) {
+            Ok(rnk) => match self.continuity {
+                Continuity::Right => points.point(rnk).1,
+                Continuity::Left => points.point(rnk.saturating_sub(1)).1,
+            },
+            Err(rnk) => points.point(rnk.saturating_sub(1)).1,
+        }
+    }
+
+    pub fn extend(&mut self, from_time: &T, value: &T)
+    where
+        T: Tolerance,
+    {
+        self.extend_with_tolerance(from_time, value, &TolerancePolicy::default());
+    }
+
+    /// Like `extend`, but compares times and values using the given tolerance policy.
+    pub fn extend_with_tolerance(&mut self, from_time: &T, value: &T, tol: &TolerancePolicy<T>) {
+        check_not_nan(*from_time, || "PiecewiseConstant::extend: time".to_string());
+        check_not_nan(*value, || "PiecewiseConstant::extend: value".to_string());
+        let last_point = self.points.last_point_mut();
+        debug_assert!(*from_time >= last_point.0 - tol.tolerance(last_point.0));
+        if tol.is_close(last_point.1, *value) {
+            // The value is (by tolerance) the same as the last point, so we don't need to add a new point.
+            return;
+        }
+        if tol.is_close(last_point.0, *from_time) {
+            last_point.1 = *value;
+        } else {
+            self.points.push_point(Point(*from_time, *value));
+        }
+    }
+}
+
+impl<T: Num> PiecewiseConstant<T, ChunkedPoints<T>> {
+    pub fn points(&self) -> &ChunkedPoints<T> {
+        &self.points
+    }
+
+    /// Drops the chunks of points before `time`, see `ChunkedPoints::truncate_before`.
+    /// Evaluating the function before the first remaining point panics afterwards.
+    pub fn truncate_before(&mut self, time: T) {
+        if self.points.truncate_before(time) > 0 {
+            self.domain[0] = max(self.domain[0], self.points.point(0).0);
+        }
+    }
 }
 
 impl<T: Num> PiecewiseConstant<T> {
-    pub fn new(domain: [impl Into<T>; 2], points: Vec<Point<T>>) -> Self {
+    /// Creates the step function with the given points, which are stored inline if there are
+    /// only a few of them, without going through a heap allocated `Vec`.
+    pub fn new(domain: [impl Into<T>; 2], points: impl IntoIterator<Item = Point<T>>) -> Self {
         let domain = domain.map(|x| x.into());
+        let points: Points<T> = points.into_iter().collect();
+        check_points(&points, "PiecewiseConstant::new");
         debug_assert!(domain[0] <= domain[1], "The domain is not well defined.");
         debug_assert!(!points.is_empty(), "There must be at least one point.");
         debug_assert!(
@@ -27,55 +144,577 @@ impl<T: Num> PiecewiseConstant<T> {
             "The points are not sorted by x-coordinate."
         );
 
-        Self { domain, points }
+        Self {
+            domain,
+            points,
+            continuity: Continuity::Right,
+        }
     }
 
-    pub fn domain(&self) -> [T; 2] {
-        self.domain
+    /// Builds the step function on the real line where each step `(x, value)` lasts until the next.
+    /// A step within tolerance of the previous one replaces it, and steps with the value of the
+    /// previous step are merged. Returns `None` if there are no steps or they are not sorted by x.
+    pub fn from_steps(steps: impl IntoIterator<Item = (T, T)>) -> Option<Self>
+    where
+        T: Tolerance,
+    {
+        let mut steps = steps.into_iter();
+        let (x, value) = steps.next()?;
+        let mut f = PiecewiseConstant::new([-T::INFINITY, T::INFINITY], vec![Point(x, value)]);
+        for (x, value) in steps {
+            if x < f.points.last().unwrap().0 - T::TOL {
+                return None;
+            }
+            f.extend(&x, &value);
+        }
+        Some(f)
     }
 
     pub fn points(&self) -> &[Point<T>] {
         &self.points
     }
 
-    pub fn get_rnk(&self, at: T) -> Result<usize, usize> {
-        self.points.binary_search_by_key(&at, |&Point(x, _)| x)
+    /// Returns the number of bytes allocated on the heap for the points.
+    /// Functions with at most `INLINE_POINTS` points do not allocate.
+    pub fn heap_size(&self) -> usize {
+        if self.points.spilled() {
+            self.points.capacity() * std::mem::size_of::<Point<T>>()
+        } else {
+            0
+        }
     }
 
-    pub fn eval(&self, at: impl Into<T>) -> T {
-        let rnk = self.get_rnk(at.into());
-        match rnk {
-            Ok(rnk) => self.points[rnk].1,
-            Err(rnk) => {
-                if rnk == 0 {
-                    self.points[0].1
+    /// Converts the function to another numeric backend by applying `convert` to all numbers,
+    /// including the (possibly infinite) domain bounds.
+    pub fn map_num<U: Num>(&self, convert: impl Fn(T) -> U) -> PiecewiseConstant<U> {
+        PiecewiseConstant {
+            domain: self.domain.map(&convert),
+            points: self
+                .points
+                .iter()
+                .map(|p| Point(convert(p.0), convert(p.1)))
+                .collect(),
+            continuity: self.continuity,
+        }
+    }
+
+    /// Returns the value of the step starting at `at`, i.e. the limit from the right.
+    fn value_after(&self, at: T) -> T {
+        let rnk = self.points.partition_point(|p| p.0 <= at);
+        self.points[rnk.saturating_sub(1)].1
+    }
+
+    /// Evaluates the function at the sorted points `xs`, walking the points only once.
+    pub fn eval_sorted(&self, xs: &[T]) -> Vec<T> {
+        debug_assert!(
+            xs.windows(2).all(|w| w[0] <= w[1]),
+            "The points are not sorted."
+        );
+        let mut i = 0;
+        xs.iter()
+            .map(|&x| {
+                while i < self.points.len()
+                    && (self.points[i].0 < x
+                        || (self.points[i].0 == x && self.continuity == Continuity::Right))
+                {
+                    i += 1;
+                }
+                self.points[i.saturating_sub(1)].1
+            })
+            .collect()
+    }
+
+    /// Evaluates the function on the uniform grid `t0, t0 + dt, ..., t0 + (n - 1)·dt`.
+    pub fn sample_grid(&self, t0: T, dt: T, n: usize) -> Vec<T> {
+        debug_assert!(dt >= T::ZERO, "The grid spacing must be non-negative.");
+        let mut k = T::ZERO;
+        let xs: Vec<T> = (0..n)
+            .map(|_| {
+                let x = t0 + k * dt;
+                k += T::ONE;
+                x
+            })
+            .collect();
+        self.eval_sorted(&xs)
+    }
+
+    /// Returns the function shifted to the right by `dt`, i.e. `x -> self(x - dt)`.
+    pub fn shift_x(&self, dt: T) -> PiecewiseConstant<T> {
+        let mut shifted = self.clone();
+        shifted.shift_x_in_place(dt);
+        shifted
+    }
+
+    /// Shifts the function to the right by `dt`, including its domain.
+    pub fn shift_x_in_place(&mut self, dt: T) {
+        self.domain = self.domain.map(|x| x + dt);
+        for p in self.points.iter_mut() {
+            p.0 += dt;
+        }
+    }
+
+    /// Returns the function shifted upwards by `dv`, i.e. `x -> self(x) + dv`.
+    pub fn shift_y(&self, dv: T) -> PiecewiseConstant<T> {
+        let mut shifted = self.clone();
+        shifted.shift_y_in_place(dv);
+        shifted
+    }
+
+    /// Shifts the function upwards by `dv`.
+    pub fn shift_y_in_place(&mut self, dv: T) {
+        for p in self.points.iter_mut() {
+            p.1 += dv;
+        }
+    }
+
+    /// Returns the restriction of the function to `[from, to]`.
+    /// A finite lower bound becomes the first point.
+    pub fn restrict(&self, from: impl Into<T>, to: impl Into<T>) -> PiecewiseConstant<T> {
+        let (from, to): (T, T) = (from.into(), to.into());
+        debug_assert!(
+            self.domain[0] <= from && from <= to && to <= self.domain[1],
+            "The bounds are not ordered or not in the domain."
+        );
+        let start = self.points.partition_point(|p| p.0 <= from);
+        let end = start.max(self.points.partition_point(|p| p.0 <= to));
+        let mut points: Points<T> = SmallVec::with_capacity(end - start + 1);
+        if from > -T::INFINITY || start == end {
+            let x = if from > -T::INFINITY { from } else { to };
+            points.push(Point(x, self.value_after(x)));
+        }
+        points.extend(self.points[start..end].iter().cloned());
+        PiecewiseConstant {
+            domain: [from, to],
+            points,
+            continuity: self.continuity,
+        }
+    }
+
+    /// Returns the same function without the points whose value equals (within tolerance) the
+    /// value of the previous step.
+    pub fn simplify(&self) -> PiecewiseConstant<T>
+    where
+        T: Tolerance,
+    {
+        let mut points: Points<T> = SmallVec::with_capacity(self.points.len());
+        for p in &self.points {
+            if points.last().is_none_or(|last| abs(last.1 - p.1) > T::TOL) {
+                points.push(p.clone());
+            }
+        }
+        PiecewiseConstant {
+            domain: self.domain,
+            points,
+            continuity: self.continuity,
+        }
+    }
+
+    /// Returns the composition θ ↦ self(rhs(θ)) for a non-decreasing `rhs`.
+    /// The steps of the result start at the first times at which `rhs` reaches the points of `self`.
+    pub fn compose(&self, rhs: &PiecewiseLinear<T>) -> PiecewiseConstant<T>
+    where
+        T: Tolerance,
+    {
+        let rhs_points = rhs.points();
+        debug_assert!(
+            (0..=rhs_points.len()).all(|i| rhs.gradient(i) >= -T::TOL),
+            "The inner function must be non-decreasing."
+        );
+        let [domain_start, domain_end] = rhs.domain();
+        let start = if domain_start > -T::INFINITY {
+            domain_start
+        } else {
+            rhs_points[0].0
+        };
+        let mut points: Points<T> = SmallVec::with_capacity(self.points.len() + 1);
+        points.push(Point(start, self.value_after(rhs.eval(start))));
+
+        let mut j = 0;
+        for p in &self.points {
+            while j < rhs_points.len() && rhs_points[j].1 < p.0 {
+                j += 1;
+            }
+            // The first time at which `rhs` reaches `p.0`.
+            let time = if j == 0 {
+                if rhs.first_slope() > T::ZERO {
+                    rhs_points[0].0 - (rhs_points[0].1 - p.0) / rhs.first_slope()
                 } else {
-                    self.points[rnk - 1].1
+                    -T::INFINITY
                 }
+            } else if j == rhs_points.len() {
+                if rhs.last_slope() > T::ZERO {
+                    let last = &rhs_points[j - 1];
+                    last.0 + (p.0 - last.1) / rhs.last_slope()
+                } else {
+                    T::INFINITY
+                }
+            } else {
+                let (q, r) = (&rhs_points[j - 1], &rhs_points[j]);
+                q.0 + (p.0 - q.1) * (r.0 - q.0) / (r.1 - q.1)
+            };
+            if time > domain_end {
+                break;
+            }
+            if time <= start {
+                continue;
+            }
+            match points.last_mut() {
+                Some(last) if last.0 == time => last.1 = p.1,
+                _ => points.push(Point(time, p.1)),
             }
         }
+        points.dedup_by(|p, q| p.1 == q.1);
+
+        PiecewiseConstant {
+            domain: rhs.domain(),
+            points,
+            continuity: self.continuity,
+        }
     }
 
-    pub fn extend(&mut self, from_time: &T, value: &T) {
-        let last_point = self.points.last_mut().unwrap();
-        debug_assert!(*from_time >= last_point.0 - T::TOL);
-        if abs(last_point.1 - *value) <= T::TOL {
-            // The value is (by tolerance) the same as the last point, so we don't need to add a new point.
-            return;
+    /// Returns the function that equals `f` before `switch_time` and `g` from `switch_time` on.
+    pub fn concat(f: &PiecewiseConstant<T>, g: &PiecewiseConstant<T>, switch_time: T) -> Self {
+        debug_assert!(
+            f.domain[0] <= switch_time
+                && switch_time <= f.domain[1]
+                && g.domain[0] <= switch_time
+                && switch_time <= g.domain[1],
+            "The switch time must lie in both domains."
+        );
+        debug_assert_eq!(
+            f.continuity, g.continuity,
+            "The functions use different conventions at their breakpoints."
+        );
+        let mut points: Points<T> = f
+            .points
+            .iter()
+            .filter(|p| p.0 < switch_time)
+            .cloned()
+            .collect();
+        if points.is_empty() && f.domain[0] < switch_time {
+            // The first value of `f` needs a point before the switch time.
+            let time = max(f.domain[0], switch_time - T::ONE);
+            points.push(Point(time, f.points[0].1));
         }
-        if abs(last_point.0 - *from_time) <= T::TOL {
-            last_point.1 = *value;
-        } else {
-            self.points.push(Point(*from_time, *value));
+        let switch = Point(switch_time, g.value_after(switch_time));
+        let later = g.points.iter().filter(|p| p.0 > switch_time).cloned();
+        for p in iter::once(switch).chain(later) {
+            if points.last().is_none_or(|last| last.1 != p.1) {
+                points.push(p);
+            }
+        }
+        PiecewiseConstant {
+            domain: [f.domain[0], g.domain[1]],
+            points,
+            continuity: g.continuity,
+        }
+    }
+
+    /// Returns the convolution x ↦ ∫ self(y)·other(x - y) dy, which is piecewise linear.
+    /// Both functions need to vanish outside of a bounded interval.
+    /// (Convolving piecewise linear functions would yield piecewise cubic functions instead.)
+    pub fn convolve(&self, other: &PiecewiseConstant<T>) -> PiecewiseLinear<T> {
+        // Convolving two boxes gives a trapezoid, i.e. a sum of four ramps x ↦ max(x - t, 0).
+        let mut ramps: Vec<(T, T)> = Vec::new();
+        for (a, b, u) in self.boxes() {
+            for (c, d, w) in other.boxes() {
+                let height = u * w;
+                ramps.extend([
+                    (a + c, height),
+                    (a + d, -height),
+                    (b + c, -height),
+                    (b + d, height),
+                ]);
+            }
+        }
+        ramps.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut points: Vec<Point<T>> = Vec::with_capacity(ramps.len());
+        let (mut value, mut slope) = (T::ZERO, T::ZERO);
+        for (t, change) in ramps {
+            match points.last() {
+                Some(last) if last.0 == t => {}
+                Some(last) => {
+                    value += slope * (t - last.0);
+                    points.push(Point(t, value));
+                }
+                None => points.push(Point(t, value)),
+            }
+            slope += change;
+        }
+        if points.is_empty() {
+            points.push(Point(T::ZERO, T::ZERO));
+        }
+        PiecewiseLinear::new([-T::INFINITY, T::INFINITY], T::ZERO, T::ZERO, points)
+    }
+
+    /// Returns the bounded steps `[start, end)` with non-zero value.
+    fn boxes(&self) -> Vec<(T, T, T)> {
+        debug_assert!(
+            (self.domain[0] > -T::INFINITY || self.points[0].1 == T::ZERO)
+                && (self.domain[1] < T::INFINITY || self.points.last().unwrap().1 == T::ZERO),
+            "The function must vanish outside of a bounded interval."
+        );
+        let start = iter::once(self.domain[0])
+            .filter(|&x| x > -T::INFINITY && x < self.points[0].0)
+            .map(|x| Point(x, self.points[0].1));
+        let end = iter::once(self.domain[1])
+            .filter(|&x| x < T::INFINITY && x > self.points.last().unwrap().0)
+            .map(|x| Point(x, T::ZERO));
+        start
+            .chain(self.points.iter().cloned())
+            .chain(end)
+            .tuple_windows()
+            .filter(|(p, _)| p.1 != T::ZERO)
+            .map(|(p, q)| (p.0, q.0, p.1))
+            .collect()
+    }
+
+    /// Returns the antiderivative of the function that takes the value `c0` at the first point.
+    pub fn integral(&self, c0: T) -> PiecewiseLinear<T> {
+        let mut points: Vec<Point<T>> = Vec::with_capacity(self.points.len());
+        let mut value = c0;
+        for (i, p) in self.points.iter().enumerate() {
+            if i > 0 {
+                let prev = &self.points[i - 1];
+                value += (p.0 - prev.0) * prev.1;
+            }
+            points.push(Point(p.0, value));
+        }
+        PiecewiseLinear::new(
+            self.domain,
+            self.points[0].1,
+            self.points.last().unwrap().1,
+            points,
+        )
+    }
+
+    /// Returns the integral of the function from `from` to `to`, computed exactly from its steps.
+    /// If a bound is infinite, the integral is infinite unless the function vanishes there.
+    pub fn integrate(&self, from: impl Into<T>, to: impl Into<T>) -> T {
+        let (from, to): (T, T) = (from.into(), to.into());
+        debug_assert!(from <= to, "The bounds are not ordered.");
+        let start = self.points.partition_point(|p| p.0 <= from);
+        let end = start.max(self.points.partition_point(|p| p.0 < to));
+        iter::once(from)
+            .chain(self.points[start..end].iter().map(|p| p.0))
+            .chain(iter::once(to))
+            .tuple_windows()
+            .map(|(lower, upper)| {
+                let value = self.value_after(lower);
+                if lower == upper || value == T::ZERO {
+                    T::ZERO
+                } else if lower <= -T::INFINITY || upper >= T::INFINITY {
+                    if value > T::ZERO {
+                        T::INFINITY
+                    } else {
+                        -T::INFINITY
+                    }
+                } else {
+                    (upper - lower) * value
+                }
+            })
+            .sum()
+    }
+
+    /// Returns true, if the functions differ by at most `tol` on the intersection of their domains.
+    pub fn approx_eq(&self, other: &PiecewiseConstant<T>, tol: T) -> bool
+    where
+        T: Tolerance,
+    {
+        (self - other).sup_norm() <= tol
+    }
+
+    /// Returns the pointwise minimum of `self` and `other` on the intersection of their domains.
+    pub fn min(&self, other: &PiecewiseConstant<T>) -> PiecewiseConstant<T>
+    where
+        T: Tolerance,
+    {
+        sum_op(self, other, min, &TolerancePolicy::default())
+    }
+
+    /// Returns the pointwise maximum of `self` and `other` on the intersection of their domains.
+    pub fn max(&self, other: &PiecewiseConstant<T>) -> PiecewiseConstant<T>
+    where
+        T: Tolerance,
+    {
+        sum_op(self, other, max, &TolerancePolicy::default())
+    }
+
+    /// Returns `self + other`, where adjacent steps whose values are close under `tol` are merged.
+    pub fn add_with_tolerance(
+        &self,
+        other: &PiecewiseConstant<T>,
+        tol: &TolerancePolicy<T>,
+    ) -> PiecewiseConstant<T> {
+        sum_op(self, other, |a, b| a + b, tol)
+    }
+
+    /// Returns the L1 distance ∫|self - other| over the intersection of the domains.
+    pub fn l1_distance(&self, other: &PiecewiseConstant<T>) -> T
+    where
+        T: Tolerance,
+    {
+        let diff = self - other;
+        let abs_diff = PiecewiseConstant {
+            domain: diff.domain,
+            points: diff.points.iter().map(|p| Point(p.0, abs(p.1))).collect(),
+            continuity: diff.continuity,
+        };
+        abs_diff.integrate(diff.domain[0], diff.domain[1])
+    }
+
+    /// Returns the squared L2 distance ∫(self - other)² over the intersection of the domains.
+    pub fn l2_distance_squared(&self, other: &PiecewiseConstant<T>) -> T
+    where
+        T: Tolerance,
+    {
+        let diff = self - other;
+        let squared_diff = PiecewiseConstant {
+            domain: diff.domain,
+            points: diff.points.iter().map(|p| Point(p.0, p.1 * p.1)).collect(),
+            continuity: diff.continuity,
+        };
+        squared_diff.integrate(diff.domain[0], diff.domain[1])
+    }
+
+    /// Returns the L2 distance, i.e. the square root of [`Self::l2_distance_squared`].
+    pub fn l2_distance(&self, other: &PiecewiseConstant<T>) -> f64
+    where
+        T: Tolerance + ToF64,
+    {
+        self.l2_distance_squared(other).to_f64().sqrt()
+    }
+
+    /// Returns the L∞ distance sup|self - other| over the intersection of the domains.
+    pub fn linf_distance(&self, other: &PiecewiseConstant<T>) -> T
+    where
+        T: Tolerance,
+    {
+        (self - other).sup_norm()
+    }
+
+    /// Returns the supremum of the absolute value of the function.
+    pub fn sup_norm(&self) -> T {
+        self.points.iter().map(|p| abs(p.1)).fold(T::ZERO, max)
+    }
+}
+
+/// Applies `op` pointwise to both functions on the intersection of their domains.
+fn sum_op<T: Num, F: Fn(T, T) -> T>(
+    lhs: &PiecewiseConstant<T>,
+    rhs: &PiecewiseConstant<T>,
+    op: F,
+    tol: &TolerancePolicy<T>,
+) -> PiecewiseConstant<T> {
+    debug_assert_eq!(
+        lhs.continuity, rhs.continuity,
+        "The functions use different conventions at their breakpoints."
+    );
+    let domain = [
+        max(lhs.domain[0], rhs.domain[0]),
+        min(lhs.domain[1], rhs.domain[1]),
+    ];
+    let times = iter::once(domain[0])
+        .filter(|&x| x > -T::INFINITY)
+        .chain(
+            lhs.points
+                .iter()
+                .map(|p| p.0)
+                .merge(rhs.points.iter().map(|p| p.0)),
+        )
+        .filter(|&x| x >= domain[0] && x <= domain[1]);
+
+    let mut points: Points<T> = SmallVec::with_capacity(lhs.points.len() + rhs.points.len() + 1);
+    for x in times {
+        let value = op(lhs.value_after(x), rhs.value_after(x));
+        match points.last() {
+            Some(last) if last.0 == x || tol.is_close(last.1, value) => {}
+            _ => points.push(Point(x, value)),
+        }
+    }
+    if points.is_empty() {
+        let x = max(lhs.points[0].0, rhs.points[0].0);
+        points.push(Point(x, op(lhs.value_after(x), rhs.value_after(x))));
+    }
+    check_points(
+        &points,
+        "Pointwise operation on piecewise constant functions",
+    );
+
+    PiecewiseConstant {
+        domain,
+        points,
+        continuity: lhs.continuity,
+    }
+}
+
+impl<T: Num + Tolerance> Add<&PiecewiseConstant<T>> for &PiecewiseConstant<T> {
+    type Output = PiecewiseConstant<T>;
+
+    #[inline]
+    fn add(self, rhs: &PiecewiseConstant<T>) -> Self::Output {
+        sum_op(self, rhs, |a, b| a + b, &TolerancePolicy::default())
+    }
+}
+
+impl<T: Num + Tolerance> Sub<&PiecewiseConstant<T>> for &PiecewiseConstant<T> {
+    type Output = PiecewiseConstant<T>;
+
+    #[inline]
+    fn sub(self, rhs: &PiecewiseConstant<T>) -> Self::Output {
+        sum_op(self, rhs, |a, b| a - b, &TolerancePolicy::default())
+    }
+}
+
+impl<T: Num> Neg for &PiecewiseConstant<T> {
+    type Output = PiecewiseConstant<T>;
+
+    fn neg(self) -> Self::Output {
+        PiecewiseConstant {
+            domain: self.domain,
+            points: self.points.iter().map(|p| Point(p.0, -p.1)).collect(),
+            continuity: self.continuity,
+        }
+    }
+}
+
+impl<T: Num> Mul<T> for &PiecewiseConstant<T> {
+    type Output = PiecewiseConstant<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        check_not_nan(rhs, || "Scaling a piecewise constant function".to_string());
+        PiecewiseConstant {
+            domain: self.domain,
+            points: self.points.iter().map(|p| Point(p.0, p.1 * rhs)).collect(),
+            continuity: self.continuity,
+        }
+    }
+}
+
+impl<T: Num> Div<T> for &PiecewiseConstant<T> {
+    type Output = PiecewiseConstant<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        check_not_nan(rhs, || "Dividing a piecewise constant function".to_string());
+        PiecewiseConstant {
+            domain: self.domain,
+            points: self.points.iter().map(|p| Point(p.0, p.1 / rhs)).collect(),
+            continuity: self.continuity,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{float::F64, num::Num, points};
+    use crate::{
+        float::F64,
+        num::{Num, Tolerance},
+        piecewise_linear::PiecewiseLinear,
+        points,
+    };
 
-    use super::PiecewiseConstant;
+    use super::{Continuity, PiecewiseConstant};
 
     #[test]
     pub fn it_evals_correctly() {
@@ -107,4 +746,287 @@ mod tests {
         assert_eq!(f.eval(4.0), 3.0);
         assert_eq!(f.points.len(), 2)
     }
+
+    #[test]
+    pub fn it_subtracts_correctly() {
+        let f: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 1.0), (2.0, 3.0)]);
+        let g: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [F64::ZERO, F64::INFINITY],
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 3.0)],
+        );
+        let h = &f - &g;
+        assert_eq!(h.points(), points![(0.0, 1.0), (1.0, 0.0)]);
+        assert_eq!(h.sup_norm(), 1.0);
+    }
+
+    #[test]
+    pub fn it_scales_correctly() {
+        let f: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 1.0), (2.0, 3.0)]);
+        assert_eq!(
+            (&f * F64::from(2.0)).points(),
+            points![(0.0, 2.0), (2.0, 6.0)]
+        );
+        assert_eq!(
+            (&f / F64::from(2.0)).points(),
+            points![(0.0, 0.5), (2.0, 1.5)]
+        );
+    }
+
+    #[test]
+    pub fn it_integrates_correctly() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 0.0)],
+        );
+        assert_eq!(f.integrate(0.0, 3.0), 5.0);
+        assert_eq!(f.integrate(1.0, 2.5), 2.5);
+        assert_eq!(f.integrate(-1.0, 0.0), 1.0);
+        assert_eq!(f.integrate(2.0, F64::INFINITY), 3.0);
+        assert_eq!(f.integrate(-F64::INFINITY, 0.0), F64::INFINITY);
+    }
+
+    #[test]
+    pub fn it_computes_the_antiderivative() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 0.0)],
+        );
+        let integral = f.integral(1.0.into());
+        assert_eq!(
+            integral.points(),
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 6.0)]
+        );
+        assert_eq!(integral.eval(-1.0), 0.0);
+        assert_eq!(integral.eval(10.0), 6.0);
+        assert_eq!(integral.derivative(), f);
+    }
+
+    #[test]
+    pub fn it_removes_redundant_steps() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (1.0, 1.0), (2.0, 3.0), (3.0, 3.0), (4.0, 0.0)],
+        );
+        assert_eq!(
+            f.simplify().points(),
+            points![(0.0, 1.0), (2.0, 3.0), (4.0, 0.0)]
+        );
+    }
+
+    #[test]
+    pub fn it_restricts_to_a_subdomain() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 0.0)],
+        );
+        let g = f.restrict(1.0, 2.0);
+        assert_eq!(g.domain(), [F64::from(1.0), F64::from(2.0)]);
+        assert_eq!(g.points(), points![(1.0, 1.0), (2.0, 3.0)]);
+        assert_eq!(
+            f.restrict(-F64::INFINITY, 2.5).points(),
+            points![(0.0, 1.0), (2.0, 3.0)]
+        );
+        assert_eq!(
+            f.restrict(2.0, F64::INFINITY).points(),
+            points![(2.0, 3.0), (3.0, 0.0)]
+        );
+    }
+
+    #[test]
+    pub fn it_shifts_in_time_and_value() {
+        let mut f: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 1.0), (2.0, 3.0)]);
+        f.shift_x_in_place(1.0.into());
+        f.shift_y_in_place(1.0.into());
+        assert_eq!(f.domain(), [F64::ONE, F64::INFINITY]);
+        assert_eq!(f.points(), points![(1.0, 2.0), (3.0, 4.0)]);
+        assert_eq!(f.shift_x((-1.0).into()).eval(2.0), 4.0);
+    }
+
+    #[test]
+    pub fn it_compares_approximately() {
+        let f: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 1.0), (2.0, 3.0)]);
+        let g: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [F64::ZERO, F64::INFINITY],
+            points![(0.0, 1.0), (1.0, 1.5), (2.0, 3.0)],
+        );
+        assert!(f.approx_eq(&g, 0.5.into()));
+        assert!(!f.approx_eq(&g, 0.25.into()));
+    }
+
+    #[test]
+    pub fn it_evaluates_sorted_points() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 0.0)],
+        );
+        let xs: Vec<F64> = [-1.0, 0.0, 1.0, 2.0, 2.0, 2.5, 3.0, 4.0]
+            .iter()
+            .map(|&x| F64::from(x))
+            .collect();
+        let expected: Vec<F64> = xs.iter().map(|&x| f.eval(x)).collect();
+        assert_eq!(f.eval_sorted(&xs), expected);
+    }
+
+    #[test]
+    pub fn it_takes_the_minimum_and_maximum() {
+        let f: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 1.0), (2.0, 3.0)]);
+        let g: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 0.0), (1.0, 2.0), (3.0, 0.0)],
+        );
+        assert_eq!(
+            f.min(&g).points(),
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 0.0)]
+        );
+        assert_eq!(
+            f.max(&g).points(),
+            points![(0.0, 1.0), (1.0, 2.0), (2.0, 3.0)]
+        );
+        assert_eq!((-&f.max(&g)).points(), (-&f).min(&-&g).points());
+        assert_eq!((&f + &g).eval(1.5), 3.0);
+    }
+
+    #[test]
+    pub fn it_composes_with_a_non_decreasing_function() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 0.0)],
+        );
+        let linear: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            2.0,
+            2.0,
+            points![(0.0, 0.0)],
+        );
+        assert_eq!(
+            f.compose(&linear).points(),
+            points![(0.0, 1.0), (1.0, 3.0), (1.5, 0.0)]
+        );
+
+        let with_plateau: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 2.0), (3.0, 4.0)],
+        );
+        let h = f.compose(&with_plateau);
+        assert_eq!(h.points(), points![(0.0, 1.0), (1.0, 3.0), (2.5, 0.0)]);
+        for i in -10..50 {
+            let x = F64::from(i as f64 / 10.0);
+            assert_eq!(h.eval(x), f.eval(with_plateau.eval(x)));
+        }
+    }
+
+    #[test]
+    pub fn it_concatenates_functions() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0)],
+        );
+        let g: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [F64::ZERO, F64::INFINITY],
+            points![(0.0, 3.0), (2.0, 5.0), (4.0, 0.0)],
+        );
+        assert_eq!(
+            PiecewiseConstant::concat(&f, &g, 1.0.into()).points(),
+            points![(0.0, 1.0), (1.0, 3.0), (2.0, 5.0), (4.0, 0.0)]
+        );
+        assert_eq!(
+            PiecewiseConstant::concat(&f, &g, 3.0.into()).points(),
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 5.0), (4.0, 0.0)]
+        );
+        assert_eq!(
+            PiecewiseConstant::concat(&g, &f, 0.0.into()).points(),
+            points![(0.0, 1.0), (2.0, 3.0)]
+        );
+    }
+
+    #[test]
+    pub fn it_computes_distances() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [F64::ZERO, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0), (3.0, 0.0)],
+        );
+        let g: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 0.0), (1.0, 2.0), (3.0, 0.0)],
+        );
+        assert_eq!(f.l1_distance(&g), 3.0);
+        assert_eq!(f.l2_distance_squared(&g), 3.0);
+        assert_eq!(f.l2_distance(&g), 3.0f64.sqrt());
+        assert_eq!(f.linf_distance(&g), 1.0);
+    }
+
+    #[test]
+    pub fn it_samples_on_a_grid() {
+        let f: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::INFINITY], points![(0.0, 1.0), (2.0, 3.0)]);
+        let samples = f.sample_grid(F64::ZERO, 1.0.into(), 4);
+        let expected: Vec<F64> = [1.0, 1.0, 3.0, 3.0].iter().map(|&x| F64::from(x)).collect();
+        assert_eq!(samples, expected);
+        assert!(f.sample_grid(F64::ZERO, 1.0.into(), 0).is_empty());
+    }
+
+    #[test]
+    pub fn it_respects_the_continuity_convention() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 3.0)],
+        );
+        assert_eq!(f.continuity(), Continuity::Right);
+        let g = f.clone().with_continuity(Continuity::Left);
+        assert_eq!(f.eval(2.0), 3.0);
+        assert_eq!(g.eval(2.0), 1.0);
+        assert_eq!(g.eval(0.0), 1.0);
+        assert_eq!(g.eval(2.5), 3.0);
+        let xs = [F64::ZERO, F64::from(2.0), F64::from(3.0)];
+        assert_eq!(g.eval_sorted(&xs), xs.map(|x| g.eval(x)).to_vec());
+
+        let sum = &g + &g;
+        assert_eq!(sum.continuity(), Continuity::Left);
+        assert_eq!(sum.eval(2.0), 2.0);
+        assert_eq!(sum.eval(2.5), 6.0);
+        assert_eq!(g.integrate(0.0, 3.0), f.integrate(0.0, 3.0));
+        assert_eq!(g.restrict(2.0, 3.0).points(), points![(2.0, 3.0)]);
+    }
+
+    #[test]
+    pub fn it_builds_from_steps() {
+        let f = PiecewiseConstant::from_steps(
+            [
+                (0.0, 1.0),
+                (1.0, 1.0),
+                (2.0, 2.0),
+                (2.0 + 1e-12, 3.0),
+                (4.0, 0.0),
+            ]
+            .map(|(x, y)| (F64::from(x), F64::from(y))),
+        )
+        .unwrap();
+        assert_eq!(f.points(), points![(0.0, 1.0), (2.0, 3.0), (4.0, 0.0)]);
+        let unsorted = [(1.0, 0.0), (0.0, 1.0)].map(|(x, y)| (F64::from(x), F64::from(y)));
+        assert!(PiecewiseConstant::from_steps(unsorted).is_none());
+        assert!(PiecewiseConstant::<F64>::from_steps([]).is_none());
+    }
+
+    #[test]
+    pub fn it_convolves_step_functions() {
+        let f: PiecewiseConstant<F64> = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(-1.0, 0.0), (0.0, 2.0), (2.0, 0.0)],
+        );
+        let g: PiecewiseConstant<F64> =
+            PiecewiseConstant::new([F64::ZERO, F64::ONE], points![(0.0, 1.0)]);
+        let h = f.convolve(&g);
+        assert_eq!(
+            h.points(),
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 2.0), (3.0, 0.0)]
+        );
+        assert_eq!(h.integrate(-F64::INFINITY, F64::INFINITY), 4.0);
+    }
 }
@@ -1,18 +1,132 @@
 use itertools::{EitherOrBoth, Itertools};
 use num_traits::abs;
-use std::cmp::{max, min};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::{Add, Neg, Sub};
+use std::iter;
+use std::ops::{Add, Div, Mul, Neg, Sub};
 
-use crate::num::Num;
-use crate::point::Point;
+use crate::chunked_points::ChunkedPoints;
+use crate::num::{max, min, Num, ToF64, Tolerance, Total};
+use crate::piecewise_constant::PiecewiseConstant;
+use crate::piecewise_quadratic::{PiecewiseQuadratic, Quadratic};
+use crate::point::{Point, PointStorage};
+use crate::tolerance::TolerancePolicy;
+use crate::validation::{check_not_nan, check_points};
 
+/// A linear piece of a `PiecewiseLinear` function on `[start, end]`.
+/// On a piece unbounded to the left, `start_value` is the limit of the function (possibly ±∞).
 #[derive(Debug, Clone, PartialEq)]
-pub struct PiecewiseLinear<T: Num> {
+pub struct Segment<T: Num> {
+    pub start: T,
+    pub end: T,
+    pub start_value: T,
+    pub slope: T,
+}
+
+/// A continuous piecewise linear function through its points, continued with the first and
+/// last slope. The points are stored in a `Vec` unless another `PointStorage` is chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseLinear<T: Num, P: PointStorage<T> = Vec<Point<T>>> {
     domain: [T; 2],
     first_slope: T,
     last_slope: T,
-    points: Vec<Point<T>>, // TODO: Maybe use a NonEmptyVec here
+    points: P, // TODO: Maybe use a NonEmptyVec here
+}
+
+impl<T: Num, P: PointStorage<T>> PiecewiseLinear<T, P> {
+    pub fn domain(&self) -> [T; 2] {
+        self.domain
+    }
+
+    pub fn first_slope(&self) -> T {
+        self.first_slope
+    }
+
+    pub fn last_slope(&self) -> T {
+        self.last_slope
+    }
+
+    /// Returns the function with its points in the storage `Q`, e.g. to record a long history in
+    /// `ChunkedPoints`, or to convert such a function back for the operations on `Vec` points.
+    pub fn to_storage<Q: PointStorage<T>>(&self) -> PiecewiseLinear<T, Q> {
+        PiecewiseLinear {
+            domain: self.domain,
+            first_slope: self.first_slope,
+            last_slope: self.last_slope,
+            points: Q::from_points(self.points.iter_points().cloned()),
+        }
+    }
+
+    pub fn get_rnk(&self, at: &T) -> Result<usize, usize> {
+        self.points.rank(at)
+    }
+
+    /// Evaluates the function at `at`, which must not lie before dropped points of the storage.
+    pub fn eval(&self, at: impl Into<T>) -> T {
+        let at = at.into();
+        if let Some(from) = self.points.valid_from() {
+            assert!(at >= from, "The points before {} were dropped.", from);
+        }
+        self.eval_with_rank(self.get_rnk(&at), at)
+    }
+
+    pub fn eval_with_rank(&self, rnk: Result<usize, usize>, at: T) -> T {
+        let points = &self.points;
+        match rnk {
+            Ok(rnk) => points.point(rnk).1,
+            Err(rnk) => {
+                if rnk == points.num_points() {
+                    let last = points.point(rnk - 1);
+                    last.1 + (at - last.0) * self.last_slope
+                } else if rnk == 0 {
+                    let first = points.point(rnk);
+                    first.1 + (at - first.0) * self.first_slope
+                } else {
+                    let left = points.point(rnk - 1);
+                    let right = points.point(rnk);
+                    left.1 + (at - left.0) * (right.1 - left.1) / (right.0 - left.0)
+                }
+            }
+        }
+    }
+
+    pub fn extend(&mut self, from_time: &T, slope: T)
+    where
+        T: Tolerance,
+    {
+        self.extend_with_tolerance(from_time, slope, &TolerancePolicy::default());
+    }
+
+    /// Like `extend`, but compares times and slopes using the given tolerance policy.
+    pub fn extend_with_tolerance(&mut self, from_time: &T, slope: T, tol: &TolerancePolicy<T>) {
+        check_not_nan(*from_time, || "PiecewiseLinear::extend: time".to_string());
+        check_not_nan(slope, || "PiecewiseLinear::extend: slope".to_string());
+        let last_point = self.points.point(self.points.num_points() - 1);
+        assert!(*from_time >= last_point.0 - tol.tolerance(last_point.0));
+        if tol.is_close(self.last_slope, slope) {
+            return;
+        }
+        if !tol.is_close(*from_time, last_point.0) {
+            let value = last_point.1 + (*from_time - last_point.0) * self.last_slope;
+            self.points.push_point(Point(*from_time, value));
+        }
+        self.last_slope = slope;
+    }
+}
+
+impl<T: Num> PiecewiseLinear<T, ChunkedPoints<T>> {
+    pub fn points(&self) -> &ChunkedPoints<T> {
+        &self.points
+    }
+
+    /// Drops the chunks of points before `time`, see `ChunkedPoints::truncate_before`.
+    /// Evaluating the function before the first remaining point panics afterwards.
+    pub fn truncate_before(&mut self, time: T) {
+        if self.points.truncate_before(time) > 0 {
+            self.domain[0] = max(self.domain[0], self.points.point(0).0);
+        }
+    }
 }
 
 impl<T: Num> PiecewiseLinear<T> {
@@ -25,6 +139,13 @@ impl<T: Num> PiecewiseLinear<T> {
         let domain: [T; 2] = domain.map(|x| x.into());
         let first_slope: T = first_slope.into();
         let last_slope: T = last_slope.into();
+        check_not_nan(first_slope, || {
+            "PiecewiseLinear::new: first slope".to_string()
+        });
+        check_not_nan(last_slope, || {
+            "PiecewiseLinear::new: last slope".to_string()
+        });
+        check_points(&points, "PiecewiseLinear::new");
         debug_assert!(domain[0] <= domain[1], "The domain is not well defined.");
         debug_assert!(!points.is_empty(), "There must be at least one point.");
         debug_assert!(
@@ -48,16 +169,35 @@ impl<T: Num> PiecewiseLinear<T> {
         }
     }
 
-    pub fn domain(&self) -> [T; 2] {
-        self.domain
-    }
-
-    pub fn first_slope(&self) -> T {
-        self.first_slope
-    }
-
-    pub fn last_slope(&self) -> T {
-        self.last_slope
+    /// Interpolates the samples `(x, y)` on the real line, extrapolating linearly with the slopes
+    /// of the first and last segments. Samples whose x-value is within tolerance of the previous
+    /// sample are dropped. Returns `None` if there are no samples or they are not sorted by x.
+    pub fn from_samples(samples: impl IntoIterator<Item = (T, T)>) -> Option<Self>
+    where
+        T: Tolerance,
+    {
+        let mut points: Vec<Point<T>> = Vec::new();
+        for (x, y) in samples {
+            match points.last() {
+                Some(last) if x < last.0 - T::TOL => return None,
+                Some(last) if x <= last.0 + T::TOL => {}
+                _ => points.push(Point(x, y)),
+            }
+        }
+        if points.is_empty() {
+            return None;
+        }
+        let mut f = PiecewiseLinear {
+            domain: [-T::INFINITY, T::INFINITY],
+            first_slope: T::ZERO,
+            last_slope: T::ZERO,
+            points,
+        };
+        if f.points.len() > 1 {
+            f.first_slope = f.gradient(1);
+            f.last_slope = f.gradient(f.points.len() - 1);
+        }
+        Some(f)
     }
 
     pub fn points(&self) -> &[Point<T>] {
@@ -68,39 +208,115 @@ impl<T: Num> PiecewiseLinear<T> {
         &mut self.points
     }
 
-    pub fn get_rnk(&self, at: &T) -> Result<usize, usize> {
-        self.points.binary_search_by(|p| p.0.cmp(at))
+    /// Makes sure that `additional` more points can be added without reallocating.
+    pub fn reserve(&mut self, additional: usize) {
+        self.points.reserve(additional);
     }
 
-    pub fn eval(&self, at: impl Into<T>) -> T {
-        let at = at.into();
-        self.eval_with_rank(self.get_rnk(&at), at)
+    /// Returns the number of bytes allocated on the heap for the points.
+    pub fn heap_size(&self) -> usize {
+        self.points.capacity() * std::mem::size_of::<Point<T>>()
     }
 
-    pub fn eval_with_rank(&self, rnk: Result<usize, usize>, at: T) -> T {
-        match rnk {
-            Ok(rnk) => self.points[rnk].1,
-            Err(rnk) => {
-                if rnk == self.points.len() {
-                    let last = &self.points[rnk - 1];
-                    last.1 + (at - last.0) * self.last_slope
-                } else if rnk == 0 {
-                    let first = &self.points[rnk];
-                    first.1 + (at - first.0) * self.first_slope
-                } else {
-                    let left = &self.points[rnk - 1];
-                    let right = &self.points[rnk];
-                    left.1 + (at - left.0) * (right.1 - left.1) / (right.0 - left.0)
+    /// Converts the function to another numeric backend by applying `convert` to all numbers,
+    /// including the (possibly infinite) domain bounds.
+    pub fn map_num<U: Num>(&self, convert: impl Fn(T) -> U) -> PiecewiseLinear<U> {
+        PiecewiseLinear {
+            domain: self.domain.map(&convert),
+            first_slope: convert(self.first_slope),
+            last_slope: convert(self.last_slope),
+            points: self
+                .points
+                .iter()
+                .map(|p| Point(convert(p.0), convert(p.1)))
+                .collect(),
+        }
+    }
+
+    /// Evaluates the function at the sorted points `xs`, walking the breakpoints only once.
+    /// Runs of points within the same interior piece are interpolated in one batch, which float
+    /// backends vectorize.
+    pub fn eval_sorted(&self, xs: &[T]) -> Vec<T> {
+        debug_assert!(
+            xs.windows(2).all(|w| w[0] <= w[1]),
+            "The points are not sorted."
+        );
+        let mut values = Vec::with_capacity(xs.len());
+        let mut cursor = PiecewiseLinearCursor::new();
+        let mut start = 0;
+        while start < xs.len() {
+            let x = xs[start];
+            match cursor.seek(self, x) {
+                Err(i) if i > 0 && i < self.points.len() => {
+                    let (left, right) = (&self.points[i - 1], &self.points[i]);
+                    let end = start + xs[start..].partition_point(|&y| y < right.0);
+                    T::interpolate_into(
+                        left.0,
+                        left.1,
+                        right.1 - left.1,
+                        right.0 - left.0,
+                        &xs[start..end],
+                        &mut values,
+                    );
+                    start = end;
+                }
+                rnk => {
+                    values.push(self.eval_with_rank(rnk, x));
+                    start += 1;
                 }
             }
         }
+        values
+    }
+
+    /// Evaluates the function on the uniform grid `t0, t0 + dt, ..., t0 + (n - 1)·dt`.
+    pub fn sample_grid(&self, t0: T, dt: T, n: usize) -> Vec<T> {
+        debug_assert!(dt >= T::ZERO, "The grid spacing must be non-negative.");
+        let mut k = T::ZERO;
+        let xs: Vec<T> = (0..n)
+            .map(|_| {
+                let x = t0 + k * dt;
+                k += T::ONE;
+                x
+            })
+            .collect();
+        self.eval_sorted(&xs)
+    }
+
+    /// Returns the linear pieces of the function covering its domain from left to right,
+    /// including the outer pieces if the domain extends beyond the first or last point.
+    pub fn segments(&self) -> impl Iterator<Item = Segment<T>> + '_ {
+        iter::once(self.domain[0])
+            .chain(self.points.iter().map(|p| p.0))
+            .chain(iter::once(self.domain[1]))
+            .tuple_windows()
+            .enumerate()
+            .filter(|(_, (start, end))| start < end)
+            .map(|(i, (start, end))| {
+                let slope = self.gradient(i);
+                let start_value = if i > 0 {
+                    self.points[i - 1].1
+                } else if start > -T::INFINITY {
+                    self.eval(start)
+                } else if slope == T::ZERO {
+                    self.points[0].1
+                } else if slope > T::ZERO {
+                    -T::INFINITY
+                } else {
+                    T::INFINITY
+                };
+                Segment {
+                    start,
+                    end,
+                    start_value,
+                    slope,
+                }
+            })
     }
 
     /// Returns the gradient between `points[i-1].0` (or `domain[0]` if `i == 0`) and `times[i]`
     /// (or `domain[1]` if `i == len(times)`)
     pub fn gradient(&self, i: usize) -> T {
-        // TODO: The following code has not been tested!
-
         debug_assert!(i <= self.points.len(), "i is not in the expected range.");
         if i == 0 {
             self.first_slope
@@ -113,9 +329,13 @@ impl<T: Num> PiecewiseLinear<T> {
         }
     }
 
-    /// Returns the composition h(x):= self(rhs(x))
-    pub fn compose(&self, rhs: &PiecewiseLinear<T>) -> PiecewiseLinear<T> {
-        // TODO: The following code has not been tested!
+    /// Returns the composition h(x):= self(rhs(x)) for a monotone increasing `rhs`.
+    /// The breakpoints of h are the breakpoints of `rhs` and the preimages of the breakpoints of
+    /// `self` under `rhs`. Both functions are traversed once, so this takes linear time.
+    pub fn compose(&self, rhs: &PiecewiseLinear<T>) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
         let g = self;
         let f = rhs;
 
@@ -123,108 +343,1138 @@ impl<T: Num> PiecewiseLinear<T> {
             f.is_monotone(),
             "Composition g ⚬ f is only implemented for f monotone increasing."
         );
+        let f_img = f.image();
+        debug_assert!(
+            g.domain[0] <= f_img.0 + T::TOL && g.domain[1] >= f_img.1 - T::TOL,
+            "The domains do not match for composition."
+        );
+
+        let mut points: Vec<Point<T>> = Vec::with_capacity(f.points.len() + g.points.len());
+        let mut push = |p: Point<T>| {
+            if T::EXACT_ARITHMETIC || points.last().is_none_or(|last| p.0 > last.0 + T::TOL) {
+                points.push(p);
+            }
+        };
+
+        // g.points[..i_g] lie at or before the current value of f.
+        let mut i_g = match g.get_rnk(&f_img.0) {
+            Ok(i) => i + 1,
+            Err(i) => i,
+        };
+        // Traverse the pieces of f (see `gradient`), each followed by its right endpoint.
+        for i_f in 0..=f.points.len() {
+            let end_value = f.points.get(i_f).map_or(f_img.1, |p| p.1);
+            if f.gradient(i_f) > T::ZERO {
+                while i_g < g.points.len() && g.points[i_g].0 < end_value {
+                    let Point(x, y) = g.points[i_g];
+                    push(Point(f.inverse(x, i_f), y));
+                    i_g += 1;
+                }
+            }
+            if let Some(p) = f.points.get(i_f) {
+                let rnk = match g.points.get(i_g) {
+                    Some(q) if q.0 == p.1 => {
+                        i_g += 1;
+                        Ok(i_g - 1)
+                    }
+                    _ => Err(i_g),
+                };
+                push(Point(p.0, g.eval_with_rank(rnk, p.1)));
+            }
+        }
+
+        // By the chain rule, using the slopes of g just after f(domain[0]) and just before f(domain[1]).
+        let first_slope = if f.first_slope == T::ZERO {
+            T::ZERO
+        } else {
+            let rnk = match g.get_rnk(&f_img.0) {
+                Ok(i) => i + 1,
+                Err(i) => i,
+            };
+            g.gradient(rnk) * f.first_slope
+        };
+        let last_slope = if f.last_slope == T::ZERO {
+            T::ZERO
+        } else {
+            let rnk = match g.get_rnk(&f_img.1) {
+                Ok(i) => i,
+                Err(i) => i,
+            };
+            g.gradient(rnk) * f.last_slope
+        };
+        PiecewiseLinear {
+            domain: f.domain,
+            first_slope,
+            last_slope,
+            points,
+        }
+    }
+
+    fn is_monotone(&self) -> bool {
+        self.first_slope >= T::ZERO
+            && self.last_slope >= T::ZERO
+            && self.points.windows(2).all(|w| w[0].1 <= w[1].1)
+    }
+
+    /// Returns the infimum and supremum of a monotone function on its domain.
+    fn image(&self) -> (T, T) {
+        debug_assert!(
+            self.is_monotone(),
+            "Only implemented for monotone functions."
+        );
+        let first = &self.points[0];
+        let last = self.points.last().unwrap();
+        let lower = if self.first_slope == T::ZERO {
+            first.1
+        } else if self.domain[0] <= -T::INFINITY {
+            -T::INFINITY
+        } else {
+            first.1 + (self.domain[0] - first.0) * self.first_slope
+        };
+        let upper = if self.last_slope == T::ZERO {
+            last.1
+        } else if self.domain[1] >= T::INFINITY {
+            T::INFINITY
+        } else {
+            last.1 + (self.domain[1] - last.0) * self.last_slope
+        };
+        (lower, upper)
+    }
+
+    /// Returns the x with self(x) = value on the i-th piece (see `gradient`), which must not be flat.
+    fn inverse(&self, value: T, i: usize) -> T {
+        let gradient = self.gradient(i);
+        debug_assert!(gradient != T::ZERO, "The piece must not be flat.");
+        let anchor = &self.points[i.saturating_sub(1)];
+        anchor.0 + (value - anchor.1) / gradient
+    }
+
+    /// Appends a piece from the last point to `time` with the given `slope`, which also becomes the
+    /// last slope. If the piece continues the previous one (within tolerance), the last point is
+    /// moved to `time` instead of adding a new one.
+    pub fn extend_with_slope_until(&mut self, time: T, slope: T)
+    where
+        T: Tolerance,
+    {
+        let n = self.points.len();
+        let last_point = &self.points[n - 1];
+        assert!(time >= last_point.0 - T::TOL);
+        if time > last_point.0 + T::TOL {
+            let point = Point(time, last_point.1 + (time - last_point.0) * slope);
+            if n >= 2 && abs(self.gradient(n - 1) - slope) <= T::TOL {
+                self.points[n - 1] = point;
+            } else {
+                self.points.push(point);
+            }
+        }
+        self.last_slope = slope;
+    }
+
+    /// Appends a linear piece from the last point to `(time, value)`. Afterwards, the function
+    /// continues with the slope of this piece. If `time` is within tolerance of the last point,
+    /// the value of the last point is replaced instead.
+    pub fn extend_to(&mut self, time: T, value: T)
+    where
+        T: Tolerance,
+    {
+        let last_point = self.points.last_mut().unwrap();
+        assert!(time >= last_point.0 - T::TOL);
+        if time <= last_point.0 + T::TOL {
+            last_point.1 = value;
+            return;
+        }
+        let slope = (value - last_point.1) / (time - last_point.0);
+        self.extend_with_slope_until(time, slope);
+        self.points.last_mut().unwrap().1 = value;
+    }
+
+    /// Returns the function shifted to the right by `dt`, i.e. `x -> self(x - dt)`.
+    pub fn shift_x(&self, dt: T) -> PiecewiseLinear<T> {
+        let mut shifted = self.clone();
+        shifted.shift_x_in_place(dt);
+        shifted
+    }
+
+    /// Shifts the function to the right by `dt`, including its domain.
+    pub fn shift_x_in_place(&mut self, dt: T) {
+        self.domain = self.domain.map(|x| x + dt);
+        for p in self.points.iter_mut() {
+            p.0 += dt;
+        }
+    }
+
+    /// Returns the function shifted upwards by `dv`, i.e. `x -> self(x) + dv`.
+    pub fn shift_y(&self, dv: T) -> PiecewiseLinear<T> {
+        let mut shifted = self.clone();
+        shifted.shift_y_in_place(dv);
+        shifted
+    }
+
+    /// Shifts the function upwards by `dv`.
+    pub fn shift_y_in_place(&mut self, dv: T) {
+        for p in self.points.iter_mut() {
+            p.1 += dv;
+        }
+    }
+
+    /// Returns the restriction of the function to `[from, to]`. Finite bounds become breakpoints
+    /// and the outer slopes are those of the pieces at the bounds.
+    pub fn restrict(&self, from: impl Into<T>, to: impl Into<T>) -> PiecewiseLinear<T> {
+        let (from, to): (T, T) = (from.into(), to.into());
+        debug_assert!(
+            self.domain[0] <= from && from <= to && to <= self.domain[1],
+            "The bounds are not ordered or not in the domain."
+        );
+        let start = self.points.partition_point(|p| p.0 <= from);
+        let end = start.max(self.points.partition_point(|p| p.0 < to));
+        let mut points: Vec<Point<T>> = Vec::with_capacity(end - start + 2);
+        if from > -T::INFINITY {
+            points.push(Point(from, self.eval(from)));
+        }
+        points.extend_from_slice(&self.points[start..end]);
+        if to < T::INFINITY && points.last().is_none_or(|p| p.0 < to) {
+            points.push(Point(to, self.eval(to)));
+        }
+        if points.is_empty() {
+            // The function is restricted to the whole real line.
+            points.extend_from_slice(&self.points);
+        }
+        PiecewiseLinear::new([from, to], self.gradient(start), self.gradient(end), points)
+    }
+
+    /// Returns the function that equals `f` before `switch_time` and `g` after it.
+    /// Both functions need to agree (within tolerance) at `switch_time`.
+    pub fn concat(f: &PiecewiseLinear<T>, g: &PiecewiseLinear<T>, switch_time: T) -> Self
+    where
+        T: Tolerance,
+    {
+        debug_assert!(
+            f.domain[0] <= switch_time
+                && switch_time <= f.domain[1]
+                && g.domain[0] <= switch_time
+                && switch_time <= g.domain[1],
+            "The switch time must lie in both domains."
+        );
+        debug_assert!(
+            abs(f.eval(switch_time) - g.eval(switch_time)) <= T::TOL,
+            "The functions do not agree at the switch time."
+        );
+        let left = f.restrict(f.domain[0], switch_time);
+        let right = g.restrict(switch_time, g.domain[1]);
+        let mut points = left.points;
+        points.extend_from_slice(&right.points[1..]);
+        PiecewiseLinear::new(
+            [f.domain[0], g.domain[1]],
+            left.first_slope,
+            right.last_slope,
+            points,
+        )
+    }
+
+    /// Returns the same function without the breakpoints that lie (within tolerance) on the line
+    /// through their neighbors. The first and last points are compared with the outer slopes.
+    pub fn simplify(&self) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        let n = self.points.len();
+        let mut points: Vec<Point<T>> = Vec::with_capacity(n);
+        for (i, p) in self.points.iter().enumerate() {
+            let expected = match (points.last(), self.points.get(i + 1)) {
+                (None, None) => None,
+                (None, Some(next)) => Some(next.1 - (next.0 - p.0) * self.first_slope),
+                (Some(prev), None) => Some(prev.1 + (p.0 - prev.0) * self.last_slope),
+                (Some(prev), Some(next)) => {
+                    Some(prev.1 + (p.0 - prev.0) * (next.1 - prev.1) / (next.0 - prev.0))
+                }
+            };
+            let redundant = expected.is_some_and(|value| abs(value - p.1) <= T::TOL);
+            if !redundant || (i + 1 == n && points.is_empty()) {
+                points.push(p.clone());
+            }
+        }
+        PiecewiseLinear::new(self.domain, self.first_slope, self.last_slope, points)
+    }
+
+    /// Returns a function with fewer breakpoints that deviates from `self` by at most `epsilon`.
+    /// Starting from the first point, each segment greedily extends to the furthest breakpoint
+    /// such that all skipped breakpoints stay within `epsilon`. The outer rays are kept as they are.
+    pub fn approximate(&self, epsilon: T) -> PiecewiseLinear<T> {
+        debug_assert!(epsilon >= T::ZERO, "The error bound must be non-negative.");
+        let n = self.points.len();
+        let mut points: Vec<Point<T>> = vec![self.points[0].clone()];
+        let mut anchor = 0;
+        while anchor + 1 < n {
+            let a = &self.points[anchor];
+            // The slopes of segments starting at `a` that keep all skipped points within epsilon.
+            let (mut lower, mut upper) = (-T::INFINITY, T::INFINITY);
+            let mut furthest = anchor + 1;
+            for (j, p) in self.points.iter().enumerate().skip(anchor + 1) {
+                let width = p.0 - a.0;
+                let slope = (p.1 - a.1) / width;
+                if lower <= slope && slope <= upper {
+                    furthest = j;
+                }
+                lower = max(lower, (p.1 - epsilon - a.1) / width);
+                upper = min(upper, (p.1 + epsilon - a.1) / width);
+                if lower > upper {
+                    break;
+                }
+            }
+            points.push(self.points[furthest].clone());
+            anchor = furthest;
+        }
+        PiecewiseLinear::new(self.domain, self.first_slope, self.last_slope, points)
+    }
+
+    /// Returns true, if the functions differ by at most `tol` on the intersection of their domains.
+    pub fn approx_eq(&self, other: &PiecewiseLinear<T>, tol: T) -> bool
+    where
+        T: Tolerance,
+    {
+        (self - other).sup_norm() <= tol
+    }
+
+    /// Returns the minimum of the function on `[from, to]` (or its limit at an infinite bound).
+    pub fn min_over(&self, from: impl Into<T>, to: impl Into<T>) -> T {
+        self.extremum_candidates(from.into(), to.into())
+            .map(|p| p.1)
+            .reduce(min)
+            .unwrap()
+    }
+
+    /// Returns the maximum of the function on `[from, to]` (or its limit at an infinite bound).
+    pub fn max_over(&self, from: impl Into<T>, to: impl Into<T>) -> T {
+        self.extremum_candidates(from.into(), to.into())
+            .map(|p| p.1)
+            .reduce(max)
+            .unwrap()
+    }
+
+    /// Returns the first time in `[from, to]` at which the function attains its maximum on
+    /// this interval. If the function only approaches its supremum at an infinite bound, that
+    /// bound is returned.
+    pub fn argmax_over(&self, from: impl Into<T>, to: impl Into<T>) -> T {
+        self.extremum_candidates(from.into(), to.into())
+            .reduce(|best, p| if p.1 > best.1 { p } else { best })
+            .unwrap()
+            .0
+    }
+
+    /// The bounds and the breakpoints in between together with the values there, which are the
+    /// only candidates for extrema of a piecewise linear function.
+    fn extremum_candidates(&self, from: T, to: T) -> impl Iterator<Item = Point<T>> + '_ {
+        debug_assert!(from <= to, "The bounds are not ordered.");
+        let first = &self.points[0];
+        let last = self.points.last().unwrap();
+        let value_at = move |x: T| {
+            let (slope, point, sign) = if x <= -T::INFINITY {
+                (self.first_slope, first, -T::ONE)
+            } else if x >= T::INFINITY {
+                (self.last_slope, last, T::ONE)
+            } else {
+                return self.eval(x);
+            };
+            if slope == T::ZERO {
+                point.1
+            } else if slope * sign > T::ZERO {
+                T::INFINITY
+            } else {
+                -T::INFINITY
+            }
+        };
+        let start = self.points.partition_point(|p| p.0 <= from);
+        let end = start.max(self.points.partition_point(|p| p.0 < to));
+        iter::once(Point(from, value_at(from)))
+            .chain(self.points[start..end].iter().cloned())
+            .chain(iter::once(Point(to, value_at(to))))
+    }
+
+    /// Returns the supremum of the absolute value of the function on its domain.
+    pub fn sup_norm(&self) -> T {
+        if (self.first_slope != T::ZERO && self.domain[0] <= -T::INFINITY)
+            || (self.last_slope != T::ZERO && self.domain[1] >= T::INFINITY)
+        {
+            return T::INFINITY;
+        }
+        let boundary_values = self
+            .domain
+            .iter()
+            .filter(|x| abs(**x) < T::INFINITY)
+            .map(|&x| abs(self.eval(x)));
+        self.points
+            .iter()
+            .map(|p| abs(p.1))
+            .chain(boundary_values)
+            .reduce(max)
+            .unwrap_or(T::ZERO)
+    }
+
+    /// Returns the slope function. At breakpoints, it takes the slope to the right, so that the
+    /// derivative is right-continuous like all step functions.
+    /// As step functions take their first value before their first point, the first slope is
+    /// represented by an additional point before the first breakpoint (at `domain[0]` if finite).
+    pub fn derivative(&self) -> PiecewiseConstant<T> {
+        let first = &self.points[0];
+        let mut points: Vec<Point<T>> = Vec::with_capacity(self.points.len() + 1);
+        if self.domain[0] < first.0 && self.first_slope != self.gradient(1) {
+            let start = max(self.domain[0], first.0 - T::ONE);
+            points.push(Point(start, self.first_slope));
+        }
+        for (i, p) in self.points.iter().enumerate() {
+            let slope = self.gradient(i + 1);
+            if points.last().is_none_or(|last| last.1 != slope) {
+                points.push(Point(p.0, slope));
+            }
+        }
+        PiecewiseConstant::new(self.domain, points)
+    }
+
+    /// Returns the antiderivative of the function that vanishes at the first point.
+    pub fn integral(&self) -> PiecewiseQuadratic<T> {
+        let two = T::ONE + T::ONE;
+        let first = &self.points[0];
+        let mut pieces: Vec<Quadratic<T>> = Vec::with_capacity(self.points.len() + 1);
+        pieces.push(Quadratic {
+            anchor: first.0,
+            coefficients: [T::ZERO, first.1, self.first_slope / two],
+        });
+        let mut value = T::ZERO;
+        for (i, p) in self.points.iter().enumerate() {
+            let slope = self.gradient(i + 1);
+            pieces.push(Quadratic {
+                anchor: p.0,
+                coefficients: [value, p.1, slope / two],
+            });
+            if let Some(q) = self.points.get(i + 1) {
+                value += (q.0 - p.0) * (p.1 + q.1) / two;
+            }
+        }
+        PiecewiseQuadratic::new(
+            self.domain,
+            self.points.iter().map(|p| p.0).collect(),
+            pieces,
+        )
+    }
+
+    /// Returns the integral of the function from `from` to `to`, computed exactly from its pieces.
+    /// If a bound is infinite, the integral is infinite unless the function vanishes there.
+    pub fn integrate(&self, from: impl Into<T>, to: impl Into<T>) -> T {
+        let (from, to): (T, T) = (from.into(), to.into());
+        debug_assert!(from <= to, "The bounds are not ordered.");
+        let start = self.points.partition_point(|p| p.0 <= from);
+        let end = start.max(self.points.partition_point(|p| p.0 < to));
+        iter::once(from)
+            .chain(self.points[start..end].iter().map(|p| p.0))
+            .chain(iter::once(to))
+            .tuple_windows()
+            .map(|(lower, upper)| self.integrate_piece(lower, upper))
+            .sum()
+    }
+
+    /// Integrates between `lower` and `upper`, where the function is linear.
+    fn integrate_piece(&self, lower: T, upper: T) -> T {
+        if lower == upper {
+            return T::ZERO;
+        }
+        let signed_infinity = |x: T| {
+            if x == T::ZERO {
+                T::ZERO
+            } else if x > T::ZERO {
+                T::INFINITY
+            } else {
+                -T::INFINITY
+            }
+        };
+        if upper >= T::INFINITY {
+            let last = self.points.last().unwrap();
+            let slope = self.last_slope;
+            return signed_infinity(if slope != T::ZERO { slope } else { last.1 });
+        }
+        if lower <= -T::INFINITY {
+            let first = &self.points[0];
+            let slope = self.first_slope;
+            return signed_infinity(if slope != T::ZERO { -slope } else { first.1 });
+        }
+        let two = T::ONE + T::ONE;
+        (upper - lower) * (self.eval(lower) + self.eval(upper)) / two
+    }
+
+    /// Returns the first time not before `from` at which the function is at most `threshold`.
+    pub fn first_time_at_most(&self, threshold: T, from: impl Into<T>) -> Option<T> {
+        let from: T = from.into();
+        if self.eval(from) <= threshold {
+            return Some(from);
+        }
+        let start = self.points.partition_point(|p| p.0 <= from);
+        let mut prev = Point(from, self.eval(from));
+        for p in &self.points[start..] {
+            if p.1 <= threshold {
+                return Some(prev.0 + (threshold - prev.1) * (p.0 - prev.0) / (p.1 - prev.1));
+            }
+            prev = p.clone();
+        }
+        if self.last_slope < T::ZERO {
+            let time = prev.0 + (threshold - prev.1) / self.last_slope;
+            if time <= self.domain[1] {
+                return Some(time);
+            }
+        }
+        None
+    }
+
+    /// Returns the first time not before `from` at which the function is at least `threshold`.
+    pub fn first_time_at_least(&self, threshold: T, from: impl Into<T>) -> Option<T> {
+        (-self).first_time_at_most(-threshold, from)
+    }
+
+    /// Returns the times at which `self - other` changes its sign (beyond tolerance) in increasing
+    /// order. If the difference vanishes on an interval between the sign change, the start of the
+    /// interval is returned.
+    pub fn crossings(&self, other: &PiecewiseLinear<T>) -> Vec<T>
+    where
+        T: Tolerance,
+    {
+        let diff = self - other;
+        let sign = |value: T| {
+            if value > T::TOL {
+                1
+            } else if value < -T::TOL {
+                -1
+            } else {
+                0
+            }
+        };
+        let first = &diff.points[0];
+        let last = diff.points.last().unwrap();
+        let mut times: Vec<T> = Vec::new();
+
+        let mut last_sign = if diff.domain[0] < first.0 {
+            sign(diff.eval(first.0 - T::ONE))
+        } else {
+            0
+        };
+        if last_sign != 0 && sign(first.1) == -last_sign {
+            times.push(first.0 - first.1 / diff.first_slope);
+        }
+        let mut zero_since: Option<T> = None;
+        let mut prev: Option<&Point<T>> = None;
+        for p in &diff.points {
+            match sign(p.1) {
+                0 => {
+                    zero_since.get_or_insert(p.0);
+                }
+                s => {
+                    if last_sign == -s {
+                        if let Some(start) = zero_since {
+                            times.push(start);
+                        } else if let Some(q) = prev {
+                            times.push(q.0 + q.1 * (p.0 - q.0) / (q.1 - p.1));
+                        }
+                    }
+                    last_sign = s;
+                    zero_since = None;
+                }
+            }
+            prev = Some(p);
+        }
+
+        if diff.domain[1] > last.0 {
+            let right_sign = sign(diff.eval(last.0 + T::ONE));
+            if last_sign == -right_sign {
+                if let Some(start) = zero_since {
+                    times.push(start);
+                } else if sign(last.1) != 0 {
+                    times.push(last.0 - last.1 / diff.last_slope);
+                }
+            }
+        }
+        times.retain(|&t| t > diff.domain[0] && t < diff.domain[1]);
+        times
+    }
+
+    /// Returns the L1 distance ∫|self - other| over the intersection of the domains.
+    pub fn l1_distance(&self, other: &PiecewiseLinear<T>) -> T
+    where
+        T: Tolerance,
+    {
+        let diff = self - other;
+        let abs_diff = diff.max(&-&diff);
+        abs_diff.integrate(abs_diff.domain[0], abs_diff.domain[1])
+    }
+
+    /// Returns the squared L2 distance ∫(self - other)² over the intersection of the domains.
+    pub fn l2_distance_squared(&self, other: &PiecewiseLinear<T>) -> T
+    where
+        T: Tolerance,
+    {
+        let three = T::ONE + T::ONE + T::ONE;
+        (self - other)
+            .segments()
+            .map(|s| {
+                if s.start_value == T::ZERO && s.slope == T::ZERO {
+                    T::ZERO
+                } else if s.start <= -T::INFINITY || s.end >= T::INFINITY {
+                    T::INFINITY
+                } else {
+                    let (u, v) = (s.start_value, s.start_value + (s.end - s.start) * s.slope);
+                    (s.end - s.start) * (u * u + u * v + v * v) / three
+                }
+            })
+            .sum()
+    }
+
+    /// Returns the L2 distance, i.e. the square root of [`Self::l2_distance_squared`].
+    pub fn l2_distance(&self, other: &PiecewiseLinear<T>) -> f64
+    where
+        T: Tolerance + ToF64,
+    {
+        self.l2_distance_squared(other).to_f64().sqrt()
+    }
+
+    /// Returns the L∞ distance sup|self - other| over the intersection of the domains.
+    pub fn linf_distance(&self, other: &PiecewiseLinear<T>) -> T
+    where
+        T: Tolerance,
+    {
+        (self - other).sup_norm()
+    }
+
+    /// Returns `self + other`, where breakpoints closer than `tol` to the previous one are dropped.
+    pub fn add_with_tolerance(
+        &self,
+        other: &PiecewiseLinear<T>,
+        tol: &TolerancePolicy<T>,
+    ) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        sum_op(self, other, |a, b| a + b, tol)
+    }
+
+    /// Returns the pointwise minimum of `self` and `other` on the intersection of their domains.
+    /// Besides the breakpoints of both functions, the result has a breakpoint wherever they cross.
+    pub fn min(&self, other: &PiecewiseLinear<T>) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        let diff = self - other;
+        let first = &diff.points[0];
+        let last = diff.points.last().unwrap();
+
+        let mut times: Vec<T> = Vec::with_capacity(2 * diff.points.len() + 2);
+        let mut push_time = |t: T| {
+            if times.last().is_none_or(|&last| t > last + T::TOL) {
+                times.push(t);
+            }
+        };
+        if diff.first_slope != T::ZERO {
+            let crossing = first.0 - first.1 / diff.first_slope;
+            if crossing < first.0 && crossing > diff.domain[0] {
+                push_time(crossing);
+            }
+        }
+        for (p, q) in diff.points.iter().tuple_windows() {
+            push_time(p.0);
+            if (p.1 > T::TOL && q.1 < -T::TOL) || (p.1 < -T::TOL && q.1 > T::TOL) {
+                push_time(p.0 + p.1 * (q.0 - p.0) / (p.1 - q.1));
+            }
+        }
+        push_time(last.0);
+        if diff.last_slope != T::ZERO {
+            let crossing = last.0 - last.1 / diff.last_slope;
+            if crossing > last.0 && crossing < diff.domain[1] {
+                push_time(crossing);
+            }
+        }
+
+        let points: Vec<Point<T>> = times
+            .iter()
+            .map(|&t| Point(t, min(self.eval(t), other.eval(t))))
+            .collect();
+
+        // Left of the first and right of the last point, the functions do not cross anymore.
+        let first_diff = diff.eval(times[0]);
+        let self_left =
+            first_diff < -T::TOL || (first_diff <= T::TOL && diff.first_slope >= T::ZERO);
+        let last_diff = diff.eval(*times.last().unwrap());
+        let self_right = last_diff < -T::TOL || (last_diff <= T::TOL && diff.last_slope <= T::ZERO);
+        PiecewiseLinear::new(
+            diff.domain,
+            if self_left {
+                self.first_slope
+            } else {
+                other.first_slope
+            },
+            if self_right {
+                self.last_slope
+            } else {
+                other.last_slope
+            },
+            points,
+        )
+    }
+
+    /// Returns the pointwise maximum of `self` and `other` on the intersection of their domains.
+    /// Besides the breakpoints of both functions, the result has a breakpoint wherever they cross.
+    pub fn max(&self, other: &PiecewiseLinear<T>) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        -&(-self).min(&-other)
+    }
+
+    /// Returns the running maximum x ↦ sup_{y ≤ x} self(y), i.e. the smallest non-decreasing
+    /// function above `self`. On a domain unbounded to the left, the first slope must not be negative.
+    pub fn running_max(&self) -> PiecewiseLinear<T> {
+        debug_assert!(
+            self.domain[0] > -T::INFINITY || self.first_slope >= T::ZERO,
+            "The running maximum is unbounded."
+        );
+        let first = &self.points[0];
+        let start = iter::once(self.domain[0])
+            .filter(|&x| x > -T::INFINITY && x < first.0)
+            .map(|x| Point(x, self.eval(x)));
+
+        let mut points: Vec<Point<T>> = Vec::with_capacity(self.points.len() + 1);
+        // Whether the running maximum currently coincides with the function.
+        let mut following = true;
+        let mut prev: Option<Point<T>> = None;
+        for q in start.chain(self.points.iter().cloned()) {
+            match (points.last(), &prev) {
+                (Some(last), Some(p)) if !following || q.1 < last.1 => {
+                    let maximum = last.1;
+                    if q.1 < maximum {
+                        following = false;
+                    } else {
+                        // The function reaches the maximum again on the segment from `p` to `q`.
+                        let crossing = p.0 + (maximum - p.1) * (q.0 - p.0) / (q.1 - p.1);
+                        if crossing < q.0 {
+                            points.push(Point(crossing, maximum));
+                        }
+                        following = true;
+                        points.push(q.clone());
+                    }
+                }
+                _ => points.push(q.clone()),
+            }
+            prev = Some(q);
+        }
+
+        let last = points.last().unwrap().clone();
+        let last_slope = if following {
+            max(self.last_slope, T::ZERO)
+        } else if self.last_slope > T::ZERO {
+            let f_last = self.points.last().unwrap();
+            let crossing = f_last.0 + (last.1 - f_last.1) / self.last_slope;
+            if crossing < self.domain[1] {
+                points.push(Point(crossing, last.1));
+                self.last_slope
+            } else {
+                T::ZERO
+            }
+        } else {
+            T::ZERO
+        };
+        PiecewiseLinear::new(
+            self.domain,
+            max(self.first_slope, T::ZERO),
+            last_slope,
+            points,
+        )
+    }
+
+    /// Returns the function clamped to `[lower, upper]`, with breakpoints where it crosses a bound.
+    /// The bounds need to be finite.
+    pub fn clamp(&self, lower: T, upper: T) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        debug_assert!(lower <= upper, "The bounds are not ordered.");
+        self.max(&self.constant(lower)).min(&self.constant(upper))
+    }
+
+    /// Returns `x -> max(self(x), 0)`, with breakpoints where the function crosses zero.
+    pub fn non_negative_part(&self) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        self.max(&self.constant(T::ZERO))
+    }
+
+    /// Returns the constant function with the given value on the domain of `self`.
+    fn constant(&self, value: T) -> PiecewiseLinear<T> {
+        PiecewiseLinear::new(
+            self.domain,
+            T::ZERO,
+            T::ZERO,
+            vec![Point(self.points[0].0, value)],
+        )
+    }
+
+    /// Returns true, if the slopes of the pieces are non-decreasing (within tolerance).
+    pub fn is_convex(&self) -> bool
+    where
+        T: Tolerance,
+    {
+        (0..self.points.len()).all(|i| self.gradient(i) <= self.gradient(i + 1) + T::TOL)
+    }
+
+    /// Returns true, if the slopes of the pieces are non-increasing (within tolerance).
+    pub fn is_concave(&self) -> bool
+    where
+        T: Tolerance,
+    {
+        (0..self.points.len()).all(|i| self.gradient(i) + T::TOL >= self.gradient(i + 1))
+    }
+
+    /// Returns true, if the function is convex on its domain, i.e. ignoring the outer slopes on the
+    /// sides where the domain is bounded.
+    fn is_convex_on_domain(&self) -> bool
+    where
+        T: Tolerance,
+    {
+        self.segments()
+            .tuple_windows()
+            .all(|(s, t)| s.slope <= t.slope + T::TOL)
+    }
+
+    /// Returns the lower convex envelope, i.e. the largest convex function below `self`.
+    /// On an unbounded domain, the envelope only exists if the first slope does not exceed the
+    /// last slope.
+    pub fn convex_envelope(&self) -> PiecewiseLinear<T> {
+        let unbounded_left = self.domain[0] <= -T::INFINITY;
+        let unbounded_right = self.domain[1] >= T::INFINITY;
         debug_assert!(
-            {
-                let f_img = f.image();
-                g.domain[0] <= f_img.0 + T::TOL && g.domain[1] >= f_img.1 - T::TOL
+            !unbounded_left || !unbounded_right || self.first_slope <= self.last_slope,
+            "The convex envelope is unbounded from below."
+        );
+        let first = &self.points[0];
+        let last = self.points.last().unwrap();
+        let candidates = iter::once(self.domain[0])
+            .filter(|&x| !unbounded_left && x < first.0)
+            .map(|x| Point(x, self.eval(x)))
+            .chain(self.points.iter().cloned())
+            .chain(
+                iter::once(self.domain[1])
+                    .filter(|&x| !unbounded_right && x > last.0)
+                    .map(|x| Point(x, self.eval(x))),
+            );
+
+        // Andrew's monotone chain for the lower hull.
+        let mut hull: Vec<Point<T>> = Vec::with_capacity(self.points.len() + 2);
+        for p in candidates {
+            while hull.len() >= 2 {
+                let (a, b) = (&hull[hull.len() - 2], &hull[hull.len() - 1]);
+                if (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0) > T::ZERO {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(p);
+        }
+
+        // The outer rays lie below the hull points whose segments are flatter or steeper.
+        let slope = |p: &Point<T>, q: &Point<T>| (q.1 - p.1) / (q.0 - p.0);
+        let mut start = 0;
+        while unbounded_left
+            && start + 1 < hull.len()
+            && slope(&hull[start], &hull[start + 1]) < self.first_slope
+        {
+            start += 1;
+        }
+        let mut end = hull.len();
+        while unbounded_right
+            && end > start + 1
+            && slope(&hull[end - 2], &hull[end - 1]) > self.last_slope
+        {
+            end -= 1;
+        }
+        let points = hull[start..end].to_vec();
+        let first_slope = if unbounded_left || points.len() < 2 {
+            self.first_slope
+        } else {
+            slope(&points[0], &points[1])
+        };
+        let last_slope = if unbounded_right || points.len() < 2 {
+            self.last_slope
+        } else {
+            slope(&points[points.len() - 2], &points[points.len() - 1])
+        };
+        PiecewiseLinear::new(self.domain, first_slope, last_slope, points)
+    }
+
+    /// Returns the (min-plus) infimal convolution x ↦ inf_y self(y) + other(x - y) of two convex
+    /// functions. Its pieces are the pieces of both functions sorted by slope, restricted to the
+    /// slopes that both outer rays admit (e.g. the concatenation of two rate-latency servers).
+    /// Returns `None` if one of the functions is not convex on its domain or if the infimal
+    /// convolution is unbounded from below.
+    pub fn inf_convolution(&self, other: &PiecewiseLinear<T>) -> Option<PiecewiseLinear<T>>
+    where
+        T: Tolerance,
+    {
+        if !self.is_convex_on_domain() || !other.is_convex_on_domain() {
+            return None;
+        }
+        let (left_slopes, right_slopes): (Vec<T>, Vec<T>) = [self, other]
+            .iter()
+            .map(|f| {
+                let left = (f.domain[0] <= -T::INFINITY).then_some(f.first_slope);
+                let right = (f.domain[1] >= T::INFINITY).then_some(f.last_slope);
+                (left, right)
+            })
+            .fold((vec![], vec![]), |(mut l, mut r), (left, right)| {
+                l.extend(left);
+                r.extend(right);
+                (l, r)
+            });
+        let first_slope = left_slopes.into_iter().reduce(max);
+        let last_slope = right_slopes.into_iter().reduce(min);
+        if first_slope.zip(last_slope).is_some_and(|(a, b)| a > b) {
+            return None;
+        }
+
+        // The bounded pieces with admissible slopes and the point at which they start.
+        let admissible = |f: &PiecewiseLinear<T>| -> (Point<T>, Vec<Segment<T>>) {
+            let pieces: Vec<Segment<T>> = f
+                .segments()
+                .filter(|s| s.start > -T::INFINITY && s.end < T::INFINITY)
+                .collect();
+            let start = first_slope.map_or(0, |a| pieces.partition_point(|s| s.slope < a));
+            let end = last_slope.map_or(pieces.len(), |b| pieces.partition_point(|s| s.slope <= b));
+            let anchor = match pieces.get(start) {
+                Some(s) => Point(s.start, s.start_value),
+                None => match pieces.last() {
+                    Some(s) => Point(s.end, s.start_value + (s.end - s.start) * s.slope),
+                    None => f.points[0].clone(),
+                },
+            };
+            (anchor, pieces[start..start.max(end)].to_vec())
+        };
+        let (p, self_pieces) = admissible(self);
+        let (q, other_pieces) = admissible(other);
+
+        let mut points: Vec<Point<T>> = vec![Point(p.0 + q.0, p.1 + q.1)];
+        for s in self_pieces
+            .iter()
+            .merge_by(&other_pieces, |s, t| s.slope <= t.slope)
+        {
+            let last = points.last().unwrap();
+            let width = s.end - s.start;
+            points.push(Point(last.0 + width, last.1 + width * s.slope));
+        }
+        let domain = [
+            if first_slope.is_some() {
+                -T::INFINITY
+            } else {
+                points[0].0
+            },
+            if last_slope.is_some() {
+                T::INFINITY
+            } else {
+                points.last().unwrap().0
             },
-            "The domains do not match for composition."
+        ];
+        let result = PiecewiseLinear::new(
+            domain,
+            first_slope.unwrap_or(T::ZERO),
+            last_slope.unwrap_or(T::ZERO),
+            points,
         );
+        Some(result.simplify())
+    }
 
-        let mut points: Vec<Point<T>> = Vec::new(); // todo: add some heuristic capacity
-        let f_img = f.image();
-        let g_rnk = match g.get_rnk(&f_img.0) {
-            Ok(i) => i,
-            Err(i) => i,
-        };
-        // g.points[g_rnk - 1].0 < f_img.0 <= g.points[g_rnk].0
-        let first_slope = g.gradient(g_rnk) * f.first_slope; // By chain rule
-
-        let mut i_g = max(1, g_rnk); // Start of interval
-
-        debug_assert!(i_g == g.points.len() - 1 || f.domain[0] <= g.points[i_g + 1].0);
-
-        for i_f in 0..f.points.len() {
-            // Interval (f.points[i_f - 1], f.points[i_f])
-            while i_g < g.points.len() && g.points[i_g - 1].0 <= f.points[i_f].1 {
-                let next_time = max(f_img.0, g.points[i_g - 1].0);
-                if f.gradient(i_f) != T::ZERO {
-                    let inv = f.inverse(next_time, i_f);
-                    if points.last().map_or(true, |x| inv > x.0 + T::TOL) {
-                        let p = Point(inv, g.eval(next_time)); // todo: use rnk for g
-                        points.push(p);
-                    }
-                }
-                i_g += 1;
-            }
-            if points
-                .last()
-                .map_or(true, |x| f.points[i_f].0 > x.0 + T::TOL)
-            {
-                let p = Point(f.points[i_f].0, g.eval(f.points[i_f].1)); // todo: use rnk for g
-                points.push(p);
-            }
+    /// Returns the convolution x ↦ ∫ self(y)·other(x - y) dy of two continuous functions that vanish
+    /// outside of their first and last point. The exact convolution is a cubic spline whose knots
+    /// are the sums of the breakpoints of both functions; the returned function agrees with it at
+    /// the knots and is linear in between.
+    pub fn convolve(&self, other: &PiecewiseLinear<T>) -> PiecewiseLinear<T>
+    where
+        T: Tolerance,
+    {
+        // Each function is a sum of ramps x ↦ a·max(x - s, 0), one per change of slope, and the
+        // convolution of two ramps is x ↦ a·b·max(x - s - t, 0)³ / 6.
+        let (self_ramps, other_ramps) = (self.ramps(), other.ramps());
+        let mut cubics: Vec<(T, T)> = Vec::with_capacity(self_ramps.len() * other_ramps.len());
+        for &(s, a) in &self_ramps {
+            cubics.extend(other_ramps.iter().map(|&(t, b)| (s + t, a * b)));
         }
+        cubics.sort_by(|a, b| a.0.total_cmp(&b.0));
 
-        while i_g <= g.points.len() && g.points[i_g - 1].0 <= f_img.1 {
-            let next_time = max(f_img.0, g.points[i_g - 1].0);
-            if f.gradient(f.points.len()) != T::ZERO {
-                let inv = f.inverse(next_time, f.points.len()); // todo: check usages of inverse
-                if points.last().map_or(true, |x| inv > x.0 + T::TOL) {
-                    let p = Point(inv, g.eval(next_time)); // todo: use rnk for g
-                    points.push(p);
+        // The value and the first three derivatives of the spline at the last knot
+        let two = T::ONE + T::ONE;
+        let six = two + two + two;
+        let (mut value, mut d1, mut d2, mut d3) = (T::ZERO, T::ZERO, T::ZERO, T::ZERO);
+        let mut points: Vec<Point<T>> = Vec::with_capacity(cubics.len());
+        for (knot, weight) in cubics {
+            match points.last() {
+                Some(last) if last.0 == knot => {}
+                Some(last) => {
+                    let h = knot - last.0;
+                    value += h * (d1 + h * (d2 / two + h * d3 / six));
+                    d1 += h * (d2 + h * d3 / two);
+                    d2 += h * d3;
+                    points.push(Point(knot, value));
                 }
+                None => points.push(Point(knot, value)),
             }
-            i_g += 1;
+            d3 += weight;
         }
-
-        let last_slope = g.gradient(i_g) * f.last_slope;
-        PiecewiseLinear {
-            domain: f.domain,
-            first_slope,
-            last_slope,
-            points,
+        if points.is_empty() {
+            points.push(Point(T::ZERO, T::ZERO));
         }
+        PiecewiseLinear::new([-T::INFINITY, T::INFINITY], T::ZERO, T::ZERO, points)
     }
 
-    fn is_monotone(&self) -> bool {
-        return self.first_slope >= T::ZERO
-            && self.last_slope >= T::ZERO
-            && self.points.windows(2).all(|w| w[0].1 <= w[1].1);
-    }
-    fn image(&self) -> (T, T) {
+    /// Returns the changes of slope at the points, including the change from and to zero at the
+    /// first and the last point.
+    fn ramps(&self) -> Vec<(T, T)> {
         debug_assert!(
-            self.is_monotone(),
-            "Only implemented for monotone functions."
+            self.points[0].1 == T::ZERO && self.points.last().unwrap().1 == T::ZERO,
+            "The function must vanish outside of its points."
         );
-        // TODO: The performance could be improved by determining the rank.
-        (self.eval(self.domain[0]), self.eval(self.domain[1]))
+        let n = self.points.len();
+        let slope = |i: usize| match i {
+            0 => T::ZERO,
+            i if i == n => T::ZERO,
+            i => {
+                (self.points[i].1 - self.points[i - 1].1)
+                    / (self.points[i].0 - self.points[i - 1].0)
+            }
+        };
+        (0..n)
+            .map(|i| (self.points[i].0, slope(i + 1) - slope(i)))
+            .filter(|&(_, change)| change != T::ZERO)
+            .collect()
     }
 
-    fn inverse(&self, _p0: T, _p1: usize) -> T {
-        todo!("Not yet implemented!")
+    /// Approximates the function by a step function that deviates by at most `max_error` between
+    /// the first and the last point. Each step takes the average value of the function on the step,
+    /// so that integrals are preserved. The function must be constant outside of its points.
+    pub fn to_step_function(&self, max_error: T) -> PiecewiseConstant<T>
+    where
+        T: Tolerance,
+    {
+        debug_assert!(max_error > T::ZERO, "The error bound must be positive.");
+        debug_assert!(
+            self.first_slope == T::ZERO && self.last_slope == T::ZERO,
+            "The function must be constant outside of its points."
+        );
+        let two = T::ONE + T::ONE;
+        let first = self.points[0].clone();
+        let mut step_fn = PiecewiseConstant::new(self.domain, vec![first]);
+        for (p, q) in self.points.iter().tuple_windows() {
+            let slope = (q.1 - p.1) / (q.0 - p.0);
+            let width = if slope == T::ZERO {
+                q.0 - p.0
+            } else {
+                two * max_error / abs(slope)
+            };
+            let mut x = p.0;
+            while x < q.0 {
+                let next = min(x + width, q.0);
+                step_fn.extend(&x, &(p.1 + slope * ((x + next) / two - p.0)));
+                x = next;
+            }
+        }
+        let last = self.points.last().unwrap();
+        step_fn.extend(&last.0, &last.1);
+        step_fn
     }
+}
 
-    pub fn extend(&mut self, from_time: &T, slope: T) {
-        let last_point = self.points.last().unwrap();
-        assert!(*from_time >= last_point.0 - T::TOL);
-        if abs(self.last_slope - slope) <= T::TOL {
-            return;
+impl<T: Num> PiecewiseLinear<T> {
+    /// Returns the sum of all `functions` on the intersection of their domains.
+    /// The breakpoints are merged with a heap, so summing k functions with n breakpoints in total
+    /// takes O(n log k) time instead of the O(k·n) of repeated pairwise sums.
+    /// The empty sum is the zero function on the real line.
+    pub fn sum<'a>(functions: impl IntoIterator<Item = &'a PiecewiseLinear<T>>) -> Self
+    where
+        T: Tolerance,
+        T: 'a,
+    {
+        let functions: Vec<&PiecewiseLinear<T>> = functions.into_iter().collect();
+        if functions.is_empty() {
+            return PiecewiseLinear::new(
+                [-T::INFINITY, T::INFINITY],
+                T::ZERO,
+                T::ZERO,
+                vec![Point(T::ZERO, T::ZERO)],
+            );
+        }
+        let domain = [
+            functions.iter().map(|f| f.domain[0]).reduce(max).unwrap(),
+            functions.iter().map(|f| f.domain[1]).reduce(min).unwrap(),
+        ];
+        let start = if domain[0] > -T::INFINITY {
+            domain[0]
+        } else {
+            functions.iter().map(|f| f.points[0].0).reduce(min).unwrap()
+        };
+
+        // For each function, the index of its next breakpoint and the slope of its current piece.
+        let mut cursors: Vec<usize> = functions
+            .iter()
+            .map(|f| f.points.partition_point(|p| p.0 <= start))
+            .collect();
+        let mut slopes: Vec<T> = functions
+            .iter()
+            .zip(&cursors)
+            .map(|(f, &i)| f.gradient(i))
+            .collect();
+        let mut slope: T = slopes.iter().copied().sum();
+        let mut value: T = functions.iter().map(|f| f.eval(start)).sum();
+
+        let mut heap: BinaryHeap<Reverse<(Total<T>, usize)>> = functions
+            .iter()
+            .zip(&cursors)
+            .enumerate()
+            .filter_map(|(j, (f, &i))| f.points.get(i).map(|p| Reverse((Total(p.0), j))))
+            .collect();
+        let mut points: Vec<Point<T>> = vec![Point(start, value)];
+        let mut time = start;
+        while let Some(&Reverse((Total(next), _))) = heap.peek() {
+            if next > domain[1] {
+                break;
+            }
+            value += slope * (next - time);
+            time = next;
+            while let Some(&Reverse((Total(x), j))) = heap.peek() {
+                if x != next {
+                    break;
+                }
+                heap.pop();
+                let f = functions[j];
+                cursors[j] += 1;
+                let new_slope = f.gradient(cursors[j]);
+                slope += new_slope - slopes[j];
+                slopes[j] = new_slope;
+                if let Some(p) = f.points.get(cursors[j]) {
+                    heap.push(Reverse((Total(p.0), j)));
+                }
+            }
+            if T::EXACT_ARITHMETIC || points.last().unwrap().0 < time - T::TOL {
+                points.push(Point(time, value));
+            }
         }
-        if abs(*from_time - last_point.0) > T::TOL {
-            self.points.push(Point(
-                *from_time,
-                last_point.1 + (*from_time - last_point.0) * self.last_slope,
-            ));
+        if domain[1] < T::INFINITY && time < domain[1] {
+            points.push(Point(domain[1], value + slope * (domain[1] - time)));
+        }
+
+        PiecewiseLinear {
+            domain,
+            first_slope: functions.iter().map(|f| f.first_slope).sum(),
+            last_slope: functions.iter().map(|f| f.last_slope).sum(),
+            points,
         }
-        self.last_slope = slope;
     }
 }
 
-fn sum_op<T: Num, F: Fn(T, T) -> T>(
+fn sum_op<T: Num + Tolerance, F: Fn(T, T) -> T>(
     lhs: &PiecewiseLinear<T>,
     rhs: &PiecewiseLinear<T>,
     op: F,
+    tol: &TolerancePolicy<T>,
 ) -> PiecewiseLinear<T> {
     let new_domain = [
         max(lhs.domain[0], rhs.domain[0]),
@@ -306,17 +1556,17 @@ fn sum_op<T: Num, F: Fn(T, T) -> T>(
     let new_iter = lhs.points[lhs_rng.0..lhs_rng.1]
         .iter()
         .merge_join_by(rhs.points[rhs_rng.0..rhs_rng.1].iter(), |x, y| {
-            x.0.cmp(&y.0)
+            x.0.total_cmp(&y.0)
         });
 
     let mut cur_i = lhs_rng.0;
     let mut cur_j = rhs_rng.0;
 
-    // Returns true, if `t` lies in the future by an offset of T::TOL.
+    // Returns true, if `t` lies in the future by more than the tolerance.
     let time_in_tolerance = |t: T, list: &Vec<Point<T>>| -> bool {
         match list.last() {
             None => true,
-            Some(p) => p.0 < (t - T::TOL),
+            Some(p) => p.0 < (t - tol.tolerance(t)),
         }
     };
 
@@ -350,6 +1600,10 @@ fn sum_op<T: Num, F: Fn(T, T) -> T>(
     if let Some(value) = last_point {
         new_points.push(value);
     }
+    check_points(
+        &new_points,
+        "Pointwise operation on piecewise linear functions",
+    );
 
     PiecewiseLinear {
         domain: new_domain,
@@ -359,21 +1613,21 @@ fn sum_op<T: Num, F: Fn(T, T) -> T>(
     }
 }
 
-impl<T: Num> Add<&PiecewiseLinear<T>> for &PiecewiseLinear<T> {
+impl<T: Num + Tolerance> Add<&PiecewiseLinear<T>> for &PiecewiseLinear<T> {
     type Output = PiecewiseLinear<T>;
 
     #[inline]
     fn add(self, rhs: &PiecewiseLinear<T>) -> Self::Output {
-        sum_op(self, rhs, |a, b| a + b)
+        sum_op(self, rhs, |a, b| a + b, &TolerancePolicy::default())
     }
 }
 
-impl<T: Num> Sub<&PiecewiseLinear<T>> for &PiecewiseLinear<T> {
+impl<T: Num + Tolerance> Sub<&PiecewiseLinear<T>> for &PiecewiseLinear<T> {
     type Output = PiecewiseLinear<T>;
 
     #[inline]
     fn sub(self, rhs: &PiecewiseLinear<T>) -> Self::Output {
-        sum_op(self, rhs, |a, b| a - b)
+        sum_op(self, rhs, |a, b| a - b, &TolerancePolicy::default())
     }
 }
 
@@ -381,12 +1635,40 @@ impl<T: Num> Neg for &PiecewiseLinear<T> {
     type Output = PiecewiseLinear<T>;
 
     fn neg(self) -> Self::Output {
-        return PiecewiseLinear::new(
+        PiecewiseLinear::new(
             self.domain,
             -self.first_slope,
             -self.last_slope,
             self.points.iter().map(|p| Point(p.0, -p.1)).collect_vec(),
-        );
+        )
+    }
+}
+
+impl<T: Num> Mul<T> for &PiecewiseLinear<T> {
+    type Output = PiecewiseLinear<T>;
+
+    fn mul(self, rhs: T) -> Self::Output {
+        check_not_nan(rhs, || "Scaling a piecewise linear function".to_string());
+        PiecewiseLinear {
+            domain: self.domain,
+            first_slope: self.first_slope * rhs,
+            last_slope: self.last_slope * rhs,
+            points: self.points.iter().map(|p| Point(p.0, p.1 * rhs)).collect(),
+        }
+    }
+}
+
+impl<T: Num> Div<T> for &PiecewiseLinear<T> {
+    type Output = PiecewiseLinear<T>;
+
+    fn div(self, rhs: T) -> Self::Output {
+        check_not_nan(rhs, || "Dividing a piecewise linear function".to_string());
+        PiecewiseLinear {
+            domain: self.domain,
+            first_slope: self.first_slope / rhs,
+            last_slope: self.last_slope / rhs,
+            points: self.points.iter().map(|p| Point(p.0, p.1 / rhs)).collect(),
+        }
     }
 }
 
@@ -405,9 +1687,244 @@ impl<T: Num> Display for PiecewiseLinear<T> {
     }
 }
 
+/// Remembers the rank of the last evaluation of a function, such that evaluating it at
+/// non-decreasing times walks the breakpoints once instead of binary searching every time.
+/// The cursor does not borrow the function and stays valid while the function is extended.
+/// Evaluating at an earlier time falls back to a binary search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PiecewiseLinearCursor {
+    rank: usize,
+}
+
+impl PiecewiseLinearCursor {
+    pub fn new() -> Self {
+        PiecewiseLinearCursor { rank: 0 }
+    }
+
+    /// Returns the rank of `at` in the points of `f` like `get_rnk`.
+    #[inline]
+    pub fn seek<T: Num>(&mut self, f: &PiecewiseLinear<T>, at: T) -> Result<usize, usize> {
+        let points = &f.points;
+        let mut i = self.rank.min(points.len());
+        if i > 0 && points[i - 1].0 >= at {
+            i = points.partition_point(|p| p.0 < at);
+        } else {
+            while i < points.len() && points[i].0 < at {
+                i += 1;
+            }
+        }
+        self.rank = i;
+        if i < points.len() && points[i].0 == at {
+            Ok(i)
+        } else {
+            Err(i)
+        }
+    }
+
+    #[inline]
+    pub fn eval<T: Num>(&mut self, f: &PiecewiseLinear<T>, at: T) -> T {
+        let rnk = self.seek(f, at);
+        f.eval_with_rank(rnk, at)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::{float::F64, num::Num, piecewise_linear::PiecewiseLinear, points};
+    use crate::{
+        float::F64,
+        num::{max, min, Num, Tolerance},
+        piecewise_linear::{PiecewiseLinear, PiecewiseLinearCursor, Segment},
+        point::Point,
+        points,
+    };
+
+    #[test]
+    fn it_takes_the_minimum_and_maximum_with_crossings() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            1.0,
+            points![(0.0, 0.0)],
+        );
+        let g: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            -1.0,
+            points![(0.0, 1.0)],
+        );
+        let lower = f.min(&g);
+        assert_eq!(
+            lower,
+            PiecewiseLinear::new(
+                [-F64::INFINITY, F64::INFINITY],
+                1.0,
+                -1.0,
+                points![(0.0, 0.0), (0.5, 0.5)]
+            )
+        );
+        let upper = f.max(&g);
+        assert_eq!(upper.eval(-1.0), 2.0);
+        assert_eq!(upper.eval(0.5), 0.5);
+        assert_eq!(upper.eval(2.0), 2.0);
+
+        let h: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [0.0, 4.0],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (2.0, 2.0), (4.0, 0.0)],
+        );
+        let one: PiecewiseLinear<F64> =
+            PiecewiseLinear::new([0.0, 4.0], 0.0, 0.0, points![(0.0, 1.0)]);
+        let clipped = h.min(&one);
+        assert_eq!(
+            clipped.points(),
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 1.0), (4.0, 0.0)]
+        );
+        assert_eq!(h.max(&one).eval(0.5), 1.0);
+        assert_eq!(h.max(&one).eval(1.5), 1.5);
+    }
+
+    fn assert_composition(g: &PiecewiseLinear<F64>, f: &PiecewiseLinear<F64>) {
+        let h = g.compose(f);
+        for x in -20..=50 {
+            let x = F64::from(x as f64 / 5.0);
+            if x >= f.domain()[0] && x <= f.domain()[1] {
+                assert!(
+                    num_traits::abs(h.eval(x) - g.eval(f.eval(x))) <= F64::TOL,
+                    "h({}) = {} != {}",
+                    x,
+                    h.eval(x),
+                    g.eval(f.eval(x))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_composes_with_increasing_functions() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 3.0)],
+        );
+        let g: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            0.0,
+            points![(1.0, 1.0), (2.5, 4.0)],
+        );
+        assert_eq!(
+            g.compose(&f),
+            PiecewiseLinear::new(
+                [-F64::INFINITY, F64::INFINITY],
+                1.0,
+                0.0,
+                points![(0.0, 0.0), (0.5, 1.0), (1.0, 3.0), (1.5, 4.0), (2.0, 4.0)]
+            )
+        );
+        assert_composition(&g, &f);
+        assert_composition(&f, &g);
+    }
+
+    #[test]
+    fn it_composes_with_flat_pieces_and_bounded_domains() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-1.0, 6.0],
+            0.5,
+            0.0,
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 1.0), (3.0, 2.0)],
+        );
+        let g: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-1.0, 2.0],
+            -1.0,
+            3.0,
+            points![(-0.25, 0.0), (1.0, 1.0), (1.5, 0.0)],
+        );
+        assert_composition(&g, &f);
+        let h = g.compose(&f);
+        assert_eq!(h.domain(), [F64::from(-1.0), F64::from(6.0)]);
+        assert_eq!(h.first_slope(), -0.5);
+        assert_eq!(h.last_slope(), 0.0);
+    }
+
+    #[test]
+    fn it_integrates_over_an_interval() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (1.0, 2.0), (3.0, 0.0)],
+        );
+        assert_eq!(f.integrate(-1.0, 4.0), 3.0);
+        assert_eq!(f.integrate(0.5, 2.0), 2.25);
+        assert_eq!(f.integrate(1.0, 1.0), 0.0);
+        assert_eq!(f.integrate(-F64::INFINITY, F64::INFINITY), 3.0);
+
+        let g: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            0.0,
+            points![(0.0, 1.0)],
+        );
+        assert_eq!(g.integrate(0.0, F64::INFINITY), F64::INFINITY);
+        assert_eq!(g.integrate(-F64::INFINITY, 0.0), -F64::INFINITY);
+    }
+
+    #[test]
+    fn it_differentiates() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            -1.0,
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 4.0), (3.0, 3.0)],
+        );
+        let derivative = f.derivative();
+        assert_eq!(
+            derivative.points(),
+            points![(-1.0, 0.0), (0.0, 2.0), (2.0, -1.0)]
+        );
+        assert_eq!(derivative.eval(-5.0), 0.0);
+        assert_eq!(derivative.eval(0.0), 2.0);
+        assert_eq!(derivative.eval(2.5), -1.0);
+        assert_eq!(derivative.eval(10.0), -1.0);
+
+        let g: PiecewiseLinear<F64> =
+            PiecewiseLinear::new([0.0, 4.0], 1.0, 1.0, points![(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(g.derivative().points(), points![(0.0, 1.0)]);
+    }
+
+    #[test]
+    fn it_scales_by_a_scalar() {
+        let f: PiecewiseLinear<F64> =
+            PiecewiseLinear::new([0.0, 4.0], 1.0, -1.0, points![(1.0, 2.0), (2.0, 4.0)]);
+        let doubled = &f * F64::from(2.0);
+        assert_eq!(
+            doubled,
+            PiecewiseLinear::new([0.0, 4.0], 2.0, -2.0, points![(1.0, 4.0), (2.0, 8.0)])
+        );
+        assert_eq!(&doubled / F64::from(2.0), f);
+    }
+
+    #[test]
+    fn it_approximates_by_a_step_function() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (2.0, 2.0), (3.0, 2.0), (4.0, 0.0)],
+        );
+        let step_fn = f.to_step_function(0.25.into());
+        assert_eq!(step_fn.eval(0.25), 0.25);
+        assert_eq!(step_fn.eval(1.75), 1.75);
+        assert_eq!(step_fn.eval(2.5), 2.0);
+        assert_eq!(step_fn.eval(3.25), 1.25);
+        assert_eq!(step_fn.eval(5.0), 0.0);
+        for x in 0..=40 {
+            let x = F64::from(x as f64 / 10.0);
+            assert!(num_traits::abs(step_fn.eval(x) - f.eval(x)) <= F64::from(0.25));
+        }
+    }
 
     #[test]
     fn it_adds_two_piecewise_linear_functions() {
@@ -435,4 +1952,576 @@ mod tests {
         f.extend(&(F64::from(2.0) - F64::TOL / 2.0.into()), F64::from(2.0));
         assert_eq!(f.points.len(), 3);
     }
+
+    #[test]
+    fn it_removes_collinear_points() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            0.0,
+            points![
+                (0.0, 0.0),
+                (1.0, 1.0),
+                (2.0, 2.0),
+                (3.0, 1.0),
+                (4.0, 1.0),
+                (5.0, 1.0)
+            ],
+        );
+        let simplified = f.simplify();
+        assert_eq!(simplified.points(), points![(2.0, 2.0), (3.0, 1.0)]);
+        for x in [-1.0, 0.5, 2.5, 4.5, 6.0] {
+            assert_eq!(simplified.eval(x), f.eval(x));
+        }
+
+        let line: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 1.0)],
+        );
+        assert_eq!(line.simplify().points(), points![(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn it_approximates_within_the_error_bound() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 0.1), (2.0, 0.0), (3.0, 1.0)],
+        );
+        let g = f.approximate(0.2.into());
+        assert_eq!(g.points(), points![(0.0, 0.0), (2.0, 0.0), (3.0, 1.0)]);
+
+        let zigzag: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            (0..100)
+                .map(|i| {
+                    Point(
+                        F64::from(i as f64),
+                        F64::from((i % 3) as f64 * 0.01 + i as f64),
+                    )
+                })
+                .collect(),
+        );
+        let epsilon = F64::from(0.05);
+        let approx = zigzag.approximate(epsilon);
+        assert!(approx.points().len() < 10);
+        for i in 0..1000 {
+            let x = i as f64 / 10.0 - 1.0;
+            assert!(num_traits::abs(approx.eval(x) - zigzag.eval(x)) <= epsilon);
+        }
+    }
+
+    #[test]
+    fn it_restricts_to_a_subdomain() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            2.0,
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        );
+        let g = f.restrict(0.5, 3.0);
+        assert_eq!(g.domain(), [F64::from(0.5), F64::from(3.0)]);
+        assert_eq!(
+            g.points(),
+            points![(0.5, 0.5), (1.0, 1.0), (2.0, 0.0), (3.0, 2.0)]
+        );
+        assert_eq!(g.first_slope(), 1.0);
+        assert_eq!(g.last_slope(), 2.0);
+
+        let h = f.restrict(-F64::INFINITY, 1.0);
+        assert_eq!(h.points(), points![(0.0, 0.0), (1.0, 1.0)]);
+        assert_eq!(h.first_slope(), -1.0);
+        assert_eq!(h.last_slope(), 1.0);
+
+        let point = f.restrict(1.0, 1.0);
+        assert_eq!(point.points(), points![(1.0, 1.0)]);
+    }
+
+    #[test]
+    fn it_shifts_in_time_and_value() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [F64::ZERO, F64::INFINITY],
+            0.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 2.0)],
+        );
+        let g = f.shift_x(2.0.into()).shift_y((-1.0).into());
+        assert_eq!(g.domain(), [F64::from(2.0), F64::INFINITY]);
+        assert_eq!(g.points(), points![(2.0, -1.0), (3.0, 1.0)]);
+        for x in [2.0, 2.5, 4.0] {
+            assert_eq!(g.eval(x), f.eval(x - 2.0) - F64::ONE);
+        }
+    }
+
+    #[test]
+    fn it_clamps_with_crossings() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            1.0,
+            points![(0.0, 0.0), (2.0, 2.0), (3.0, -1.0)],
+        );
+        let g = f.non_negative_part();
+        let expected = points![
+            (0.0, 0.0),
+            (2.0, 2.0),
+            (2.0 + 2.0 / 3.0, 0.0),
+            (3.0, 0.0),
+            (4.0, 0.0)
+        ];
+        assert_eq!(g.points().len(), expected.len());
+        for (p, q) in g.points().iter().zip(expected) {
+            assert_eq!(p.0, q.0);
+            assert!(num_traits::abs(p.1 - q.1) <= F64::TOL);
+        }
+        assert_eq!(g.eval(-2.0), 2.0);
+        assert_eq!(g.eval(10.0), 6.0);
+
+        let h = f.clamp(0.0.into(), 1.0.into());
+        for x in [-5.0, -0.5, 0.5, 1.0, 1.5, 2.5, 2.9, 3.5, 4.5, 10.0] {
+            let expected = min(max(f.eval(x), F64::ZERO), F64::ONE);
+            assert!(num_traits::abs(h.eval(x) - expected) <= F64::TOL);
+        }
+        assert_eq!(h.first_slope(), 0.0);
+        assert_eq!(h.last_slope(), 0.0);
+    }
+
+    #[test]
+    fn it_computes_the_convex_envelope() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            2.0,
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 1.0), (3.0, 3.0)],
+        );
+        assert!(!f.is_convex());
+        assert!(!f.is_concave());
+        let envelope = f.convex_envelope();
+        assert!(envelope.is_convex());
+        assert_eq!(
+            envelope.points(),
+            points![(0.0, 0.0), (2.0, 1.0), (3.0, 3.0)]
+        );
+        assert!(!(-&envelope).is_convex());
+        assert!((-&envelope).is_concave());
+
+        let steep: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 1.0), (3.0, 3.0)],
+        );
+        let envelope = steep.convex_envelope();
+        assert_eq!(envelope.points(), points![(2.0, 1.0)]);
+        assert_eq!(envelope.eval(0.0), -1.0);
+        assert_eq!(envelope.eval(3.0), 2.0);
+
+        let bounded = steep.restrict(0.5, 3.0).convex_envelope();
+        assert_eq!(
+            bounded.points(),
+            points![(0.5, 1.0), (2.0, 1.0), (3.0, 3.0)]
+        );
+        assert_eq!(bounded.first_slope(), 0.0);
+    }
+
+    #[test]
+    fn it_compares_approximately() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            1.0,
+            points![(0.0, 0.0), (2.0, 2.0)],
+        );
+        let g: PiecewiseLinear<F64> =
+            PiecewiseLinear::new([F64::ZERO, F64::INFINITY], 1.0, 1.0, points![(1.0, 1.25)]);
+        assert!(f.approx_eq(&g, 0.25.into()));
+        assert!(!f.approx_eq(&g, 0.2.into()));
+        assert!(!f.approx_eq(&(&g * F64::from(2.0)), 10.0.into()));
+    }
+
+    #[test]
+    fn it_finds_crossings_and_threshold_times() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            -2.0,
+            points![(0.0, 2.0), (1.0, 0.0), (2.0, 0.0), (3.0, -1.0), (4.0, 1.0)],
+        );
+        let zero: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0)],
+        );
+        assert_eq!(
+            f.crossings(&zero),
+            vec![F64::ONE, F64::from(3.5), F64::from(4.5)]
+        );
+        assert_eq!(zero.crossings(&f), f.crossings(&zero));
+        assert_eq!(f.first_time_at_most(0.0.into(), -5.0), Some(F64::ONE));
+        assert_eq!(f.first_time_at_most(0.0.into(), 3.5), Some(F64::from(3.5)));
+        assert_eq!(f.first_time_at_most(0.0.into(), 3.75), Some(F64::from(4.5)));
+        assert_eq!(
+            f.first_time_at_least(3.0.into(), -5.0),
+            Some(F64::from(-5.0))
+        );
+        assert_eq!(f.first_time_at_least(1.0.into(), 3.0), Some(F64::from(4.0)));
+        assert_eq!(f.first_time_at_least(3.0.into(), 0.5), None);
+    }
+
+    #[test]
+    fn it_evaluates_sorted_points() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            2.0,
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        );
+        let xs: Vec<F64> = [-1.0, 0.0, 0.0, 0.5, 1.0, 1.5, 2.0, 3.0]
+            .iter()
+            .map(|&x| F64::from(x))
+            .collect();
+        let expected: Vec<F64> = xs.iter().map(|&x| f.eval(x)).collect();
+        assert_eq!(f.eval_sorted(&xs), expected);
+
+        // Long runs within a piece take the vectorized path, including a partial chunk.
+        let xs: Vec<F64> = (0..=37).map(|k| F64::from(k as f64 / 17.0)).collect();
+        let expected: Vec<F64> = xs.iter().map(|&x| f.eval(x)).collect();
+        assert_eq!(f.eval_sorted(&xs), expected);
+    }
+
+    #[test]
+    fn it_evaluates_with_a_cursor() {
+        let mut f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 1.0)],
+        );
+        let mut cursor = PiecewiseLinearCursor::new();
+        assert_eq!(cursor.eval(&f, F64::from(-1.0)), 0.0);
+        assert_eq!(cursor.eval(&f, F64::from(0.5)), 0.5);
+        assert_eq!(cursor.seek(&f, F64::from(1.0)), Ok(1));
+        f.extend(&2.0.into(), (-1.0).into());
+        assert_eq!(cursor.eval(&f, F64::from(3.0)), 1.0);
+        // Going back in time falls back to a binary search.
+        assert_eq!(cursor.eval(&f, F64::from(0.25)), 0.25);
+        assert_eq!(cursor.seek(&f, F64::from(2.0)), Ok(2));
+    }
+
+    #[test]
+    fn it_sums_many_functions() {
+        let functions: Vec<PiecewiseLinear<F64>> = (0..20)
+            .map(|i| {
+                let x = F64::from(i as f64);
+                PiecewiseLinear::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    0.0,
+                    -1.0,
+                    vec![Point(x, F64::ZERO), Point(x + F64::ONE, F64::ONE)],
+                )
+            })
+            .collect();
+        let sum = PiecewiseLinear::sum(&functions);
+        let pairwise = functions[1..]
+            .iter()
+            .fold(functions[0].clone(), |acc, f| &acc + f);
+        assert!(sum.approx_eq(&pairwise, F64::TOL));
+        assert_eq!(sum.points().len(), 21);
+        assert_eq!(sum.last_slope(), -20.0);
+
+        let bounded = PiecewiseLinear::sum([&functions[0], &functions[3].restrict(-1.0, 3.5)]);
+        assert_eq!(bounded.domain(), [F64::from(-1.0), F64::from(3.5)]);
+        assert_eq!(
+            bounded.points(),
+            points![
+                (-1.0, 0.0),
+                (0.0, 0.0),
+                (1.0, 1.0),
+                (3.0, -1.0),
+                (3.5, -1.0)
+            ]
+        );
+        assert_eq!(PiecewiseLinear::<F64>::sum([]).eval(1.0), 0.0);
+    }
+
+    #[test]
+    fn it_concatenates_functions() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            1.0,
+            points![(0.0, 0.0)],
+        );
+        let g: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [F64::ZERO, F64::INFINITY],
+            0.0,
+            -1.0,
+            points![(0.0, 2.0), (3.0, 2.0)],
+        );
+        let h = PiecewiseLinear::concat(&f, &g, 2.0.into());
+        assert_eq!(h.domain(), [-F64::INFINITY, F64::INFINITY]);
+        assert_eq!(h.points(), points![(0.0, 0.0), (2.0, 2.0), (3.0, 2.0)]);
+        assert_eq!(h.first_slope(), 1.0);
+        assert_eq!(h.last_slope(), -1.0);
+        assert_eq!(h.eval(4.0), 1.0);
+    }
+
+    #[test]
+    fn it_computes_distances() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [F64::ZERO, F64::from(3.0)],
+            0.0,
+            0.0,
+            points![(0.0, 1.0), (2.0, -1.0), (3.0, -1.0)],
+        );
+        let zero: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0)],
+        );
+        assert_eq!(f.l1_distance(&zero), 2.0);
+        assert!(num_traits::abs(f.l2_distance_squared(&zero) - F64::from(5.0 / 3.0)) <= F64::TOL);
+        assert!((f.l2_distance(&zero) - (5.0f64 / 3.0).sqrt()).abs() <= 1e-9);
+        assert_eq!(f.linf_distance(&zero), 1.0);
+
+        let g = f.restrict(0.0, 2.0).shift_y(1.0.into());
+        assert_eq!(f.l1_distance(&g), 2.0);
+        assert_eq!(f.l2_distance_squared(&g), 2.0);
+        let ray: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            1.0,
+            points![(0.0, 0.0)],
+        );
+        assert_eq!(ray.l2_distance_squared(&zero), F64::INFINITY);
+    }
+
+    #[test]
+    fn it_computes_the_running_maximum() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            1.0,
+            points![(0.0, 0.0), (1.0, 2.0), (2.0, 0.0), (4.0, 4.0), (5.0, 3.0)],
+        );
+        let g = f.running_max();
+        assert_eq!(
+            g.points(),
+            points![(0.0, 0.0), (1.0, 2.0), (3.0, 2.0), (4.0, 4.0), (6.0, 4.0)]
+        );
+        assert_eq!(g.last_slope(), 1.0);
+        assert_eq!(g.eval(8.0), 6.0);
+        assert!(g.is_monotone());
+
+        let decreasing: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [F64::ZERO, F64::from(10.0)],
+            -1.0,
+            -1.0,
+            points![(1.0, 1.0)],
+        );
+        let g = decreasing.running_max();
+        assert_eq!(g.points(), points![(0.0, 2.0)]);
+        assert_eq!(g.eval(5.0), 2.0);
+    }
+
+    #[test]
+    fn it_samples_on_a_grid() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            -1.0,
+            points![(0.0, 0.0), (1.0, 1.0)],
+        );
+        let samples = f.sample_grid((-0.5).into(), 0.5.into(), 6);
+        let expected: Vec<F64> = [0.0, 0.0, 0.5, 1.0, 0.5, 0.0]
+            .iter()
+            .map(|&x| F64::from(x))
+            .collect();
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn it_iterates_over_segments() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            1.0,
+            0.0,
+            points![(0.0, 0.0), (2.0, 2.0)],
+        );
+        let segments: Vec<Segment<F64>> = f.segments().collect();
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    start: -F64::INFINITY,
+                    end: F64::ZERO,
+                    start_value: -F64::INFINITY,
+                    slope: F64::ONE,
+                },
+                Segment {
+                    start: F64::ZERO,
+                    end: F64::from(2.0),
+                    start_value: F64::ZERO,
+                    slope: F64::ONE,
+                },
+                Segment {
+                    start: F64::from(2.0),
+                    end: F64::INFINITY,
+                    start_value: F64::from(2.0),
+                    slope: F64::ZERO,
+                },
+            ]
+        );
+        let g = f.restrict(-1.0, 2.0);
+        let starts: Vec<F64> = g.segments().map(|s| s.start).collect();
+        assert_eq!(starts, vec![F64::from(-1.0), F64::ZERO]);
+        assert_eq!(g.segments().next().unwrap().start_value, -1.0);
+    }
+
+    #[test]
+    fn it_interpolates_samples() {
+        let f = PiecewiseLinear::from_samples(
+            [(0.0, 0.0), (1.0, 2.0), (1.0 + 1e-12, 5.0), (2.0, 3.0)]
+                .map(|(x, y)| (F64::from(x), F64::from(y))),
+        )
+        .unwrap();
+        assert_eq!(f.points(), points![(0.0, 0.0), (1.0, 2.0), (2.0, 3.0)]);
+        assert_eq!(f.first_slope(), 2.0);
+        assert_eq!(f.last_slope(), 1.0);
+        assert_eq!(f.eval(-1.0), -2.0);
+
+        let unsorted = [(1.0, 0.0), (0.0, 1.0)].map(|(x, y)| (F64::from(x), F64::from(y)));
+        assert!(PiecewiseLinear::from_samples(unsorted).is_none());
+        assert!(PiecewiseLinear::<F64>::from_samples([]).is_none());
+        let single = PiecewiseLinear::from_samples([(F64::ONE, F64::ONE)]).unwrap();
+        assert_eq!(single.eval(5.0), 1.0);
+    }
+
+    #[test]
+    fn it_extends_to_points() {
+        let mut f: PiecewiseLinear<F64> =
+            PiecewiseLinear::new([F64::ZERO, F64::INFINITY], 0.0, 0.0, points![(0.0, 0.0)]);
+        f.extend_to(1.0.into(), 2.0.into());
+        f.extend_to(2.0.into(), 4.0.into());
+        assert_eq!(f.points(), points![(0.0, 0.0), (2.0, 4.0)]);
+        assert_eq!(f.last_slope(), 2.0);
+
+        f.extend_with_slope_until(3.0.into(), (-1.0).into());
+        assert_eq!(f.points(), points![(0.0, 0.0), (2.0, 4.0), (3.0, 3.0)]);
+        f.extend_with_slope_until(5.0.into(), (-1.0).into());
+        assert_eq!(f.points(), points![(0.0, 0.0), (2.0, 4.0), (5.0, 1.0)]);
+        assert_eq!(f.eval(6.0), 0.0);
+
+        f.extend_to(F64::from(5.0) + F64::TOL / F64::from(2.0), 2.0.into());
+        assert_eq!(f.points(), points![(0.0, 0.0), (2.0, 4.0), (5.0, 2.0)]);
+    }
+
+    #[test]
+    fn it_computes_the_inf_convolution_of_convex_functions() {
+        let rate_latency = |rate: f64, latency: f64| -> PiecewiseLinear<F64> {
+            PiecewiseLinear::new(
+                [F64::ZERO, F64::INFINITY],
+                0.0,
+                rate,
+                vec![
+                    Point(F64::ZERO, F64::ZERO),
+                    Point(F64::from(latency), F64::ZERO),
+                ],
+            )
+        };
+        let h = rate_latency(2.0, 1.0)
+            .inf_convolution(&rate_latency(3.0, 0.5))
+            .unwrap();
+        assert_eq!(h.domain(), [F64::ZERO, F64::INFINITY]);
+        assert_eq!(h.points(), points![(1.5, 0.0)]);
+        assert_eq!(h.last_slope(), 2.0);
+
+        let abs: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -1.0,
+            1.0,
+            points![(0.0, 0.0)],
+        );
+        let h = abs.inf_convolution(&(&abs * F64::from(2.0))).unwrap();
+        assert_eq!(h, abs);
+
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [F64::ZERO, F64::from(3.0)],
+            0.0,
+            0.0,
+            points![(0.0, 1.0), (1.0, 0.0), (3.0, 2.0)],
+        );
+        let g: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [F64::ZERO, F64::from(2.0)],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (2.0, 1.0)],
+        );
+        let h = f.inf_convolution(&g).unwrap();
+        assert_eq!(h.domain(), [F64::ZERO, F64::from(5.0)]);
+        for i in 0..=50 {
+            let x = F64::from(i as f64 / 10.0);
+            let expected = (0..=200)
+                .map(|j| F64::from(j as f64 / 100.0))
+                .filter(|&z| x - z >= F64::ZERO && x - z <= F64::from(3.0))
+                .map(|z| f.eval(x - z) + g.eval(z))
+                .reduce(min)
+                .unwrap();
+            assert!(num_traits::abs(h.eval(x) - expected) <= F64::from(1e-6));
+        }
+
+        assert!(g.inf_convolution(&(-&f)).is_none());
+        let falling: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            -2.0,
+            -2.0,
+            points![(0.0, 0.0)],
+        );
+        assert!(abs.inf_convolution(&falling).is_none());
+    }
+
+    #[test]
+    fn it_convolves_continuous_functions() {
+        let hat: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        );
+        // The convolution of two hats is the cubic B-spline with knots 0, ..., 4.
+        let h = hat.convolve(&hat);
+        let expected = [0.0, 1.0 / 6.0, 2.0 / 3.0, 1.0 / 6.0, 0.0];
+        assert_eq!(h.points().len(), expected.len());
+        for (point, (x, y)) in h.points().iter().zip(expected.iter().enumerate()) {
+            assert_eq!(point.0, x as f64);
+            assert!(num_traits::abs(point.1 - F64::from(*y)) <= F64::from(1e-12));
+        }
+        assert_eq!(h.eval(-1.0), 0.0);
+        assert!(num_traits::abs(h.eval(5.0)) <= F64::from(1e-12));
+    }
+
+    #[test]
+    fn it_finds_extrema_over_intervals() {
+        let f: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            -1.0,
+            points![(0.0, 0.0), (1.0, 3.0), (2.0, 1.0), (3.0, 3.0)],
+        );
+        assert_eq!(f.max_over(0.0, 2.0), 3.0);
+        assert_eq!(f.argmax_over(0.0, 4.0), 1.0);
+        assert_eq!(f.argmax_over(1.5, 4.0), 3.0);
+        assert_eq!(f.min_over(0.5, 2.5), 1.0);
+        assert_eq!(f.max_over(1.5, 1.75), 2.0);
+        assert_eq!(f.min_over(0.0, F64::INFINITY), -F64::INFINITY);
+        assert_eq!(f.max_over(-F64::INFINITY, 0.0), 0.0);
+        assert_eq!(f.argmax_over(-F64::INFINITY, 0.5), 0.5);
+    }
 }
@@ -0,0 +1,296 @@
+use itertools::{EitherOrBoth, Itertools};
+use num_traits::abs;
+use std::ops::Add;
+
+use crate::num::{max, min, Num};
+
+/// The quadratic function `c[0] + c[1]·(x - anchor) + c[2]·(x - anchor)²`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quadratic<T: Num> {
+    pub anchor: T,
+    pub coefficients: [T; 3],
+}
+
+impl<T: Num> Quadratic<T> {
+    pub fn eval(&self, at: T) -> T {
+        let [c0, c1, c2] = self.coefficients;
+        if abs(at) >= T::INFINITY {
+            // Only the dominating term matters (and we must avoid computing ∞ - ∞).
+            return if c2 != T::ZERO {
+                if c2 > T::ZERO {
+                    T::INFINITY
+                } else {
+                    -T::INFINITY
+                }
+            } else if c1 != T::ZERO {
+                c1 * (at - self.anchor)
+            } else {
+                c0
+            };
+        }
+        let d = at - self.anchor;
+        c0 + d * (c1 + d * c2)
+    }
+
+    /// Returns the same function expressed around the new anchor.
+    pub fn reanchor(&self, anchor: T) -> Self {
+        let [c0, c1, c2] = self.coefficients;
+        let d = anchor - self.anchor;
+        let two = T::ONE + T::ONE;
+        Quadratic {
+            anchor,
+            coefficients: [c0 + d * (c1 + d * c2), c1 + two * c2 * d, c2],
+        }
+    }
+
+    /// Returns the integral from `from` to `to`.
+    /// If a bound is infinite, the integral is infinite unless the function vanishes identically.
+    pub fn integrate(&self, from: T, to: T) -> T {
+        if abs(from) >= T::INFINITY || abs(to) >= T::INFINITY {
+            if self.coefficients.iter().all(|&c| c == T::ZERO) {
+                return T::ZERO;
+            }
+            let at_infinity = if abs(to) >= T::INFINITY {
+                self.eval(to)
+            } else {
+                -self.eval(from)
+            };
+            return if at_infinity >= T::ZERO {
+                T::INFINITY
+            } else {
+                -T::INFINITY
+            };
+        }
+        let [c0, c1, c2] = self.coefficients;
+        let two = T::ONE + T::ONE;
+        let three = two + T::ONE;
+        let (u, v) = (from - self.anchor, to - self.anchor);
+        c0 * (v - u) + c1 * (v * v - u * u) / two + c2 * (v * v * v - u * u * u) / three
+    }
+}
+
+impl<T: Num> Add for Quadratic<T> {
+    type Output = Quadratic<T>;
+
+    fn add(self, rhs: Quadratic<T>) -> Self::Output {
+        let rhs = rhs.reanchor(self.anchor);
+        Quadratic {
+            anchor: self.anchor,
+            coefficients: [
+                self.coefficients[0] + rhs.coefficients[0],
+                self.coefficients[1] + rhs.coefficients[1],
+                self.coefficients[2] + rhs.coefficients[2],
+            ],
+        }
+    }
+}
+
+/// A piecewise quadratic function, where `pieces[i]` is valid between `breakpoints[i - 1]`
+/// (or `domain[0]`) and `breakpoints[i]` (or `domain[1]`).
+/// It typically arises as the integral of a `PiecewiseLinear` function.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiecewiseQuadratic<T: Num> {
+    domain: [T; 2],
+    breakpoints: Vec<T>,
+    pieces: Vec<Quadratic<T>>,
+}
+
+impl<T: Num> PiecewiseQuadratic<T> {
+    pub fn new(domain: [impl Into<T>; 2], breakpoints: Vec<T>, pieces: Vec<Quadratic<T>>) -> Self {
+        let domain: [T; 2] = domain.map(|x| x.into());
+        debug_assert!(domain[0] <= domain[1], "The domain is not well defined.");
+        debug_assert!(
+            pieces.len() == breakpoints.len() + 1,
+            "There must be exactly one piece more than breakpoints."
+        );
+        debug_assert!(
+            breakpoints.windows(2).all(|w| w[0] < w[1]),
+            "The breakpoints are not sorted."
+        );
+        debug_assert!(
+            breakpoints
+                .iter()
+                .all(|&b| domain[0] <= b && b <= domain[1]),
+            "The breakpoints are not in the domain."
+        );
+        debug_assert!(
+            pieces.iter().all(|p| abs(p.anchor) < T::INFINITY),
+            "The anchors must be finite."
+        );
+        Self {
+            domain,
+            breakpoints,
+            pieces,
+        }
+    }
+
+    pub fn domain(&self) -> [T; 2] {
+        self.domain
+    }
+
+    pub fn breakpoints(&self) -> &[T] {
+        &self.breakpoints
+    }
+
+    pub fn pieces(&self) -> &[Quadratic<T>] {
+        &self.pieces
+    }
+
+    /// Returns the index of the piece valid at `at`, where breakpoints belong to the right piece.
+    fn piece_index(&self, at: T) -> usize {
+        self.breakpoints.partition_point(|&b| b <= at)
+    }
+
+    pub fn eval(&self, at: impl Into<T>) -> T {
+        let at = at.into();
+        self.pieces[self.piece_index(at)].eval(at)
+    }
+
+    /// Returns the integral of the function from `from` to `to` within its domain.
+    pub fn integrate(&self, from: impl Into<T>, to: impl Into<T>) -> T {
+        let (from, to): (T, T) = (from.into(), to.into());
+        debug_assert!(from <= to, "The bounds are not ordered.");
+        debug_assert!(
+            self.domain[0] <= from && to <= self.domain[1],
+            "The bounds are not in the domain."
+        );
+        let first = self.piece_index(from);
+        let last = self.piece_index(to);
+        (first..=last)
+            .map(|i| {
+                let lower = if i == first {
+                    from
+                } else {
+                    self.breakpoints[i - 1]
+                };
+                let upper = if i == last { to } else { self.breakpoints[i] };
+                if lower == upper {
+                    T::ZERO
+                } else {
+                    self.pieces[i].integrate(lower, upper)
+                }
+            })
+            .sum()
+    }
+}
+
+impl<T: Num> Add<&PiecewiseQuadratic<T>> for &PiecewiseQuadratic<T> {
+    type Output = PiecewiseQuadratic<T>;
+
+    /// Adds both functions on the intersection of their domains.
+    fn add(self, rhs: &PiecewiseQuadratic<T>) -> Self::Output {
+        let domain = [
+            max(self.domain[0], rhs.domain[0]),
+            min(self.domain[1], rhs.domain[1]),
+        ];
+        let inner = |bs: &[T]| -> (usize, usize) {
+            (
+                bs.partition_point(|&b| b <= domain[0]),
+                bs.partition_point(|&b| b < domain[1]),
+            )
+        };
+        let (lhs_start, lhs_end) = inner(&self.breakpoints);
+        let (rhs_start, rhs_end) = inner(&rhs.breakpoints);
+
+        let (mut i, mut j) = (lhs_start, rhs_start);
+        let mut breakpoints: Vec<T> = Vec::new();
+        let mut pieces: Vec<Quadratic<T>> = vec![self.pieces[i] + rhs.pieces[j]];
+        for b in self.breakpoints[lhs_start..lhs_end]
+            .iter()
+            .merge_join_by(rhs.breakpoints[rhs_start..rhs_end].iter(), |x, y| {
+                x.total_cmp(y)
+            })
+        {
+            let at = match b {
+                EitherOrBoth::Left(&b) => {
+                    i += 1;
+                    b
+                }
+                EitherOrBoth::Right(&b) => {
+                    j += 1;
+                    b
+                }
+                EitherOrBoth::Both(&b, _) => {
+                    i += 1;
+                    j += 1;
+                    b
+                }
+            };
+            breakpoints.push(at);
+            pieces.push(self.pieces[i] + rhs.pieces[j]);
+        }
+        PiecewiseQuadratic::new(domain, breakpoints, pieces)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float::F64,
+        num::{Num, Tolerance},
+        piecewise_linear::PiecewiseLinear,
+        points,
+    };
+
+    use super::{PiecewiseQuadratic, Quadratic};
+
+    fn queue() -> PiecewiseLinear<F64> {
+        PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            0.0,
+            points![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        )
+    }
+
+    #[test]
+    fn it_integrates_a_piecewise_linear_function() {
+        let integral = queue().integral();
+        assert_eq!(integral.eval(-5.0), 0.0);
+        assert_eq!(integral.eval(0.5), 0.125);
+        assert_eq!(integral.eval(1.0), 0.5);
+        assert_eq!(integral.eval(1.5), 0.875);
+        assert_eq!(integral.eval(2.0), 1.0);
+        assert_eq!(integral.eval(F64::INFINITY), 1.0);
+
+        let unbounded: PiecewiseLinear<F64> = PiecewiseLinear::new(
+            [-F64::INFINITY, F64::INFINITY],
+            0.0,
+            1.0,
+            points![(0.0, 1.0)],
+        );
+        assert_eq!(unbounded.integral().eval(2.0), 4.0);
+        assert_eq!(unbounded.integral().eval(F64::INFINITY), F64::INFINITY);
+    }
+
+    #[test]
+    fn it_integrates_piecewise_quadratic_functions() {
+        let integral = queue().integral();
+        assert!(num_traits::abs(integral.integrate(0.0, 2.0) - F64::ONE) <= F64::TOL);
+        assert_eq!(integral.integrate(-F64::INFINITY, 0.0), 0.0);
+        assert_eq!(integral.integrate(0.0, F64::INFINITY), F64::INFINITY);
+    }
+
+    #[test]
+    fn it_adds_piecewise_quadratic_functions() {
+        let f: PiecewiseQuadratic<F64> = PiecewiseQuadratic::new(
+            [-F64::INFINITY, F64::INFINITY],
+            vec![1.0.into()],
+            vec![
+                Quadratic {
+                    anchor: 0.0.into(),
+                    coefficients: [0.0.into(), 0.0.into(), 1.0.into()],
+                },
+                Quadratic {
+                    anchor: 1.0.into(),
+                    coefficients: [1.0.into(), 2.0.into(), 0.0.into()],
+                },
+            ],
+        );
+        let sum = &f + &queue().integral();
+        assert_eq!(sum.breakpoints().len(), 3);
+        assert_eq!(sum.eval(-1.0), 1.0);
+        assert_eq!(sum.eval(1.0), 1.5);
+        assert_eq!(sum.eval(3.0), 6.0);
+    }
+}
@@ -1,8 +1,4 @@
-use std::{
-    cmp::{max, min},
-    iter::once,
-    path::Path,
-};
+use std::{iter::once, path::Path};
 
 use plotters::{
     prelude::{BitMapBackend, ChartBuilder, IntoDrawingArea, LabelAreaPosition},
@@ -10,9 +6,12 @@ use plotters::{
     style::{ShapeStyle, RED, WHITE},
 };
 
-use crate::{num::Num, piecewise_linear::PiecewiseLinear};
+use crate::{
+    num::{max, min, Num, ToF64},
+    piecewise_linear::PiecewiseLinear,
+};
 
-pub fn plot<T: Num, P: AsRef<Path> + ?Sized>(pwl: PiecewiseLinear<T>, path: &P) {
+pub fn plot<T: Num + ToF64, P: AsRef<Path> + ?Sized>(pwl: PiecewiseLinear<T>, path: &P) {
     let drawing_area = BitMapBackend::new(path, (1024, 768)).into_drawing_area();
     drawing_area.fill(&WHITE).unwrap();
 
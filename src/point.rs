@@ -1,5 +1,7 @@
 use std::fmt::{Display, Formatter};
 
+use smallvec::{Array, SmallVec};
+
 use crate::num::Num;
 
 #[derive(Debug, Clone)]
@@ -26,3 +28,110 @@ macro_rules! points {
         }
     };
 }
+
+/// The storage of the points of a piecewise function, sorted by x-coordinate and never empty.
+/// Functions store their points in a `Vec` by default; functions with millions of breakpoints
+/// can use `ChunkedPoints` instead.
+pub trait PointStorage<T: Num> {
+    fn from_points(points: impl IntoIterator<Item = Point<T>>) -> Self;
+
+    fn num_points(&self) -> usize;
+
+    /// Returns the point at `index`, which must be in range.
+    fn point(&self, index: usize) -> &Point<T>;
+
+    fn last_point_mut(&mut self) -> &mut Point<T>;
+
+    fn push_point(&mut self, point: Point<T>);
+
+    /// Like `binary_search` on a slice of the points: the index of the point at `at`, or the index
+    /// where a point at `at` would be inserted.
+    fn rank(&self, at: &T) -> Result<usize, usize>;
+
+    fn iter_points<'a>(&'a self) -> impl Iterator<Item = &'a Point<T>>
+    where
+        T: 'a;
+
+    /// Returns the x-coordinate from which on the function can be evaluated if earlier points were
+    /// dropped, and `None` if all points are stored.
+    #[inline]
+    fn valid_from(&self) -> Option<T> {
+        None
+    }
+}
+
+impl<T: Num> PointStorage<T> for Vec<Point<T>> {
+    fn from_points(points: impl IntoIterator<Item = Point<T>>) -> Self {
+        points.into_iter().collect()
+    }
+
+    #[inline]
+    fn num_points(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn point(&self, index: usize) -> &Point<T> {
+        &self[index]
+    }
+
+    #[inline]
+    fn last_point_mut(&mut self) -> &mut Point<T> {
+        self.last_mut().unwrap()
+    }
+
+    #[inline]
+    fn push_point(&mut self, point: Point<T>) {
+        self.push(point);
+    }
+
+    #[inline]
+    fn rank(&self, at: &T) -> Result<usize, usize> {
+        self.binary_search_by(|p| p.0.total_cmp(at))
+    }
+
+    fn iter_points<'a>(&'a self) -> impl Iterator<Item = &'a Point<T>>
+    where
+        T: 'a,
+    {
+        self.iter()
+    }
+}
+
+impl<T: Num, A: Array<Item = Point<T>>> PointStorage<T> for SmallVec<A> {
+    fn from_points(points: impl IntoIterator<Item = Point<T>>) -> Self {
+        points.into_iter().collect()
+    }
+
+    #[inline]
+    fn num_points(&self) -> usize {
+        self.len()
+    }
+
+    #[inline]
+    fn point(&self, index: usize) -> &Point<T> {
+        &self[index]
+    }
+
+    #[inline]
+    fn last_point_mut(&mut self) -> &mut Point<T> {
+        self.last_mut().unwrap()
+    }
+
+    #[inline]
+    fn push_point(&mut self, point: Point<T>) {
+        self.push(point);
+    }
+
+    #[inline]
+    fn rank(&self, at: &T) -> Result<usize, usize> {
+        self.binary_search_by(|p| p.0.total_cmp(at))
+    }
+
+    fn iter_points<'a>(&'a self) -> impl Iterator<Item = &'a Point<T>>
+    where
+        T: 'a,
+    {
+        self.iter()
+    }
+}
@@ -0,0 +1,163 @@
+use crate::{
+    dynamic_flow::DynamicFlow,
+    fixed_point::{run_fixed_point_iteration, FixedPointConfig},
+    instance::Instance,
+    num::{Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TotalTravelTimes<T: Num> {
+    pub total: T,
+    pub by_commodity: Vec<T>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison<T: Num> {
+    pub equilibrium: TotalTravelTimes<T>,
+    pub reference: TotalTravelTimes<T>,
+    /// The total travel time of the equilibrium divided by the one of the reference flow.
+    pub ratio: T,
+    pub ratio_by_commodity: Vec<T>,
+}
+
+/// Computes the total travel time of all particles of a completely loaded flow.
+/// The travel time spent on an edge equals `∫ t·f⁻(t) dt - ∫ t·f⁺(t) dt` if all particles leave
+/// the edge eventually, which allows for an exact computation from the rate functions.
+pub fn total_travel_times<T: Num>(
+    instance: &Instance<T>,
+    flow: &DynamicFlow<T>,
+) -> TotalTravelTimes<T> {
+    let commodity_by_path = instance.commodity_by_path();
+    let mut by_commodity = vec![T::ZERO; instance.commodities.len()];
+    for (inflow, outflow) in flow.inflow().iter().zip(flow.outflow().iter()) {
+        for (path, f) in outflow.function_by_comm() {
+            by_commodity[commodity_by_path[*path]] += first_moment(f);
+        }
+        for (path, f) in inflow.function_by_comm() {
+            by_commodity[commodity_by_path[*path]] -= first_moment(f);
+        }
+    }
+    TotalTravelTimes {
+        total: by_commodity.iter().copied().sum(),
+        by_commodity,
+    }
+}
+
+pub fn compare<T: Num>(
+    instance: &Instance<T>,
+    equilibrium: &DynamicFlow<T>,
+    reference: &DynamicFlow<T>,
+) -> Comparison<T> {
+    let equilibrium = total_travel_times(instance, equilibrium);
+    let reference = total_travel_times(instance, reference);
+    Comparison {
+        ratio: ratio(equilibrium.total, reference.total),
+        ratio_by_commodity: equilibrium
+            .by_commodity
+            .iter()
+            .zip(reference.by_commodity.iter())
+            .map(|(&eq, &re)| ratio(eq, re))
+            .collect(),
+        equilibrium,
+        reference,
+    }
+}
+
+/// Computes an (approximate) equilibrium using the fixed-point iteration and compares it with the
+/// loading of the prescribed path inflows (e.g. a system optimum computed elsewhere).
+pub fn price_of_anarchy<T: Num + Tolerance>(
+    instance: &Instance<T>,
+    config: &FixedPointConfig<T>,
+    reference_path_inflows: &[Vec<PiecewiseConstant<T>>],
+) -> Comparison<T> {
+    let equilibrium = run_fixed_point_iteration(instance, config).flow;
+    let reference = instance.load(reference_path_inflows);
+    compare(instance, &equilibrium, &reference)
+}
+
+fn ratio<T: Num>(numerator: T, denominator: T) -> T {
+    if denominator != T::ZERO {
+        numerator / denominator
+    } else if numerator == T::ZERO {
+        T::ONE
+    } else {
+        T::INFINITY
+    }
+}
+
+/// Returns `∫ t·f(t) dt` over the domain of `f`.
+fn first_moment<T: Num>(f: &PiecewiseConstant<T>) -> T {
+    let two = T::ONE + T::ONE;
+    let points = f.points();
+    let [domain_start, domain_end] = f.domain();
+    let mut moment = segment_moment(domain_start, points[0].0, points[0].1, two);
+    for w in points.windows(2) {
+        moment += segment_moment(w[0].0, w[1].0, w[0].1, two);
+    }
+    let last = points.last().unwrap();
+    moment + segment_moment(last.0, domain_end, last.1, two)
+}
+
+fn segment_moment<T: Num>(start: T, end: T, value: T, two: T) -> T {
+    if value == T::ZERO || start >= end {
+        T::ZERO
+    } else if start <= -T::INFINITY || end >= T::INFINITY {
+        value.signum() * T::INFINITY
+    } else {
+        value * (end * end - start * start) / two
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::two_parallel_edges,
+        fixed_point::{FixedPointConfig, UpdateRule},
+        float::F64,
+        num::Num,
+        piecewise_constant::PiecewiseConstant,
+        points,
+    };
+
+    use super::{compare, price_of_anarchy, total_travel_times};
+
+    #[test]
+    fn it_computes_total_travel_times_exactly() {
+        let instance = two_parallel_edges(F64::ONE).instance;
+        let all_on_first_path = vec![vec![
+            instance.commodities[0].inflow.clone(),
+            PiecewiseConstant::new([-F64::INFINITY, F64::INFINITY], points![(0.0, 0.0)]),
+        ]];
+        let flow = instance.load(&all_on_first_path);
+        let travel_times = total_travel_times(&instance, &flow);
+        assert_eq!(travel_times.total, 6.0);
+        assert_eq!(travel_times.by_commodity, vec![F64::from(6.0)]);
+
+        let comparison = compare(&instance, &flow, &flow);
+        assert_eq!(comparison.ratio, 1.0);
+        assert_eq!(comparison.ratio_by_commodity, vec![F64::ONE]);
+    }
+
+    #[test]
+    fn it_compares_the_equilibrium_with_a_prescribed_flow() {
+        let instance = two_parallel_edges(F64::ONE).instance;
+        let all_on_first_path = vec![vec![
+            instance.commodities[0].inflow.clone(),
+            PiecewiseConstant::new([-F64::INFINITY, F64::INFINITY], points![(0.0, 0.0)]),
+        ]];
+        let comparison = price_of_anarchy(
+            &instance,
+            &FixedPointConfig {
+                horizon: 1.0.into(),
+                interval_length: 0.125.into(),
+                max_iterations: 20,
+                target_relative_gap: F64::ZERO,
+                update_rule: UpdateRule::Msa,
+            },
+            &all_on_first_path,
+        );
+        assert_eq!(comparison.reference.total, 6.0);
+        assert!(comparison.ratio < F64::ONE);
+    }
+}
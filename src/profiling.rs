@@ -0,0 +1,48 @@
+use std::ops::AddAssign;
+
+/// The number of breakpoints created per family of functions of a flow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PointCounts {
+    pub inflow: usize,
+    pub outflow: usize,
+    pub queues: usize,
+}
+
+impl AddAssign for PointCounts {
+    fn add_assign(&mut self, rhs: Self) {
+        self.inflow += rhs.inflow;
+        self.outflow += rhs.outflow;
+        self.queues += rhs.queues;
+    }
+}
+
+/// Counts the work done while building a flow, see `DynamicFlow::counters`.
+/// The counters are only maintained with the `profiling` feature.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProfilingCounters {
+    /// The number of calls to `DynamicFlow::extend`.
+    pub phases: usize,
+    /// The number of edges whose inflow changed in some phase.
+    pub edge_extensions: usize,
+    /// The number of outflow changes taken from the event queue.
+    pub outflow_changes_processed: usize,
+    /// The number of queue depletions taken from the depletion queue.
+    pub depletions_processed: usize,
+    pub points_created: PointCounts,
+    /// The number of rate maps newly allocated and reused by the rate pool of the flow.
+    /// Maps handled by the per-thread pools of parallel phases are not included.
+    pub maps_allocated: usize,
+    pub maps_reused: usize,
+}
+
+impl AddAssign for ProfilingCounters {
+    fn add_assign(&mut self, rhs: Self) {
+        self.phases += rhs.phases;
+        self.edge_extensions += rhs.edge_extensions;
+        self.outflow_changes_processed += rhs.outflow_changes_processed;
+        self.depletions_processed += rhs.depletions_processed;
+        self.points_created += rhs.points_created;
+        self.maps_allocated += rhs.maps_allocated;
+        self.maps_reused += rhs.maps_reused;
+    }
+}
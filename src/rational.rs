@@ -0,0 +1,864 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Rem, RemAssign, Sub, SubAssign},
+    sync::{LazyLock, RwLock},
+};
+
+use num_rational::{BigRational, Ratio};
+use num_traits::{
+    float::FloatCore, CheckedAdd, CheckedDiv, CheckedMul, CheckedSub, Num as NumTraitsNum, One,
+    Signed, ToPrimitive, Zero,
+};
+
+use crate::num::{Num, ToF64, Tolerance};
+
+/// An exact rational number with `i128` numerator and denominator, extended by ±∞.
+///
+/// `Num` requires `Copy` and constant values, which rules out arbitrary precision integers.
+/// Exactness is kept as long as all numerators and denominators stay within `±(2^127 - 1)`.
+/// Results that leave this range become `Overflow`, which like a float NaN is ordered above all
+/// other values and propagates through all operations, so computations end instead of panicking
+/// and the overflow can be detected afterwards with `is_nan`.
+///
+/// Converted floats have power of two denominators, e.g. `0.1` has the denominator `2^55`, so
+/// products of three such numbers already overflow. For inputs that are not dyadic, prefer exact
+/// fractions created by `new`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Rational128 {
+    NegInfinity,
+    Finite(Ratio<i128>),
+    Infinity,
+    /// The result of an operation whose exact value is out of range.
+    Overflow,
+}
+
+impl Rational128 {
+    /// Returns the fraction `numer / denom`.
+    pub fn new(numer: i128, denom: i128) -> Self {
+        Rational128::Finite(Ratio::new(numer, denom))
+    }
+
+    #[inline]
+    fn finite(self, op: &str) -> Ratio<i128> {
+        match self {
+            Rational128::Finite(x) => x,
+            _ => panic!("The {} of an infinite rational is not defined.", op),
+        }
+    }
+
+    /// Wraps the result of a checked operation, which is `None` if it overflowed.
+    #[inline]
+    fn checked(result: Option<Ratio<i128>>) -> Self {
+        result.map_or(Rational128::Overflow, Rational128::Finite)
+    }
+}
+
+impl From<i64> for Rational128 {
+    #[inline]
+    fn from(val: i64) -> Self {
+        Rational128::Finite(Ratio::from_integer(val as i128))
+    }
+}
+
+impl From<f64> for Rational128 {
+    /// Converts the float exactly, returning `Overflow` if its binary exponent is out of range
+    /// and for NaN.
+    fn from(val: f64) -> Self {
+        if val == f64::INFINITY {
+            return Rational128::Infinity;
+        } else if val == f64::NEG_INFINITY {
+            return Rational128::NegInfinity;
+        } else if val.is_nan() {
+            return Rational128::Overflow;
+        } else if val == 0.0 {
+            return Rational128::ZERO;
+        }
+        let (mut mantissa, mut exponent, sign) = FloatCore::integer_decode(val);
+        while exponent < 0 && mantissa % 2 == 0 && mantissa > 0 {
+            mantissa /= 2;
+            exponent += 1;
+        }
+        let numer = sign as i128 * mantissa as i128;
+        let Some(scale) = 2i128.checked_pow(exponent.unsigned_abs() as u32) else {
+            return Rational128::Overflow;
+        };
+        if exponent >= 0 {
+            Rational128::checked(numer.checked_mul(scale).map(Ratio::from_integer))
+        } else {
+            Rational128::new(numer, scale)
+        }
+    }
+}
+
+impl Rem for Rational128 {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        if self.is_nan() || rhs.is_nan() {
+            return Rational128::Overflow;
+        }
+        Rational128::Finite(self.finite("remainder") % rhs.finite("remainder"))
+    }
+}
+
+impl Div for Rational128 {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            _ if self.is_nan() || rhs.is_nan() => Rational128::Overflow,
+            (Rational128::Finite(x), Rational128::Finite(y)) => {
+                assert!(!y.is_zero(), "Division by zero.");
+                Rational128::checked(x.checked_div(&y))
+            }
+            (Rational128::Finite(_), _) => Rational128::ZERO,
+            (_, Rational128::Finite(y)) if y.is_positive() => self,
+            (_, Rational128::Finite(y)) if y.is_negative() => -self,
+            _ => panic!("The quotient {} / {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Sub for Rational128 {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            _ if self.is_nan() || rhs.is_nan() => Rational128::Overflow,
+            (Rational128::Finite(x), Rational128::Finite(y)) => {
+                Rational128::checked(x.checked_sub(&y))
+            }
+            _ => self + -rhs,
+        }
+    }
+}
+
+impl Mul for Rational128 {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            _ if self.is_nan() || rhs.is_nan() => Rational128::Overflow,
+            (Rational128::Finite(x), Rational128::Finite(y)) => {
+                Rational128::checked(x.checked_mul(&y))
+            }
+            // We use the convention 0·∞ = 0.
+            _ if self.is_zero() || rhs.is_zero() => Rational128::ZERO,
+            _ if self.is_positive() == rhs.is_positive() => Rational128::Infinity,
+            _ => Rational128::NegInfinity,
+        }
+    }
+}
+
+impl Add for Rational128 {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self, rhs) {
+            _ if self.is_nan() || rhs.is_nan() => Rational128::Overflow,
+            (Rational128::Finite(x), Rational128::Finite(y)) => {
+                Rational128::checked(x.checked_add(&y))
+            }
+            (Rational128::Finite(_), _) => rhs,
+            (_, Rational128::Finite(_)) => self,
+            _ if self == rhs => self,
+            _ => panic!("The sum {} + {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Zero for Rational128 {
+    #[inline]
+    fn zero() -> Self {
+        Rational128::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        *self == Rational128::ZERO
+    }
+}
+
+impl One for Rational128 {
+    #[inline]
+    fn one() -> Self {
+        Rational128::ONE
+    }
+}
+
+impl Display for Rational128 {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rational128::NegInfinity => write!(f, "-inf"),
+            Rational128::Finite(x) => Display::fmt(x, f),
+            Rational128::Infinity => write!(f, "inf"),
+            Rational128::Overflow => write!(f, "overflow"),
+        }
+    }
+}
+
+impl Neg for Rational128 {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        match self {
+            Rational128::NegInfinity => Rational128::Infinity,
+            Rational128::Finite(x) => Rational128::Finite(-x),
+            Rational128::Infinity => Rational128::NegInfinity,
+            Rational128::Overflow => Rational128::Overflow,
+        }
+    }
+}
+
+impl std::iter::Sum for Rational128 {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Rational128::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl NumTraitsNum for Rational128 {
+    type FromStrRadixErr = <Ratio<i128> as NumTraitsNum>::FromStrRadixErr;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        Ratio::from_str_radix(str, radix).map(Rational128::Finite)
+    }
+}
+
+impl Signed for Rational128 {
+    #[inline]
+    fn abs(&self) -> Self {
+        if self.is_negative() {
+            -*self
+        } else {
+            *self
+        }
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Rational128::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        if self.is_nan() {
+            Rational128::Overflow
+        } else if self.is_positive() {
+            Rational128::ONE
+        } else if self.is_negative() {
+            -Rational128::ONE
+        } else {
+            Rational128::ZERO
+        }
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        *self > Rational128::ZERO
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        *self < Rational128::ZERO
+    }
+}
+
+impl AddAssign for Rational128 {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Rational128 {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Rational128 {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl RemAssign for Rational128 {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl DivAssign for Rational128 {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Num for Rational128 {
+    const ZERO: Self = Rational128::Finite(Ratio::new_raw(0, 1));
+    const ONE: Self = Rational128::Finite(Ratio::new_raw(1, 1));
+    const INFINITY: Self = Rational128::Infinity;
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.cmp(other)
+    }
+
+    #[inline]
+    fn is_nan(self) -> bool {
+        self == Rational128::Overflow
+    }
+}
+
+impl Tolerance for Rational128 {}
+
+impl ToF64 for Rational128 {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        match self {
+            Rational128::NegInfinity => f64::NEG_INFINITY,
+            Rational128::Finite(x) => *x.numer() as f64 / *x.denom() as f64,
+            Rational128::Infinity => f64::INFINITY,
+            Rational128::Overflow => f64::NAN,
+        }
+    }
+}
+
+/// An exact rational number of arbitrary precision, extended by ±∞.
+///
+/// `Num` requires `Copy`, so a `Rational` is a handle to a `BigRational` in a global table, which
+/// keeps every distinct value until the program ends. Equal values share a handle, so handles are
+/// compared for equality and hashed directly. Unlike `Rational128`, exact loadings never
+/// overflow, which makes them the ground truth for the float backends, but the table grows with
+/// every new value, so this backend suits small and medium instances.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Rational(u32);
+
+/// The finite values of all handles, where the handle `i` refers to `values[i - 2]`.
+/// The handles 0 and 1 are -∞ and ∞.
+struct RationalTable {
+    values: Vec<BigRational>,
+    handles: HashMap<BigRational, u32>,
+}
+
+static TABLE: LazyLock<RwLock<RationalTable>> = LazyLock::new(|| {
+    let mut table = RationalTable {
+        values: Vec::new(),
+        handles: HashMap::new(),
+    };
+    // The handles of `Rational::ZERO` and `Rational::ONE`
+    table.insert(BigRational::zero());
+    table.insert(BigRational::one());
+    RwLock::new(table)
+});
+
+impl RationalTable {
+    fn insert(&mut self, value: BigRational) -> u32 {
+        let handle = u32::try_from(self.values.len() + 2).expect("Too many distinct rationals.");
+        self.values.push(value.clone());
+        self.handles.insert(value, handle);
+        handle
+    }
+
+    #[inline]
+    fn get(&self, x: Rational) -> &BigRational {
+        &self.values[x.0 as usize - 2]
+    }
+}
+
+impl Rational {
+    const NEG_INFINITY: Rational = Rational(0);
+
+    /// Returns the fraction `numer / denom`.
+    pub fn new(numer: i64, denom: i64) -> Self {
+        Rational::from_big(BigRational::new(numer.into(), denom.into()))
+    }
+
+    /// Returns the handle of `value`.
+    pub fn from_big(value: BigRational) -> Self {
+        if let Some(&handle) = TABLE.read().unwrap().handles.get(&value) {
+            return Rational(handle);
+        }
+        let mut table = TABLE.write().unwrap();
+        // Another thread may have inserted the value in the meantime.
+        match table.handles.get(&value) {
+            Some(&handle) => Rational(handle),
+            None => Rational(table.insert(value)),
+        }
+    }
+
+    /// Returns the value, or `None` for ±∞.
+    pub fn to_big(self) -> Option<BigRational> {
+        self.is_finite()
+            .then(|| TABLE.read().unwrap().get(self).clone())
+    }
+
+    #[inline]
+    fn is_finite(self) -> bool {
+        self.0 >= 2
+    }
+
+    /// Applies `op` to two finite numbers.
+    #[inline]
+    fn finite_op(
+        self,
+        rhs: Self,
+        op: impl FnOnce(&BigRational, &BigRational) -> BigRational,
+    ) -> Self {
+        let value = {
+            let table = TABLE.read().unwrap();
+            op(table.get(self), table.get(rhs))
+        };
+        Rational::from_big(value)
+    }
+}
+
+impl From<i64> for Rational {
+    #[inline]
+    fn from(val: i64) -> Self {
+        Rational::from_big(BigRational::from_integer(val.into()))
+    }
+}
+
+impl From<f64> for Rational {
+    /// Converts the float exactly.
+    fn from(val: f64) -> Self {
+        if val == f64::INFINITY {
+            Rational::INFINITY
+        } else if val == f64::NEG_INFINITY {
+            Rational::NEG_INFINITY
+        } else {
+            Rational::from_big(BigRational::from_float(val).expect("NaN is not a rational."))
+        }
+    }
+}
+
+impl Rem for Rational {
+    type Output = Self;
+
+    #[inline]
+    fn rem(self, rhs: Self) -> Self::Output {
+        assert!(
+            self.is_finite() && rhs.is_finite(),
+            "The remainder of an infinite rational is not defined."
+        );
+        self.finite_op(rhs, |x, y| x % y)
+    }
+}
+
+impl Div for Rational {
+    type Output = Self;
+
+    #[inline]
+    fn div(self, rhs: Self) -> Self::Output {
+        match (self.is_finite(), rhs.is_finite()) {
+            (true, true) => {
+                assert!(!rhs.is_zero(), "Division by zero.");
+                self.finite_op(rhs, |x, y| x / y)
+            }
+            (true, false) => Rational::ZERO,
+            (false, true) if rhs.is_positive() => self,
+            (false, true) if rhs.is_negative() => -self,
+            _ => panic!("The quotient {} / {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl Sub for Rational {
+    type Output = Self;
+
+    #[inline]
+    fn sub(self, rhs: Self) -> Self::Output {
+        if self.is_finite() && rhs.is_finite() {
+            self.finite_op(rhs, |x, y| x - y)
+        } else {
+            self + -rhs
+        }
+    }
+}
+
+impl Mul for Rational {
+    type Output = Self;
+
+    #[inline]
+    fn mul(self, rhs: Self) -> Self::Output {
+        match (self.is_finite(), rhs.is_finite()) {
+            (true, true) => self.finite_op(rhs, |x, y| x * y),
+            // We use the convention 0·∞ = 0.
+            _ if self.is_zero() || rhs.is_zero() => Rational::ZERO,
+            _ if self.is_positive() == rhs.is_positive() => Rational::INFINITY,
+            _ => Rational::NEG_INFINITY,
+        }
+    }
+}
+
+impl Add for Rational {
+    type Output = Self;
+
+    #[inline]
+    fn add(self, rhs: Self) -> Self::Output {
+        match (self.is_finite(), rhs.is_finite()) {
+            (true, true) => self.finite_op(rhs, |x, y| x + y),
+            (true, false) => rhs,
+            (false, true) => self,
+            _ if self == rhs => self,
+            _ => panic!("The sum {} + {} is not defined.", self, rhs),
+        }
+    }
+}
+
+impl PartialOrd for Rational {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.total_cmp(other))
+    }
+}
+
+impl Zero for Rational {
+    #[inline]
+    fn zero() -> Self {
+        Rational::ZERO
+    }
+
+    #[inline]
+    fn is_zero(&self) -> bool {
+        *self == Rational::ZERO
+    }
+}
+
+impl One for Rational {
+    #[inline]
+    fn one() -> Self {
+        Rational::ONE
+    }
+}
+
+impl Display for Rational {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Rational::NEG_INFINITY => write!(f, "-inf"),
+            Rational::INFINITY => write!(f, "inf"),
+            _ => Display::fmt(TABLE.read().unwrap().get(*self), f),
+        }
+    }
+}
+
+impl Debug for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Rational({})", self)
+    }
+}
+
+impl Neg for Rational {
+    type Output = Self;
+
+    #[inline]
+    fn neg(self) -> Self::Output {
+        match self {
+            Rational::NEG_INFINITY => Rational::INFINITY,
+            Rational::INFINITY => Rational::NEG_INFINITY,
+            _ => Rational::ZERO - self,
+        }
+    }
+}
+
+impl std::iter::Sum for Rational {
+    #[inline]
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Rational::ZERO, |acc, x| acc + x)
+    }
+}
+
+impl NumTraitsNum for Rational {
+    type FromStrRadixErr = <BigRational as NumTraitsNum>::FromStrRadixErr;
+
+    #[inline]
+    fn from_str_radix(str: &str, radix: u32) -> Result<Self, Self::FromStrRadixErr> {
+        BigRational::from_str_radix(str, radix).map(Rational::from_big)
+    }
+}
+
+impl Signed for Rational {
+    #[inline]
+    fn abs(&self) -> Self {
+        if self.is_negative() {
+            -*self
+        } else {
+            *self
+        }
+    }
+
+    #[inline]
+    fn abs_sub(&self, other: &Self) -> Self {
+        if self <= other {
+            Rational::ZERO
+        } else {
+            *self - *other
+        }
+    }
+
+    #[inline]
+    fn signum(&self) -> Self {
+        if self.is_positive() {
+            Rational::ONE
+        } else if self.is_negative() {
+            -Rational::ONE
+        } else {
+            Rational::ZERO
+        }
+    }
+
+    #[inline]
+    fn is_positive(&self) -> bool {
+        *self > Rational::ZERO
+    }
+
+    #[inline]
+    fn is_negative(&self) -> bool {
+        *self < Rational::ZERO
+    }
+}
+
+impl AddAssign for Rational {
+    #[inline]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Rational {
+    #[inline]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl MulAssign for Rational {
+    #[inline]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl RemAssign for Rational {
+    #[inline]
+    fn rem_assign(&mut self, rhs: Self) {
+        *self = *self % rhs;
+    }
+}
+
+impl DivAssign for Rational {
+    #[inline]
+    fn div_assign(&mut self, rhs: Self) {
+        *self = *self / rhs;
+    }
+}
+
+impl Num for Rational {
+    const ZERO: Self = Rational(2);
+    const ONE: Self = Rational(3);
+    const INFINITY: Self = Rational(1);
+
+    #[inline]
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        match (self.is_finite(), other.is_finite()) {
+            _ if self == other => Ordering::Equal,
+            (true, true) => {
+                let table = TABLE.read().unwrap();
+                table.get(*self).cmp(table.get(*other))
+            }
+            (true, false) if *other == Rational::INFINITY => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) => other.total_cmp(self).reverse(),
+            // -∞ has the smaller handle.
+            (false, false) => self.0.cmp(&other.0),
+        }
+    }
+}
+
+impl Tolerance for Rational {}
+
+impl ToF64 for Rational {
+    #[inline]
+    fn to_f64(self) -> f64 {
+        match self {
+            Rational::NEG_INFINITY => f64::NEG_INFINITY,
+            Rational::INFINITY => f64::INFINITY,
+            _ => TABLE.read().unwrap().get(self).to_f64().unwrap(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::{nested_merges, vickrey_bottleneck},
+        float::F64,
+        num::{Num, ToF64, Tolerance},
+        price_of_anarchy::total_travel_times,
+    };
+
+    use super::{Rational, Rational128};
+
+    #[test]
+    fn it_computes_with_infinities() {
+        let half = Rational128::new(1, 2);
+        assert_eq!(half + half, Rational128::ONE);
+        assert_eq!(
+            Rational128::ONE / Rational128::from(3),
+            Rational128::new(1, 3)
+        );
+        assert_eq!(Rational128::INFINITY - half, Rational128::INFINITY);
+        assert_eq!(-Rational128::INFINITY * half, -Rational128::INFINITY);
+        assert_eq!(Rational128::INFINITY * Rational128::ZERO, Rational128::ZERO);
+        assert_eq!(half / Rational128::INFINITY, Rational128::ZERO);
+        assert!(-Rational128::INFINITY < half && half < Rational128::INFINITY);
+        assert_eq!(Rational128::new(-3, 4).to_f64(), -0.75);
+        assert_eq!(Rational128::from(-0.75), Rational128::new(-3, 4));
+        assert_eq!(Rational128::from(6.0), Rational128::from(6));
+        assert_eq!(Rational128::from(0.0), Rational128::ZERO);
+        assert_eq!(Rational128::from(f64::NEG_INFINITY), -Rational128::INFINITY);
+    }
+
+    #[test]
+    fn it_reports_overflows() {
+        let big = Rational128::new(1, i128::MAX);
+        assert_eq!(big * big, Rational128::Overflow);
+        assert!((big * big + Rational128::INFINITY).is_nan());
+        assert!((Rational128::ZERO * (big * big)).is_nan());
+        assert!(Rational128::from(f64::MIN_POSITIVE).is_nan());
+        assert!(Rational128::from(f64::NAN).is_nan());
+        assert!(Rational128::INFINITY < Rational128::Overflow);
+    }
+
+    // With `validate`, the loading already panics at the first value that is not a number.
+    #[cfg(not(feature = "validate"))]
+    #[test]
+    fn it_detects_overflowing_loadings() {
+        use crate::{
+            network::Network, network_loader::PathInflow, piecewise_constant::PiecewiseConstant,
+            point::Point,
+        };
+
+        // Floats have large power of two denominators, so exact loadings of them may overflow.
+        let mut network = Network::new();
+        network.add_edge(0, 1, Rational128::from(0.7), Rational128::from(0.1));
+        network.add_edge(1, 2, Rational128::from(0.3), Rational128::from(0.2));
+        let inflow = PiecewiseConstant::new(
+            [-Rational128::INFINITY, Rational128::INFINITY],
+            [(0.0, 1.9), (0.35, 0.2), (1.3, 1.1), (2.1, 0.0)]
+                .map(|(t, v)| Point(Rational128::from(t), Rational128::from(v)))
+                .to_vec(),
+        );
+        let flow = network.load(&[PathInflow {
+            path: &[0, 1],
+            inflow: &inflow,
+        }]);
+        assert!(flow.contains_nan());
+    }
+
+    #[test]
+    fn it_loads_networks_exactly() {
+        let example = nested_merges::<Rational128>();
+        let flow = example.instance.load(&example.path_inflows);
+        let total = total_travel_times(&example.instance, &flow).total;
+        assert_eq!(total, example.total_travel_time);
+        assert_eq!(total, Rational128::new(21, 2));
+
+        let exact = vickrey_bottleneck(
+            Rational128::from(3),
+            Rational128::new(2, 3),
+            Rational128::ONE,
+            Rational128::new(1, 3),
+        );
+        let float = vickrey_bottleneck::<F64>(
+            3.0.into(),
+            (2.0 / 3.0).into(),
+            1.0.into(),
+            (1.0 / 3.0).into(),
+        );
+        let exact_flow = exact.instance.load(&exact.path_inflows);
+        let float_flow = float.instance.load(&float.path_inflows);
+        let exact_total = total_travel_times(&exact.instance, &exact_flow).total;
+        assert_eq!(exact_total, exact.total_travel_time);
+        let float_total = total_travel_times(&float.instance, &float_flow).total;
+        assert!((float_total.to_f64() - exact_total.to_f64()).abs() <= 1e-9);
+    }
+
+    #[test]
+    fn it_computes_with_big_rationals() {
+        let third = Rational::new(1, 3);
+        assert_eq!(third + third + third, Rational::ONE);
+        assert_eq!(Rational::from(0.75), Rational::new(3, 4));
+        assert_eq!(Rational::new(-3, 4).to_f64(), -0.75);
+        assert_eq!(Rational::INFINITY - third, Rational::INFINITY);
+        assert_eq!(-Rational::INFINITY * third, -Rational::INFINITY);
+        assert_eq!(Rational::INFINITY * Rational::ZERO, Rational::ZERO);
+        assert_eq!(third / Rational::INFINITY, Rational::ZERO);
+        assert!(-Rational::INFINITY < -third && -third < Rational::ZERO);
+        assert!(third < Rational::INFINITY);
+
+        // Products that overflow `Rational128` stay exact.
+        let tiny = Rational::new(1, i64::MAX);
+        let product = tiny * tiny * tiny;
+        assert!(product > Rational::ZERO);
+        assert_eq!(product / tiny / tiny, tiny);
+        let max = Rational::from(i64::MAX);
+        assert_eq!(product * max * max * max, Rational::ONE);
+    }
+
+    #[test]
+    fn it_loads_float_inputs_exactly() {
+        use crate::{
+            network::Network, network_loader::PathInflow, piecewise_constant::PiecewiseConstant,
+            point::Point,
+        };
+
+        // The loading that overflows `Rational128` in `it_detects_overflowing_loadings`
+        fn load<T: Num + Tolerance + From<f64>>() -> crate::dynamic_flow::DynamicFlow<T> {
+            let mut network = Network::new();
+            network.add_edge(0, 1, T::from(0.7), T::from(0.1));
+            network.add_edge(1, 2, T::from(0.3), T::from(0.2));
+            let inflow = PiecewiseConstant::new(
+                [-T::INFINITY, T::INFINITY],
+                [(0.0, 1.9), (0.35, 0.2), (1.3, 1.1), (2.1, 0.0)]
+                    .map(|(t, v)| Point(T::from(t), T::from(v)))
+                    .to_vec(),
+            );
+            network.load(&[PathInflow {
+                path: &[0, 1],
+                inflow: &inflow,
+            }])
+        }
+        let exact = load::<Rational>();
+        let float = load::<F64>();
+        assert!(!exact.contains_nan());
+        for (e, f) in exact.queues().iter().zip(float.queues()) {
+            assert_eq!(e.points().len(), f.points().len());
+            for (p, q) in e.points().iter().zip(f.points()) {
+                assert!((p.0.to_f64() - q.0.to_f64()).abs() <= 1e-9);
+                assert!((p.1.to_f64() - q.1.to_f64()).abs() <= 1e-9);
+            }
+        }
+    }
+}
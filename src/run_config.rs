@@ -0,0 +1,364 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    compression::CompressedWriter,
+    decimal::Decimal,
+    double_double::DoubleDouble,
+    dynamic_flow::TimeWindow,
+    export_csv::export_csv,
+    export_visualization::{
+        VisualizationDynamicFlow, VisualizationInstanceFlow, WebVisualizationFlow,
+        WebVisualizationOptions,
+    },
+    float::{FastF64, F64},
+    format_error::FormatError,
+    instance::Instance,
+    interval::Interval,
+    network_loader::NetworkLoader,
+    num::{Num, ToF64, Tolerance},
+    rational::{Rational, Rational128},
+    scenario::{metrics, uniform_split, ScenarioOverrides, ScenarioTable},
+    tolerance::TolerancePolicy,
+};
+
+/// The number type in which a run is computed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Backend {
+    #[default]
+    F64,
+    FastF64,
+    DoubleDouble,
+    Interval,
+    Decimal,
+    /// Exact rationals of arbitrary precision, see `Rational`.
+    Rational,
+    /// Exact rationals of `Rational128`. The run fails if their numbers overflow.
+    Rational128,
+}
+
+/// A file written after the flow of a run is built.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ExportTarget {
+    /// The queues and flow rates in the format of `VisualizationDynamicFlow`.
+    /// Files ending in `.gz` or `.zst` are compressed, see `CompressedWriter`.
+    /// With `withNetwork`, the network and the paths are embedded, see `VisualizationInstanceFlow`.
+    #[serde(rename_all = "camelCase")]
+    Visualization {
+        path: PathBuf,
+        #[serde(default)]
+        with_network: bool,
+    },
+    /// The flow in the format of the web visualization, see `WebVisualizationFlow`, with the
+    /// default options. The file is compressed like that of `Visualization`.
+    WebVisualization { path: PathBuf },
+    /// The metrics of the run as a CSV table with a single row, see `ScenarioTable::to_csv`.
+    /// The file is compressed like that of `Visualization`.
+    Metrics { path: PathBuf },
+    /// The queues and rates as tidy CSV files in the directory `dir`, see `export_csv`.
+    Csv { dir: PathBuf },
+}
+
+/// A run read from a TOML or YAML file, e.g.
+/// ```toml
+/// name = "peak"
+/// instance = "instances/grid.json"
+/// backend = "doubleDouble"
+/// horizon = 50.0
+/// absoluteTolerance = 1e-9
+///
+/// [[exports]]
+/// kind = "metrics"
+/// path = "out/peak.csv"
+/// ```
+/// The instance is given in the format of `InstanceJson` and the inflow of each commodity is split
+/// evenly among its paths. Relative paths are resolved against the directory of the config file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct RunConfig {
+    #[serde(default = "default_name")]
+    pub name: String,
+    pub instance: PathBuf,
+    #[serde(default)]
+    pub backend: Backend,
+    /// If set, the network inflow of all commodities stops at this time.
+    pub horizon: Option<f64>,
+    /// Replace the absolute and relative tolerance of the default `TolerancePolicy`.
+    pub absolute_tolerance: Option<f64>,
+    pub relative_tolerance: Option<f64>,
+    /// If set, all exports are restricted to this time window, e.g. `window = { from = 0, to = 60 }`.
+    /// The metrics are computed on the whole flow.
+    pub window: Option<TimeWindow<f64>>,
+    #[serde(default)]
+    pub exports: Vec<ExportTarget>,
+}
+
+fn default_name() -> String {
+    String::from("run")
+}
+
+/// The metrics of a finished run, converted to `f64` independently of the backend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunReport {
+    pub total_travel_time: f64,
+    pub max_queue: f64,
+    pub last_outflow_change: f64,
+    pub breakpoints: usize,
+}
+
+impl RunConfig {
+    pub fn from_toml(toml: &str) -> Result<Self, FormatError> {
+        Ok(toml::from_str(toml)?)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, FormatError> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// Reads a config file, which is parsed as YAML if its extension is `yaml` or `yml` and as
+    /// TOML otherwise.
+    pub fn read(path: &Path) -> Result<Self, FormatError> {
+        let content = fs::read_to_string(path)?;
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => Self::from_yaml(&content),
+            _ => Self::from_toml(&content),
+        }
+    }
+
+    /// Loads the instance, builds the flow in the configured backend and writes all exports.
+    /// Relative paths are resolved against `base_dir`.
+    pub fn run(&self, base_dir: &Path) -> Result<RunReport, FormatError> {
+        match self.backend {
+            Backend::F64 => self.run_with::<F64>(base_dir),
+            Backend::FastF64 => self.run_with::<FastF64>(base_dir),
+            Backend::DoubleDouble => self.run_with::<DoubleDouble>(base_dir),
+            Backend::Interval => self.run_with::<Interval>(base_dir),
+            Backend::Decimal => self.run_with::<Decimal>(base_dir),
+            Backend::Rational => self.run_with::<Rational>(base_dir),
+            Backend::Rational128 => self.run_with::<Rational128>(base_dir),
+        }
+    }
+
+    fn run_with<T: Num + Tolerance + ToF64 + From<f64>>(
+        &self,
+        base_dir: &Path,
+    ) -> Result<RunReport, FormatError> {
+        let json = fs::read_to_string(base_dir.join(&self.instance))?;
+        let base: Instance<T> = Instance::from_json(&json)?;
+        let instance = ScenarioOverrides {
+            horizon: self.horizon.map(T::from),
+            ..ScenarioOverrides::new(self.name.clone())
+        }
+        .apply(&base);
+
+        let default = TolerancePolicy::<T>::default();
+        let tolerance = TolerancePolicy::new(
+            self.absolute_tolerance.map_or(default.absolute, T::from),
+            self.relative_tolerance.map_or(default.relative, T::from),
+        );
+        let path_inflows = uniform_split(&instance);
+        let network = &instance.network;
+        let flow = NetworkLoader::new(&instance.flat_path_inflows(&path_inflows))
+            .with_tolerance(tolerance)
+            .build_flow(
+                network.num_edges(),
+                network.capacity(),
+                network.inv_capacity(),
+                network.travel_time(),
+            );
+        if flow.contains_nan() {
+            return Err(FormatError::Invalid(format!(
+                "The flow of the backend {:?} contains values that are not a number, e.g. because \
+                 its numbers overflowed.",
+                self.backend
+            )));
+        }
+        let metrics = metrics(self.name.clone(), &instance, &flow);
+        let flow = match self.window {
+            Some(window) => flow.restrict(TimeWindow {
+                from: window.from.into(),
+                to: window.to.into(),
+            }),
+            None => flow,
+        };
+
+        for export in &self.exports {
+            match export {
+                ExportTarget::Visualization { path, with_network } => {
+                    let mut writer = CompressedWriter::create(&base_dir.join(path))?;
+                    if *with_network {
+                        let export = VisualizationInstanceFlow {
+                            instance: &instance,
+                            flow: &flow,
+                        };
+                        serde_json::to_writer(&mut writer, &export)?;
+                    } else {
+                        serde_json::to_writer(&mut writer, &VisualizationDynamicFlow(&flow))?;
+                    }
+                    writer.finish()?
+                }
+                ExportTarget::WebVisualization { path } => {
+                    let mut writer = CompressedWriter::create(&base_dir.join(path))?;
+                    let export = WebVisualizationFlow {
+                        instance: &instance,
+                        flow: &flow,
+                        options: &WebVisualizationOptions::default(),
+                    };
+                    serde_json::to_writer(&mut writer, &export)?;
+                    writer.finish()?
+                }
+                ExportTarget::Csv { dir } => {
+                    fs::create_dir_all(base_dir.join(dir))?;
+                    export_csv(&flow, &base_dir.join(dir))?
+                }
+                ExportTarget::Metrics { path } => {
+                    let mut writer = CompressedWriter::create(&base_dir.join(path))?;
+                    let table = ScenarioTable {
+                        rows: vec![metrics.clone()],
+                    };
+                    writer.write_all(table.to_csv().as_bytes())?;
+                    writer.finish()?
+                }
+            }
+        }
+        Ok(RunReport {
+            total_travel_time: metrics.total_travel_time.to_f64(),
+            max_queue: metrics.max_queue.to_f64(),
+            last_outflow_change: metrics.last_outflow_change.to_f64(),
+            breakpoints: metrics.breakpoints,
+        })
+    }
+}
+
+/// Reads the config file at `path` and runs it relative to the directory of the file.
+pub fn run_config_file(path: &Path) -> Result<RunReport, FormatError> {
+    let config = RunConfig::read(path)?;
+    config.run(path.parent().unwrap_or(Path::new(".")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::{example_instances::nested_merges, float::F64};
+
+    use super::{run_config_file, Backend, ExportTarget, RunConfig};
+
+    #[test]
+    fn it_parses_toml_and_yaml() {
+        let toml = RunConfig::from_toml(
+            r#"
+            instance = "instance.json"
+            backend = "doubleDouble"
+            horizon = 2.0
+
+            [[exports]]
+            kind = "metrics"
+            path = "metrics.csv"
+            "#,
+        )
+        .unwrap();
+        let yaml = RunConfig::from_yaml(
+            r#"
+            instance: instance.json
+            backend: doubleDouble
+            horizon: 2.0
+            exports:
+              - kind: metrics
+                path: metrics.csv
+            "#,
+        )
+        .unwrap();
+        assert_eq!(toml, yaml);
+        assert_eq!(toml.name, "run");
+        assert_eq!(toml.backend, Backend::DoubleDouble);
+        assert_eq!(
+            toml.exports,
+            vec![ExportTarget::Metrics {
+                path: "metrics.csv".into()
+            }]
+        );
+        assert!(RunConfig::from_toml("instance = 1").is_err());
+    }
+
+    #[test]
+    fn it_runs_a_config_file() {
+        let dir = env::temp_dir().join("dynamic-flows-rs-run-config");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("instance.json"),
+            nested_merges::<F64>().instance.to_json(),
+        )
+        .unwrap();
+        let config = r#"
+            name = "merges"
+            instance = "instance.json"
+
+            [[exports]]
+            kind = "metrics"
+            path = "metrics.csv"
+
+            [[exports]]
+            kind = "visualization"
+            path = "flow.json"
+
+            [[exports]]
+            kind = "visualization"
+            path = "flow.json.zst"
+
+            [[exports]]
+            kind = "visualization"
+            path = "network.json"
+            withNetwork = true
+
+            [[exports]]
+            kind = "webVisualization"
+            path = "web.json"
+
+            [[exports]]
+            kind = "csv"
+            dir = "csv"
+        "#;
+        fs::write(dir.join("run.toml"), config).unwrap();
+        let report = run_config_file(&dir.join("run.toml")).unwrap();
+        assert!(report.total_travel_time > 0.0);
+        assert!(report.breakpoints > 0);
+
+        let csv = fs::read_to_string(dir.join("metrics.csv")).unwrap();
+        assert!(csv.lines().nth(1).unwrap().starts_with("merges,"));
+        let json = fs::read_to_string(dir.join("flow.json")).unwrap();
+        assert!(json.starts_with("{\"queues\":"));
+        assert!(fs::read_to_string(dir.join("network.json"))
+            .unwrap()
+            .starts_with("{\"network\":{\"numNodes\":"));
+        assert!(fs::read_to_string(dir.join("web.json"))
+            .unwrap()
+            .starts_with("{\"network\":{\"nodes\":"));
+        let compressed = fs::read(dir.join("flow.json.zst")).unwrap();
+        assert_eq!(
+            zstd::decode_all(compressed.as_slice()).unwrap(),
+            json.as_bytes()
+        );
+        assert!(fs::read_to_string(dir.join("csv").join("outflow.csv"))
+            .unwrap()
+            .starts_with("edge,commodity,time,value\n"));
+
+        let fast = format!("backend = \"fastF64\"\n{}", config);
+        fs::write(dir.join("fast.toml"), fast).unwrap();
+        assert_eq!(run_config_file(&dir.join("fast.toml")).unwrap(), report);
+
+        let windowed = format!("window = {{ from = 1.0, to = 2.0 }}\n{}", config);
+        fs::write(dir.join("windowed.toml"), windowed).unwrap();
+        assert_eq!(run_config_file(&dir.join("windowed.toml")).unwrap(), report);
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("flow.json")).unwrap()).unwrap();
+        assert_eq!(json["queues"][0]["domain"], serde_json::json!([1.0, 2.0]));
+    }
+}
@@ -0,0 +1,300 @@
+use std::{io, sync::Mutex};
+
+use rayon::{prelude::*, ThreadPoolBuilder};
+
+use crate::{
+    dynamic_flow::DynamicFlow,
+    fixed_point::{run_fixed_point_iteration, FixedPointConfig},
+    instance::Instance,
+    num::{max, Num, Tolerance},
+    piecewise_constant::PiecewiseConstant,
+    point::Point,
+    price_of_anarchy::total_travel_times,
+};
+
+/// Describes how a scenario deviates from the base instance.
+#[derive(Debug, Clone)]
+pub struct ScenarioOverrides<T: Num> {
+    pub name: String,
+    /// Pairs of an edge and its new capacity.
+    pub capacities: Vec<(usize, T)>,
+    /// Pairs of a commodity and a factor by which its inflow rate is scaled.
+    pub demand_factors: Vec<(usize, T)>,
+    /// If set, the network inflow of all commodities stops at this time.
+    pub horizon: Option<T>,
+}
+
+impl<T: Num> ScenarioOverrides<T> {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            capacities: Vec::new(),
+            demand_factors: Vec::new(),
+            horizon: None,
+        }
+    }
+
+    pub fn apply(&self, base: &Instance<T>) -> Instance<T> {
+        let mut instance = base.clone();
+        for &(edge, capacity) in &self.capacities {
+            instance.network.set_capacity(edge, capacity);
+        }
+        for &(commodity, factor) in &self.demand_factors {
+            let inflow = &mut instance.commodities[commodity].inflow;
+            *inflow = &*inflow * factor;
+        }
+        if let Some(horizon) = self.horizon {
+            for commodity in instance.commodities.iter_mut() {
+                commodity.inflow = cut_off(&commodity.inflow, horizon);
+            }
+        }
+        instance
+    }
+}
+
+/// Determines how the path inflows of a scenario are obtained.
+#[derive(Debug, Clone)]
+pub enum Loading<T: Num> {
+    /// The inflow of each commodity is split evenly among its paths.
+    UniformSplit,
+    /// The path inflows are computed by the fixed-point iteration.
+    Equilibrium(FixedPointConfig<T>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioMetrics<T: Num> {
+    pub name: String,
+    pub total_travel_time: T,
+    pub max_queue: T,
+    /// The last time at which the outflow of some edge changes.
+    pub last_outflow_change: T,
+    /// The total number of breakpoints of all queue and flow rate functions.
+    pub breakpoints: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScenarioTable<T: Num> {
+    pub rows: Vec<ScenarioMetrics<T>>,
+}
+
+const CSV_HEADER: &str = "name,total_travel_time,max_queue,last_outflow_change,breakpoints\n";
+
+impl<T: Num> ScenarioMetrics<T> {
+    pub fn to_csv_row(&self) -> String {
+        format!(
+            "{},{},{},{},{}\n",
+            self.name,
+            self.total_travel_time,
+            self.max_queue,
+            self.last_outflow_change,
+            self.breakpoints
+        )
+    }
+}
+
+impl<T: Num> ScenarioTable<T> {
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(CSV_HEADER);
+        for row in &self.rows {
+            csv.push_str(&row.to_csv_row());
+        }
+        csv
+    }
+}
+
+/// Loads every scenario derived from `base` and collects their metrics in the given order.
+pub fn run_scenarios<T: Num + Tolerance>(
+    base: &Instance<T>,
+    scenarios: &[ScenarioOverrides<T>],
+    loading: &Loading<T>,
+) -> ScenarioTable<T> {
+    ScenarioTable {
+        rows: scenarios
+            .iter()
+            .map(|overrides| run_scenario(base, overrides, loading))
+            .collect(),
+    }
+}
+
+/// Loads the scenarios concurrently on a pool of `num_threads` threads.
+/// Only the metrics of a scenario are kept once it has finished, which bounds the memory usage
+/// by the number of threads. Each finished scenario is written to `sink` as a CSV row right away
+/// (in the order of completion), while the returned table keeps the order of `scenarios`.
+pub fn run_scenarios_parallel<T: Num + Tolerance, W: io::Write + Send>(
+    base: &Instance<T>,
+    scenarios: &[ScenarioOverrides<T>],
+    loading: &Loading<T>,
+    num_threads: usize,
+    sink: W,
+) -> io::Result<ScenarioTable<T>> {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .map_err(io::Error::other)?;
+    let sink = Mutex::new(sink);
+    sink.lock().unwrap().write_all(CSV_HEADER.as_bytes())?;
+
+    let rows: io::Result<Vec<ScenarioMetrics<T>>> = pool.install(|| {
+        scenarios
+            .par_iter()
+            .map(|overrides| {
+                let row = run_scenario(base, overrides, loading);
+                let mut sink = sink.lock().unwrap();
+                sink.write_all(row.to_csv_row().as_bytes())?;
+                sink.flush()?;
+                Ok(row)
+            })
+            .collect()
+    });
+    Ok(ScenarioTable { rows: rows? })
+}
+
+pub fn run_scenario<T: Num + Tolerance>(
+    base: &Instance<T>,
+    overrides: &ScenarioOverrides<T>,
+    loading: &Loading<T>,
+) -> ScenarioMetrics<T> {
+    let instance = overrides.apply(base);
+    let flow = match loading {
+        Loading::UniformSplit => instance.load(&uniform_split(&instance)),
+        Loading::Equilibrium(config) => run_fixed_point_iteration(&instance, config).flow,
+    };
+    metrics(overrides.name.clone(), &instance, &flow)
+}
+
+/// Splits the inflow of each commodity evenly among its paths.
+pub fn uniform_split<T: Num>(instance: &Instance<T>) -> Vec<Vec<PiecewiseConstant<T>>> {
+    instance
+        .commodities
+        .iter()
+        .map(|c| {
+            let num_paths: T = c.paths.iter().map(|_| T::ONE).sum();
+            let share = &c.inflow / num_paths;
+            vec![share; c.paths.len()]
+        })
+        .collect()
+}
+
+pub fn metrics<T: Num>(
+    name: String,
+    instance: &Instance<T>,
+    flow: &DynamicFlow<T>,
+) -> ScenarioMetrics<T> {
+    ScenarioMetrics {
+        name,
+        total_travel_time: total_travel_times(instance, flow).total,
+        max_queue: flow
+            .queues()
+            .iter()
+            .map(|q| q.sup_norm())
+            .fold(T::ZERO, max),
+        last_outflow_change: flow
+            .outflow()
+            .iter()
+            .flat_map(|o| o.function_by_comm().values())
+            .map(|f| f.points().last().unwrap().0)
+            .fold(T::ZERO, max),
+        breakpoints: flow.num_breakpoints(),
+    }
+}
+
+fn cut_off<T: Num>(f: &PiecewiseConstant<T>, horizon: T) -> PiecewiseConstant<T> {
+    let mut points: Vec<Point<T>> = f
+        .points()
+        .iter()
+        .filter(|p| p.0 < horizon)
+        .cloned()
+        .collect();
+    points.push(Point(horizon, T::ZERO));
+    PiecewiseConstant::new(f.domain(), points)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float::F64,
+        instance::{Commodity, Instance},
+        network::Network,
+        num::Num,
+        piecewise_constant::PiecewiseConstant,
+        points,
+    };
+
+    use super::{run_scenarios, run_scenarios_parallel, Loading, ScenarioOverrides};
+
+    fn bottleneck() -> Instance<F64> {
+        let mut network = Network::new();
+        network.add_edge(0, 1, 1.0.into(), 1.0.into());
+        Instance::new(
+            network,
+            vec![Commodity {
+                paths: vec![vec![0]],
+                inflow: PiecewiseConstant::new(
+                    [-F64::INFINITY, F64::INFINITY],
+                    points![(0.0, 3.0), (1.0, 0.0)],
+                ),
+            }],
+        )
+    }
+
+    #[test]
+    fn it_runs_a_parameter_sweep() {
+        let scenarios = vec![
+            ScenarioOverrides::new("base"),
+            ScenarioOverrides {
+                capacities: vec![(0, 3.0.into())],
+                ..ScenarioOverrides::new("wide")
+            },
+            ScenarioOverrides {
+                demand_factors: vec![(0, 2.0.into())],
+                horizon: Some(0.5.into()),
+                ..ScenarioOverrides::new("short peak")
+            },
+        ];
+        let table = run_scenarios(&bottleneck(), &scenarios, &Loading::UniformSplit);
+        assert_eq!(table.rows.len(), 3);
+
+        assert_eq!(table.rows[0].total_travel_time, 6.0);
+        assert_eq!(table.rows[0].max_queue, 2.0);
+        assert_eq!(table.rows[0].last_outflow_change, 4.0);
+
+        assert_eq!(table.rows[1].total_travel_time, 3.0);
+        assert_eq!(table.rows[1].max_queue, F64::ZERO);
+
+        assert_eq!(table.rows[2].max_queue, 2.5);
+        assert_eq!(table.rows[2].last_outflow_change, 4.0);
+
+        let csv = table.to_csv();
+        assert!(csv.starts_with("name,total_travel_time"));
+        assert!(csv.contains("\nwide,3,0,2,"));
+    }
+
+    #[test]
+    fn it_runs_scenarios_in_parallel() {
+        let scenarios: Vec<ScenarioOverrides<F64>> = (1..=8)
+            .map(|i| ScenarioOverrides {
+                capacities: vec![(0, F64::from(i as f64))],
+                ..ScenarioOverrides::new(format!("capacity {}", i))
+            })
+            .collect();
+        let mut output: Vec<u8> = Vec::new();
+        let table = run_scenarios_parallel(
+            &bottleneck(),
+            &scenarios,
+            &Loading::UniformSplit,
+            4,
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            table,
+            run_scenarios(&bottleneck(), &scenarios, &Loading::UniformSplit)
+        );
+
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 9);
+        for row in &table.rows {
+            assert!(output.contains(&row.to_csv_row()));
+        }
+    }
+}
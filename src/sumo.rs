@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use quick_xml::{events::Event, Reader};
+
+use crate::{
+    format_error::FormatError,
+    network::Network,
+    num::Num,
+    xml::{attributes, required},
+};
+
+/// A network read from a SUMO `net.xml` file, in which time is measured in seconds.
+/// Junctions become nodes in the order in which edges first use them.
+#[derive(Debug, Clone)]
+pub struct SumoNetwork<T: Num> {
+    pub network: Network<T>,
+    /// The SUMO id of the junction of each node.
+    pub junction_ids: Vec<String>,
+    /// The SUMO id of each edge.
+    pub edge_ids: Vec<String>,
+    /// The number of lanes open to passenger cars of each edge.
+    pub num_lanes: Vec<usize>,
+}
+
+/// The lanes of an edge read so far, see `parse_sumo_network`.
+struct SumoEdge {
+    id: String,
+    from: String,
+    to: String,
+    num_lanes: usize,
+    travel_time: f64,
+}
+
+/// Returns whether passenger cars may use a lane with the given `allow` and `disallow` lists.
+fn allows_cars(attributes: &HashMap<String, String>) -> bool {
+    let listed = |key: &str| {
+        attributes.get(key).map(|classes| {
+            classes
+                .split_whitespace()
+                .any(|class| class == "passenger" || class == "all")
+        })
+    };
+    listed("allow").unwrap_or(true) && !listed("disallow").unwrap_or(false)
+}
+
+/// Parses a SUMO network. Internal edges of junctions are skipped, and so are edges without lanes
+/// open to passenger cars. The lanes of an edge are aggregated: The capacity of an edge is
+/// `lane_capacity` (in vehicles per second) times its number of car lanes, and its travel time is
+/// that of its fastest lane at the speed limit.
+pub fn parse_sumo_network<T: Num + From<f64>>(
+    xml: &str,
+    lane_capacity: f64,
+) -> Result<SumoNetwork<T>, FormatError> {
+    debug_assert!(lane_capacity > 0.0, "The lane capacity must be positive.");
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    let mut sumo = SumoNetwork {
+        network: Network::new(),
+        junction_ids: Vec::new(),
+        edge_ids: Vec::new(),
+        num_lanes: Vec::new(),
+    };
+    let mut nodes_by_junction: HashMap<String, usize> = HashMap::new();
+    let mut current: Option<SumoEdge> = None;
+    loop {
+        let (element, is_empty) = match reader.read_event()? {
+            Event::Start(e) => (e, false),
+            Event::Empty(e) => (e, true),
+            Event::End(e) if e.name().as_ref() == b"edge" => {
+                if let Some(edge) = current.take() {
+                    sumo.add_edge(edge, lane_capacity, &mut nodes_by_junction);
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        match element.name().as_ref() {
+            b"edge" => {
+                let attributes = attributes(&element)?;
+                // Internal edges, crossings and walking areas have a function other than normal
+                let normal = attributes.get("function").is_none_or(|f| f == "normal");
+                if normal && !is_empty {
+                    current = Some(SumoEdge {
+                        id: required(&attributes, "edge", "id")?,
+                        from: required(&attributes, "edge", "from")?,
+                        to: required(&attributes, "edge", "to")?,
+                        num_lanes: 0,
+                        travel_time: f64::INFINITY,
+                    });
+                }
+            }
+            b"lane" => {
+                if let Some(edge) = current.as_mut() {
+                    let attributes = attributes(&element)?;
+                    let speed: f64 = required(&attributes, "lane", "speed")?;
+                    let length: f64 = required(&attributes, "lane", "length")?;
+                    if speed.is_nan() || speed <= 0.0 || length.is_nan() || length < 0.0 {
+                        return Err(FormatError::Invalid(format!(
+                            "A lane of the edge {} has an invalid speed or length.",
+                            edge.id
+                        )));
+                    }
+                    if allows_cars(&attributes) {
+                        edge.num_lanes += 1;
+                        edge.travel_time = edge.travel_time.min(length / speed);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(sumo)
+}
+
+impl<T: Num + From<f64>> SumoNetwork<T> {
+    fn add_edge(
+        &mut self,
+        edge: SumoEdge,
+        lane_capacity: f64,
+        nodes_by_junction: &mut HashMap<String, usize>,
+    ) {
+        if edge.num_lanes == 0 {
+            return;
+        }
+        let mut node = |junction: String| {
+            *nodes_by_junction
+                .entry(junction.clone())
+                .or_insert_with(|| {
+                    self.junction_ids.push(junction);
+                    self.network.add_node()
+                })
+        };
+        let (tail, head) = (node(edge.from), node(edge.to));
+        self.network.add_edge(
+            tail,
+            head,
+            (lane_capacity * edge.num_lanes as f64).into(),
+            edge.travel_time.into(),
+        );
+        self.edge_ids.push(edge.id);
+        self.num_lanes.push(edge.num_lanes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::float::F64;
+
+    use super::parse_sumo_network;
+
+    const NETWORK: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<net version="1.16" junctionCornerDetail="5">
+    <location netOffset="0.00,0.00" convBoundary="0.00,0.00,200.00,0.00"/>
+    <edge id=":J1_0" function="internal">
+        <lane id=":J1_0_0" index="0" speed="13.89" length="4.00" shape="98.00,-4.80 102.00,-4.80"/>
+    </edge>
+    <edge id="E0" from="J0" to="J1" priority="-1">
+        <lane id="E0_0" index="0" allow="pedestrian" speed="2.00" length="100.00" shape="0,0 100,0"/>
+        <lane id="E0_1" index="1" speed="10.00" length="100.00" shape="0,0 100,0"/>
+        <lane id="E0_2" index="2" disallow="bicycle" speed="20.00" length="100.00" shape="0,0 100,0"/>
+    </edge>
+    <edge id="E1" from="J1" to="J2" priority="-1">
+        <lane id="E1_0" index="0" speed="25.00" length="50.00" shape="100,0 150,0"/>
+    </edge>
+    <edge id="E2" from="J2" to="J3" priority="-1">
+        <lane id="E2_0" index="0" allow="pedestrian bicycle" speed="5.00" length="50.00" shape="150,0 200,0"/>
+    </edge>
+    <junction id="J0" type="dead_end" x="0.00" y="0.00" incLanes="" intLanes="" shape="0,0"/>
+</net>
+"#;
+
+    #[test]
+    fn it_aggregates_lanes() {
+        let sumo = parse_sumo_network::<F64>(NETWORK, 0.5).unwrap();
+        assert_eq!(sumo.edge_ids, vec!["E0", "E1"]);
+        assert_eq!(sumo.junction_ids, vec!["J0", "J1", "J2"]);
+        assert_eq!(sumo.num_lanes, vec![2, 1]);
+        assert_eq!(sumo.network.capacity()[0], 1.0);
+        assert_eq!(sumo.network.travel_time()[0], 5.0);
+        assert_eq!(sumo.network.travel_time()[1], 2.0);
+        assert!(sumo.network.is_path(&[0, 1]));
+    }
+}
@@ -0,0 +1,117 @@
+use crate::{
+    dynamic_flow::DynamicFlow,
+    network::Network,
+    num::{Num, Tolerance},
+    piecewise_linear::PiecewiseLinear,
+};
+
+/// The end of an edge at which a boundary of a `TimeSpaceDiagram` lies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundarySide {
+    Entrance,
+    Exit,
+}
+
+/// The cumulative number of particles that passed an end of an edge of a path.
+#[derive(Clone, Debug)]
+pub struct TimeSpaceBoundary<T: Num> {
+    pub edge: usize,
+    pub side: BoundarySide,
+    /// The distance from the start of the path, measured in free-flow travel time.
+    pub position: T,
+    /// The number of particles of all commodities that passed the boundary until each time.
+    pub cumulative: PiecewiseLinear<T>,
+}
+
+/// The data of a time-space diagram of a path: The cumulative count curves at the entrance and the
+/// exit of each edge of the path, in the order of the path. The horizontal distance between the
+/// entrance and exit curve of an edge is the travel time of the particle entering at that count,
+/// and their vertical distance is the number of particles on the edge, so queues and shockwaves can
+/// be read off the diagram. The entrance of an edge and the exit of the previous edge are at the
+/// same position, but may differ by the flow merging or diverging at their node.
+#[derive(Clone, Debug)]
+pub struct TimeSpaceDiagram<T: Num> {
+    pub path: Vec<usize>,
+    pub boundaries: Vec<TimeSpaceBoundary<T>>,
+}
+
+impl<T: Num> TimeSpaceDiagram<T> {
+    pub fn new(network: &Network<T>, flow: &DynamicFlow<T>, path: &[usize]) -> Self
+    where
+        T: Tolerance,
+    {
+        debug_assert!(network.is_path(path), "The edges do not form a path.");
+        let mut boundaries = Vec::with_capacity(2 * path.len());
+        let mut position = T::ZERO;
+        for &edge in path {
+            boundaries.push(TimeSpaceBoundary {
+                edge,
+                side: BoundarySide::Entrance,
+                position,
+                cumulative: flow.inflow()[edge].accumulative(),
+            });
+            position += network.travel_time()[edge];
+            boundaries.push(TimeSpaceBoundary {
+                edge,
+                side: BoundarySide::Exit,
+                position,
+                cumulative: flow.outflow()[edge].accumulative(),
+            });
+        }
+        TimeSpaceDiagram {
+            path: path.to_vec(),
+            boundaries,
+        }
+    }
+
+    /// Returns the times at which a particle entering the path at `departure` passes the
+    /// boundaries, i.e. its trajectory in the diagram.
+    pub fn trajectory(&self, network: &Network<T>, flow: &DynamicFlow<T>, departure: T) -> Vec<T> {
+        let mut times = Vec::with_capacity(self.boundaries.len());
+        let mut time = departure;
+        for &edge in &self.path {
+            times.push(time);
+            time = flow.exit_time(
+                edge,
+                time,
+                network.inv_capacity()[edge],
+                network.travel_time()[edge],
+            );
+            times.push(time);
+        }
+        times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        example_instances::vickrey_bottleneck, export_csv::write_time_space_csv, float::F64,
+    };
+
+    use super::{BoundarySide, TimeSpaceDiagram};
+
+    #[test]
+    fn it_computes_cumulative_counts() {
+        let instance = vickrey_bottleneck::<F64>(2.0.into(), 1.0.into(), 1.0.into(), 4.0.into());
+        let network = &instance.instance.network;
+        let flow = instance.instance.load(&instance.path_inflows);
+        let diagram = TimeSpaceDiagram::new(network, &flow, &[0]);
+
+        let [entrance, exit] = [&diagram.boundaries[0], &diagram.boundaries[1]];
+        assert_eq!(entrance.side, BoundarySide::Entrance);
+        assert_eq!((exit.side, exit.position), (BoundarySide::Exit, 1.0.into()));
+        assert_eq!(entrance.cumulative.eval(4.0), 8.0);
+        assert_eq!(exit.cumulative.eval(5.0), 4.0);
+        assert_eq!(exit.cumulative.eval(9.0), 8.0);
+        // The particle entering at count 4 leaves when the exit count reaches 4
+        let trajectory = diagram.trajectory(network, &flow, 2.0.into());
+        assert_eq!(trajectory, vec![F64::from(2.0), 5.0.into()]);
+
+        let mut csv = Vec::new();
+        write_time_space_csv(&diagram, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert!(csv.starts_with("edge,side,position,time,cumulative\n0,entrance,0,"));
+        assert!(csv.contains("\n0,exit,1,9,8\n"));
+    }
+}
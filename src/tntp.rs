@@ -0,0 +1,345 @@
+use std::{cmp::Reverse, collections::HashMap};
+
+use priority_queue::PriorityQueue;
+
+use crate::{
+    format_error::{parse_token, FormatError},
+    instance::{Commodity, Instance},
+    network::Network,
+    num::{Num, Total},
+    piecewise_constant::PiecewiseConstant,
+};
+
+/// A network read from a TNTP `_net.tntp` file of the TransportationNetworks collection.
+/// TNTP numbers nodes from 1, which become nodes `0..num_nodes` here. The first `num_zones` nodes
+/// are the zones, of which those before `first_thru_node` may not be passed through by paths.
+#[derive(Debug, Clone)]
+pub struct TntpNetwork<T: Num> {
+    pub network: Network<T>,
+    pub num_zones: usize,
+    /// The first node (counted from 0) that paths may pass through.
+    pub first_thru_node: usize,
+}
+
+/// The demand of a TNTP `_trips.tntp` file, where the zones are counted from 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TntpTrips {
+    pub num_zones: usize,
+    /// Triples of origin, destination and the static flow between them.
+    pub od_flows: Vec<(usize, usize, f64)>,
+}
+
+/// The metadata of a TNTP file and its remaining lines with their line numbers.
+struct TntpFile<'a> {
+    metadata: HashMap<String, String>,
+    lines: Vec<(usize, &'a str)>,
+}
+
+/// Reads the metadata lines `<KEY> value` up to `<END OF METADATA>`.
+fn read_metadata(content: &str) -> Result<TntpFile<'_>, FormatError> {
+    let mut metadata = HashMap::new();
+    let mut lines = content.lines().enumerate().map(|(i, line)| (i + 1, line));
+    for (number, line) in lines.by_ref() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('~') {
+            continue;
+        }
+        if line == "<END OF METADATA>" {
+            let lines = lines
+                .map(|(number, line)| (number, line.trim()))
+                .filter(|(_, line)| !line.is_empty() && !line.starts_with('~'))
+                .collect();
+            return Ok(TntpFile { metadata, lines });
+        }
+        let (key, value) = line
+            .strip_prefix('<')
+            .and_then(|line| line.split_once('>'))
+            .ok_or_else(|| FormatError::at_line(number, "Expected a metadata line <KEY> value."))?;
+        metadata.insert(key.trim().to_string(), value.trim().to_string());
+    }
+    Err(FormatError::Invalid(String::from(
+        "The file ends before <END OF METADATA>.",
+    )))
+}
+
+fn metadata_count(metadata: &HashMap<String, String>, key: &str) -> Result<usize, FormatError> {
+    metadata
+        .get(key)
+        .ok_or_else(|| FormatError::Invalid(format!("The metadata <{}> is missing.", key)))?
+        .parse()
+        .map_err(|_| FormatError::Invalid(format!("The metadata <{}> is not a count.", key)))
+}
+
+/// The period of the capacities of most networks of the TransportationNetworks collection, which
+/// give capacities in vehicles per hour and free flow times in minutes.
+pub const TNTP_CAPACITY_PERIOD: f64 = 60.0;
+
+/// Parses a TNTP network file. The free flow time of each link becomes the travel time of its
+/// edge, so times are measured in the unit of the free flow times. The capacity of a link is the
+/// number of vehicles per `capacity_period` in that unit, usually `TNTP_CAPACITY_PERIOD`, and
+/// becomes the capacity `capacity / capacity_period` of its edge. A `capacity_period` of 1 keeps
+/// the capacities of the file.
+pub fn parse_tntp_network<T: Num + From<f64>>(
+    content: &str,
+    capacity_period: f64,
+) -> Result<TntpNetwork<T>, FormatError> {
+    debug_assert!(
+        capacity_period > 0.0,
+        "The capacity period must be positive."
+    );
+    let TntpFile { metadata, lines } = read_metadata(content)?;
+    let num_zones = metadata_count(&metadata, "NUMBER OF ZONES")?;
+    let num_nodes = metadata_count(&metadata, "NUMBER OF NODES")?;
+    let num_links = metadata_count(&metadata, "NUMBER OF LINKS")?;
+    let first_thru_node = metadata_count(&metadata, "FIRST THRU NODE")?;
+
+    let mut network = Network::new();
+    for _ in 0..num_nodes {
+        network.add_node();
+    }
+    for (number, line) in lines {
+        let tokens: Vec<&str> = line.trim_end_matches(';').split_whitespace().collect();
+        if tokens.len() < 5 {
+            return Err(FormatError::at_line(
+                number,
+                "Expected at least five columns.",
+            ));
+        }
+        let tail: usize = parse_token(number, tokens[0])?;
+        let head: usize = parse_token(number, tokens[1])?;
+        let capacity: f64 = parse_token(number, tokens[2])?;
+        let free_flow_time: f64 = parse_token(number, tokens[4])?;
+        if !(1..=num_nodes).contains(&tail) || !(1..=num_nodes).contains(&head) {
+            return Err(FormatError::at_line(
+                number,
+                "The link connects unknown nodes.",
+            ));
+        }
+        if capacity.is_nan() || capacity <= 0.0 {
+            return Err(FormatError::at_line(
+                number,
+                "The capacity must be positive.",
+            ));
+        }
+        if free_flow_time.is_nan() || free_flow_time < 0.0 {
+            return Err(FormatError::at_line(
+                number,
+                "The free flow time is negative.",
+            ));
+        }
+        network.add_edge(
+            tail - 1,
+            head - 1,
+            (capacity / capacity_period).into(),
+            free_flow_time.into(),
+        );
+    }
+    if network.num_edges() != num_links {
+        return Err(FormatError::Invalid(format!(
+            "Expected {} links, but found {}.",
+            num_links,
+            network.num_edges()
+        )));
+    }
+    Ok(TntpNetwork {
+        network,
+        num_zones,
+        first_thru_node: first_thru_node.saturating_sub(1),
+    })
+}
+
+/// Parses a TNTP trips file, i.e. blocks `Origin o` followed by entries `d : flow;`.
+pub fn parse_tntp_trips(content: &str) -> Result<TntpTrips, FormatError> {
+    let TntpFile { metadata, lines } = read_metadata(content)?;
+    let num_zones = metadata_count(&metadata, "NUMBER OF ZONES")?;
+    let zone = |number: usize, token: &str| -> Result<usize, FormatError> {
+        let zone: usize = parse_token(number, token)?;
+        if !(1..=num_zones).contains(&zone) {
+            return Err(FormatError::at_line(
+                number,
+                &format!("Unknown zone {}.", zone),
+            ));
+        }
+        Ok(zone - 1)
+    };
+
+    let mut od_flows = Vec::new();
+    let mut origin = None;
+    for (number, line) in lines {
+        if let Some(token) = line.strip_prefix("Origin") {
+            origin = Some(zone(number, token)?);
+            continue;
+        }
+        let origin = origin.ok_or_else(|| FormatError::at_line(number, "Expected `Origin`."))?;
+        for entry in line.split(';').filter(|e| !e.trim().is_empty()) {
+            let (destination, flow) = entry
+                .split_once(':')
+                .ok_or_else(|| FormatError::at_line(number, "Expected `destination : flow`."))?;
+            let flow: f64 = parse_token(number, flow)?;
+            if flow.is_nan() || flow < 0.0 {
+                return Err(FormatError::at_line(number, "The flow is negative."));
+            }
+            od_flows.push((origin, zone(number, destination)?, flow));
+        }
+    }
+    Ok(TntpTrips {
+        num_zones,
+        od_flows,
+    })
+}
+
+impl<T: Num> TntpNetwork<T> {
+    /// Returns the edge by which each node is reached on a shortest path from `origin` with
+    /// respect to the travel times, or `None` for the origin and unreachable nodes.
+    /// Zones before `first_thru_node` are only left from the origin.
+    pub fn shortest_path_tree(&self, origin: usize) -> Vec<Option<usize>> {
+        let network = &self.network;
+        let mut distance = vec![T::INFINITY; network.num_nodes()];
+        let mut predecessor = vec![None; network.num_nodes()];
+        let mut queue: PriorityQueue<usize, Reverse<Total<T>>> = PriorityQueue::new();
+        distance[origin] = T::ZERO;
+        queue.push(origin, Reverse(Total(T::ZERO)));
+        while let Some((node, Reverse(Total(dist)))) = queue.pop() {
+            if node != origin && node < self.first_thru_node {
+                continue;
+            }
+            for edge in network.outgoing_edges(node) {
+                let head = network.head(edge);
+                let candidate = dist + network.travel_time()[edge];
+                if candidate < distance[head] {
+                    distance[head] = candidate;
+                    predecessor[head] = Some(edge);
+                    queue.push_increase(head, Reverse(Total(candidate)));
+                }
+            }
+        }
+        predecessor
+    }
+
+    /// Creates an instance with one commodity per OD pair of `trips` with positive flow, whose
+    /// network inflow rate is the flow times `profile` and which uses a single shortest path.
+    /// E.g. a profile of value 1 on `[0, 2)` sends the hourly flows of the trips file for two hours
+    /// if the time unit is hours.
+    pub fn instance(
+        &self,
+        trips: &TntpTrips,
+        profile: &PiecewiseConstant<T>,
+    ) -> Result<Instance<T>, FormatError>
+    where
+        T: From<f64>,
+    {
+        if trips.num_zones != self.num_zones {
+            return Err(FormatError::Invalid(format!(
+                "The network has {} zones, but the trips have {}.",
+                self.num_zones, trips.num_zones
+            )));
+        }
+        let mut trees: HashMap<usize, Vec<Option<usize>>> = HashMap::new();
+        let mut commodities = Vec::new();
+        for &(origin, destination, flow) in &trips.od_flows {
+            if origin == destination || flow == 0.0 {
+                continue;
+            }
+            let tree = trees
+                .entry(origin)
+                .or_insert_with(|| self.shortest_path_tree(origin));
+            let mut path = Vec::new();
+            let mut node = destination;
+            while node != origin {
+                let edge = tree[node].ok_or_else(|| {
+                    FormatError::Invalid(format!(
+                        "Zone {} cannot reach zone {}.",
+                        origin + 1,
+                        destination + 1
+                    ))
+                })?;
+                path.push(edge);
+                node = self.network.tail(edge);
+            }
+            path.reverse();
+            commodities.push(Commodity {
+                paths: vec![path],
+                inflow: profile * T::from(flow),
+            });
+        }
+        Ok(Instance::new(self.network.clone(), commodities))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float::F64, format_error::FormatError, num::Num, piecewise_constant::PiecewiseConstant,
+        points,
+    };
+
+    use super::{parse_tntp_network, parse_tntp_trips, TNTP_CAPACITY_PERIOD};
+
+    const NETWORK: &str = "<NUMBER OF ZONES> 2
+<NUMBER OF NODES> 4
+<FIRST THRU NODE> 3
+<NUMBER OF LINKS> 5
+<END OF METADATA>
+
+~ 	Init node 	Term node 	Capacity 	Length 	Free Flow Time 	B	Power	Speed limit 	Toll 	Type	;
+	1	3	2.0	1	1.0	0.15	4	0	0	1	;
+	3	4	1.0	1	2.0	0.15	4	0	0	1	;
+	4	2	2.0	1	1.0	0.15	4	0	0	1	;
+	1	2	1.0	1	0.5	0.15	4	0	0	1	;
+	2	4	4.0	1	0.5	0.15	4	0	0	1	;
+";
+
+    const TRIPS: &str = "<NUMBER OF ZONES> 2
+<TOTAL OD FLOW> 3.0
+<END OF METADATA>
+
+
+Origin 	1
+    1 :       0.0;    2 :       3.0;
+
+Origin 	2
+    1 :       0.0;    2 :       0.0;
+";
+
+    #[test]
+    fn it_reads_tntp_files() {
+        let network = parse_tntp_network::<F64>(NETWORK, 1.0).unwrap();
+        assert_eq!(network.network.num_nodes(), 4);
+        assert_eq!(network.network.capacity()[1], 1.0);
+        assert_eq!(network.network.travel_time()[3], 0.5);
+        assert_eq!(network.first_thru_node, 2);
+        let per_minute = parse_tntp_network::<F64>(NETWORK, TNTP_CAPACITY_PERIOD).unwrap();
+        assert_eq!(per_minute.network.capacity()[4], 4.0 / 60.0);
+        assert_eq!(per_minute.network.travel_time()[3], 0.5);
+
+        let trips = parse_tntp_trips(TRIPS).unwrap();
+        assert_eq!(trips.od_flows.len(), 4);
+        assert_eq!(trips.od_flows[1], (0, 1, 3.0));
+
+        let profile = PiecewiseConstant::new(
+            [-F64::INFINITY, F64::INFINITY],
+            points![(0.0, 1.0), (2.0, 0.0)],
+        );
+        let instance = network.instance(&trips, &profile).unwrap();
+        assert_eq!(instance.commodities.len(), 1);
+        assert_eq!(instance.commodities[0].paths, vec![vec![3]]);
+        assert_eq!(instance.commodities[0].inflow.eval(1.0), 3.0);
+
+        let flow = instance.load(&[vec![instance.commodities[0].inflow.clone()]]);
+        assert_eq!(flow.queues()[3].eval(2.0), 4.0);
+    }
+
+    #[test]
+    fn it_does_not_pass_through_zones() {
+        let network = parse_tntp_network::<F64>(NETWORK, 1.0).unwrap();
+        assert_eq!(network.shortest_path_tree(0)[3], Some(1));
+
+        let all_thru = NETWORK.replace("<FIRST THRU NODE> 3", "<FIRST THRU NODE> 1");
+        let network = parse_tntp_network::<F64>(&all_thru, 1.0).unwrap();
+        assert_eq!(network.shortest_path_tree(0)[3], Some(4));
+
+        let unknown_node = NETWORK.replace("\t4\t2\t2.0", "\t4\t5\t2.0");
+        let result = parse_tntp_network::<F64>(&unknown_node, 1.0);
+        assert!(matches!(result, Err(FormatError::Invalid(_))));
+    }
+}
@@ -0,0 +1,72 @@
+use num_traits::abs;
+
+use crate::num::{max, Num, Tolerance};
+
+/// Decides when two numbers are considered equal.
+/// Values of magnitude `scale` may differ by `absolute + relative · scale`.
+/// The default policy only uses the absolute tolerance `T::TOL`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TolerancePolicy<T: Num> {
+    pub absolute: T,
+    pub relative: T,
+}
+
+impl<T: Num + Tolerance> Default for TolerancePolicy<T> {
+    fn default() -> Self {
+        TolerancePolicy {
+            absolute: T::TOL,
+            relative: T::ZERO,
+        }
+    }
+}
+
+impl<T: Num> TolerancePolicy<T> {
+    pub fn new(absolute: T, relative: T) -> Self {
+        debug_assert!(absolute >= T::ZERO && relative >= T::ZERO);
+        TolerancePolicy { absolute, relative }
+    }
+
+    /// Returns the admissible deviation for values of magnitude `scale`.
+    #[inline]
+    pub fn tolerance(&self, scale: T) -> T {
+        if self.relative == T::ZERO {
+            self.absolute
+        } else {
+            self.absolute + self.relative * abs(scale)
+        }
+    }
+
+    /// Returns whether `a` and `b` are equal up to the tolerance for their magnitude.
+    #[inline]
+    pub fn is_close(&self, a: T, b: T) -> bool {
+        if a == b {
+            return true;
+        }
+        let scale = max(abs(a), abs(b));
+        scale < T::INFINITY && abs(a - b) <= self.tolerance(scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float::F64,
+        num::{Num, Tolerance},
+    };
+
+    use super::TolerancePolicy;
+
+    #[test]
+    fn it_scales_with_the_magnitude() {
+        let default = TolerancePolicy::<F64>::default();
+        assert!(default.is_close(1.0.into(), (1.0 + 1e-10).into()));
+        assert!(!default.is_close(1e6.into(), (1e6 + 1e-6).into()));
+        assert!(default.is_close(F64::INFINITY, F64::INFINITY));
+        assert!(!default.is_close(F64::INFINITY, 1.0.into()));
+
+        let relative = TolerancePolicy::new(F64::TOL, 1e-9.into());
+        assert!(relative.is_close(1e6.into(), (1e6 + 1e-6).into()));
+        assert!(!relative.is_close(1.0.into(), (1.0 + 1e-8).into()));
+        assert_eq!(relative.tolerance(F64::ZERO), F64::TOL);
+    }
+}
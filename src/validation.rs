@@ -0,0 +1,142 @@
+use std::fmt::{Display, Formatter};
+
+use crate::{instance::Instance, num::Num, point::Point};
+
+/// A number that is NaN or has a forbidden sign.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvalidValue<T: Num> {
+    /// Capacities must be positive.
+    Capacity { edge: usize, value: T },
+    /// Travel times must be non-negative.
+    TravelTime { edge: usize, value: T },
+    /// Inflow rates must be non-negative.
+    Inflow { commodity: usize, time: T, value: T },
+}
+
+impl<T: Num> Display for InvalidValue<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidValue::Capacity { edge, value } => {
+                write!(f, "Edge {} has the invalid capacity {}.", edge, value)
+            }
+            InvalidValue::TravelTime { edge, value } => {
+                write!(f, "Edge {} has the invalid travel time {}.", edge, value)
+            }
+            InvalidValue::Inflow {
+                commodity,
+                time,
+                value,
+            } => write!(
+                f,
+                "Commodity {} has the invalid inflow rate {} at time {}.",
+                commodity, value, time
+            ),
+        }
+    }
+}
+
+/// Returns all capacities, travel times and inflow rates of the instance that are NaN or have a
+/// forbidden sign. Note that NaN floats compare greater than all other values and hence slip
+/// through the assertions on the sign.
+pub fn validate_instance<T: Num>(instance: &Instance<T>) -> Result<(), Vec<InvalidValue<T>>> {
+    let network = &instance.network;
+    let mut invalid = Vec::new();
+    for (edge, &value) in network.capacity().iter().enumerate() {
+        if value.is_nan() || value <= T::ZERO {
+            invalid.push(InvalidValue::Capacity { edge, value });
+        }
+    }
+    for (edge, &value) in network.travel_time().iter().enumerate() {
+        if value.is_nan() || value < T::ZERO {
+            invalid.push(InvalidValue::TravelTime { edge, value });
+        }
+    }
+    for (commodity, c) in instance.commodities.iter().enumerate() {
+        for p in c.inflow.points() {
+            if p.0.is_nan() || p.1.is_nan() || p.1 < T::ZERO {
+                invalid.push(InvalidValue::Inflow {
+                    commodity,
+                    time: p.0,
+                    value: p.1,
+                });
+            }
+        }
+    }
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(invalid)
+    }
+}
+
+/// Panics if `value` is NaN and the `validate` feature is enabled.
+/// `context` describes where the value came from, e.g. the edge and commodity.
+#[inline]
+pub fn check_not_nan<T: Num>(value: T, context: impl FnOnce() -> String) {
+    if cfg!(feature = "validate") && value.is_nan() {
+        panic!("Encountered NaN: {}", context());
+    }
+}
+
+/// Panics if `value` is NaN or negative and the `validate` feature is enabled.
+#[inline]
+pub fn check_non_negative<T: Num>(value: T, context: impl FnOnce() -> String) {
+    if cfg!(feature = "validate") && (value.is_nan() || value < T::ZERO) {
+        panic!("Encountered the invalid value {}: {}", value, context());
+    }
+}
+
+/// Panics if a coordinate of `points` is NaN and the `validate` feature is enabled.
+#[inline]
+pub fn check_points<T: Num>(points: &[Point<T>], context: &str) {
+    if cfg!(feature = "validate") {
+        for (i, p) in points.iter().enumerate() {
+            check_not_nan(p.0, || format!("{}: time of point {}", context, i));
+            check_not_nan(p.1, || format!("{}: value of point {}", context, i));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        float::F64,
+        instance::{Commodity, Instance},
+        network::Network,
+        num::Num,
+        piecewise_constant::PiecewiseConstant,
+        points,
+    };
+
+    use super::{validate_instance, InvalidValue};
+
+    #[test]
+    fn it_reports_invalid_values() {
+        let mut network: Network<F64> = Network::new();
+        network.add_edge(0, 1, f64::NAN.into(), 1.0.into());
+        network.add_edge(1, 2, 1.0.into(), 1.0.into());
+        let commodity = Commodity {
+            paths: vec![vec![0, 1]],
+            inflow: PiecewiseConstant::new(
+                [-F64::INFINITY, F64::INFINITY],
+                points![(0.0, 1.0), (1.0, -1.0), (2.0, 0.0)],
+            ),
+        };
+        let instance = Instance::new(network, vec![commodity]);
+        let invalid = validate_instance(&instance).unwrap_err();
+        assert_eq!(invalid.len(), 2);
+        assert!(matches!(invalid[0], InvalidValue::Capacity { edge: 0, .. }));
+        assert_eq!(
+            invalid[1],
+            InvalidValue::Inflow {
+                commodity: 0,
+                time: 1.0.into(),
+                value: (-1.0).into()
+            }
+        );
+        assert_eq!(
+            invalid[1].to_string(),
+            "Commodity 0 has the invalid inflow rate -1 at time 1."
+        );
+    }
+}
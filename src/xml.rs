@@ -0,0 +1,49 @@
+use std::{collections::HashMap, str::FromStr};
+
+use quick_xml::events::BytesStart;
+
+use crate::format_error::FormatError;
+
+/// Returns the unescaped attributes of an XML element by their name.
+pub fn attributes(element: &BytesStart) -> Result<HashMap<String, String>, FormatError> {
+    let mut attributes = HashMap::new();
+    for attribute in element.attributes() {
+        let attribute = attribute.map_err(quick_xml::Error::from)?;
+        let key = String::from_utf8_lossy(attribute.key.as_ref()).into_owned();
+        attributes.insert(key, attribute.unescape_value()?.into_owned());
+    }
+    Ok(attributes)
+}
+
+/// Parses the attribute `key` of an element named `element`, if it is present.
+pub fn optional<V: FromStr>(
+    attributes: &HashMap<String, String>,
+    element: &str,
+    key: &str,
+) -> Result<Option<V>, FormatError> {
+    attributes
+        .get(key)
+        .map(|value| {
+            value.trim().parse().map_err(|_| {
+                FormatError::Invalid(format!(
+                    "Cannot parse the attribute {}=\"{}\" of <{}>.",
+                    key, value, element
+                ))
+            })
+        })
+        .transpose()
+}
+
+/// Parses the attribute `key` of an element named `element`, which must be present.
+pub fn required<V: FromStr>(
+    attributes: &HashMap<String, String>,
+    element: &str,
+    key: &str,
+) -> Result<V, FormatError> {
+    optional(attributes, element, key)?.ok_or_else(|| {
+        FormatError::Invalid(format!(
+            "The element <{}> has no attribute {}.",
+            element, key
+        ))
+    })
+}